@@ -0,0 +1,129 @@
+//! Throughput of building quadtrees from random bodies and walking them for
+//! the gravity, to compare changes to the tree against.
+//!
+//! ```sh
+//! cargo bench --bench quadtree
+//! ```
+
+use bevy::math::Vec2;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use spacesim::physics_plugin::{build_tree, G, THETA_THRESHOLD};
+use spacesim::quadtree::{InsertionOrder, QuadTree};
+use std::hint::black_box;
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+/// Half the size of the bounds of [`build_tree`], which the bodies are
+/// spread over.
+const HALF_SIZE: f32 = 1000.;
+
+/// `count` bodies spread uniformly over the tree's bounds, the same ones
+/// for every run.
+fn random_bodies(count: usize) -> Vec<(Vec2, f32)> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..count)
+        .map(|_| {
+            let position = Vec2::new(
+                rng.random_range(-HALF_SIZE..HALF_SIZE),
+                rng.random_range(-HALF_SIZE..HALF_SIZE),
+            );
+            (position, rng.random_range(1.0..1000.0))
+        })
+        .collect()
+}
+
+/// Adding the bodies one by one to a new, empty tree.
+fn add_node(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_node");
+    for count in SIZES {
+        let bodies = random_bodies(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &bodies, |b, bodies| {
+            b.iter_batched_ref(
+                || QuadTree::new(Vec2::ZERO, HALF_SIZE),
+                |q_tree| {
+                    for &(position, mass) in bodies {
+                        q_tree.add_node(position, mass).unwrap();
+                    }
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+/// Rebuilding a tree in place the way the force calculation does every
+/// step, reusing the memory of the nodes.
+fn rebuild(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rebuild");
+    for count in SIZES {
+        let bodies = random_bodies(count);
+        let mut q_tree = build_tree(bodies.iter().copied());
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &bodies, |b, bodies| {
+            b.iter(|| q_tree.rebuild_from(bodies.iter().copied()).unwrap());
+        });
+    }
+    group.finish();
+}
+
+/// Building a tree fitted to the bodies in one go, with the bodies in the
+/// order they are given and sorted along the Morton curve.
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+    for count in SIZES {
+        let bodies = random_bodies(count);
+        group.throughput(Throughput::Elements(count as u64));
+        for order in [InsertionOrder::Given, InsertionOrder::Morton] {
+            let id = BenchmarkId::new(format!("{order:?}"), count);
+            group.bench_with_input(id, &bodies, |b, bodies| {
+                b.iter(|| QuadTree::build_with(bodies, order));
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Walking the tree for the gravity at every body, with the nodes in the
+/// order they were created and compacted.
+fn traversal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("traversal");
+    group.sample_size(10);
+    for count in SIZES {
+        let bodies = random_bodies(count);
+        let inserted = build_tree(bodies.iter().copied());
+        let mut compacted = inserted.clone();
+        compacted.compact();
+        group.throughput(Throughput::Elements(count as u64));
+        for (layout, q_tree) in [("Inserted", &inserted), ("Compacted", &compacted)] {
+            let id = BenchmarkId::new(layout, count);
+            group.bench_with_input(id, &bodies, |b, bodies| {
+                b.iter(|| {
+                    for &(position, _) in bodies {
+                        black_box(q_tree.accumulate_acceleration(position, THETA_THRESHOLD, G, 0.));
+                    }
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Laying out the nodes of a tree depth first.
+fn compact(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compact");
+    for count in SIZES {
+        let q_tree = build_tree(random_bodies(count));
+        group.throughput(Throughput::Elements(q_tree.node_count() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &q_tree, |b, q_tree| {
+            let mut q_tree = q_tree.clone();
+            b.iter(|| q_tree.compact());
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, add_node, rebuild, build, traversal, compact);
+criterion_main!(benches);
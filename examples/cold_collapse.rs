@@ -0,0 +1,127 @@
+//! A disc of bodies starting at rest, which falls in on itself, bounces and
+//! settles into a cluster, stepped with a [`Simulation`] on its own.
+//!
+//! Checks that the disc collapses within a few free-fall times, that it
+//! settles close to virial equilibrium and that the energy is kept through
+//! the violent collapse, with the gravity softened so close passes don't
+//! fling bodies out.
+//!
+//! ```sh
+//! cargo run --release --example cold_collapse
+//! ```
+
+use bevy::math::Vec2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use spacesim::physics_plugin::G;
+use spacesim::simulation::{SimBody, Simulation, SimulationConfig};
+
+const BODIES: usize = 500;
+const MASS: f32 = 2_000_000.;
+const RADIUS: f32 = 300.;
+const SOFTENING: f32 = 10.;
+const DT: f32 = 0.01;
+/// Free-fall times simulated
+const DURATION: f32 = 4.;
+/// Steps between the measurements
+const INTERVAL: usize = 50;
+
+/// Kinetic and potential energy of the bodies, the potential summed over
+/// all the pairs with the same softening as the gravity.
+fn energies(simulation: &Simulation) -> (f32, f32) {
+    let bodies = &simulation.bodies;
+    let kinetic: f32 = bodies
+        .iter()
+        .map(|body| 0.5 * body.mass * body.velocity.length_squared())
+        .sum();
+    let config = simulation.config;
+    let mut potential = 0.;
+    for (i, a) in bodies.iter().enumerate() {
+        for b in &bodies[i + 1..] {
+            let distance_squared = a.position.distance_squared(b.position);
+            potential -= config.g * a.mass * b.mass
+                / (distance_squared + config.softening * config.softening).sqrt();
+        }
+    }
+    (kinetic, potential)
+}
+
+/// Distance from the center of mass within which half of the bodies are.
+fn half_mass_radius(simulation: &Simulation) -> f32 {
+    let bodies = &simulation.bodies;
+    let center = bodies.iter().map(|body| body.position).sum::<Vec2>() / bodies.len() as f32;
+    let mut distances: Vec<f32> = bodies
+        .iter()
+        .map(|body| body.position.distance(center))
+        .collect();
+    let middle = distances.len() / 2;
+    *distances.select_nth_unstable_by(middle, f32::total_cmp).1
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let bodies = (0..BODIES)
+        .map(|_| {
+            // Uniform over the area of the disc
+            let distance = RADIUS * rng.random::<f32>().sqrt();
+            let angle = rng.random_range(0.0..std::f32::consts::TAU);
+            SimBody::new(Vec2::from_angle(angle) * distance, Vec2::ZERO, MASS)
+        })
+        .collect();
+    let mut simulation = Simulation::new(
+        bodies,
+        SimulationConfig {
+            // Tighter than the app's, or the tree's force error shows up as
+            // energy drift when the bodies crowd into the center.
+            theta_threshold: 0.25,
+            softening: SOFTENING,
+            ..Default::default()
+        },
+    );
+
+    let free_fall_time = (RADIUS.powi(3) / (G * MASS * BODIES as f32)).sqrt();
+    let (kinetic, potential) = energies(&simulation);
+    let initial_energy = kinetic + potential;
+    let initial_radius = half_mass_radius(&simulation);
+
+    let mut max_drift = 0f32;
+    let mut smallest = (initial_radius, 0.);
+    let mut virial_ratio = 0.;
+    let steps = (DURATION * free_fall_time / DT).ceil() as usize;
+    for step in 1..=steps {
+        simulation.step(DT);
+        if step % INTERVAL != 0 {
+            continue;
+        }
+        let (kinetic, potential) = energies(&simulation);
+        let drift = (kinetic + potential - initial_energy) / initial_energy.abs();
+        max_drift = max_drift.max(drift.abs());
+        virial_ratio = 2. * kinetic / potential.abs();
+        let radius = half_mass_radius(&simulation);
+        if radius < smallest.0 {
+            smallest = (radius, step as f32 * DT);
+        }
+    }
+
+    let (smallest_radius, collapse_time) = smallest;
+    println!(
+        "Half-mass radius {initial_radius:.1} shrank to {smallest_radius:.1} after {:.2} free-fall times",
+        collapse_time / free_fall_time
+    );
+    println!(
+        "Virial ratio 2K/|U| at the end {virial_ratio:.2}, largest energy drift {max_drift:.2e}"
+    );
+    assert!(
+        smallest_radius < initial_radius / 2.,
+        "the disc should collapse"
+    );
+    assert!(
+        collapse_time < 2. * free_fall_time,
+        "the disc should collapse within two free-fall times"
+    );
+    assert!(
+        (0.5..1.5).contains(&virial_ratio),
+        "the cluster should settle close to virial equilibrium"
+    );
+    assert!(max_drift < 1e-2, "energy drifted by {max_drift:.2e}");
+}
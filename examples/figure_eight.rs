@@ -0,0 +1,85 @@
+//! Three equal bodies chasing each other along a figure eight, the
+//! periodic orbit found by Moore and by Chenciner and Montgomery, stepped
+//! with a [`Simulation`] on its own.
+//!
+//! Checks that the bodies are back where they started after one period and
+//! that RK4 keeps the energy on the way.
+//!
+//! ```sh
+//! cargo run --example figure_eight
+//! ```
+
+use bevy::math::Vec2;
+use spacesim::integrator::IntegratorKind;
+use spacesim::physics_plugin::{potential_energy, G};
+use spacesim::simulation::{SimBody, Simulation, SimulationConfig};
+
+/// Position of the first body and velocity of the third in units where the
+/// gravitational constant and the masses are 1. The second body mirrors the
+/// first through the origin, the third starts there, and the other two move
+/// with half its velocity the opposite way.
+const POSITION: Vec2 = Vec2::new(0.970_004_4, -0.243_087_5);
+const VELOCITY: Vec2 = Vec2::new(-0.932_407_4, -0.864_731_5);
+/// Period in the same units
+const PERIOD: f32 = 6.325_914;
+
+const MASS: f32 = 100_000_000.;
+/// Length the unit positions are scaled to
+const LENGTH: f32 = 100.;
+const STEPS: usize = 20_000;
+
+fn energy(simulation: &Simulation) -> f32 {
+    let bodies: Vec<(Vec2, f32)> = simulation
+        .bodies
+        .iter()
+        .map(|body| (body.position, body.mass))
+        .collect();
+    let kinetic: f32 = simulation
+        .bodies
+        .iter()
+        .map(|body| 0.5 * body.mass * body.velocity.length_squared())
+        .sum();
+    kinetic + potential_energy(&bodies, 0., simulation.config.g)
+}
+
+fn main() {
+    // With G M and the length scaled, time scales with sqrt(L³ / G M) and
+    // velocities with L over that.
+    let time_scale = (LENGTH.powi(3) / (G * MASS)).sqrt();
+    let speed_scale = LENGTH / time_scale;
+    let start = [POSITION * LENGTH, -POSITION * LENGTH, Vec2::ZERO];
+    let mut simulation = Simulation::new(
+        vec![
+            SimBody::new(start[0], -VELOCITY / 2. * speed_scale, MASS),
+            SimBody::new(start[1], -VELOCITY / 2. * speed_scale, MASS),
+            SimBody::new(start[2], VELOCITY * speed_scale, MASS),
+        ],
+        SimulationConfig {
+            theta_threshold: 0.,
+            integrator: IntegratorKind::Rk4,
+            ..Default::default()
+        },
+    );
+    let period = PERIOD * time_scale;
+    let dt = period / STEPS as f32;
+    let initial_energy = energy(&simulation);
+
+    let mut max_drift = 0f32;
+    for _ in 0..STEPS {
+        simulation.step(dt);
+        let drift = (energy(&simulation) - initial_energy) / initial_energy.abs();
+        max_drift = max_drift.max(drift.abs());
+    }
+
+    let miss = simulation
+        .bodies
+        .iter()
+        .zip(start)
+        .map(|(body, start)| body.position.distance(start))
+        .fold(0., f32::max)
+        / LENGTH;
+    println!("After one period of {period:.3} s the bodies are within {miss:.2e} of their start, relative to the orbit's size");
+    println!("Largest energy drift {max_drift:.2e}");
+    assert!(miss < 1e-3, "bodies missed their start by {miss:.2e}");
+    assert!(max_drift < 1e-4, "energy drifted by {max_drift:.2e}");
+}
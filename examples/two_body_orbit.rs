@@ -0,0 +1,84 @@
+//! Two equal bodies on a circular orbit around their common center, stepped
+//! with a [`Simulation`] on its own, without a window or a Bevy app.
+//!
+//! Checks that one orbit takes as long as Kepler's third law says and that
+//! velocity Verlet keeps the energy over several orbits.
+//!
+//! ```sh
+//! cargo run --example two_body_orbit
+//! ```
+
+use bevy::math::Vec2;
+use spacesim::physics_plugin::{potential_energy, G};
+use spacesim::simulation::{SimBody, Simulation, SimulationConfig};
+
+const MASS: f32 = 100_000_000.;
+const SEPARATION: f32 = 200.;
+const DT: f32 = 0.01;
+const ORBITS: usize = 3;
+
+/// Kinetic plus potential energy of the bodies, the potential summed over
+/// all the pairs.
+fn energy(simulation: &Simulation) -> f32 {
+    let bodies: Vec<(Vec2, f32)> = simulation
+        .bodies
+        .iter()
+        .map(|body| (body.position, body.mass))
+        .collect();
+    let kinetic: f32 = simulation
+        .bodies
+        .iter()
+        .map(|body| 0.5 * body.mass * body.velocity.length_squared())
+        .sum();
+    kinetic + potential_energy(&bodies, 0., simulation.config.g)
+}
+
+fn main() {
+    // Each body circles the center at half the separation with half the
+    // relative speed.
+    let relative_speed = (G * 2. * MASS / SEPARATION).sqrt();
+    let offset = Vec2::new(SEPARATION / 2., 0.);
+    let velocity = Vec2::new(0., relative_speed / 2.);
+    let mut simulation = Simulation::new(
+        vec![
+            SimBody::new(offset, velocity, MASS),
+            SimBody::new(-offset, -velocity, MASS),
+        ],
+        SimulationConfig {
+            // Opens every node, so the gravity is summed exactly.
+            theta_threshold: 0.,
+            ..Default::default()
+        },
+    );
+    let expected_period = std::f32::consts::TAU * (SEPARATION.powi(3) / (G * 2. * MASS)).sqrt();
+    let initial_energy = energy(&simulation);
+
+    // The period is the time the line between the bodies takes to turn all
+    // the way around, interpolated within the step it completes the turn.
+    let mut angle = 0.;
+    let mut direction = offset.normalize();
+    let mut period = None;
+    let mut max_drift = 0f32;
+    let steps = (ORBITS as f32 * expected_period / DT).ceil() as usize;
+    for step in 1..=steps {
+        simulation.step(DT);
+        let next = (simulation.bodies[0].position - simulation.bodies[1].position).normalize();
+        // Accurate for the tiny angle of a single step, unlike `acos`.
+        let turned = direction.perp_dot(next).atan2(direction.dot(next));
+        if period.is_none() && angle + turned >= std::f32::consts::TAU {
+            let fraction = (std::f32::consts::TAU - angle) / turned;
+            period = Some((step as f32 - 1. + fraction) * DT);
+        }
+        angle += turned;
+        direction = next;
+        let drift = (energy(&simulation) - initial_energy) / initial_energy.abs();
+        max_drift = max_drift.max(drift.abs());
+    }
+
+    let period = period.expect("the bodies should complete an orbit");
+    let period_error = (period - expected_period).abs() / expected_period;
+    println!("Period {period:.3} s, Kepler's third law gives {expected_period:.3} s");
+    println!("Period error {period_error:.2e}, largest energy drift {max_drift:.2e}");
+    assert!(period_error < 1e-3, "period off by {period_error:.2e}");
+    assert!(max_drift < 1e-4, "energy drifted by {max_drift:.2e}");
+}
@@ -0,0 +1,145 @@
+# Mission objectives
+objective-reach-orbit = Reach an orbit of radius { $radius }
+objective-rendezvous = Get within { $distance } of the target
+objective-survive = Survive for { $duration } s
+objective-done = done
+objective-failed = failed
+objective-progress = { $percent } %
+
+# Lesson cards
+lesson-step = Step { $current }/{ $total }
+lesson-try = Try:
+lesson-continue = (N to continue)
+
+# Help overlay
+help-title = Controls
+mode-on = on
+mode-off = off
+action-toggle-help = Show or hide this help
+action-toggle-probe = Turn the Barnes-Hut probe on or off
+action-place-probe = Place the probe
+action-toggle-comparison = Start or stop the ghost comparison
+action-cycle-palette = Switch the color palette
+action-next-lesson-step = Next lesson step
+action-toggle-pause = Pause or resume the simulation
+action-toggle-editing = Enter or leave editing
+action-restart = Restart the scenario
+action-cycle-streamlines = Show streamlines of the acceleration, velocity, or none
+action-toggle-contours = Show or hide contours of the potential
+action-toggle-timings = Show or hide the physics timings
+action-toggle-photo-mode = Enter or leave photo mode
+action-capture-photo = Capture a photo (photo mode)
+action-pan-up = Move the camera up (photo mode)
+action-pan-down = Move the camera down (photo mode)
+action-pan-left = Move the camera left (photo mode)
+action-pan-right = Move the camera right (photo mode)
+action-zoom-in = Zoom in (photo mode)
+action-zoom-out = Zoom out (photo mode)
+action-bloom-up = More bloom (photo mode)
+action-bloom-down = Less bloom (photo mode)
+action-exposure-up = Brighter (photo mode)
+action-exposure-down = Darker (photo mode)
+action-export-frame = Save the frame at high resolution
+action-toggle-long-exposure = Start or stop the long exposure
+action-save-long-exposure = Save the long exposure
+action-cycle-quality = Next quality preset
+action-inspect-body = Inspect the forces on a body
+action-toggle-tidal-stress = Show or hide the tidal stress on the bodies
+action-toggle-camera-recording = Start recording the camera path, or stop and save it
+action-toggle-reference-frame = Keep the view on the body holding most of the mass
+action-cycle-cost-heatmap = Color the view by the cost of the force calculation: interactions, time or off
+action-follow-body = Follow a body with the camera
+action-release-camera = Stop following the body
+action-toggle-starfield = Show stars in the background
+action-cycle-measure-tool = Measure distances, angles or the mass in a region, or stop measuring
+action-place-measure-point = Place a point of the measurement
+action-toggle-tree-overlay = Show the cells of the gravity tree
+action-add-bookmark = Bookmark the current moment, then type a note and press Enter
+action-jump-to-bookmark = Jump to the next bookmark
+action-toggle-trails = Draw the recent path of every body
+action-toggle-orbit-prediction = Draw the predicted path of the inspected body
+action-toggle-massive = Show or hide the bodies with mass
+action-toggle-massless = Show or hide the massless bodies
+action-toggle-tracers = Show or hide the tracers
+action-cycle-solo = Show only the bodies with mass, the massless ones, the tracers, or all
+action-export-tree = Save the gravity tree as JSON and SVG
+action-fit-all = Zoom to fit all the bodies
+action-fit-selection = Zoom to the inspected body
+action-place-body = Place a body while editing, drag for its velocity and scroll for its mass
+action-delete-body = Delete the inspected body
+action-clear-bodies = Delete all the bodies and start over from an empty simulation
+action-undock = Undock the parts docked to the inspected body
+
+# Main menu
+menu-title = Choose a scenario
+
+# Built-in scenarios
+scenario-random-disc = Random disc
+scenario-random-disc-description = 2000 bodies circling a heavy central body
+scenario-stable-disc = Stable disc
+scenario-stable-disc-description = 2000 bodies on the orbits the gravity of the whole disc calls for
+scenario-halo = Disc in a halo
+scenario-halo-description = 500 bodies circling a heavy central body inside a halo of 20000 background bodies
+scenario-solar-system = Solar system
+scenario-solar-system-description = The sun and its planets, the distances compressed to fit the view
+scenario-binary-star = Binary star
+scenario-binary-star-description = Two stars circling each other inside a disc
+scenario-galaxy-disc = Galaxy disc
+scenario-galaxy-disc-description = A bulge in an exponential disc on its circular orbits
+
+# Physics timings
+timings-tree-build = Tree build: { $ms } ms
+timings-traversal = Traversal: { $ms } ms
+timings-integration = Integration: { $ms } ms
+timings-collision = Collision: { $ms } ms
+timings-total = Total: { $ms } ms
+timings-sim-rate = Simulated: { $rate } s per second
+timings-sim-rate-behind = Simulated: { $rate } of { $target } s per second, over budget
+timings-watchdog = Gravity of { $count } bodies reused to keep the frame on time
+
+# Encounter timeline
+timeline-encounter = In { $time } s: body { $body } passes { $other } at { $distance }
+timeline-bookmark = { $index }. At { $time } s:
+timeline-bookmark-draft = Note for { $time } s:
+
+# Failed gravity tree builds
+tree-failure-invalid-body = Physics skipped: a body's position or mass is no longer a number
+tree-failure-not-found = Physics skipped: a body to move wasn't in the gravity tree
+
+# Force inspector
+inspector-gravity = Gravity: { $value }
+inspector-drag = Drag: { $value }
+inspector-thrust = Thrust: { $value }
+inspector-tether = Tethers: { $value }
+inspector-custom = Custom forces: { $value }
+inspector-impulse = Impulses: { $value }
+inspector-total = Total: { $value }
+inspector-ephemeris = Off the two-body orbit by: { $value }
+
+# Measurement tools
+measure-ruler-hint = Click two points to measure the distance between them
+measure-angle-hint = Click three points to measure the angle at the second
+measure-region-hint = Drag out a circle to measure the mass inside
+measure-distance = Distance: { $value }
+measure-angle = Angle: { $value }°
+measure-radius = Radius: { $value }
+measure-mass = Mass: { $value }
+measure-density = Average density: { $value }
+
+# Control panel
+panel-physics = Physics
+panel-theta = Theta threshold
+panel-g = Gravitational constant
+panel-softening = Softening length
+panel-time-scale = Time scale
+panel-sim-rate = Simulated seconds per second:
+panel-bodies = Bodies
+panel-body-count = { $count } moving bodies
+panel-disc-count = Bodies of the random disc
+panel-restart = Restart the scenario
+panel-inspected = Inspected body
+panel-mass = Mass: { $value }
+panel-velocity-x = Velocity x: { $value }
+panel-velocity-y = Velocity y: { $value }
+panel-speed = Speed: { $value }
+panel-nothing-inspected = Right click a body to inspect it
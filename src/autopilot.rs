@@ -0,0 +1,212 @@
+use crate::forces::Acceleration;
+use crate::physics_plugin::{Mass, PhysicsSettings, Velocity};
+use bevy::prelude::*;
+
+/// Time in seconds in which the autopilot tries to cancel out a velocity
+/// error, lower values steer more aggressively.
+const RESPONSE_TIME: f32 = 0.5;
+
+/// What the autopilot is trying to achieve.
+#[derive(Debug, Clone, Copy)]
+pub enum AutopilotMode {
+    /// Keep a circular orbit with `radius` around the `center` body.
+    HoldOrbit { center: Entity, radius: f32 },
+    /// Stay at `offset` from the `anchor` body, moving along with it.
+    HoldPosition { anchor: Entity, offset: Vec2 },
+    /// Fly to the `target` body, arriving with its velocity.
+    Intercept { target: Entity },
+}
+
+/// Controller computing thrust for the body it is attached to every step.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Autopilot {
+    /// What the controller is steering towards
+    pub mode: AutopilotMode,
+    /// Largest acceleration the engines can produce
+    pub max_acceleration: f32,
+}
+
+impl Autopilot {
+    /// Velocity the body should have to fulfill the mode, given its own
+    /// position, the position, velocity and mass of the reference body and
+    /// the gravitational constant `g`.
+    fn desired_velocity(
+        &self,
+        position: Vec2,
+        velocity: Vec2,
+        reference_position: Vec2,
+        reference_velocity: Vec2,
+        reference_mass: f32,
+        g: f32,
+    ) -> Vec2 {
+        let offset = position - reference_position;
+        match self.mode {
+            AutopilotMode::HoldOrbit { radius, .. } => {
+                let distance = offset.length();
+                if distance == 0. {
+                    return reference_velocity;
+                }
+                let radial = offset / distance;
+                // Keep orbiting in the direction the body already goes.
+                let tangent = if radial.perp_dot(velocity - reference_velocity) < 0. {
+                    -radial.perp()
+                } else {
+                    radial.perp()
+                };
+                let circular_speed = (g * reference_mass / radius).sqrt();
+                reference_velocity + tangent * circular_speed
+                    - radial * (distance - radius) / RESPONSE_TIME
+            }
+            AutopilotMode::HoldPosition {
+                offset: target_offset,
+                ..
+            } => reference_velocity + (target_offset - offset) / RESPONSE_TIME,
+            AutopilotMode::Intercept { .. } => {
+                // Fastest approach that can still brake in time.
+                let distance = offset.length();
+                let approach_speed = (2. * self.max_acceleration * distance).sqrt();
+                reference_velocity - offset.normalize_or_zero() * approach_speed
+            }
+        }
+    }
+
+    /// The body the mode is relative to.
+    fn reference(&self) -> Entity {
+        match self.mode {
+            AutopilotMode::HoldOrbit { center, .. } => center,
+            AutopilotMode::HoldPosition { anchor, .. } => anchor,
+            AutopilotMode::Intercept { target } => target,
+        }
+    }
+}
+
+/// Thrusts every body with an autopilot towards the velocity its mode asks
+/// for, limited by its maximum acceleration.
+pub fn steer_autopilots(
+    settings: Res<PhysicsSettings>,
+    autopilots: Query<(Entity, &Autopilot)>,
+    masses: Query<&Mass>,
+    mut bodies: Query<(&Transform, &Velocity, &mut Acceleration)>,
+) {
+    for (entity, autopilot) in &autopilots {
+        let reference = autopilot.reference();
+        let Ok((reference_transform, reference_velocity, _)) = bodies.get(reference) else {
+            continue;
+        };
+        let reference_position = reference_transform.translation.xy();
+        let reference_velocity = reference_velocity.0;
+        let reference_mass = masses.get(reference).map_or(0., |mass| mass.0);
+
+        let Ok((transform, velocity, mut acceleration)) = bodies.get_mut(entity) else {
+            continue;
+        };
+        let desired = autopilot.desired_velocity(
+            transform.translation.xy(),
+            velocity.0,
+            reference_position,
+            reference_velocity,
+            reference_mass,
+            settings.g,
+        );
+        acceleration.thrust =
+            ((desired - velocity.0) / RESPONSE_TIME).clamp_length_max(autopilot.max_acceleration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const G: f32 = 1.;
+    const MASS: f32 = 1000.;
+    const RADIUS: f32 = 40.;
+    const DT: f32 = 1. / 60.;
+
+    fn autopilot(mode: AutopilotMode) -> Autopilot {
+        Autopilot {
+            mode,
+            max_acceleration: 20.,
+        }
+    }
+
+    /// Position and velocity of a body flown by `autopilot` for `duration`
+    /// from `position` and `velocity`, around a reference body resting at
+    /// the origin whose gravity it feels when `gravity` is set.
+    fn fly(
+        autopilot: Autopilot,
+        mut position: Vec2,
+        mut velocity: Vec2,
+        gravity: bool,
+        duration: f32,
+    ) -> (Vec2, Vec2) {
+        for _ in 0..(duration / DT) as usize {
+            let desired =
+                autopilot.desired_velocity(position, velocity, Vec2::ZERO, Vec2::ZERO, MASS, G);
+            let mut acceleration =
+                ((desired - velocity) / RESPONSE_TIME).clamp_length_max(autopilot.max_acceleration);
+            if gravity {
+                acceleration -= position.normalize() * G * MASS / position.length_squared();
+            }
+            velocity += acceleration * DT;
+            position += velocity * DT;
+        }
+        (position, velocity)
+    }
+
+    #[test]
+    fn circular_orbit_needs_no_thrust() {
+        let hold = autopilot(AutopilotMode::HoldOrbit {
+            center: Entity::PLACEHOLDER,
+            radius: RADIUS,
+        });
+        let speed = (G * MASS / RADIUS).sqrt();
+        for (position, velocity) in [
+            (Vec2::new(RADIUS, 0.), Vec2::new(0., speed)),
+            (Vec2::new(0., -RADIUS), Vec2::new(-speed, 0.)),
+        ] {
+            let desired =
+                hold.desired_velocity(position, velocity, Vec2::ZERO, Vec2::ZERO, MASS, G);
+            assert!(desired.distance(velocity) < 1e-4, "{desired} {velocity}");
+        }
+    }
+
+    #[test]
+    fn hold_orbit_settles_on_the_radius() {
+        let hold = autopilot(AutopilotMode::HoldOrbit {
+            center: Entity::PLACEHOLDER,
+            radius: RADIUS,
+        });
+        let speed = (G * MASS / RADIUS).sqrt();
+        // Too far out and too slow
+        let (position, velocity) = fly(
+            hold,
+            Vec2::new(1.5 * RADIUS, 0.),
+            Vec2::new(0., 0.5 * speed),
+            true,
+            10.,
+        );
+        assert!((position.length() - RADIUS).abs() < 0.5, "{position}");
+        assert!(
+            (velocity.length() - speed).abs() < speed * 0.05,
+            "{velocity}"
+        );
+        assert!(position.dot(velocity).abs() < 0.05 * RADIUS * speed);
+    }
+
+    #[test]
+    fn intercept_closes_in_on_the_target() {
+        let intercept = autopilot(AutopilotMode::Intercept {
+            target: Entity::PLACEHOLDER,
+        });
+        let start = Vec2::new(-60., 80.);
+        let mut distance = start.length();
+        for duration in [1., 2., 4.] {
+            let (position, _) = fly(intercept, start, Vec2::new(5., 0.), false, duration);
+            assert!(position.length() < distance, "{duration}: {position}");
+            distance = position.length();
+        }
+        let (position, velocity) = fly(intercept, start, Vec2::new(5., 0.), false, 15.);
+        assert!(position.length() < 1., "{position}");
+        assert!(velocity.length() < 2., "{velocity}");
+    }
+}
@@ -0,0 +1,106 @@
+use crate::physics_plugin::{build_fitted_tree, BodyMaterial, Mass, PhysicsSettings, Velocity};
+use crate::radius::BodyDensity;
+use crate::scenario::SimRng;
+use crate::theme::Theme;
+use bevy::prelude::*;
+use rand::Rng;
+
+/// Marks a body of a background population. Background bodies feel the
+/// gravity of everything like any other body, but only attract others
+/// through a fixed number of super-particles the whole population of a
+/// world is merged into, so there can be far more of them.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Background;
+
+/// Number of super-particles every world's background population is merged
+/// into for the force calculation.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct BackgroundAggregation {
+    pub super_particles: usize,
+}
+
+impl Default for BackgroundAggregation {
+    fn default() -> Self {
+        BackgroundAggregation {
+            super_particles: 256,
+        }
+    }
+}
+
+/// Merges the `bodies`, given by their positions and masses, into about
+/// `count` super-particles: the internal nodes of a tree built from them.
+pub fn super_particles(bodies: &[(Vec2, f32)], count: usize) -> Vec<(Vec2, f32)> {
+    if bodies.is_empty() {
+        return Vec::new();
+    }
+    build_fitted_tree(bodies)
+        .aggregate(count)
+        .into_iter()
+        .map(|node| (node.center_of_mass, node.mass))
+        .collect()
+}
+
+/// A heavy body with a disc of bodies around it, inside a much larger halo
+/// of background bodies.
+pub fn spawn_halo(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+    settings: Res<PhysicsSettings>,
+    mut rng: ResMut<SimRng>,
+) {
+    let rng = &mut rng.rng;
+    let circle = meshes.add(Circle::new(1.));
+    let material = materials.add(ColorMaterial::from(theme.body()));
+    let halo_material = materials.add(ColorMaterial::from(theme.outline()));
+    commands.insert_resource(BodyMaterial(material.clone()));
+
+    let central_mass = 100_000_000_000.;
+    commands.spawn((
+        Velocity(Vec2::ZERO),
+        Mass(central_mass),
+        BodyDensity::sized(central_mass, 30.),
+        Mesh2d(circle.clone()),
+        MeshMaterial2d(material.clone()),
+        Transform::default(),
+    ));
+
+    let disc_mass = 10_000_000.;
+    for _ in 0..500 {
+        let radius = rng.random_range(80.0..250.0);
+        let dir = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU));
+        let speed = (settings.g * central_mass / radius).sqrt();
+        commands.spawn((
+            Velocity(dir.perp() * speed),
+            Mass(disc_mass),
+            Mesh2d(circle.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::from_translation((dir * radius).extend(0.)),
+        ));
+    }
+
+    let halo_mass = 1_000_000.;
+    for _ in 0..20_000 {
+        let radius = rng.random_range(50.0..600.0);
+        let dir = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU));
+        // Circular speed in a random direction, the halo doesn't rotate as
+        // a whole.
+        let speed = (settings.g * central_mass / radius).sqrt();
+        let velocity = if rng.random_bool(0.5) {
+            dir.perp()
+        } else {
+            -dir.perp()
+        } * speed;
+        commands.spawn((
+            Background,
+            Velocity(velocity),
+            Mass(halo_mass),
+            // Small enough to stay in the background
+            BodyDensity::sized(halo_mass, 1.),
+            Mesh2d(circle.clone()),
+            MeshMaterial2d(halo_material.clone()),
+            Transform::from_translation((dir * radius).extend(-1.)),
+        ));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::background::Background;
+use crate::body_count::Tracer;
+use crate::physics_plugin::{Mass, Velocity};
+use crate::populations::Population;
+use crate::radius::Radius;
+use crate::worlds::SimWorld;
+use bevy::ecs::query::QueryItem;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+/// A body with its physics data, as [`Bodies`] reads it.
+#[derive(Debug, Clone, Copy)]
+pub struct Body {
+    pub entity: Entity,
+    pub position: Vec2,
+    /// Zero for the bodies which don't move
+    pub velocity: Vec2,
+    /// Zero for the massless bodies, which are attracted without attracting
+    pub mass: f32,
+    pub radius: f32,
+    pub world: SimWorld,
+    pub population: Population,
+    /// Merged with the other background bodies for the gravity, see
+    /// [`Background`]
+    pub background: bool,
+    /// Whether the body moves, bodies without a [`Velocity`] stay in place
+    pub moving: bool,
+}
+
+type BodyData = (
+    Entity,
+    &'static Transform,
+    Option<&'static Mass>,
+    Option<&'static Velocity>,
+    Option<&'static Radius>,
+    Option<&'static SimWorld>,
+    Has<Tracer>,
+    Has<Background>,
+);
+
+type IsBody = Or<(With<Mass>, With<Velocity>)>;
+
+/// Every body the physics simulates, read-only, with whichever of its
+/// components it has filled in. Bodies are whatever has a [`Mass`] or a
+/// [`Velocity`], the parts docked to a composite aren't bodies of their
+/// own.
+///
+/// For code that only looks at the bodies, so it doesn't have to write a
+/// query that misses some of them, e.g. the ones without a velocity.
+#[derive(SystemParam)]
+pub struct Bodies<'w, 's> {
+    query: Query<'w, 's, BodyData, IsBody>,
+}
+
+fn body(
+    (entity, transform, mass, velocity, radius, world, tracer, background): QueryItem<BodyData>,
+) -> Body {
+    Body {
+        entity,
+        position: transform.translation.xy(),
+        velocity: velocity.map_or(Vec2::ZERO, |velocity| velocity.0),
+        mass: mass.map_or(0., |mass| mass.0),
+        radius: radius.map_or(0., |radius| radius.0),
+        world: world.copied().unwrap_or_default(),
+        population: Population::of(mass.is_some(), tracer),
+        background,
+        moving: velocity.is_some(),
+    }
+}
+
+impl Bodies<'_, '_> {
+    pub fn iter(&self) -> impl Iterator<Item = Body> + '_ {
+        self.query.iter().map(body)
+    }
+
+    /// The body of the `entity`, `None` when it isn't a body.
+    pub fn get(&self, entity: Entity) -> Option<Body> {
+        self.query.get(entity).ok().map(body)
+    }
+
+    pub fn count(&self) -> usize {
+        self.query.iter().count()
+    }
+}
@@ -0,0 +1,115 @@
+use crate::physics_plugin::{BodyMaterial, Mass, Velocity};
+use crate::scenario::{ScenarioEntity, SimRng};
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+use rand::Rng;
+
+/// Weight of the latest frame in the smoothed frame time.
+const SMOOTHING: f32 = 0.1;
+/// Frame times within this fraction of the target leave the count alone.
+const TOLERANCE: f32 = 0.1;
+
+/// Massless body added by the [`BodyCountController`], attracted by the
+/// others without attracting them.
+#[derive(Component)]
+pub struct Tracer;
+
+/// Keeps the frame time near `target_frame_time` by spawning tracers while
+/// there is time to spare and despawning them when frames take too long,
+/// so the simulation looks as rich as the machine allows.
+#[derive(Resource, Debug)]
+pub struct BodyCountController {
+    /// Frame time to aim for in seconds
+    pub target_frame_time: f32,
+    /// Tracers spawned or despawned per adjustment
+    pub step: usize,
+    pub max_tracers: usize,
+    /// Time between adjustments, so the frame time can settle
+    timer: Timer,
+    smoothed_frame_time: Option<f32>,
+    mesh: Option<Handle<Mesh>>,
+}
+
+impl BodyCountController {
+    /// Controller aiming for `fps` frames per second.
+    pub fn new(fps: f32) -> Self {
+        BodyCountController {
+            target_frame_time: 1. / fps,
+            step: 50,
+            max_tracers: 20_000,
+            timer: Timer::from_seconds(0.5, TimerMode::Repeating),
+            smoothed_frame_time: None,
+            mesh: None,
+        }
+    }
+}
+
+/// Spawns or despawns tracers depending on the smoothed frame time. New
+/// tracers are placed next to a random body with mass, moving along with
+/// it, so they follow the structure of the scenario.
+#[allow(clippy::too_many_arguments)]
+pub fn scale_body_count(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut controller: ResMut<BodyCountController>,
+    mut rng: ResMut<SimRng>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    material: Option<Res<BodyMaterial>>,
+    tracers: Query<Entity, With<Tracer>>,
+    bodies: Query<(&Transform, &Velocity, Option<&SimWorld>), With<Mass>>,
+) {
+    let frame_time = time.delta_secs();
+    let smoothed = controller
+        .smoothed_frame_time
+        .map_or(frame_time, |smoothed| {
+            smoothed + (frame_time - smoothed) * SMOOTHING
+        });
+    controller.smoothed_frame_time = Some(smoothed);
+    if !controller.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let count = tracers.iter().count();
+    if smoothed > controller.target_frame_time * (1. + TOLERANCE) {
+        for entity in tracers.iter().take(controller.step) {
+            commands.entity(entity).despawn();
+        }
+        return;
+    }
+    if smoothed > controller.target_frame_time * (1. - TOLERANCE) {
+        return;
+    }
+
+    let anchors: Vec<_> = bodies.iter().collect();
+    let (Some(material), false) = (material, anchors.is_empty()) else {
+        return;
+    };
+    let mesh = controller
+        .mesh
+        .get_or_insert_with(|| meshes.add(Circle::new(1.)))
+        .clone();
+    let rng = &mut rng.rng;
+    for _ in 0..controller
+        .step
+        .min(controller.max_tracers.saturating_sub(count))
+    {
+        let (transform, velocity, world) = anchors[rng.random_range(0..anchors.len())];
+        let offset = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU))
+            * (transform.scale.x + rng.random_range(1.0..10.0));
+        let mut tracer = commands.spawn((
+            Tracer,
+            ScenarioEntity,
+            Velocity(velocity.0),
+            Mesh2d(mesh.clone()),
+            MeshMaterial2d(material.0.clone()),
+            Transform {
+                translation: (transform.translation.xy() + offset).extend(0.),
+                scale: Vec3::new(1.5, 1.5, 1.),
+                ..Default::default()
+            },
+        ));
+        if let Some(world) = world {
+            tracer.insert(*world);
+        }
+    }
+}
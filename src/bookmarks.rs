@@ -0,0 +1,295 @@
+use crate::comparison::AccuracyComparison;
+use crate::docking::Dockable;
+use crate::input::{Action, Actions, InputMap};
+use crate::mission::Mission;
+use crate::physics_plugin::{BodyMaterial, Mass, Velocity};
+use crate::radius::{BodyDensity, Radius};
+use crate::scenario::ScenarioEntity;
+use crate::scenario_file::BodySpec;
+use crate::state::{restart_time, SimState};
+use crate::theme::Theme;
+use crate::worlds::SimWorld;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::{ButtonState, InputSystem};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A body as it was when a [`Bookmark`] was taken.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookmarkedBody {
+    #[serde(default)]
+    pub world: u32,
+    pub body: BodySpec,
+}
+
+/// The bodies of the scenario at some simulated time, with a note.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub time: f32,
+    pub note: String,
+    pub bodies: Vec<BookmarkedBody>,
+}
+
+/// What a bookmarks file holds.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BookmarkFile {
+    #[serde(default)]
+    bookmarks: Vec<Bookmark>,
+}
+
+/// Bookmarks of the session, taken with Y, and jumped to one after the
+/// other with J (with the default input map). Pressing the bookmark key
+/// snapshots the bodies right away, the note is typed afterwards and taken
+/// with Enter, Escape drops the bookmark.
+///
+/// Only the bodies are restored when jumping, with their positions,
+/// velocities, masses, radii, colors and whether they are dockable.
+/// Tethers, autopilots and docked parts are not part of a bookmark.
+#[derive(Resource, Debug, Default)]
+pub struct Bookmarks {
+    pub entries: Vec<Bookmark>,
+    /// File the bookmarks are saved to whenever one is added, they are
+    /// only kept for the session without one
+    pub path: Option<PathBuf>,
+    /// Index of the bookmark jumped to last
+    pub current: Option<usize>,
+    /// Bookmark whose note is being typed
+    draft: Option<Bookmark>,
+    /// Simulated time the current run started at, that of the bookmark
+    /// after jumping to one
+    time_offset: f32,
+}
+
+impl Bookmarks {
+    /// The bookmarks of `path`, none when there is no such file yet, with
+    /// new ones saved to it.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let entries = match std::fs::read_to_string(path) {
+            Ok(source) => {
+                let file: BookmarkFile = toml::from_str(&source).map_err(|err| err.to_string())?;
+                file.bookmarks
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.to_string()),
+        };
+        Ok(Bookmarks {
+            entries,
+            path: Some(path.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    fn save(&self, path: &Path) -> std::io::Result<()> {
+        let file = BookmarkFile {
+            bookmarks: self.entries.clone(),
+        };
+        let source = toml::to_string(&file).map_err(std::io::Error::other)?;
+        std::fs::write(path, source)
+    }
+
+    /// The bookmark whose note is being typed.
+    pub fn draft(&self) -> Option<&Bookmark> {
+        self.draft.as_ref()
+    }
+
+    /// Simulated time of the current run, counted from the start of the
+    /// scenario across jumps.
+    pub fn simulated_time(&self, time: &Time<Virtual>) -> f32 {
+        self.time_offset + time.elapsed_secs()
+    }
+}
+
+type BookmarkedQuery<'a> = (
+    &'a Transform,
+    &'a Velocity,
+    &'a Mass,
+    Option<&'a Radius>,
+    Option<&'a SimWorld>,
+    &'a MeshMaterial2d<ColorMaterial>,
+    Option<&'a Dockable>,
+);
+
+/// Snapshots the bodies of the scenario into a new draft bookmark.
+fn take_bookmark(
+    actions: Actions,
+    time: Res<Time<Virtual>>,
+    body_material: Option<Res<BodyMaterial>>,
+    materials: Res<Assets<ColorMaterial>>,
+    mut bookmarks: ResMut<Bookmarks>,
+    bodies: Query<BookmarkedQuery, (With<ScenarioEntity>, Without<Parent>)>,
+) {
+    if !actions.just_pressed(Action::AddBookmark) || bookmarks.draft.is_some() {
+        return;
+    }
+    let bodies = bodies
+        .iter()
+        .map(
+            |(transform, velocity, mass, radius, world, material, dockable)| {
+                // Bodies in the shared material take the theme's color again.
+                let color = (body_material.as_ref().map(|m| &m.0) != Some(&material.0))
+                    .then(|| materials.get(&material.0))
+                    .flatten()
+                    .map(|material| material.color.to_srgba().to_f32_array_no_alpha());
+                BookmarkedBody {
+                    world: world.map_or(0, |world| world.0),
+                    body: BodySpec {
+                        name: None,
+                        position: transform.translation.xy(),
+                        velocity: velocity.0,
+                        mass: mass.0,
+                        orbit: None,
+                        radius: radius.map(|radius| radius.0).filter(|&radius| radius > 0.),
+                        color,
+                        dockable: dockable.copied(),
+                        autopilot: None,
+                    },
+                }
+            },
+        )
+        .collect();
+    bookmarks.draft = Some(Bookmark {
+        time: bookmarks.simulated_time(&time),
+        note: String::new(),
+        bodies,
+    });
+}
+
+/// Types the note of the draft bookmark. The keys typed don't trigger
+/// their actions meanwhile.
+fn type_bookmark_note(
+    mut keyboard: EventReader<KeyboardInput>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut bookmarks: ResMut<Bookmarks>,
+) {
+    // Read every frame, so keys pressed before typing don't end up in the
+    // note.
+    let events: Vec<&KeyboardInput> = keyboard
+        .read()
+        .filter(|event| event.state == ButtonState::Pressed)
+        .collect();
+    let Some(draft) = bookmarks.draft.as_mut() else {
+        return;
+    };
+    keys.reset_all();
+
+    for event in events {
+        match &event.logical_key {
+            Key::Character(text) => draft.note.push_str(text),
+            Key::Space => draft.note.push(' '),
+            Key::Backspace => {
+                draft.note.pop();
+            }
+            Key::Escape => {
+                bookmarks.draft = None;
+                return;
+            }
+            Key::Enter => {
+                let bookmark = bookmarks.draft.take().expect("the draft is being typed");
+                bookmarks.entries.push(bookmark);
+                if let Some(path) = &bookmarks.path {
+                    if let Err(err) = bookmarks.save(path) {
+                        error!("Couldn't save the bookmarks to `{}`: {err}", path.display());
+                    }
+                }
+                return;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replaces the bodies of the scenario with those of the bookmark after
+/// the one jumped to last, and continues simulating from its time.
+///
+/// Like a restart, whatever refers to the replaced bodies is reset.
+#[allow(clippy::too_many_arguments)]
+fn jump_to_bookmark(
+    actions: Actions,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+    mut time: ResMut<Time<Virtual>>,
+    mut bookmarks: ResMut<Bookmarks>,
+    comparison: Option<ResMut<AccuracyComparison>>,
+    entities: Query<Entity, (With<ScenarioEntity>, Without<Parent>)>,
+) {
+    if !actions.just_pressed(Action::JumpToBookmark) || bookmarks.entries.is_empty() {
+        return;
+    }
+    let index = bookmarks
+        .current
+        .map_or(0, |current| (current + 1) % bookmarks.entries.len());
+    let bookmark = &bookmarks.entries[index];
+
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    let circle = meshes.add(Circle::new(1.));
+    let material = materials.add(ColorMaterial::from(theme.body()));
+    commands.insert_resource(BodyMaterial(material.clone()));
+    let mut colored: HashMap<[u32; 3], Handle<ColorMaterial>> = HashMap::default();
+    let several_worlds = bookmark.bodies.iter().any(|body| body.world > 0);
+    for BookmarkedBody { world, body } in &bookmark.bodies {
+        let material = match body.color {
+            Some(color) => colored
+                .entry(color.map(f32::to_bits))
+                .or_insert_with(|| materials.add(Color::srgb(color[0], color[1], color[2])))
+                .clone(),
+            None => material.clone(),
+        };
+        let mut entity = commands.spawn((
+            ScenarioEntity,
+            Velocity(body.velocity),
+            Mass(body.mass),
+            Mesh2d(circle.clone()),
+            MeshMaterial2d(material),
+            Transform::from_translation(body.position.extend(0.)),
+        ));
+        if let Some(radius) = body.radius {
+            entity.insert(BodyDensity::sized(body.mass, radius));
+        }
+        if let Some(dockable) = body.dockable {
+            entity.insert(dockable);
+        }
+        if several_worlds {
+            entity.insert(SimWorld(*world));
+        }
+    }
+
+    restart_time(&mut time);
+    bookmarks.time_offset = bookmark.time;
+    bookmarks.current = Some(index);
+    if let Some(mut comparison) = comparison {
+        comparison.stop();
+    }
+    commands.remove_resource::<Mission>();
+}
+
+fn clear_current_bookmark(mut bookmarks: ResMut<Bookmarks>) {
+    bookmarks.current = None;
+    bookmarks.draft = None;
+    bookmarks.time_offset = 0.;
+}
+
+/// Takes the [`Bookmarks`] and jumps between them. They are listed in the
+/// timeline of the [`EncounterPlugin`](crate::encounters::EncounterPlugin).
+pub struct BookmarkPlugin;
+
+impl Plugin for BookmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Bookmarks>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .add_systems(OnEnter(SimState::Loading), clear_current_bookmark)
+            .add_systems(PreUpdate, type_bookmark_note.after(InputSystem))
+            .add_systems(
+                Update,
+                (take_bookmark, jump_to_bookmark)
+                    .chain()
+                    .run_if(not(in_state(SimState::Menu)).and(not(in_state(SimState::Loading)))),
+            );
+    }
+}
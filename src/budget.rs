@@ -0,0 +1,140 @@
+use crate::physics_plugin::{GravityTrees, Mass, Velocity};
+use crate::quadtree::Node;
+use bevy::prelude::*;
+
+/// Rough memory a body takes besides its tree nodes, its entity with the
+/// components, render data included.
+const BODY_BYTES: usize = 512;
+
+/// What happens to the bodies when the [`Budget`] is exceeded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OverBudget {
+    /// Despawn the bodies that were just spawned and don't fit, keeping the
+    /// ones that were there before
+    #[default]
+    RefuseSpawns,
+    /// Despawn the lightest bodies, new or not, until the rest fits
+    DropLightest,
+}
+
+/// The cap which was hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetLimit {
+    Bodies,
+    TreeNodes,
+    Memory,
+}
+
+/// Sent when bodies were despawned to stay within the [`Budget`].
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BudgetExceeded {
+    pub limit: BudgetLimit,
+    pub removed: usize,
+}
+
+/// When present, hard caps on the size of the simulation, so scenarios
+/// spawning bodies without end can't run the process out of memory.
+///
+/// The tree nodes and memory can't be known before the tree is built, they
+/// are estimated from how many nodes a body took in the last tree.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Budget {
+    pub max_bodies: usize,
+    pub max_tree_nodes: usize,
+    /// Estimated memory of the bodies and their tree nodes in bytes
+    pub max_memory: usize,
+    pub on_exceeded: OverBudget,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget {
+            max_bodies: 100_000,
+            max_tree_nodes: 1_000_000,
+            max_memory: 512 * 1024 * 1024,
+            on_exceeded: OverBudget::RefuseSpawns,
+        }
+    }
+}
+
+impl Budget {
+    pub fn with_max_bodies(mut self, max_bodies: usize) -> Self {
+        self.max_bodies = max_bodies;
+        self
+    }
+
+    pub fn with_on_exceeded(mut self, on_exceeded: OverBudget) -> Self {
+        self.on_exceeded = on_exceeded;
+        self
+    }
+
+    /// How many bodies fit, given how many tree nodes a body takes, and the
+    /// cap limiting it the most.
+    fn allowed_bodies(&self, nodes_per_body: f32) -> (usize, BudgetLimit) {
+        let by_nodes = (self.max_tree_nodes as f32 / nodes_per_body) as usize;
+        let body_memory = BODY_BYTES as f32 + nodes_per_body * size_of::<Node>() as f32;
+        let by_memory = (self.max_memory as f32 / body_memory) as usize;
+        [
+            (self.max_bodies, BudgetLimit::Bodies),
+            (by_nodes, BudgetLimit::TreeNodes),
+            (by_memory, BudgetLimit::Memory),
+        ]
+        .into_iter()
+        .min_by_key(|(allowed, _)| *allowed)
+        .unwrap_or((self.max_bodies, BudgetLimit::Bodies))
+    }
+}
+
+/// Despawns the bodies over the [`Budget`] the way it asks for, logging and
+/// sending a [`BudgetExceeded`] when it does.
+pub fn enforce_budget(
+    mut commands: Commands,
+    budget: Res<Budget>,
+    trees: Res<GravityTrees>,
+    mut exceeded: EventWriter<BudgetExceeded>,
+    bodies: Query<(Entity, Ref<Velocity>, Option<&Mass>)>,
+) {
+    let count = bodies.iter().count();
+    // A tree takes a bit under two nodes a body, until one was built assume
+    // the worst of it.
+    let nodes_per_body = if count > 0 && trees.node_count() > 0 {
+        trees.node_count() as f32 / count as f32
+    } else {
+        2.
+    };
+    let (allowed, limit) = budget.allowed_bodies(nodes_per_body);
+    if count <= allowed {
+        return;
+    }
+
+    let mut candidates: Vec<(Entity, f32)> = match budget.on_exceeded {
+        OverBudget::RefuseSpawns => bodies
+            .iter()
+            .filter(|(_, velocity, _)| velocity.is_added())
+            .map(|(entity, _, _)| (entity, 0.))
+            .collect(),
+        OverBudget::DropLightest => bodies
+            .iter()
+            .map(|(entity, _, mass)| (entity, mass.map_or(0., |mass| mass.0)))
+            .collect(),
+    };
+    // Newest spawns go first, or the lightest bodies, newest first among
+    // equals.
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)));
+    candidates.truncate(count - allowed);
+    if candidates.is_empty() {
+        return;
+    }
+
+    for &(entity, _) in &candidates {
+        commands.entity(entity).despawn_recursive();
+    }
+    warn!(
+        "Over the {limit:?} budget with {count} bodies where {allowed} fit, despawned {}",
+        candidates.len()
+    );
+    exceeded.send(BudgetExceeded {
+        limit,
+        removed: candidates.len(),
+    });
+}
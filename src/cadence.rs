@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+
+/// Part of the simulation which runs once every so many physics steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subsystem {
+    /// The gravity, skipped steps integrate the gravity of the last step
+    /// which computed it
+    Forces,
+    /// Finding the touching bodies, which are then merged or bounced
+    Collisions,
+    /// The [`SimStatistics`](crate::statistics::SimStatistics)
+    Statistics,
+    /// The [`CostHeatmap`](crate::cost_heatmap::CostHeatmap)
+    CostHeatmap,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 4] = [
+        Subsystem::Forces,
+        Subsystem::Collisions,
+        Subsystem::Statistics,
+        Subsystem::CostHeatmap,
+    ];
+
+    /// Name of the subsystem in `--cadence`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Subsystem::Forces => "forces",
+            Subsystem::Collisions => "collisions",
+            Subsystem::Statistics => "statistics",
+            Subsystem::CostHeatmap => "heatmap",
+        }
+    }
+}
+
+/// How many physics steps pass between the runs of each [`Subsystem`], so
+/// the expensive analyses don't have to run as often as the integration.
+///
+/// Skipping the forces only works for the integrators evaluating them once
+/// a step, RK4 still computes them every step.
+#[derive(Resource, Debug, Clone)]
+pub struct Cadences {
+    pub forces: u32,
+    pub collisions: u32,
+    pub statistics: u32,
+    pub cost_heatmap: u32,
+    /// Physics steps run so far
+    steps: u64,
+}
+
+impl Default for Cadences {
+    fn default() -> Self {
+        Cadences {
+            forces: 1,
+            collisions: 1,
+            // A tenth and half of a second at the default tick rate.
+            statistics: 6,
+            cost_heatmap: 30,
+            steps: 0,
+        }
+    }
+}
+
+impl Cadences {
+    /// Steps between the runs of the `subsystem`, at least 1.
+    pub fn every(&self, subsystem: Subsystem) -> u32 {
+        match subsystem {
+            Subsystem::Forces => self.forces,
+            Subsystem::Collisions => self.collisions,
+            Subsystem::Statistics => self.statistics,
+            Subsystem::CostHeatmap => self.cost_heatmap,
+        }
+        .max(1)
+    }
+
+    pub fn set(&mut self, subsystem: Subsystem, every: u32) {
+        *match subsystem {
+            Subsystem::Forces => &mut self.forces,
+            Subsystem::Collisions => &mut self.collisions,
+            Subsystem::Statistics => &mut self.statistics,
+            Subsystem::CostHeatmap => &mut self.cost_heatmap,
+        } = every;
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Cadences like `statistics=30,heatmap=60`, the subsystems left out
+    /// keep their default.
+    pub fn parse(cadences: &str) -> Option<Self> {
+        let mut parsed = Cadences::default();
+        for cadence in cadences.split(',') {
+            let (name, every) = cadence.split_once('=')?;
+            let subsystem = Subsystem::ALL
+                .into_iter()
+                .find(|subsystem| subsystem.name() == name.trim())?;
+            let every = every.trim().parse().ok().filter(|&every| every > 0)?;
+            parsed.set(subsystem, every);
+        }
+        Some(parsed)
+    }
+}
+
+/// Counts the physics steps, at the start of each.
+pub fn count_step(mut cadences: ResMut<Cadences>) {
+    cadences.steps += 1;
+}
+
+/// Run condition for the systems in the fixed schedules, true in the first
+/// step and then every [`Cadences::every`] steps of the `subsystem`. Systems running several times a step, like the ones in
+/// every substep, run in all of them or none.
+pub fn on_cadence(subsystem: Subsystem) -> impl Fn(Res<Cadences>) -> bool {
+    move |cadences| cadences.steps.saturating_sub(1) % u64::from(cadences.every(subsystem)) == 0
+}
+
+/// Run condition for the systems running once a frame, true when at least
+/// [`Cadences::every`] steps of the `subsystem` passed since it last was,
+/// and the first time it is checked.
+pub fn cadence_elapsed(
+    subsystem: Subsystem,
+) -> impl FnMut(Res<Cadences>, Local<Option<u64>>) -> bool {
+    move |cadences, mut last| {
+        let due =
+            last.is_none_or(|last| cadences.steps >= last + u64::from(cadences.every(subsystem)));
+        if due {
+            *last = Some(cadences.steps);
+        }
+        due
+    }
+}
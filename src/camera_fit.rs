@@ -0,0 +1,119 @@
+use crate::bodies::Bodies;
+use crate::follow_camera::CameraFollow;
+use crate::input::{Action, Actions, InputMap};
+use crate::inspector::Inspector;
+use crate::physics_plugin::MainCamera;
+use bevy::prelude::*;
+
+/// Fraction of the remaining way to the framing the camera covers per
+/// second.
+const FIT_SPEED: f32 = 4.;
+/// Room left around the framed bodies, as a fraction of their extent.
+const MARGIN: f32 = 0.1;
+/// Fraction of the shorter side of the view the disc of a framed single
+/// body takes up.
+const SELECTION_FRACTION: f32 = 0.1;
+
+/// Where the main camera is moving to after framing all the bodies with
+/// Home or the inspected one with End (with the default input map).
+///
+/// All the bodies are framed around their center of mass rather than the
+/// middle of their bounding box, so a heavy star stays near the middle of
+/// the view while a few bodies fly off.
+#[derive(Resource, Debug, Default)]
+pub struct CameraFit {
+    /// Center and projection scale the camera is easing towards
+    pub target: Option<(Vec2, f32)>,
+}
+
+/// Center and scale framing the box from `min` to `max` around `center` in
+/// a view `screen` pixels large.
+fn framing(center: Vec2, min: Vec2, max: Vec2, screen: Vec2) -> (Vec2, f32) {
+    let half_extent = (max - center).max(center - min) * (1. + MARGIN);
+    let scale = (half_extent * 2. / screen).max_element();
+    (center, scale.max(f32::MIN_POSITIVE))
+}
+
+fn fit_camera(
+    actions: Actions,
+    bodies: Bodies,
+    inspector: Res<Inspector>,
+    mut follow: ResMut<CameraFollow>,
+    mut fit: ResMut<CameraFit>,
+    cameras: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    let Ok(projection) = cameras.get_single() else {
+        return;
+    };
+    let screen = projection.area.size() / projection.scale;
+
+    if actions.just_pressed(Action::FitAll) {
+        let (mut min, mut max) = (Vec2::MAX, Vec2::MIN);
+        let (mut mass, mut weighted) = (0., Vec2::ZERO);
+        for body in bodies.iter() {
+            min = min.min(body.position - body.radius);
+            max = max.max(body.position + body.radius);
+            mass += body.mass;
+            weighted += body.position * body.mass;
+        }
+        if min.x > max.x {
+            return;
+        }
+        // Only massless bodies, which have no center of mass.
+        let center = if mass > 0. {
+            weighted / mass
+        } else {
+            (min + max) / 2.
+        };
+        fit.target = Some(framing(center, min, max, screen));
+        follow.target = None;
+    }
+
+    if actions.just_pressed(Action::FitSelection) {
+        let Some(body) = inspector.target.and_then(|target| bodies.get(target)) else {
+            return;
+        };
+        let scale = body.radius.max(1.) * 2. / (screen.min_element() * SELECTION_FRACTION);
+        fit.target = Some((body.position, scale));
+        follow.target = None;
+    }
+}
+
+/// Eases the main camera towards the [`CameraFit`] target, in real time so
+/// it also moves while the simulation is paused.
+fn ease_camera_fit(
+    time: Res<Time<Real>>,
+    mut fit: ResMut<CameraFit>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let Some((center, scale)) = fit.target else {
+        return;
+    };
+    let t = 1. - (-FIT_SPEED * time.delta_secs()).exp();
+    let mut arrived = true;
+    for (mut transform, mut projection) in &mut cameras {
+        let position = transform.translation.xy().lerp(center, t);
+        transform.translation = position.extend(transform.translation.z);
+        // Zooming in log space keeps the speed even from far out.
+        projection.scale = (projection.scale.ln().lerp(scale.ln(), t)).exp();
+        arrived &=
+            position.distance(center) < scale && (projection.scale / scale - 1.).abs() < 1e-3;
+    }
+    if arrived {
+        fit.target = None;
+    }
+}
+
+/// Frames all the bodies or the inspected one on a key press, see
+/// [`CameraFit`].
+pub struct CameraFitPlugin;
+
+impl Plugin for CameraFitPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraFit>()
+            .init_resource::<CameraFollow>()
+            .init_resource::<Inspector>()
+            .init_resource::<InputMap>()
+            .add_systems(Update, (fit_camera, ease_camera_fit).chain());
+    }
+}
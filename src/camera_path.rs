@@ -0,0 +1,291 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::MainCamera;
+use crate::state::SimState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Simulated seconds between two keyframes of a recording while the
+/// camera stays still.
+const RECORD_INTERVAL: f32 = 0.5;
+
+/// How the camera moves from the previous keyframe to the next one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    Linear,
+    /// Starts and stops gently
+    #[default]
+    Smooth,
+    /// Starts gently and arrives at full speed
+    EaseIn,
+    /// Leaves at full speed and stops gently
+    EaseOut,
+}
+
+impl Easing {
+    /// Progress through the move at the fraction `t` of its time.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0., 1.);
+        match self {
+            Easing::Linear => t,
+            Easing::Smooth => t * t * (3. - 2. * t),
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2. - t),
+        }
+    }
+}
+
+/// Where the camera is at a point of the simulated time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    /// Simulated seconds since the scenario was loaded
+    pub time: f32,
+    /// Center of the view, relative to the `target` if there is one
+    #[serde(default)]
+    pub position: Vec2,
+    /// Scale of the projection, above 1 shows more of the world
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+    /// Index of the body the camera follows, the same body in every replay
+    /// of a deterministic run
+    #[serde(default)]
+    pub target: Option<u32>,
+    /// How the camera moves here from the previous keyframe
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+fn default_zoom() -> f32 {
+    1.
+}
+
+/// Keyframes the camera moves through, sorted by their time. Two keyframes
+/// at the same time cut from one shot to the other.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    /// Parses a path from TOML, a `[[keyframes]]` table for each keyframe.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut path: CameraPath = toml::from_str(source).map_err(|err| err.to_string())?;
+        if let Some(keyframe) = path
+            .keyframes
+            .iter()
+            .find(|keyframe| !keyframe.time.is_finite() || keyframe.zoom <= 0.)
+        {
+            return Err(format!(
+                "keyframe at {} s needs a finite time and a positive zoom",
+                keyframe.time
+            ));
+        }
+        path.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(path)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let source = toml::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, source)
+    }
+
+    /// Center and zoom of the view at `time`, with the position of the
+    /// target bodies given by `target_position`. Holds the first keyframe
+    /// before it and the last one after it, `None` without keyframes.
+    pub fn sample(
+        &self,
+        time: f32,
+        target_position: impl Fn(u32) -> Option<Vec2>,
+    ) -> Option<(Vec2, f32)> {
+        let center = |keyframe: &CameraKeyframe| {
+            keyframe.position
+                + keyframe
+                    .target
+                    .and_then(&target_position)
+                    .unwrap_or_default()
+        };
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+        let (from, to) = match next {
+            0 => (self.keyframes.first()?, self.keyframes.first()?),
+            next if next == self.keyframes.len() => {
+                (self.keyframes.last()?, self.keyframes.last()?)
+            }
+            next => (&self.keyframes[next - 1], &self.keyframes[next]),
+        };
+        let duration = to.time - from.time;
+        if duration <= 0. {
+            return Some((center(to), to.zoom));
+        }
+        let t = to.easing.apply((time - from.time) / duration);
+        // Zooming by the same factor every second looks steady, unlike
+        // changing the scale at a steady rate.
+        let zoom = from.zoom * (to.zoom / from.zoom).powf(t);
+        Some((center(from).lerp(center(to), t), zoom))
+    }
+}
+
+/// Plays a [`CameraPath`] back from the start of every scenario, so every
+/// replay of a run is filmed the same way, or records the moves of the
+/// camera into a new one.
+#[derive(Resource, Debug, Default)]
+pub struct CameraDirector {
+    /// Path the camera follows, unless recording
+    pub playback: Option<CameraPath>,
+    recording: Option<CameraPath>,
+    /// Simulated seconds since the scenario was loaded
+    elapsed: f32,
+}
+
+impl CameraDirector {
+    pub fn with_playback(mut self, path: CameraPath) -> Self {
+        self.playback = Some(path);
+        self
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+}
+
+/// Starts the path over, and the recording with it, when a scenario is
+/// loaded.
+fn restart_camera_path(mut director: ResMut<CameraDirector>) {
+    director.elapsed = 0.;
+    if let Some(recording) = director.recording.as_mut() {
+        recording.keyframes.clear();
+    }
+}
+
+fn advance_camera_path(time: Res<Time>, mut director: ResMut<CameraDirector>) {
+    director.elapsed += time.delta_secs();
+}
+
+/// Starts recording, or stops it and saves the recording next to the
+/// working directory.
+fn toggle_camera_recording(actions: Actions, mut director: ResMut<CameraDirector>) {
+    if !actions.just_pressed(Action::ToggleCameraRecording) {
+        return;
+    }
+    let Some(recording) = director.recording.take() else {
+        director.recording = Some(CameraPath::default());
+        info!("Recording the camera path");
+        return;
+    };
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let path = format!("camera-path-{seconds}.toml");
+    match recording.save(Path::new(&path)) {
+        Ok(()) => info!(
+            "Saved {} camera keyframes to `{path}`",
+            recording.keyframes.len()
+        ),
+        Err(err) => error!("Couldn't save the camera path to `{path}`: {err}"),
+    }
+}
+
+/// Adds a keyframe every [`RECORD_INTERVAL`] and whenever the camera moves.
+/// Moves made while the simulated time stands still, such as in photo
+/// mode, play back as a cut to where the camera was left.
+fn record_camera_path(
+    mut director: ResMut<CameraDirector>,
+    mut previous_elapsed: Local<f32>,
+    cameras: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+) {
+    let elapsed = director.elapsed;
+    // The time went back to 0 if the scenario was restarted.
+    let previous = previous_elapsed.min(elapsed);
+    *previous_elapsed = elapsed;
+    let (Some(recording), Ok((transform, projection))) =
+        (director.recording.as_mut(), cameras.get_single())
+    else {
+        return;
+    };
+    let keyframe = CameraKeyframe {
+        time: elapsed,
+        position: transform.translation.xy(),
+        zoom: projection.scale,
+        target: None,
+        easing: Easing::Linear,
+    };
+    let keyframes = &mut recording.keyframes;
+    let Some(&last) = keyframes.last() else {
+        keyframes.push(keyframe);
+        return;
+    };
+    if last.position == keyframe.position && last.zoom == keyframe.zoom {
+        if elapsed - last.time >= RECORD_INTERVAL {
+            keyframes.push(keyframe);
+        }
+        return;
+    }
+    let cut = keyframes.len() > 1 && keyframes[keyframes.len() - 2].time == elapsed;
+    if cut && last.time == elapsed {
+        // Still moving after the cut, only where the camera ends up counts.
+        *keyframes.last_mut().unwrap() = keyframe;
+        return;
+    }
+    // The camera stayed put until the last frame.
+    if last.time < previous {
+        keyframes.push(CameraKeyframe {
+            time: previous,
+            ..last
+        });
+    }
+    keyframes.push(keyframe);
+}
+
+fn play_camera_path(
+    director: Res<CameraDirector>,
+    bodies: Query<(Entity, &GlobalTransform)>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let Some(path) = director
+        .playback
+        .as_ref()
+        .filter(|_| !director.is_recording())
+    else {
+        return;
+    };
+    // Few keyframes follow a body, look them up only when they do.
+    let target_position = |index: u32| {
+        bodies
+            .iter()
+            .find(|(entity, _)| entity.index() == index)
+            .map(|(_, transform)| transform.translation().xy())
+    };
+    let Some((center, zoom)) = path.sample(director.elapsed, target_position) else {
+        return;
+    };
+    for (mut transform, mut projection) in &mut cameras {
+        transform.translation = center.extend(transform.translation.z);
+        projection.scale = zoom;
+    }
+}
+
+/// Scripted camera moves, see [`CameraDirector`], recording toggled with K
+/// by default.
+pub struct CameraPathPlugin;
+
+impl Plugin for CameraPathPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraDirector>()
+            .init_resource::<InputMap>()
+            .add_systems(OnEnter(SimState::Loading), restart_camera_path)
+            .add_systems(
+                Update,
+                (
+                    advance_camera_path,
+                    toggle_camera_recording,
+                    record_camera_path,
+                    play_camera_path
+                        .run_if(in_state(SimState::Running).or(in_state(SimState::Paused))),
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::physics_plugin::Velocity;
+use crate::quadtree::QuadTree;
+use bevy::prelude::*;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// How clustered the bodies were at a point in time.
+#[derive(Debug, Clone)]
+pub struct ClusteringSample {
+    /// Elapsed app time of the sample
+    pub time: f32,
+    /// Number of bodies whose nearest neighbour is within each distance
+    /// bin, the last bin also counts everything farther away
+    pub nearest_neighbor: Vec<u32>,
+    /// Two-point correlation function ξ(r) for each distance bin: how many
+    /// more pairs of bodies are that far apart than if the bodies were
+    /// spread uniformly over their bounding box, -1 meaning none at all
+    pub correlation: Vec<f32>,
+}
+
+/// When present, the distribution of nearest-neighbour distances and the
+/// two-point correlation function of the bodies are periodically measured,
+/// quantifying how clustered they are.
+#[derive(Resource, Debug)]
+pub struct ClusteringStatistics {
+    timer: Timer,
+    /// Largest distance measured, split into `bins` equally wide bins
+    pub max_radius: f32,
+    pub bins: usize,
+    /// CSV file every sample is appended to
+    pub export: Option<PathBuf>,
+    /// Every sample taken so far, oldest first
+    pub samples: Vec<ClusteringSample>,
+}
+
+impl ClusteringStatistics {
+    /// Measures every `interval` seconds up to `max_radius` in `bins` bins.
+    pub fn new(interval: f32, max_radius: f32, bins: usize) -> Self {
+        ClusteringStatistics {
+            timer: Timer::from_seconds(interval, TimerMode::Repeating),
+            max_radius,
+            bins,
+            export: None,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Appends every sample to the CSV file at `path`.
+    pub fn with_export(mut self, path: impl Into<PathBuf>) -> Self {
+        self.export = Some(path.into());
+        self
+    }
+
+    /// Measures the sample for the bodies at `positions`.
+    fn measure(&self, time: f32, positions: &[Vec2]) -> ClusteringSample {
+        let bin_width = self.max_radius / self.bins as f32;
+        let bin = |distance: f32| ((distance / bin_width) as usize).min(self.bins - 1);
+        let mut nearest_neighbor = vec![0; self.bins];
+        let mut pairs = vec![0u64; self.bins];
+
+        let min = positions.iter().copied().fold(Vec2::MAX, Vec2::min);
+        let max = positions.iter().copied().fold(Vec2::MIN, Vec2::max);
+        let bodies: Vec<(Vec2, f32)> = positions.iter().map(|&position| (position, 1.)).collect();
+        let tree = QuadTree::build(&bodies);
+
+        for &position in positions {
+            if let Some(nearest) = tree.nearest_leaf(position) {
+                nearest_neighbor[bin(nearest.center_of_mass.distance(position))] += 1;
+            }
+            tree.for_each_leaf_within(position, self.max_radius, |leaf| {
+                for (other, _) in tree.leaf_bodies(leaf) {
+                    let distance = other.distance(position);
+                    if distance > 0. && distance < self.max_radius {
+                        pairs[bin(distance)] += 1;
+                    }
+                }
+            });
+        }
+
+        // Every pair was counted from both of its ends.
+        let count = positions.len() as f32;
+        let total_pairs = count * (count - 1.) / 2.;
+        let area = (max - min).x.max(1.) * (max - min).y.max(1.);
+        let correlation = pairs
+            .iter()
+            .enumerate()
+            .map(|(index, &doubled)| {
+                let inner = index as f32 * bin_width;
+                let outer = inner + bin_width;
+                let ring = std::f32::consts::PI * (outer * outer - inner * inner);
+                let expected = total_pairs * ring / area;
+                doubled as f32 / 2. / expected - 1.
+            })
+            .collect();
+
+        ClusteringSample {
+            time,
+            nearest_neighbor,
+            correlation,
+        }
+    }
+
+    /// Appends the sample to the export file as `time,statistic,bin start,
+    /// bin end,value` rows.
+    fn export_sample(&self, path: &PathBuf, sample: &ClusteringSample) -> std::io::Result<()> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "time,statistic,bin_start,bin_end,value")?;
+        }
+        let bin_width = self.max_radius / self.bins as f32;
+        for (index, count) in sample.nearest_neighbor.iter().enumerate() {
+            let start = index as f32 * bin_width;
+            writeln!(
+                file,
+                "{},nearest_neighbor,{start},{},{count}",
+                sample.time,
+                start + bin_width
+            )?;
+        }
+        for (index, correlation) in sample.correlation.iter().enumerate() {
+            let start = index as f32 * bin_width;
+            writeln!(
+                file,
+                "{},correlation,{start},{},{correlation}",
+                sample.time,
+                start + bin_width
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn measure_clustering(
+    time: Res<Time>,
+    mut statistics: ResMut<ClusteringStatistics>,
+    bodies: Query<&Transform, With<Velocity>>,
+) {
+    if !statistics.timer.tick(time.delta()).just_finished() || statistics.bins == 0 {
+        return;
+    }
+
+    let positions: Vec<Vec2> = bodies
+        .iter()
+        .map(|transform| transform.translation.xy())
+        .collect();
+    if positions.len() < 2 {
+        return;
+    }
+    let sample = statistics.measure(time.elapsed_secs(), &positions);
+    if let Some(path) = &statistics.export {
+        if let Err(err) = statistics.export_sample(path, &sample) {
+            warn!("Couldn't export clustering statistics to {path:?}: {err}");
+        }
+    }
+    statistics.samples.push(sample);
+}
@@ -0,0 +1,330 @@
+use crate::docking::{move_composite, Collision, Dockable, DockedPart};
+use crate::physics_plugin::{Mass, Velocity};
+use crate::quadtree::{leaf_key, QuadTree};
+use crate::radius::Radius;
+use crate::timings::PhysicsTimings;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet, Instant};
+
+/// How touching bodies respond to each other.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub enum CollisionMode {
+    /// Touching bodies merge into the heavier one, which keeps their mass
+    /// and momentum, while dockable bodies dock instead
+    #[default]
+    Merge,
+    /// Bodies are pushed apart and bounce off, keeping the given share of
+    /// their speed towards each other, 1 for an elastic collision and 0
+    /// for one where they stick together
+    Bounce { restitution: f32 },
+    /// Bodies pass through each other
+    None,
+}
+
+impl CollisionMode {
+    /// Parses `merge`, `bounce` with the restitution following it, or
+    /// `none`.
+    pub fn parse(mode: &str, restitution: Option<&str>) -> Option<Self> {
+        match mode {
+            "merge" => Some(CollisionMode::Merge),
+            "bounce" => restitution
+                .and_then(|restitution| restitution.parse().ok())
+                .filter(|restitution: &f32| (0. ..=1.).contains(restitution))
+                .map(|restitution| CollisionMode::Bounce { restitution }),
+            "none" => Some(CollisionMode::None),
+            _ => None,
+        }
+    }
+}
+
+/// Run condition for the docking and [`merge_bodies`], which only happen
+/// in [`CollisionMode::Merge`].
+pub fn merges_collisions(mode: Res<CollisionMode>) -> bool {
+    *mode == CollisionMode::Merge
+}
+
+/// Run condition for [`bounce_bodies`].
+pub fn bounces_collisions(mode: Res<CollisionMode>) -> bool {
+    matches!(*mode, CollisionMode::Bounce { .. })
+}
+
+/// Merges the colliding pairs of bodies which aren't both dockable into the
+/// heavier one, which takes the mass and momentum of both and moves to
+/// their center of mass. The lighter one is despawned, along with the
+/// parts docked to it whose mass it carried. A body merges at most once a
+/// step.
+#[allow(clippy::type_complexity)]
+pub fn merge_bodies(
+    mut commands: Commands,
+    mut timings: ResMut<PhysicsTimings>,
+    mut collisions: EventReader<Collision>,
+    mut bodies: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut Mass,
+            Option<&Dockable>,
+            Option<&Children>,
+        ),
+        Without<Parent>,
+    >,
+    mut parts: Query<(&mut DockedPart, &mut Transform), With<Parent>>,
+) {
+    let start = Instant::now();
+    let mut merged: HashSet<Entity> = HashSet::default();
+    for &Collision { a, b } in collisions.read() {
+        if merged.contains(&a) || merged.contains(&b) {
+            continue;
+        }
+        let Ok([a_body, b_body]) = bodies.get_many_mut([a, b]) else {
+            continue;
+        };
+        if a_body.3.is_some() && b_body.3.is_some() {
+            // Those dock or pass through each other.
+            continue;
+        }
+        let (survivor, absorbed, (mut transform, mut velocity, mut mass, _, children), other) =
+            if a_body.2 .0 >= b_body.2 .0 {
+                (a, b, a_body, b_body)
+            } else {
+                (b, a, b_body, a_body)
+            };
+        let (other_transform, other_velocity, other_mass, ..) = other;
+
+        let total_mass = mass.0 + other_mass.0;
+        velocity.0 = (velocity.0 * mass.0 + other_velocity.0 * other_mass.0) / total_mass;
+        let center = (transform.translation.xy() * mass.0
+            + other_transform.translation.xy() * other_mass.0)
+            / total_mass;
+        mass.0 = total_mass;
+        move_composite(&mut transform, center, children, &mut parts);
+
+        commands.entity(absorbed).despawn_recursive();
+        merged.extend([survivor, absorbed]);
+    }
+    timings.collision += start.elapsed();
+}
+
+/// Pushes the overlapping bodies of the same world apart and exchanges the
+/// momentum of the ones approaching each other.
+///
+/// The candidates touching a body are the leaves of a tree of all bodies
+/// within its radius plus the largest one. Pairs are resolved in the order
+/// of their entities, each once a step. Runs on the cadence of the
+/// [`Collisions`](crate::cadence::Subsystem::Collisions) like the docking.
+#[allow(clippy::type_complexity)]
+pub fn bounce_bodies(
+    mode: Res<CollisionMode>,
+    mut timings: ResMut<PhysicsTimings>,
+    mut bodies: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            &Mass,
+            &Radius,
+            Option<&SimWorld>,
+        ),
+        Without<Parent>,
+    >,
+) {
+    let CollisionMode::Bounce { restitution } = *mode else {
+        return;
+    };
+    let start = Instant::now();
+    let mut states: Vec<(Entity, Vec2, Vec2, f32, f32, SimWorld)> = bodies
+        .iter()
+        .map(|(entity, transform, velocity, mass, radius, world)| {
+            (
+                entity,
+                transform.translation.xy(),
+                velocity.0,
+                mass.0,
+                radius.0,
+                world.copied().unwrap_or_default(),
+            )
+        })
+        .collect();
+    states.sort_by_key(|state| state.0);
+    if states.len() < 2 {
+        timings.collision += start.elapsed();
+        return;
+    }
+
+    let positions: Vec<(Vec2, f32)> = states.iter().map(|state| (state.1, 1.)).collect();
+    let tree = QuadTree::build(&positions);
+    let mut by_position: HashMap<(u32, u32), Vec<usize>> = HashMap::default();
+    for (index, state) in states.iter().enumerate() {
+        by_position
+            .entry(leaf_key(state.1))
+            .or_default()
+            .push(index);
+    }
+    let max_radius = states.iter().map(|state| state.4).fold(0., f32::max);
+
+    let mut pairs = Vec::new();
+    for (index, state) in states.iter().enumerate() {
+        tree.for_each_leaf_within(state.1, state.4 + max_radius, |leaf| {
+            let others = tree
+                .leaf_bodies(leaf)
+                .filter_map(|(position, _)| by_position.get(&leaf_key(position)))
+                .flatten();
+            for &other in others {
+                let other_state = &states[other];
+                if other > index
+                    && other_state.5 == state.5
+                    && state.1.distance(other_state.1) < state.4 + other_state.4
+                {
+                    pairs.push((index, other));
+                }
+            }
+        });
+    }
+    pairs.sort();
+    pairs.dedup();
+
+    for (a, b) in pairs {
+        let (_, a_position, a_velocity, a_mass, a_radius, _) = states[a];
+        let (_, b_position, b_velocity, b_mass, b_radius, _) = states[b];
+        let offset = b_position - a_position;
+        let distance = offset.length();
+        let penetration = a_radius + b_radius - distance;
+        if penetration <= 0. {
+            continue;
+        }
+        // Bodies on top of each other are split along x.
+        let normal = if distance > 0. {
+            offset / distance
+        } else {
+            Vec2::X
+        };
+
+        // The lighter body is moved the most, the center of mass stays put.
+        let total_mass = a_mass + b_mass;
+        states[a].1 -= normal * penetration * b_mass / total_mass;
+        states[b].1 += normal * penetration * a_mass / total_mass;
+
+        let approach_speed = (b_velocity - a_velocity).dot(normal);
+        if approach_speed < 0. {
+            let impulse = -(1. + restitution) * approach_speed / (1. / a_mass + 1. / b_mass);
+            states[a].2 -= normal * impulse / a_mass;
+            states[b].2 += normal * impulse / b_mass;
+        }
+    }
+
+    for (entity, position, velocity, ..) in states {
+        let Ok((_, mut transform, mut body_velocity, ..)) = bodies.get_mut(entity) else {
+            continue;
+        };
+        if transform.translation.xy() != position {
+            transform.translation = position.extend(transform.translation.z);
+        }
+        if body_velocity.0 != velocity {
+            body_velocity.0 = velocity;
+        }
+    }
+    timings.collision += start.elapsed();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A world in `mode` with two overlapping bodies of radius 1 approaching
+    /// each other off center, both `dockable` when set.
+    fn two_bodies(mode: CollisionMode, dockable: bool) -> (World, Entity, Entity) {
+        let mut world = World::new();
+        world.insert_resource(mode);
+        world.init_resource::<PhysicsTimings>();
+        world.init_resource::<Events<Collision>>();
+        let mut body = |position: Vec2, velocity: Vec2, mass: f32| {
+            world
+                .spawn((
+                    Transform::from_translation(position.extend(0.)),
+                    Velocity(velocity),
+                    Mass(mass),
+                    Radius(1.),
+                ))
+                .id()
+        };
+        let a = body(Vec2::new(0., 0.), Vec2::new(3., 1.), 2.);
+        let b = body(Vec2::new(1.5, 0.5), Vec2::new(-2., 0.), 5.);
+        if dockable {
+            for entity in [a, b] {
+                world.entity_mut(entity).insert(Dockable {
+                    max_relative_speed: 0.,
+                    separation_speed: 0.,
+                });
+            }
+        }
+        (world, a, b)
+    }
+
+    /// Total momentum, kinetic energy and mass of the bodies.
+    fn totals(world: &mut World) -> (Vec2, f32, f32) {
+        let mut bodies = world.query::<(&Velocity, &Mass)>();
+        bodies.iter(world).fold(
+            (Vec2::ZERO, 0., 0.),
+            |(momentum, energy, total), (velocity, mass)| {
+                (
+                    momentum + velocity.0 * mass.0,
+                    energy + 0.5 * mass.0 * velocity.0.length_squared(),
+                    total + mass.0,
+                )
+            },
+        )
+    }
+
+    #[test]
+    fn elastic_bounce_keeps_momentum_and_energy() {
+        let (mut world, a, b) = two_bodies(CollisionMode::Bounce { restitution: 1. }, false);
+        let (momentum, energy, _) = totals(&mut world);
+        let mut schedule = Schedule::default();
+        schedule.add_systems(bounce_bodies);
+        schedule.run(&mut world);
+
+        let (bounced_momentum, bounced_energy, _) = totals(&mut world);
+        assert!(
+            bounced_momentum.distance(momentum) < 1e-4,
+            "{bounced_momentum}"
+        );
+        assert!(
+            (bounced_energy - energy).abs() < energy * 1e-5,
+            "{bounced_energy}"
+        );
+        // They move apart now.
+        let position = |entity| world.get::<Transform>(entity).unwrap().translation.xy();
+        let velocity = |entity| world.get::<Velocity>(entity).unwrap().0;
+        let normal = (position(b) - position(a)).normalize();
+        assert!((velocity(b) - velocity(a)).dot(normal) > 0.);
+    }
+
+    #[test]
+    fn merge_keeps_mass_momentum_and_center_of_the_heavier_body() {
+        let (mut world, a, b) = two_bodies(CollisionMode::Merge, false);
+        let (momentum, _, mass) = totals(&mut world);
+        world.send_event(Collision { a, b });
+        let mut schedule = Schedule::default();
+        schedule.add_systems(merge_bodies);
+        schedule.run(&mut world);
+
+        assert!(world.get_entity(a).is_err());
+        let (merged_momentum, _, merged_mass) = totals(&mut world);
+        assert_eq!(merged_mass, mass);
+        assert!(merged_momentum.distance(momentum) < 1e-4);
+        let center = (Vec2::ZERO * 2. + Vec2::new(1.5, 0.5) * 5.) / 7.;
+        let position = world.get::<Transform>(b).unwrap().translation.xy();
+        assert!(position.distance(center) < 1e-5, "{position}");
+    }
+
+    #[test]
+    fn merge_leaves_dockable_pairs_to_docking() {
+        let (mut world, a, b) = two_bodies(CollisionMode::Merge, true);
+        world.send_event(Collision { a, b });
+        let mut schedule = Schedule::default();
+        schedule.add_systems(merge_bodies);
+        schedule.run(&mut world);
+        assert!(world.get_entity(a).is_ok() && world.get_entity(b).is_ok());
+    }
+}
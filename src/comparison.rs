@@ -0,0 +1,94 @@
+use crate::forces::Drag;
+use crate::input::{Action, Actions, InputMap};
+use crate::integrator::IntegratorKind;
+use crate::physics_plugin::{Mass, PhysicsSettings, Velocity};
+use crate::simulation::{Simulation, SimulationConfig};
+use crate::theme::Theme;
+use bevy::prelude::*;
+
+/// Runs a copy of the simulation with a different theta threshold in
+/// lockstep with the live one and draws it as ghost particles over it,
+/// showing how much the approximation changes the outcome. Starting it
+/// copies the current state of all the bodies.
+#[derive(Resource, Debug)]
+pub struct AccuracyComparison {
+    /// Theta threshold the ghost copy is simulated with
+    pub theta_threshold: f32,
+    ghosts: Simulation,
+}
+
+impl Default for AccuracyComparison {
+    fn default() -> Self {
+        AccuracyComparison {
+            theta_threshold: 0.5,
+            ghosts: Simulation::default(),
+        }
+    }
+}
+
+impl AccuracyComparison {
+    /// Whether the ghost copy is being simulated.
+    pub fn is_running(&self) -> bool {
+        !self.ghosts.bodies.is_empty()
+    }
+
+    /// Drops the ghost copy.
+    pub fn stop(&mut self) {
+        self.ghosts.bodies.clear();
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn toggle_comparison(
+    actions: Actions,
+    integrator: Res<IntegratorKind>,
+    settings: Res<PhysicsSettings>,
+    mut comparison: ResMut<AccuracyComparison>,
+    bodies: Query<(Entity, &Transform, &Velocity, Option<&Mass>, Option<&Drag>)>,
+) {
+    if !actions.just_pressed(Action::ToggleComparison) {
+        return;
+    }
+    if comparison.is_running() {
+        comparison.stop();
+        return;
+    }
+    comparison.ghosts = Simulation::capture(
+        &bodies,
+        SimulationConfig {
+            g: settings.g,
+            theta_threshold: comparison.theta_threshold,
+            integrator: *integrator,
+            softening: settings.softening,
+        },
+    );
+}
+
+/// Advances the ghosts by the same step and in the same order as the live
+/// bodies, only with the comparison theta.
+fn step_ghosts(time: Res<Time>, mut comparison: ResMut<AccuracyComparison>) {
+    if comparison.is_running() {
+        comparison.ghosts.step(time.delta_secs());
+    }
+}
+
+fn draw_ghosts(mut gizmos: Gizmos, theme: Res<Theme>, comparison: Res<AccuracyComparison>) {
+    for ghost in &comparison.ghosts.bodies {
+        gizmos.circle_2d(ghost.position, 2., theme.ghost());
+    }
+}
+
+/// Adds the ghost copy for comparing simulation parameters side by side.
+pub struct ComparisonPlugin;
+
+impl Plugin for ComparisonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AccuracyComparison>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .init_resource::<IntegratorKind>()
+            .init_resource::<PhysicsSettings>()
+            .add_systems(Update, (toggle_comparison, draw_ghosts).chain())
+            .add_systems(FixedUpdate, step_ghosts);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_config::{PhysicsConfig, PhysicsQuality};
+use crate::physics_plugin::{tree_potential, MainCamera, Mass, PhysicsSettings};
+use crate::quadtree::QuadTree;
+use crate::quality::Quality;
+use crate::theme::Theme;
+use bevy::prelude::*;
+use bevy::utils::Duration;
+
+/// Number of potential samples along each side of the view.
+const SAMPLES_PER_SIDE: usize = 48;
+/// Number of contour levels drawn.
+const LEVELS: usize = 12;
+/// Seconds between resampling the potential.
+const REFRESH_INTERVAL: f32 = 0.5;
+
+/// Iso-potential contours over the view, resampled every
+/// [`REFRESH_INTERVAL`] seconds. Saddles of the contours show Lagrange
+/// points.
+#[derive(Resource, Debug)]
+pub struct PotentialContours {
+    pub active: bool,
+    /// Include the quadrupole moments of the tree nodes on top of their
+    /// monopoles
+    pub quadrupole: bool,
+    timer: Timer,
+    /// Line segments of the contours with the index of their level
+    segments: Vec<(Vec2, Vec2, usize)>,
+}
+
+impl Default for PotentialContours {
+    fn default() -> Self {
+        PotentialContours {
+            active: false,
+            quadrupole: true,
+            timer: Timer::from_seconds(REFRESH_INTERVAL, TimerMode::Repeating),
+            segments: Vec::new(),
+        }
+    }
+}
+
+impl PotentialContours {
+    /// Resamples the potential every `seconds` instead of every
+    /// [`REFRESH_INTERVAL`] seconds.
+    pub fn set_refresh_interval(&mut self, seconds: f32) {
+        self.timer.set_duration(Duration::from_secs_f32(seconds));
+    }
+}
+
+fn toggle_contours(actions: Actions, mut contours: ResMut<PotentialContours>) {
+    if actions.just_pressed(Action::ToggleContours) {
+        contours.active = !contours.active;
+        contours.segments.clear();
+        // Sample right away instead of waiting for the next refresh.
+        let duration = contours.timer.duration();
+        contours.timer.set_elapsed(duration);
+    }
+}
+
+/// Where the `level` crosses the edge between corners `a` and `b` with
+/// potentials `value_a` and `value_b`, if it does.
+fn crossing(a: Vec2, b: Vec2, value_a: f32, value_b: f32, level: f32) -> Option<Vec2> {
+    if (value_a < level) == (value_b < level) {
+        return None;
+    }
+    Some(a.lerp(b, (level - value_a) / (value_b - value_a)))
+}
+
+/// Samples the potential on a grid over the view and traces the contours
+/// through it with marching squares.
+fn sample_contours(
+    time: Res<Time<Real>>,
+    quality: PhysicsQuality,
+    settings: Res<PhysicsSettings>,
+    mut contours: ResMut<PotentialContours>,
+    cameras: Query<(&GlobalTransform, &OrthographicProjection), With<MainCamera>>,
+    bodies: Query<(&Mass, &Transform)>,
+) {
+    if !contours.active || !contours.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let Ok((camera_transform, projection)) = cameras.get_single() else {
+        return;
+    };
+
+    let view = Rect::from_center_size(
+        camera_transform.translation().xy() + projection.area.center(),
+        projection.area.size(),
+    );
+    let spacing = view.size() / (SAMPLES_PER_SIDE - 1) as f32;
+    let point = |x: usize, y: usize| view.min + Vec2::new(x as f32, y as f32) * spacing;
+
+    let sources: Vec<(Vec2, f32)> = bodies
+        .iter()
+        .map(|(mass, transform)| (transform.translation.xy(), mass.0))
+        .collect();
+    let q_tree = QuadTree::build(&sources);
+    let theta_threshold = quality.settings().theta_threshold;
+    let mut potentials = Vec::with_capacity(SAMPLES_PER_SIDE * SAMPLES_PER_SIDE);
+    for y in 0..SAMPLES_PER_SIDE {
+        for x in 0..SAMPLES_PER_SIDE {
+            potentials.push(tree_potential(
+                &q_tree,
+                point(x, y),
+                theta_threshold,
+                settings.g,
+                settings.softening,
+                contours.quadrupole,
+            ));
+        }
+    }
+    let potential = |x: usize, y: usize| potentials[y * SAMPLES_PER_SIDE + x];
+
+    // Levels at evenly spaced quantiles of the samples, the potential is
+    // steep around the bodies and evenly spaced values would crowd there.
+    let mut sorted = potentials.clone();
+    sorted.sort_by(f32::total_cmp);
+    let levels: Vec<f32> = (1..=LEVELS)
+        .map(|level| sorted[level * (sorted.len() - 1) / (LEVELS + 1)])
+        .collect();
+
+    contours.segments.clear();
+    for y in 0..SAMPLES_PER_SIDE - 1 {
+        for x in 0..SAMPLES_PER_SIDE - 1 {
+            // Counterclockwise from the bottom-left corner.
+            let corners = [
+                point(x, y),
+                point(x + 1, y),
+                point(x + 1, y + 1),
+                point(x, y + 1),
+            ];
+            let values = [
+                potential(x, y),
+                potential(x + 1, y),
+                potential(x + 1, y + 1),
+                potential(x, y + 1),
+            ];
+            for (index, &level) in levels.iter().enumerate() {
+                let edges: [Option<Vec2>; 4] = std::array::from_fn(|edge| {
+                    let next = (edge + 1) % 4;
+                    crossing(
+                        corners[edge],
+                        corners[next],
+                        values[edge],
+                        values[next],
+                        level,
+                    )
+                });
+                match edges {
+                    [Some(a), Some(b), None, None]
+                    | [Some(a), None, Some(b), None]
+                    | [Some(a), None, None, Some(b)]
+                    | [None, Some(a), Some(b), None]
+                    | [None, Some(a), None, Some(b)]
+                    | [None, None, Some(a), Some(b)] => contours.segments.push((a, b, index)),
+                    [Some(e0), Some(e1), Some(e2), Some(e3)] => {
+                        // Saddle, the center decides which corners connect.
+                        let center = values.iter().sum::<f32>() / 4.;
+                        if (center < level) == (values[0] < level) {
+                            contours.segments.push((e0, e1, index));
+                            contours.segments.push((e2, e3, index));
+                        } else {
+                            contours.segments.push((e3, e0, index));
+                            contours.segments.push((e1, e2, index));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn draw_contours(mut gizmos: Gizmos, theme: Res<Theme>, contours: Res<PotentialContours>) {
+    if !contours.active {
+        return;
+    }
+    for &(a, b, level) in &contours.segments {
+        gizmos.line_2d(a, b, theme.ramp(level as f32 / (LEVELS - 1) as f32));
+    }
+}
+
+/// Overlay of iso-potential contours.
+pub struct ContourPlugin;
+
+impl Plugin for ContourPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PotentialContours>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .init_resource::<Quality>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<PhysicsSettings>()
+            .add_systems(
+                Update,
+                (toggle_contours, sample_contours, draw_contours).chain(),
+            );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::bodies::Bodies;
+use crate::inspector::Inspector;
+use crate::localization::Localization;
+use crate::physics_config::PhysicsConfig;
+use crate::physics_plugin::{PhysicsSettings, RandomDisc};
+use crate::quality::Quality;
+use crate::scenario::RestartScenario;
+use crate::sim_rate::SimRate;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+/// `value` after the widgets `edit` adds changed a copy of it, `None` when
+/// they left it as it was. Resources are only written when something
+/// changed, so whatever watches them isn't woken up every frame.
+fn edited<T: PartialEq + Copy>(value: T, edit: impl FnOnce(&mut T)) -> Option<T> {
+    let mut edited = value;
+    edit(&mut edited);
+    (edited != value).then_some(edited)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn show_control_panel(
+    mut contexts: EguiContexts,
+    localization: Res<Localization>,
+    quality: Res<Quality>,
+    mut config: ResMut<PhysicsConfig>,
+    mut settings: ResMut<PhysicsSettings>,
+    mut disc: ResMut<RandomDisc>,
+    mut time: ResMut<Time<Virtual>>,
+    sim_rate: Option<ResMut<SimRate>>,
+    inspector: Res<Inspector>,
+    mut restarts: EventWriter<RestartScenario>,
+    bodies: Bodies,
+) {
+    // There is nothing to draw on without a window, e.g. when headless.
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+    let text = |id: &str| localization.text(id, &[]);
+
+    egui::SidePanel::right("control_panel").show(ctx, |ui| {
+        ui.heading(text("panel-physics"));
+        let theta_threshold = config.quality_settings(*quality).theta_threshold;
+        if let Some(theta_threshold) = edited(theta_threshold, |theta_threshold| {
+            ui.add(egui::Slider::new(theta_threshold, 0.1..=8.).text(text("panel-theta")));
+        }) {
+            config.set_theta_threshold(theta_threshold);
+        }
+        if let Some(edited) = edited(*settings, |settings| {
+            ui.add(
+                egui::Slider::new(&mut settings.g, 1e-6..=1e-2)
+                    .logarithmic(true)
+                    .text(text("panel-g")),
+            );
+            ui.add(
+                egui::Slider::new(&mut settings.softening, 0.0..=50.).text(text("panel-softening")),
+            );
+        }) {
+            *settings = edited;
+        }
+
+        // The sim rate sets the speed of the virtual time itself, so it is
+        // its target that is changed then.
+        if let Some(mut sim_rate) = sim_rate {
+            let speed = sim_rate.target * 0.01;
+            if let Some(target) = edited(sim_rate.target, |target| {
+                ui.add(
+                    egui::DragValue::new(target)
+                        .speed(speed)
+                        .range(0.0..=f64::MAX)
+                        .prefix(format!("{} ", text("panel-sim-rate"))),
+                );
+            }) {
+                sim_rate.target = target;
+            }
+        } else if let Some(speed) = edited(time.relative_speed(), |speed| {
+            ui.add(
+                egui::Slider::new(speed, 0.0..=10.)
+                    .logarithmic(true)
+                    .text(text("panel-time-scale")),
+            );
+        }) {
+            time.set_relative_speed(speed);
+        }
+
+        ui.separator();
+        ui.heading(text("panel-bodies"));
+        ui.label(localization.text("panel-body-count", &[("count", bodies.count() as f64)]));
+        if let Some(count) = edited(disc.count, |count| {
+            ui.add(
+                egui::Slider::new(count, 0..=100_000)
+                    .logarithmic(true)
+                    .text(text("panel-disc-count")),
+            );
+        }) {
+            disc.count = count;
+        }
+        if ui.button(text("panel-restart")).clicked() {
+            restarts.send(RestartScenario);
+        }
+
+        ui.separator();
+        ui.heading(text("panel-inspected"));
+        match inspector.target.and_then(|target| bodies.get(target)) {
+            Some(body) => {
+                for (id, value) in [
+                    ("panel-mass", body.mass),
+                    ("panel-velocity-x", body.velocity.x),
+                    ("panel-velocity-y", body.velocity.y),
+                    ("panel-speed", body.velocity.length()),
+                ] {
+                    ui.label(localization.text(id, &[("value", value as f64)]));
+                }
+            }
+            None => {
+                ui.label(text("panel-nothing-inspected"));
+            }
+        }
+    });
+}
+
+/// A side panel with live controls of the theta threshold, the gravity,
+/// the time scale and the size of the random disc, and the mass and
+/// velocity of the body picked in the inspector.
+///
+/// The theta threshold set here lasts until the next scenario is loaded,
+/// which resolves it from the [`PhysicsConfig`] layers again.
+pub struct ControlPanelPlugin;
+
+impl Plugin for ControlPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(EguiPlugin)
+            .init_resource::<Localization>()
+            .init_resource::<Quality>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<PhysicsSettings>()
+            .init_resource::<RandomDisc>()
+            .init_resource::<Inspector>()
+            .add_event::<RestartScenario>()
+            .add_systems(Update, show_control_panel);
+    }
+}
@@ -0,0 +1,174 @@
+//! `spacesim convergence`: measures how fast the error of each integrator
+//! shrinks with the timestep on an orbit with a known solution.
+
+use crate::integrator::rk4_step;
+use crate::physics_plugin::{point_mass_acceleration, G};
+use bevy::math::Vec2;
+
+/// Mass of each of the two bodies
+const MASS: f32 = 5e9;
+/// Distance between the bodies
+const SEPARATION: f32 = 100.;
+const RUNS: usize = 6;
+/// Errors below this come mostly from the float rounding over the many
+/// steps rather than from the integrator, they are left out of the fit
+const ROUNDING_FLOOR: f32 = 1e-3;
+
+/// Position, velocity and mass of a body.
+type Body = (Vec2, Vec2, f32);
+
+/// Advances the bodies by `dt`.
+type Step = fn(&mut [Body; 2], f32);
+
+/// The integrators to measure, with the order they should converge with
+/// and the steps per half orbit of their coarsest run, every further run
+/// halves the step. Higher orders start coarser, otherwise their error
+/// drops below the float precision within the first few runs.
+const INTEGRATORS: &[(&str, Step, u32, usize)] = &[
+    ("semi-implicit Euler", euler_step, 1, 100),
+    ("velocity Verlet", verlet_step, 2, 100),
+    ("RK4", rk4, 4, 8),
+];
+
+/// Acceleration of every body caused by the other one.
+fn accelerations(bodies: &[Body; 2]) -> [Vec2; 2] {
+    let [(a, _, a_mass), (b, _, b_mass)] = *bodies;
+    [
+        point_mass_acceleration(a, b, b_mass, G),
+        point_mass_acceleration(b, a, a_mass, G),
+    ]
+}
+
+/// Same order as the simulation: move with the old velocity, then
+/// accelerate at the new positions.
+fn euler_step(bodies: &mut [Body; 2], dt: f32) {
+    for (position, velocity, _) in bodies.iter_mut() {
+        *position += *velocity * dt;
+    }
+    let accelerations = accelerations(bodies);
+    for ((_, velocity, _), acceleration) in bodies.iter_mut().zip(accelerations) {
+        *velocity += acceleration * dt;
+    }
+}
+
+/// Moves with the velocity and half the old acceleration, then accelerates
+/// with the average of the old and the new acceleration.
+fn verlet_step(bodies: &mut [Body; 2], dt: f32) {
+    let old = accelerations(bodies);
+    for ((position, velocity, _), acceleration) in bodies.iter_mut().zip(old) {
+        *position += *velocity * dt + 0.5 * acceleration * dt * dt;
+    }
+    let new = accelerations(bodies);
+    for (((_, velocity, _), old), new) in bodies.iter_mut().zip(old).zip(new) {
+        *velocity += 0.5 * (old + new) * dt;
+    }
+}
+
+/// The same step the simulation takes with RK4.
+fn rk4(bodies: &mut [Body; 2], dt: f32) {
+    let masses = bodies.map(|(_, _, mass)| mass);
+    let mut positions = bodies.map(|(position, _, _)| position);
+    let mut velocities = bodies.map(|(_, velocity, _)| velocity);
+    rk4_step(&mut positions, &mut velocities, dt, |positions| {
+        let [a, b] = [positions[0], positions[1]];
+        vec![
+            point_mass_acceleration(a, b, masses[1], G),
+            point_mass_acceleration(b, a, masses[0], G),
+        ]
+    });
+    for ((body, position), velocity) in bodies.iter_mut().zip(positions).zip(velocities) {
+        body.0 = position;
+        body.1 = velocity;
+    }
+}
+
+/// The two bodies on a circular orbit around their common center at the
+/// origin, starting on the x axis.
+fn circular_orbit() -> [Body; 2] {
+    let speed = (G * 2. * MASS / SEPARATION).sqrt() / 2.;
+    let offset = Vec2::new(SEPARATION / 2., 0.);
+    [
+        (offset, Vec2::new(0., speed), MASS),
+        (-offset, Vec2::new(0., -speed), MASS),
+    ]
+}
+
+/// Time one orbit takes.
+fn period() -> f32 {
+    let angular_speed = (G * 2. * MASS / SEPARATION.powi(3)).sqrt();
+    std::f32::consts::TAU / angular_speed
+}
+
+/// Distance between where the separation ended up after half an orbit
+/// with `steps` steps and where it should be, flipped from the start.
+///
+/// Half an orbit rather than a whole one, over a whole one the first order
+/// errors of some integrators cancel out and they look more accurate than
+/// they are.
+fn orbit_error(step: Step, steps: usize) -> f32 {
+    let mut bodies = circular_orbit();
+    let dt = period() / 2. / steps as f32;
+    for _ in 0..steps {
+        step(&mut bodies, dt);
+    }
+    let start = circular_orbit();
+    (bodies[0].0 - bodies[1].0).distance(start[1].0 - start[0].0)
+}
+
+/// Slope and intercept of the least squares line through the points.
+fn fit_line(points: &[(f64, f64)]) -> (f64, f64) {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let slope = covariance / variance;
+    (slope, mean_y - slope * mean_x)
+}
+
+/// Runs the orbit for every integrator with halving timesteps and prints
+/// the errors, the order between consecutive runs, and the order and error
+/// constant `C` of the fit `error ≈ C·dtᵖ` over all the runs.
+pub fn run() {
+    println!(
+        "Two equal bodies on a circular orbit, error after half a period of {:.3} s",
+        period() / 2.
+    );
+    for &(name, step, expected_order, coarsest_steps) in INTEGRATORS {
+        println!();
+        println!("{name} (expected order {expected_order})");
+        println!("{:>10} {:>12} {:>6}", "dt", "error", "order");
+
+        let mut points = Vec::new();
+        let mut previous_error: Option<f32> = None;
+        for run in 0..RUNS {
+            let steps = coarsest_steps << run;
+            let dt = period() / 2. / steps as f32;
+            let error = orbit_error(step, steps);
+            let order = match previous_error {
+                Some(previous) => format!("{:.2}", (previous / error).log2()),
+                None => "-".to_owned(),
+            };
+            if error < ROUNDING_FLOOR {
+                println!("{dt:>10.5} {error:>12.4e} {order:>6}  (rounding)");
+            } else {
+                println!("{dt:>10.5} {error:>12.4e} {order:>6}");
+            }
+            previous_error = Some(error);
+            if error >= ROUNDING_FLOOR {
+                points.push(((dt as f64).ln(), (error as f64).ln()));
+            }
+        }
+
+        if points.len() >= 2 {
+            let (order, intercept) = fit_line(&points);
+            println!(
+                "measured order {order:.2}, error constant {:.4e}",
+                intercept.exp()
+            );
+        }
+    }
+}
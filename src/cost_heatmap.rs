@@ -0,0 +1,174 @@
+use crate::cadence::{cadence_elapsed, Cadences, Subsystem};
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_config::{PhysicsConfig, PhysicsQuality};
+use crate::physics_plugin::{GravityTrees, MainCamera, Mass, G};
+use crate::quality::Quality;
+use crate::theme::Theme;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+use std::time::Instant;
+
+/// Number of cells along each side of the view.
+const CELLS_PER_SIDE: usize = 32;
+
+/// What the cost of the force calculation is measured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostMetric {
+    /// Tree nodes the bodies interact with, the work the traversal does
+    Interactions,
+    /// Time the traversals take, which also shows how well the nodes stay
+    /// in the cache
+    Time,
+}
+
+/// Grid over the view colored by how much the force calculation of the
+/// bodies in each cell costs, from cheap to the most expensive cell, so
+/// dense cores forcing deep traversals stand out. Off when `metric` is
+/// `None`.
+///
+/// The cost is measured by walking the trees of the last force calculation
+/// again every few steps, see [`Cadences`], so nothing is shown while the
+/// domain decomposition, which builds its own trees, computes the forces.
+#[derive(Resource, Debug, Default)]
+pub struct CostHeatmap {
+    pub metric: Option<CostMetric>,
+    /// Whether to measure on the next frame whatever the cadence
+    refresh: bool,
+    /// Cells with any cost, with their cost relative to the most expensive
+    /// one
+    cells: Vec<(Rect, f32)>,
+}
+
+/// Cycles the heat map from off through the interactions and the time.
+fn toggle_cost_heatmap(actions: Actions, mut heatmap: ResMut<CostHeatmap>) {
+    if actions.just_pressed(Action::CycleCostHeatmap) {
+        heatmap.metric = match heatmap.metric {
+            None => Some(CostMetric::Interactions),
+            Some(CostMetric::Interactions) => Some(CostMetric::Time),
+            Some(CostMetric::Time) => None,
+        };
+        heatmap.cells.clear();
+        // Measure right away instead of waiting for the next refresh.
+        heatmap.refresh = true;
+    }
+}
+
+/// Cost of the traversal for a body at `position`.
+fn traversal_cost(
+    trees: &GravityTrees,
+    world: SimWorld,
+    position: Vec2,
+    theta_threshold: f32,
+    metric: CostMetric,
+) -> f32 {
+    let Some(tree) = trees.tree(world) else {
+        return 0.;
+    };
+    match metric {
+        CostMetric::Interactions => {
+            let mut interactions = 0;
+            tree.for_each_accepted(position, theta_threshold, |_| interactions += 1);
+            interactions as f32
+        }
+        CostMetric::Time => {
+            let start = Instant::now();
+            std::hint::black_box(tree.accumulate_acceleration(position, theta_threshold, G, 0.));
+            start.elapsed().as_secs_f32()
+        }
+    }
+}
+
+fn refresh_requested(heatmap: Res<CostHeatmap>) -> bool {
+    heatmap.refresh
+}
+
+fn measure_cost(
+    quality: PhysicsQuality,
+    trees: Res<GravityTrees>,
+    mut heatmap: ResMut<CostHeatmap>,
+    cameras: Query<(&GlobalTransform, &OrthographicProjection), With<MainCamera>>,
+    bodies: Query<(&Transform, Option<&SimWorld>), With<Mass>>,
+) {
+    heatmap.refresh = false;
+    let Some(metric) = heatmap.metric else {
+        return;
+    };
+    let Ok((camera_transform, projection)) = cameras.get_single() else {
+        return;
+    };
+
+    let view = Rect::from_center_size(
+        camera_transform.translation().xy() + projection.area.center(),
+        projection.area.size(),
+    );
+    let cell_size = view.size() / CELLS_PER_SIDE as f32;
+    let theta_threshold = quality.settings().theta_threshold;
+
+    let mut costs = vec![0.; CELLS_PER_SIDE * CELLS_PER_SIDE];
+    for (transform, world) in &bodies {
+        let position = transform.translation.xy();
+        let cell = ((position - view.min) / cell_size).floor();
+        let in_view =
+            cell.cmpge(Vec2::ZERO).all() && cell.cmplt(Vec2::splat(CELLS_PER_SIDE as f32)).all();
+        if !in_view {
+            continue;
+        }
+        let world = world.copied().unwrap_or_default();
+        costs[cell.y as usize * CELLS_PER_SIDE + cell.x as usize] +=
+            traversal_cost(&trees, world, position, theta_threshold, metric);
+    }
+
+    let max_cost = costs.iter().copied().fold(0., f32::max);
+    heatmap.cells.clear();
+    if max_cost <= 0. {
+        return;
+    }
+    for (index, &cost) in costs.iter().enumerate() {
+        if cost > 0. {
+            let min = view.min
+                + Vec2::new(
+                    (index % CELLS_PER_SIDE) as f32,
+                    (index / CELLS_PER_SIDE) as f32,
+                ) * cell_size;
+            heatmap
+                .cells
+                .push((Rect::from_corners(min, min + cell_size), cost / max_cost));
+        }
+    }
+}
+
+fn draw_cost_heatmap(mut gizmos: Gizmos, theme: Res<Theme>, heatmap: Res<CostHeatmap>) {
+    if heatmap.metric.is_none() {
+        return;
+    }
+    for &(cell, cost) in &heatmap.cells {
+        // Slightly smaller than the cell, so neighbouring cells don't draw
+        // over each other's edges.
+        gizmos.rect_2d(cell.center(), cell.size() * 0.9, theme.ramp(cost));
+    }
+}
+
+/// Overlay of the [`CostHeatmap`], cycled with M by default.
+pub struct CostHeatmapPlugin;
+
+impl Plugin for CostHeatmapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CostHeatmap>()
+            .init_resource::<GravityTrees>()
+            .init_resource::<Quality>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .init_resource::<Cadences>()
+            .add_systems(
+                Update,
+                (
+                    toggle_cost_heatmap,
+                    measure_cost
+                        .run_if(refresh_requested.or(cadence_elapsed(Subsystem::CostHeatmap))),
+                    draw_cost_heatmap,
+                )
+                    .chain(),
+            );
+    }
+}
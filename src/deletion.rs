@@ -0,0 +1,132 @@
+use crate::bodies::Bodies;
+use crate::comparison::AccuracyComparison;
+use crate::ephemeris::EphemerisComparison;
+use crate::follow_camera::CameraFollow;
+use crate::input::{Action, Actions, InputMap};
+use crate::inspector::Inspector;
+use crate::mission::Mission;
+use crate::physics_plugin::{GravityTrees, Mass, Velocity};
+use crate::preview::TrajectoryPreview;
+use crate::scenario::ScenarioEntity;
+use crate::state::{restart_time, SimState};
+use crate::statistics::SimStatistics;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+/// Everything holding on to bodies between frames, which has to let go of
+/// the deleted ones.
+#[derive(SystemParam)]
+struct BodyReferences<'w> {
+    inspector: ResMut<'w, Inspector>,
+    follow: ResMut<'w, CameraFollow>,
+    preview: Option<ResMut<'w, TrajectoryPreview>>,
+    trees: ResMut<'w, GravityTrees>,
+}
+
+impl BodyReferences<'_> {
+    /// Lets go of the `deleted` body, or of all of them when `None`.
+    fn forget(&mut self, deleted: Option<Entity>) {
+        let refers = |target: Option<Entity>| {
+            target.is_some_and(|target| deleted.is_none_or(|deleted| deleted == target))
+        };
+        if refers(self.inspector.target) {
+            self.inspector.target = None;
+        }
+        if refers(self.follow.target) {
+            self.follow.target = None;
+        }
+        if let Some(preview) = &mut self.preview {
+            if refers(preview.target) {
+                preview.target = None;
+            }
+        }
+        // The trees still hold the mass of the deleted bodies, which the
+        // overlays would show until the next step rebuilds them.
+        self.trees.clear();
+    }
+}
+
+/// Despawns the inspected body with Delete (with the default input map),
+/// along with the parts docked to it.
+fn delete_inspected_body(
+    mut commands: Commands,
+    actions: Actions,
+    bodies: Bodies,
+    mut references: BodyReferences,
+) {
+    if !actions.just_pressed(Action::DeleteBody) {
+        return;
+    }
+    let Some(body) = references
+        .inspector
+        .target
+        .and_then(|target| bodies.get(target))
+    else {
+        return;
+    };
+    commands.entity(body.entity).despawn_recursive();
+    references.forget(Some(body.entity));
+}
+
+/// Despawns every body and whatever else the scenario spawned with
+/// Backspace (with the default input map), and starts the clock over, so
+/// bodies can be placed into an empty simulation. Unlike a restart the
+/// scenario isn't loaded again.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn clear_all_bodies(
+    mut commands: Commands,
+    actions: Actions,
+    mut references: BodyReferences,
+    mut time: ResMut<Time<Virtual>>,
+    comparison: Option<ResMut<AccuracyComparison>>,
+    ephemerides: Option<ResMut<EphemerisComparison>>,
+    statistics: Option<ResMut<SimStatistics>>,
+    entities: Query<
+        Entity,
+        (
+            Or<(With<ScenarioEntity>, With<Mass>, With<Velocity>)>,
+            Without<Parent>,
+        ),
+    >,
+) {
+    if !actions.just_pressed(Action::ClearBodies) {
+        return;
+    }
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    references.forget(None);
+
+    restart_time(&mut time);
+    if let Some(mut comparison) = comparison {
+        comparison.stop();
+    }
+    if let Some(mut ephemerides) = ephemerides {
+        ephemerides.errors.clear();
+    }
+    if let Some(mut statistics) = statistics {
+        *statistics = SimStatistics::default();
+    }
+    // The mission refers to bodies which are gone.
+    commands.remove_resource::<Mission>();
+}
+
+/// Deletes single bodies or all of them while a scenario is shown.
+pub struct DeletionPlugin;
+
+impl Plugin for DeletionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inspector>()
+            .init_resource::<CameraFollow>()
+            .init_resource::<GravityTrees>()
+            .init_resource::<InputMap>()
+            .add_systems(
+                Update,
+                (delete_inspected_body, clear_all_bodies).run_if(
+                    in_state(SimState::Running)
+                        .or(in_state(SimState::Paused))
+                        .or(in_state(SimState::Editing)),
+                ),
+            );
+    }
+}
@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+
+/// When present, the simulation avoids everything that could make two runs
+/// with the same seed and config differ, for replays and lockstep:
+///
+/// - bodies enter the force calculation ordered by their entities rather
+///   than by how they happen to be stored, so the trees are always built
+///   and summed up in the same order
+/// - systems reacting to the frame rate, such as the body count
+///   controller, are turned off
+/// - the [`Watchdog`](crate::watchdog::Watchdog) is ignored, the force
+///   calculation always finishes
+/// - the [`ViewRelaxation`](crate::view_relaxation::ViewRelaxation) is
+///   ignored, bodies outside the view get the same forces as inside
+///
+/// The forces of every body are always computed by a single thread in a
+/// fixed order, and collisions are handled in the order of their entities,
+/// so the results don't depend on the number of threads either way. The
+/// steps themselves are fixed by the [`TickRate`](crate::fixed_step::TickRate)
+/// rather than the frame rate.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct Determinism;
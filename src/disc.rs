@@ -0,0 +1,180 @@
+use crate::distributions::Distribution;
+use crate::equilibrium::circular_speed;
+use crate::physics_config::PhysicsQuality;
+use crate::physics_plugin::{build_fitted_tree, BodyMaterial, Mass, PhysicsSettings, Velocity};
+use crate::radius::BodyDensity;
+use crate::scenario::SimRng;
+use crate::theme::Theme;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Number of rings the disc is split into to measure its surface density
+/// and rotation curve.
+const RINGS: usize = 16;
+
+/// Parameters of the "Stable disc" scenario, a disc whose bodies get the
+/// circular speeds the gravity of the disc itself and the central body
+/// calls for.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StableDisc {
+    pub count: usize,
+    pub central_mass: f32,
+    /// Distance of the bodies from the center
+    pub radius: Distribution,
+    pub mass: Distribution,
+    /// Toomre Q the random motion of the bodies around their circular
+    /// orbits is set to, above 1 the disc is stable against collapsing into
+    /// clumps. `None` leaves the orbits perfectly circular.
+    pub toomre_q: Option<f32>,
+}
+
+impl Default for StableDisc {
+    fn default() -> Self {
+        StableDisc {
+            count: 2_000,
+            central_mass: 100_000_000_000.,
+            // Density proportional to x spreads the bodies evenly over the
+            // area of the disc.
+            radius: Distribution::PowerLaw {
+                min: 80.,
+                max: 300.,
+                exponent: -1.,
+            },
+            mass: Distribution::Uniform {
+                min: 1_000_000.,
+                max: 20_000_000.,
+            },
+            toomre_q: Some(1.5),
+        }
+    }
+}
+
+/// Velocity dispersions in the radial and tangential direction of every
+/// body giving the disc the Toomre `q`, from the radii, masses and circular
+/// speeds of its bodies and the gravitational constant `g`.
+fn dispersions(bodies: &[(f32, f32, f32)], q: f32, g: f32) -> Vec<(f32, f32)> {
+    let min = bodies.iter().map(|body| body.0).fold(f32::MAX, f32::min);
+    let max = bodies.iter().map(|body| body.0).fold(f32::MIN, f32::max);
+    let width = (max - min).max(f32::EPSILON) / RINGS as f32;
+    let ring = |radius: f32| (((radius - min) / width) as usize).min(RINGS - 1);
+
+    let mut masses = [0.; RINGS];
+    let mut speeds = [0.; RINGS];
+    let mut counts = [0; RINGS];
+    for &(radius, mass, speed) in bodies {
+        masses[ring(radius)] += mass;
+        speeds[ring(radius)] += speed;
+        counts[ring(radius)] += 1;
+    }
+    let radii: Vec<f32> = (0..RINGS).map(|i| min + (i as f32 + 0.5) * width).collect();
+    let speeds: Vec<f32> = (0..RINGS)
+        .map(|i| speeds[i] / counts[i].max(1) as f32)
+        .collect();
+
+    let rings: Vec<(f32, f32)> = (0..RINGS)
+        .map(|i| {
+            let (inner, outer) = (i.saturating_sub(1), (i + 1).min(RINGS - 1));
+            let radius = radii[i];
+            let omega = speeds[i] / radius;
+            let area = std::f32::consts::PI
+                * ((radius + width / 2.).powi(2) - (radius - width / 2.).powi(2));
+            let density = masses[i] / area;
+            // κ² = 2Ω² (1 + d ln v / d ln R), with the slope of the rotation
+            // curve from the neighbouring rings
+            let slope = if counts[inner] > 0 && counts[outer] > 0 && speeds[inner] > 0. {
+                (speeds[outer] / speeds[inner]).ln() / (radii[outer] / radii[inner]).ln()
+            } else {
+                0.
+            };
+            let kappa = (2. * omega * omega * (1. + slope)).max(0.).sqrt();
+            if kappa == 0. || omega == 0. {
+                return (0., 0.);
+            }
+            let radial = q * 3.36 * g * density / kappa;
+            // Epicyclic approximation
+            (radial, radial * kappa / (2. * omega))
+        })
+        .collect();
+    bodies.iter().map(|body| rings[ring(body.0)]).collect()
+}
+
+/// Draws from the standard normal distribution.
+fn normal(rng: &mut impl Rng) -> f32 {
+    Distribution::Normal {
+        mean: 0.,
+        std_dev: 1.,
+    }
+    .sample(rng)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_stable_disc(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+    quality: PhysicsQuality,
+    settings: Res<PhysicsSettings>,
+    disc: Res<StableDisc>,
+    mut rng: ResMut<SimRng>,
+) {
+    let rng = &mut rng.rng;
+    let circle = meshes.add(Circle::new(1.));
+    let material = materials.add(ColorMaterial::from(theme.body()));
+    commands.insert_resource(BodyMaterial(material.clone()));
+
+    let bodies: Vec<(Vec2, f32)> = (0..disc.count)
+        .map(|_| {
+            let dir = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU));
+            (dir * disc.radius.sample(rng), disc.mass.sample(rng))
+        })
+        .collect();
+
+    // The rotation curve follows from the actual gravity of the disc and
+    // the central body, not just the central body.
+    let theta_threshold = quality.settings().theta_threshold;
+    let q_tree = build_fitted_tree(
+        &bodies
+            .iter()
+            .copied()
+            .chain([(Vec2::ZERO, disc.central_mass)])
+            .collect::<Vec<_>>(),
+    );
+    let rotation: Vec<(f32, f32, f32)> = bodies
+        .iter()
+        .map(|&(position, mass)| {
+            let speed = circular_speed(&q_tree, position, Vec2::ZERO, theta_threshold, settings.g);
+            (position.length(), mass, speed)
+        })
+        .collect();
+    let dispersions = disc.toomre_q.map(|q| dispersions(&rotation, q, settings.g));
+
+    commands.spawn((
+        Velocity(Vec2::ZERO),
+        Mass(disc.central_mass),
+        BodyDensity::sized(disc.central_mass, 50.),
+        Mesh2d(circle.clone()),
+        MeshMaterial2d(material.clone()),
+        Transform::default(),
+    ));
+
+    for (i, (&(position, mass), &(_, _, speed))) in bodies.iter().zip(&rotation).enumerate() {
+        let radial = position.normalize_or_zero();
+        let tangent = radial.perp();
+        let mut velocity = tangent * speed;
+        if let Some(dispersions) = &dispersions {
+            let (radial_dispersion, tangential_dispersion) = dispersions[i];
+            velocity += radial * radial_dispersion * normal(rng)
+                + tangent * tangential_dispersion * normal(rng);
+        }
+
+        commands.spawn((
+            Velocity(velocity),
+            Mass(mass),
+            Mesh2d(circle.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::from_translation(position.extend(0.)),
+        ));
+    }
+}
@@ -0,0 +1,511 @@
+//! `spacesim coordinator` and `spacesim worker`: an experimental mode
+//! spreading one simulation over several processes, which can run on
+//! different machines. Only built with the `distributed` feature.
+//!
+//! The coordinator splits the space into the tiles of a grid fixed for the
+//! run, like the [`DomainDecomposition`] does between threads, and hands
+//! every worker a band of rows of tiles with the bodies in them. The
+//! workers keep and move their own bodies. Every step the coordinator only
+//! passes on the bodies which crossed into another band, the bodies in the
+//! rows along the edges of the bands (the ghost regions) and the mass and
+//! center of mass of every tile for the far field, so no process has to
+//! hold all the bodies.
+//!
+//! The processes talk over TCP, in messages of a line of JSON each.
+//!
+//! [`DomainDecomposition`]: crate::domain_decomposition::DomainDecomposition
+
+use crate::domain_decomposition::{tile_accelerations, Grid, TileAggregate};
+use crate::fixed_step::TickRate;
+use crate::integrator::{IntegratorKind, LastAcceleration};
+use crate::physics_plugin::{G, THETA_THRESHOLD};
+use crate::quadtree::QuadTree;
+use crate::scenario_file::ScenarioFile;
+use bevy::math::Vec2;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::Range;
+use std::time::Instant;
+
+/// Steps between the progress reports of the coordinator
+const REPORT_EVERY: usize = 100;
+/// Bodies move like with the default integrator of the live simulation.
+const INTEGRATOR: IntegratorKind = IntegratorKind::VelocityVerlet;
+
+/// A body, owned by the worker whose band its tile is in.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Body {
+    position: Vec2,
+    velocity: Vec2,
+    /// Zero for bodies which are attracted but don't attract others
+    mass: f32,
+    /// Acceleration of the last step, `None` before the first one
+    acceleration: Option<Vec2>,
+}
+
+/// The gravity the workers compute.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Gravity {
+    g: f32,
+    theta_threshold: f32,
+    softening: f32,
+}
+
+/// Messages from the coordinator to a worker.
+#[derive(Debug, Serialize, Deserialize)]
+enum Command {
+    /// Take over the `bodies` in the `rows` of tiles of the `grid`
+    Assign {
+        grid: Grid,
+        rows: Range<usize>,
+        gravity: Gravity,
+        bodies: Vec<Body>,
+    },
+    /// Move the bodies and send back the ones which left the band
+    Drift { dt: f32 },
+    /// Take the bodies which came into the band and send back what the
+    /// other workers need of it
+    Exchange { arriving: Vec<Body> },
+    /// Accelerate the bodies, from the `ghosts` in the tiles of the rows
+    /// around the band and the `aggregates` (center of mass and mass) of
+    /// all the tiles
+    Kick {
+        dt: f32,
+        ghosts: Vec<(usize, Vec<(Vec2, f32)>)>,
+        aggregates: Vec<(Vec2, f32)>,
+    },
+    /// Send back all the bodies and quit
+    Finish,
+}
+
+/// Messages from a worker to the coordinator.
+#[derive(Debug, Serialize, Deserialize)]
+enum Reply {
+    Leaving(Vec<Body>),
+    /// The aggregates of the tiles of the band with attracting bodies, and
+    /// the attracting bodies in the tiles of its first and last row
+    Summary {
+        aggregates: Vec<(Vec2, f32)>,
+        edges: Vec<(usize, Vec<(Vec2, f32)>)>,
+    },
+    Kicked,
+    Bodies(Vec<Body>),
+}
+
+/// One end of the connection between the coordinator and a worker.
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        // Every step waits for a few small messages, they shouldn't be
+        // held back to be sent together.
+        stream.set_nodelay(true)?;
+        Ok(Connection {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+        })
+    }
+
+    fn send(&mut self, message: &impl Serialize) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, message)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    fn receive<T: DeserializeOwned>(&mut self) -> io::Result<T> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+/// Error for a message which doesn't belong where it came.
+fn unexpected(message: impl Debug) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected message {message:?}"),
+    )
+}
+
+/// The bodies of a worker and what it needs to move them.
+struct Band {
+    grid: Grid,
+    rows: Range<usize>,
+    gravity: Gravity,
+    bodies: Vec<Body>,
+}
+
+impl Band {
+    fn row(&self, position: Vec2) -> usize {
+        self.grid.tile_index(position) / self.grid.tiles_per_side
+    }
+
+    /// Moves the bodies by `dt` and takes out the ones which left the band.
+    fn drift(&mut self, dt: f32) -> Vec<Body> {
+        for body in &mut self.bodies {
+            body.position +=
+                INTEGRATOR.displacement(body.velocity, &LastAcceleration(body.acceleration), dt);
+        }
+        let (staying, leaving): (Vec<Body>, Vec<Body>) = std::mem::take(&mut self.bodies)
+            .into_iter()
+            .partition(|body| self.rows.contains(&self.row(body.position)));
+        self.bodies = staying;
+        leaving
+    }
+
+    fn summary(&self) -> Reply {
+        let mut aggregates: BTreeMap<usize, TileAggregate> = BTreeMap::new();
+        let mut edges: BTreeMap<usize, Vec<(Vec2, f32)>> = BTreeMap::new();
+        for body in self.bodies.iter().filter(|body| body.mass > 0.) {
+            let tile = self.grid.tile_index(body.position);
+            aggregates
+                .entry(tile)
+                .or_default()
+                .add(body.position, body.mass);
+            let row = self.row(body.position);
+            if row == self.rows.start || row + 1 == self.rows.end {
+                edges
+                    .entry(tile)
+                    .or_default()
+                    .push((body.position, body.mass));
+            }
+        }
+        Reply::Summary {
+            aggregates: aggregates
+                .into_values()
+                .map(|aggregate| (aggregate.center_of_mass, aggregate.mass))
+                .collect(),
+            edges: edges.into_iter().collect(),
+        }
+    }
+
+    /// Accelerates the bodies over `dt` tile by tile, like the
+    /// [`DomainDecomposition`](crate::domain_decomposition::DomainDecomposition)
+    /// does.
+    fn kick(
+        &mut self,
+        dt: f32,
+        ghosts: Vec<(usize, Vec<(Vec2, f32)>)>,
+        aggregates: &[(Vec2, f32)],
+    ) {
+        let coarse = QuadTree::build(aggregates);
+        let mut sources: BTreeMap<usize, Vec<(Vec2, f32)>> = ghosts.into_iter().collect();
+        let mut targets: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (idx, body) in self.bodies.iter().enumerate() {
+            let tile = self.grid.tile_index(body.position);
+            targets.entry(tile).or_default().push(idx);
+            if body.mass > 0. {
+                sources
+                    .entry(tile)
+                    .or_default()
+                    .push((body.position, body.mass));
+            }
+        }
+
+        let Gravity {
+            g,
+            theta_threshold,
+            softening,
+        } = self.gravity;
+        for (&tile, indices) in &targets {
+            let near = self
+                .grid
+                .ring_around(tile)
+                .filter_map(|other| sources.get(&other))
+                .flatten()
+                .copied();
+            let accelerations = tile_accelerations(
+                &self.grid,
+                tile,
+                near,
+                &coarse,
+                indices.iter().map(|&idx| self.bodies[idx].position),
+                theta_threshold,
+                g,
+                softening,
+            );
+            for (&idx, acceleration) in indices.iter().zip(accelerations) {
+                let body = &mut self.bodies[idx];
+                let last = LastAcceleration(body.acceleration);
+                body.velocity += INTEGRATOR.velocity_acceleration(acceleration, &last) * dt;
+                body.acceleration = Some(acceleration);
+            }
+        }
+    }
+}
+
+/// Connects to the coordinator at the address on the command line and
+/// moves the bodies it hands over until it is done.
+pub fn run_worker(mut args: impl Iterator<Item = String>) {
+    let address = args
+        .next()
+        .expect("worker expects the address of the coordinator");
+    let stream = TcpStream::connect(&address)
+        .unwrap_or_else(|err| panic!("Couldn't connect to `{address}`: {err}"));
+    if let Err(err) = work(stream) {
+        panic!("Lost the coordinator: {err}");
+    }
+}
+
+fn work(stream: TcpStream) -> io::Result<()> {
+    let mut connection = Connection::new(stream)?;
+    let mut band = match connection.receive()? {
+        Command::Assign {
+            grid,
+            rows,
+            gravity,
+            bodies,
+        } => Band {
+            grid,
+            rows,
+            gravity,
+            bodies,
+        },
+        command => return Err(unexpected(command)),
+    };
+    loop {
+        let reply = match connection.receive()? {
+            Command::Drift { dt } => Reply::Leaving(band.drift(dt)),
+            Command::Exchange { arriving } => {
+                band.bodies.extend(arriving);
+                band.summary()
+            }
+            Command::Kick {
+                dt,
+                ghosts,
+                aggregates,
+            } => {
+                band.kick(dt, ghosts, &aggregates);
+                Reply::Kicked
+            }
+            Command::Finish => return connection.send(&Reply::Bodies(band.bodies)),
+            command => return Err(unexpected(command)),
+        };
+        connection.send(&reply)?;
+    }
+}
+
+/// Sends the `command` to every worker.
+fn broadcast(connections: &mut [Connection], command: &Command) -> io::Result<()> {
+    connections
+        .iter_mut()
+        .try_for_each(|connection| connection.send(command))
+}
+
+/// Simulates the `bodies` for `steps` steps of `dt` on the workers at the
+/// other end of the `connections`, with a grid of `tiles_per_side` squared
+/// tiles, and returns where they ended up.
+fn coordinate(
+    connections: &mut [Connection],
+    bodies: Vec<Body>,
+    tiles_per_side: usize,
+    gravity: Gravity,
+    dt: f32,
+    steps: usize,
+) -> io::Result<Vec<Body>> {
+    let grid = Grid::covering(bodies.iter().map(|body| body.position), tiles_per_side);
+    let n = grid.tiles_per_side;
+    let workers = connections.len();
+    // Rows of tiles of every worker, as even as they go.
+    let bands: Vec<Range<usize>> = (0..workers)
+        .map(|worker| worker * n / workers..(worker + 1) * n / workers)
+        .collect();
+    let owner = |position: Vec2| {
+        let row = grid.tile_index(position) / n;
+        bands
+            .iter()
+            .position(|band| band.contains(&row))
+            .expect("The bands cover all the rows")
+    };
+
+    let mut assigned = vec![Vec::new(); workers];
+    for body in bodies {
+        assigned[owner(body.position)].push(body);
+    }
+    for ((connection, rows), bodies) in connections.iter_mut().zip(&bands).zip(assigned) {
+        connection.send(&Command::Assign {
+            grid: grid.clone(),
+            rows: rows.clone(),
+            gravity,
+            bodies,
+        })?;
+    }
+
+    let start = Instant::now();
+    for step in 1..=steps {
+        broadcast(connections, &Command::Drift { dt })?;
+        let mut arriving = vec![Vec::new(); workers];
+        for connection in connections.iter_mut() {
+            match connection.receive()? {
+                Reply::Leaving(leaving) => {
+                    for body in leaving {
+                        arriving[owner(body.position)].push(body);
+                    }
+                }
+                reply => return Err(unexpected(reply)),
+            }
+        }
+
+        for (connection, arriving) in connections.iter_mut().zip(arriving) {
+            connection.send(&Command::Exchange { arriving })?;
+        }
+        let mut aggregates = Vec::new();
+        let mut edges = BTreeMap::new();
+        for connection in connections.iter_mut() {
+            match connection.receive()? {
+                Reply::Summary {
+                    aggregates: band_aggregates,
+                    edges: band_edges,
+                } => {
+                    aggregates.extend(band_aggregates);
+                    edges.extend(band_edges);
+                }
+                reply => return Err(unexpected(reply)),
+            }
+        }
+
+        for (connection, rows) in connections.iter_mut().zip(&bands) {
+            // The rows just outside of the band
+            let ghosts = edges
+                .iter()
+                .filter(|(&tile, _)| tile / n + 1 == rows.start || tile / n == rows.end)
+                .map(|(&tile, sources)| (tile, sources.clone()))
+                .collect();
+            connection.send(&Command::Kick {
+                dt,
+                ghosts,
+                aggregates: aggregates.clone(),
+            })?;
+        }
+        for connection in connections.iter_mut() {
+            match connection.receive()? {
+                Reply::Kicked => {}
+                reply => return Err(unexpected(reply)),
+            }
+        }
+
+        if step % REPORT_EVERY == 0 {
+            println!(
+                "Step {step}/{steps}, {:.1} s simulated in {:.1} s",
+                step as f32 * dt,
+                start.elapsed().as_secs_f32()
+            );
+        }
+    }
+
+    broadcast(connections, &Command::Finish)?;
+    let mut bodies = Vec::new();
+    for connection in connections.iter_mut() {
+        match connection.receive()? {
+            Reply::Bodies(band_bodies) => bodies.extend(band_bodies),
+            reply => return Err(unexpected(reply)),
+        }
+    }
+    Ok(bodies)
+}
+
+/// The positive number in `value`, panicking with `expected` otherwise.
+fn positive(value: Option<String>, expected: &str) -> usize {
+    value
+        .and_then(|value| value.parse().ok())
+        .filter(|&value| value > 0)
+        .expect(expected)
+}
+
+/// Waits for the workers, simulates the scenario file on the command line
+/// on them and prints the totals of the bodies it ends with.
+///
+/// Options: `--listen <address>` (`0.0.0.0:7878` by default),
+/// `--workers <n>`, `--tiles <n>` per side of the grid, `--steps <n>` and
+/// `--seed <n>` the groups of the scenario are drawn from.
+pub fn run_coordinator(mut args: impl Iterator<Item = String>) {
+    let path = args
+        .next()
+        .expect("coordinator expects the path of a scenario file");
+    let scenario = ScenarioFile::load(path.as_ref())
+        .unwrap_or_else(|err| panic!("Couldn't load scenario `{path}`: {err}"));
+    let mut address = "0.0.0.0:7878".to_owned();
+    let mut workers = 2;
+    let mut tiles_per_side = 8;
+    let mut steps = 1_000;
+    let mut seed = 0;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => address = args.next().expect("--listen expects an address"),
+            "--workers" => workers = positive(args.next(), "--workers expects a number of workers"),
+            "--tiles" => {
+                tiles_per_side = positive(args.next(), "--tiles expects a number of tiles per side")
+            }
+            "--steps" => steps = positive(args.next(), "--steps expects a number of steps"),
+            "--seed" => {
+                seed = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .expect("--seed expects a number")
+            }
+            _ => panic!("Unknown coordinator option `{arg}`"),
+        }
+    }
+    assert!(
+        workers <= tiles_per_side,
+        "Every worker needs at least one row of the {tiles_per_side} rows of tiles"
+    );
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let bodies: Vec<Body> = scenario
+        .initial_bodies(G, &mut rng)
+        .into_iter()
+        .map(|(position, velocity, mass)| Body {
+            position,
+            velocity,
+            mass,
+            acceleration: None,
+        })
+        .collect();
+    let gravity = Gravity {
+        g: G,
+        theta_threshold: scenario.physics.theta_threshold.unwrap_or(THETA_THRESHOLD),
+        softening: 0.,
+    };
+    let dt = 1. / scenario.physics.tick_rate.unwrap_or(TickRate::default().hz) as f32;
+
+    let listener = TcpListener::bind(&address)
+        .unwrap_or_else(|err| panic!("Couldn't listen on `{address}`: {err}"));
+    println!("Waiting for {workers} workers on {address}");
+    let mut connections: Vec<Connection> = (0..workers)
+        .map(|_| {
+            let (stream, peer) = listener.accept()?;
+            println!("Worker {peer} joined");
+            Connection::new(stream)
+        })
+        .collect::<io::Result<_>>()
+        .unwrap_or_else(|err| panic!("Couldn't accept the workers: {err}"));
+
+    println!(
+        "Simulating {} bodies of `{}` for {steps} steps",
+        bodies.len(),
+        scenario.name
+    );
+    let bodies = coordinate(&mut connections, bodies, tiles_per_side, gravity, dt, steps)
+        .unwrap_or_else(|err| panic!("Lost a worker: {err}"));
+
+    let mass: f32 = bodies.iter().map(|body| body.mass).sum();
+    let momentum: Vec2 = bodies.iter().map(|body| body.velocity * body.mass).sum();
+    println!(
+        "Done with {} bodies, total mass {mass:.4e}, momentum ({:.4e}, {:.4e})",
+        bodies.len(),
+        momentum.x,
+        momentum.y
+    );
+}
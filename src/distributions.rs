@@ -0,0 +1,61 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Random distribution a spawn parameter is drawn from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Distribution {
+    /// Always the same value
+    Constant(f32),
+    /// Every value in `min..max` equally likely
+    Uniform {
+        min: f32,
+        max: f32,
+    },
+    Normal {
+        mean: f32,
+        std_dev: f32,
+    },
+    /// Values whose logarithm is normally distributed with `mu` and `sigma`
+    LogNormal {
+        mu: f32,
+        sigma: f32,
+    },
+    /// Values in `min..max` with a density proportional to `x^-exponent`,
+    /// e.g. 2.35 for the Salpeter initial mass function
+    PowerLaw {
+        min: f32,
+        max: f32,
+        exponent: f32,
+    },
+}
+
+/// Draws from the standard normal distribution with the Box-Muller
+/// transform.
+fn standard_normal(rng: &mut impl Rng) -> f32 {
+    // Excluding 0 so the logarithm stays finite.
+    let u1: f32 = 1. - rng.random::<f32>();
+    let u2: f32 = rng.random();
+    (-2. * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+impl Distribution {
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            Distribution::Constant(value) => value,
+            Distribution::Uniform { min, max } => min + (max - min) * rng.random::<f32>(),
+            Distribution::Normal { mean, std_dev } => mean + std_dev * standard_normal(rng),
+            Distribution::LogNormal { mu, sigma } => (mu + sigma * standard_normal(rng)).exp(),
+            Distribution::PowerLaw { min, max, exponent } => {
+                let u: f32 = rng.random();
+                // Inverting the cumulative distribution.
+                let power = 1. - exponent;
+                if power.abs() < f32::EPSILON {
+                    min * (max / min).powf(u)
+                } else {
+                    let (low, high) = (min.powf(power), max.powf(power));
+                    (low + u * (high - low)).powf(1. / power)
+                }
+            }
+        }
+    }
+}
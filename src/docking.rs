@@ -0,0 +1,366 @@
+use crate::fixed_step::InterpolatedTranslation;
+use crate::input::{Action, Actions};
+use crate::inspector::Inspector;
+use crate::physics_plugin::{Mass, Velocity};
+use crate::radius::Radius;
+use crate::timings::PhysicsTimings;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+use bevy::utils::{HashSet, Instant};
+use serde::{Deserialize, Serialize};
+
+/// Allows the body to dock with other dockable bodies it touches.
+///
+/// Docked bodies become children of the composite they docked to and move
+/// with it, the composite carries their mass.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Dockable {
+    /// Largest relative speed at which a touching body still docks instead
+    /// of bouncing off or passing through
+    pub max_relative_speed: f32,
+    /// Speed at which the body is pushed away from the composite when
+    /// undocking, should be above `max_relative_speed` so it doesn't dock
+    /// again right away
+    pub separation_speed: f32,
+}
+
+/// State of a body while it is docked to a composite, its own mass and
+/// velocity are taken over by the composite.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DockedPart {
+    /// Mass the body had before docking
+    pub mass: f32,
+    /// Position relative to the composite's center of mass
+    pub offset: Vec2,
+    /// Scale the body had before docking, used to restore it
+    pub scale: Vec3,
+}
+
+/// Splits every docked part off the `composite`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Undock {
+    pub composite: Entity,
+}
+
+/// Two bodies touching, found by [`detect_collisions`]. `a` is always the
+/// entity with the lower id, so every pair has exactly one key.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Collision {
+    pub a: Entity,
+    pub b: Entity,
+}
+
+impl Collision {
+    fn new(first: Entity, second: Entity) -> Self {
+        Collision {
+            a: first.min(second),
+            b: first.max(second),
+        }
+    }
+}
+
+/// Finds every pair of touching bodies of the same world and sends a
+/// [`Collision`] for each exactly once, sorted by the entities, so the
+/// collisions are always handled in the same order.
+///
+/// Bodies are sorted along the x axis first, only bodies whose extents
+/// overlap on it are compared.
+#[allow(clippy::type_complexity)]
+pub fn detect_collisions(
+    mut timings: ResMut<PhysicsTimings>,
+    mut collisions: EventWriter<Collision>,
+    bodies: Query<
+        (Entity, &Transform, &Radius, Option<&SimWorld>),
+        (With<Velocity>, Without<Parent>),
+    >,
+) {
+    let start = Instant::now();
+    let mut extents: Vec<(Entity, Vec2, f32, SimWorld)> = bodies
+        .iter()
+        .map(|(entity, transform, radius, world)| {
+            (
+                entity,
+                transform.translation.xy(),
+                radius.0,
+                world.copied().unwrap_or_default(),
+            )
+        })
+        .collect();
+    extents.sort_by(|a, b| (a.1.x - a.2).total_cmp(&(b.1.x - b.2)));
+
+    let mut pairs: HashSet<Collision> = HashSet::default();
+    for (i, &(a, a_position, a_radius, a_world)) in extents.iter().enumerate() {
+        for &(b, b_position, b_radius, b_world) in &extents[i + 1..] {
+            // Everything further along starts past this body's extent.
+            if b_position.x - b_radius > a_position.x + a_radius {
+                break;
+            }
+            if a_world == b_world && a_position.distance(b_position) <= a_radius + b_radius {
+                pairs.insert(Collision::new(a, b));
+            }
+        }
+    }
+
+    let mut pairs: Vec<Collision> = pairs.into_iter().collect();
+    pairs.sort();
+    collisions.send_batch(pairs);
+    timings.collision += start.elapsed();
+}
+
+/// Moves the composite to `center`, with the parts docked to it, its
+/// `children`, staying where they are.
+pub(crate) fn move_composite(
+    transform: &mut Transform,
+    center: Vec2,
+    children: Option<&Children>,
+    parts: &mut Query<(&mut DockedPart, &mut Transform), With<Parent>>,
+) {
+    let shift = center - transform.translation.xy();
+    transform.translation = center.extend(transform.translation.z);
+    for &child in children.into_iter().flatten() {
+        if let Ok((mut docked, mut local)) = parts.get_mut(child) {
+            docked.offset -= shift;
+            local.translation = (docked.offset / transform.scale.xy()).extend(0.);
+        }
+    }
+}
+
+/// Docks the colliding pairs of dockable bodies which move slowly enough
+/// relative to each other, the lighter one becomes a part of the heavier
+/// one. The composite moves to the center of mass of both, so docking
+/// keeps the center of mass along with the momentum. A body docks at most
+/// once a step.
+#[allow(clippy::type_complexity)]
+pub fn dock_bodies(
+    mut commands: Commands,
+    mut timings: ResMut<PhysicsTimings>,
+    mut collisions: EventReader<Collision>,
+    mut bodies: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Velocity,
+            &mut Mass,
+            &Dockable,
+            Option<&Children>,
+        ),
+        Without<Parent>,
+    >,
+    mut parts: Query<(&mut DockedPart, &mut Transform), With<Parent>>,
+) {
+    let start = Instant::now();
+    let mut docked: HashSet<Entity> = HashSet::default();
+    for &Collision { a, b } in collisions.read() {
+        if docked.contains(&a) || docked.contains(&b) {
+            continue;
+        }
+        let (
+            Ok((_, _, a_velocity, a_mass, a_dockable, _)),
+            Ok((_, _, b_velocity, b_mass, b_dockable, _)),
+        ) = (bodies.get(a), bodies.get(b))
+        else {
+            continue;
+        };
+        let max_relative_speed = a_dockable
+            .max_relative_speed
+            .min(b_dockable.max_relative_speed);
+        if a_velocity.0.distance(b_velocity.0) > max_relative_speed {
+            continue;
+        }
+
+        let (composite, part) = if a_mass.0 >= b_mass.0 { (a, b) } else { (b, a) };
+        let Ok([composite_body, part_body]) = bodies.get_many_mut([composite, part]) else {
+            continue;
+        };
+        let (_, mut composite_transform, mut composite_velocity, mut composite_mass, _, children) =
+            composite_body;
+        let (_, part_transform, part_velocity, part_mass, _, _) = part_body;
+
+        // The composite keeps the momentum and the center of mass of both
+        // bodies.
+        let total_mass = composite_mass.0 + part_mass.0;
+        composite_velocity.0 =
+            (composite_velocity.0 * composite_mass.0 + part_velocity.0 * part_mass.0) / total_mass;
+        let part_position = part_transform.translation.xy();
+        let center = (composite_transform.translation.xy() * composite_mass.0
+            + part_position * part_mass.0)
+            / total_mass;
+        move_composite(&mut composite_transform, center, children, &mut parts);
+
+        let offset = part_position - center;
+        let docked_part = DockedPart {
+            mass: part_mass.0,
+            offset,
+            scale: part_transform.scale,
+        };
+        composite_mass.0 = total_mass;
+
+        // Children are placed relative to the parent's scale.
+        let local = Transform {
+            translation: (offset / composite_transform.scale.xy()).extend(0.),
+            scale: part_transform.scale / composite_transform.scale,
+            ..Default::default()
+        };
+        // The part moves with the composite now, it starts interpolating
+        // afresh once it undocks.
+        commands
+            .entity(part)
+            .remove::<(Mass, Velocity, InterpolatedTranslation)>()
+            .insert((docked_part, local))
+            .set_parent(composite);
+        docked.extend([a, b]);
+    }
+    timings.collision += start.elapsed();
+}
+
+/// Sends an [`Undock`] for the inspected body with D (with the default
+/// input map).
+pub fn request_undock(
+    actions: Actions,
+    inspector: Res<Inspector>,
+    mut undocks: EventWriter<Undock>,
+) {
+    if !actions.just_pressed(Action::Undock) {
+        return;
+    }
+    if let Some(composite) = inspector.target {
+        undocks.send(Undock { composite });
+    }
+}
+
+/// Splits the composites in the [`Undock`] events back into separate
+/// bodies, pushing the parts away from the composite. What remains of the
+/// composite moves to the center of mass of the rest, so undocking keeps
+/// the center of mass along with the momentum.
+pub fn undock_bodies(
+    mut commands: Commands,
+    mut events: EventReader<Undock>,
+    mut composites: Query<(&mut Transform, &mut Velocity, &mut Mass, &Children)>,
+    parts: Query<(&DockedPart, Option<&Dockable>)>,
+) {
+    for event in events.read() {
+        let Ok((mut transform, mut velocity, mut mass, children)) =
+            composites.get_mut(event.composite)
+        else {
+            continue;
+        };
+        let origin = transform.translation.xy();
+        let mut center = origin;
+
+        for &child in children {
+            let Ok((part, dockable)) = parts.get(child) else {
+                continue;
+            };
+            let separation_speed = dockable.map_or(0., |dockable| dockable.separation_speed);
+            let part_velocity = velocity.0 + part.offset.normalize_or_zero() * separation_speed;
+
+            // The rest of the composite recoils so the momentum is kept.
+            let remaining_mass = mass.0 - part.mass;
+            let part_position = origin + part.offset;
+            velocity.0 = (velocity.0 * mass.0 - part_velocity * part.mass) / remaining_mass;
+            center = (center * mass.0 - part_position * part.mass) / remaining_mass;
+            mass.0 = remaining_mass;
+
+            commands
+                .entity(child)
+                .remove_parent()
+                .remove::<DockedPart>()
+                .insert((
+                    Mass(part.mass),
+                    Velocity(part_velocity),
+                    Transform {
+                        translation: part_position.extend(0.),
+                        scale: part.scale,
+                        ..Default::default()
+                    },
+                ));
+        }
+        transform.translation = center.extend(transform.translation.z);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCKABLE: Dockable = Dockable {
+        max_relative_speed: 5.,
+        separation_speed: 8.,
+    };
+
+    /// Total momentum and center of mass of the free bodies, with the
+    /// parts counted in the composites carrying them.
+    fn momentum_and_center(world: &mut World) -> (Vec2, Vec2) {
+        let mut bodies = world.query_filtered::<(&Transform, &Velocity, &Mass), Without<Parent>>();
+        let (momentum, moment, mass) = bodies.iter(world).fold(
+            (Vec2::ZERO, Vec2::ZERO, 0.),
+            |(momentum, moment, total), (transform, velocity, mass)| {
+                (
+                    momentum + velocity.0 * mass.0,
+                    moment + transform.translation.xy() * mass.0,
+                    total + mass.0,
+                )
+            },
+        );
+        (momentum, moment / mass)
+    }
+
+    #[test]
+    fn dock_and_undock_keep_momentum_and_center_of_mass() {
+        let mut world = World::new();
+        world.init_resource::<PhysicsTimings>();
+        world.init_resource::<Events<Collision>>();
+        world.init_resource::<Events<Undock>>();
+        let mut body = |position: Vec2, velocity: Vec2, mass: f32| {
+            world
+                .spawn((
+                    Transform::from_translation(position.extend(0.)),
+                    Velocity(velocity),
+                    Mass(mass),
+                    DOCKABLE,
+                ))
+                .id()
+        };
+        let station = body(Vec2::new(10., 5.), Vec2::new(1., 0.), 100.);
+        let ship = body(Vec2::new(14., 5.), Vec2::new(3., 1.), 20.);
+        let shuttle = body(Vec2::new(7., 2.), Vec2::new(-1., 2.), 5.);
+        let (momentum, center) = momentum_and_center(&mut world);
+
+        let mut dock = Schedule::default();
+        dock.add_systems(dock_bodies);
+        for other in [ship, shuttle] {
+            world.send_event(Collision::new(station, other));
+            dock.run(&mut world);
+            let (docked_momentum, docked_center) = momentum_and_center(&mut world);
+            assert!(
+                docked_momentum.distance(momentum) < 1e-4,
+                "{docked_momentum}"
+            );
+            assert!(docked_center.distance(center) < 1e-4, "{docked_center}");
+        }
+        // The parts stay where they docked.
+        let station_position = world.get::<Transform>(station).unwrap().translation.xy();
+        for (part, position) in [(ship, Vec2::new(14., 5.)), (shuttle, Vec2::new(7., 2.))] {
+            let offset = world.get::<DockedPart>(part).unwrap().offset;
+            assert!((station_position + offset).distance(position) < 1e-4);
+        }
+
+        world.send_event(Undock { composite: station });
+        let mut undock = Schedule::default();
+        undock.add_systems(undock_bodies);
+        undock.run(&mut world);
+        let (undocked_momentum, undocked_center) = momentum_and_center(&mut world);
+        assert!(
+            undocked_momentum.distance(momentum) < 1e-3,
+            "{undocked_momentum}"
+        );
+        assert!(undocked_center.distance(center) < 1e-4, "{undocked_center}");
+        assert_eq!(world.get::<Mass>(station).unwrap().0, 100.);
+        assert!(world
+            .get::<Transform>(station)
+            .unwrap()
+            .translation
+            .xy()
+            .abs_diff_eq(Vec2::new(10., 5.), 1e-4));
+    }
+}
@@ -0,0 +1,277 @@
+use crate::physics_plugin::PhysicsSettings;
+use crate::quadtree::QuadTree;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::thread;
+
+/// When present, the force calculation splits the space into a square grid
+/// of tiles and solves them on separate threads instead of traversing one
+/// global tree.
+///
+/// Every tile builds its own tree from the bodies in it and its direct
+/// neighbours (the ghost region). Everything further away comes from a
+/// coarse global tree over the mass and center of mass of every tile,
+/// walked without the tiles the ghost region covers.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct DomainDecomposition {
+    /// Number of tiles along one side of the grid
+    pub tiles_per_side: usize,
+}
+
+/// Total mass and center of mass of the bodies inside a single tile.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct TileAggregate {
+    pub(crate) mass: f32,
+    pub(crate) center_of_mass: Vec2,
+}
+
+impl TileAggregate {
+    /// Adds a body at `position` with `mass` to the tile.
+    pub(crate) fn add(&mut self, position: Vec2, mass: f32) {
+        self.center_of_mass =
+            (self.center_of_mass * self.mass + position * mass) / (self.mass + mass);
+        self.mass += mass;
+    }
+}
+
+/// Layout of the tile grid for one force calculation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Grid {
+    /// Corner of the grid with the smallest coordinates
+    min: Vec2,
+    /// Length of the side of a single tile
+    tile_size: f32,
+    /// Number of tiles along one side of the grid
+    pub(crate) tiles_per_side: usize,
+}
+
+impl Grid {
+    /// Grid covering all the `positions` with `tiles_per_side` squared tiles.
+    pub(crate) fn covering(positions: impl Iterator<Item = Vec2>, tiles_per_side: usize) -> Self {
+        let (min, max) = positions.fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), position| (min.min(position), max.max(position)),
+        );
+        Grid {
+            min,
+            tile_size: ((max - min).max_element() / tiles_per_side as f32).max(f32::EPSILON),
+            tiles_per_side,
+        }
+    }
+
+    /// Column and row of the tile containing `position`.
+    fn tile_coords(&self, position: Vec2) -> (usize, usize) {
+        let last = self.tiles_per_side - 1;
+        let coords = ((position - self.min) / self.tile_size).max(Vec2::ZERO);
+        ((coords.x as usize).min(last), (coords.y as usize).min(last))
+    }
+
+    /// Index of the tile containing `position`.
+    pub(crate) fn tile_index(&self, position: Vec2) -> usize {
+        let (column, row) = self.tile_coords(position);
+        row * self.tiles_per_side + column
+    }
+
+    /// Center of the tile at `column` and `row`.
+    fn tile_center(&self, column: usize, row: usize) -> Vec2 {
+        self.min + (Vec2::new(column as f32, row as f32) + 0.5) * self.tile_size
+    }
+
+    /// Indices of `tile` and the tiles directly around it.
+    pub(crate) fn ring_around(&self, tile: usize) -> impl Iterator<Item = usize> {
+        let n = self.tiles_per_side;
+        let (column, row) = (tile % n, tile / n);
+        (row.saturating_sub(1)..(row + 2).min(n)).flat_map(move |other_row| {
+            (column.saturating_sub(1)..(column + 2).min(n))
+                .map(move |other_column| other_row * n + other_column)
+        })
+    }
+
+    /// Corners of the block of tiles around the one at `column` and `row`,
+    /// open towards the outside of the grid like the tiles along its edges.
+    fn block_around(&self, column: usize, row: usize) -> [Vec2; 2] {
+        let lower = |index: usize| {
+            if index <= 1 {
+                f32::NEG_INFINITY
+            } else {
+                (index - 1) as f32
+            }
+        };
+        let upper = |index: usize| {
+            if index + 2 >= self.tiles_per_side {
+                f32::INFINITY
+            } else {
+                (index + 2) as f32
+            }
+        };
+        [
+            self.min + Vec2::new(lower(column), lower(row)) * self.tile_size,
+            self.min + Vec2::new(upper(column), upper(row)) * self.tile_size,
+        ]
+    }
+}
+
+impl DomainDecomposition {
+    /// Decomposition into `tiles_per_side` squared tiles (at least one).
+    pub fn new(tiles_per_side: usize) -> Self {
+        DomainDecomposition {
+            tiles_per_side: tiles_per_side.max(1),
+        }
+    }
+
+    /// Calculates the gravitational acceleration the `sources` (position and
+    /// mass) cause at each of the `targets` with the gravity of the
+    /// `settings`, returned in the same order.
+    pub fn accelerations(
+        &self,
+        sources: &[(Vec2, f32)],
+        targets: &[Vec2],
+        theta_threshold: f32,
+        settings: &PhysicsSettings,
+    ) -> Vec<Vec2> {
+        if targets.is_empty() {
+            return Vec::new();
+        }
+
+        let n = self.tiles_per_side.max(1);
+        let grid = Grid::covering(
+            sources
+                .iter()
+                .map(|source| source.0)
+                .chain(targets.iter().copied()),
+            n,
+        );
+
+        // Sort the sources and targets into tiles and sum up the mass of each
+        // tile for the far-field approximation.
+        let mut tile_sources = vec![Vec::new(); n * n];
+        let mut aggregates = vec![TileAggregate::default(); n * n];
+        for &(position, mass) in sources {
+            let tile = grid.tile_index(position);
+            tile_sources[tile].push((position, mass));
+            aggregates[tile].add(position, mass);
+        }
+        let mut tile_targets = vec![Vec::new(); n * n];
+        for (idx, &position) in targets.iter().enumerate() {
+            tile_targets[grid.tile_index(position)].push(idx);
+        }
+        let coarse: Vec<(Vec2, f32)> = aggregates
+            .iter()
+            .filter(|aggregate| aggregate.mass > 0.)
+            .map(|aggregate| (aggregate.center_of_mass, aggregate.mass))
+            .collect();
+        let coarse = QuadTree::build(&coarse);
+
+        let workers = thread::available_parallelism()
+            .map_or(1, |count| count.get())
+            .min(n * n);
+        let mut accelerations = vec![Vec2::ZERO; targets.len()];
+        let (grid, tile_sources, tile_targets, coarse) =
+            (&grid, &tile_sources, &tile_targets, &coarse);
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|worker| {
+                    scope.spawn(move || {
+                        let mut solved = Vec::new();
+                        // Tiles are dealt out round-robin so dense regions
+                        // are less likely to land on a single worker.
+                        for tile in (worker..n * n).step_by(workers) {
+                            if tile_targets[tile].is_empty() {
+                                continue;
+                            }
+                            solve_tile(
+                                grid,
+                                tile,
+                                tile_sources,
+                                coarse,
+                                &tile_targets[tile],
+                                targets,
+                                theta_threshold,
+                                settings,
+                                &mut solved,
+                            );
+                        }
+                        solved
+                    })
+                })
+                .collect();
+
+            for handle in handles {
+                for (idx, acceleration) in handle.join().expect("Tile worker panicked") {
+                    accelerations[idx] = acceleration;
+                }
+            }
+        });
+
+        accelerations
+    }
+}
+
+/// Calculates the accelerations of the targets inside a single tile and
+/// appends them, together with the index of the target, to `solved`.
+#[allow(clippy::too_many_arguments)]
+fn solve_tile(
+    grid: &Grid,
+    tile: usize,
+    tile_sources: &[Vec<(Vec2, f32)>],
+    coarse: &QuadTree,
+    target_indices: &[usize],
+    targets: &[Vec2],
+    theta_threshold: f32,
+    settings: &PhysicsSettings,
+    solved: &mut Vec<(usize, Vec2)>,
+) {
+    let near_sources = grid
+        .ring_around(tile)
+        .flat_map(|other| tile_sources[other].iter().copied());
+    let accelerations = tile_accelerations(
+        grid,
+        tile,
+        near_sources,
+        coarse,
+        target_indices.iter().map(|&idx| targets[idx]),
+        theta_threshold,
+        settings.g,
+        settings.softening,
+    );
+    solved.extend(target_indices.iter().copied().zip(accelerations));
+}
+
+/// Accelerations at the `targets` inside `tile`, from the `near_sources` in
+/// the tile and the ring of tiles around it, see [`Grid::ring_around`], and
+/// from the `coarse` tree over the tile aggregates for everything further
+/// away.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn tile_accelerations(
+    grid: &Grid,
+    tile: usize,
+    near_sources: impl IntoIterator<Item = (Vec2, f32)>,
+    coarse: &QuadTree,
+    targets: impl IntoIterator<Item = Vec2>,
+    theta_threshold: f32,
+    g: f32,
+    softening: f32,
+) -> Vec<Vec2> {
+    let n = grid.tiles_per_side;
+    let (column, row) = (tile % n, tile / n);
+    let near = grid.block_around(column, row);
+
+    // The tree covers this tile and the ring of tiles around it.
+    let mut q_tree = QuadTree::new(grid.tile_center(column, row), grid.tile_size * 1.5);
+    for (position, mass) in near_sources {
+        let _ = q_tree.add_node(position, mass);
+    }
+
+    targets
+        .into_iter()
+        .map(|position| {
+            let near_field =
+                q_tree.accumulate_acceleration(position, theta_threshold, g, softening);
+            // The far tiles are too far away for the softening to matter.
+            let far_field =
+                coarse.accumulate_acceleration_outside(position, theta_threshold, g, 0., near);
+            near_field + far_field
+        })
+        .collect()
+}
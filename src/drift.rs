@@ -0,0 +1,212 @@
+use crate::physics_config::PhysicsQuality;
+use crate::physics_plugin::{potential_energy, Mass, PhysicsSettings, Velocity};
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Total momentum and energy of the bodies of one world.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Invariants {
+    pub momentum: Vec2,
+    pub energy: f32,
+}
+
+/// When present, the total momentum and energy of every world are
+/// periodically compared with their values at the start of the scenario and
+/// restored when they drifted by more than the tolerances, so very long runs
+/// don't slowly heat up, cool down or wander off. They are measured again
+/// whenever the bodies of a world changed since the last check, e.g. after
+/// merges, spawns or deletions, instead of steering the bodies left towards
+/// the energy of the ones gone.
+///
+/// Momentum is restored by shifting the velocities of all the bodies
+/// equally, energy by scaling their velocities relative to the center of
+/// mass.
+#[derive(Resource, Debug)]
+pub struct DriftCorrection {
+    timer: Timer,
+    /// Largest allowed drift of the momentum, relative to the sum of the
+    /// momentum magnitudes of the bodies
+    pub momentum_tolerance: f32,
+    /// Largest allowed drift of the energy, relative to the initial energy
+    pub energy_tolerance: f32,
+    /// Invariants at the start of the scenario or since the bodies last
+    /// changed, per world
+    initial: HashMap<SimWorld, Reference>,
+}
+
+impl DriftCorrection {
+    /// Checks every `interval` seconds with tolerances of 0.1%.
+    pub fn new(interval: f32) -> Self {
+        DriftCorrection {
+            timer: Timer::from_seconds(interval, TimerMode::Repeating),
+            momentum_tolerance: 1e-3,
+            energy_tolerance: 1e-3,
+            initial: HashMap::default(),
+        }
+    }
+
+    pub fn with_tolerances(mut self, momentum: f32, energy: f32) -> Self {
+        self.momentum_tolerance = momentum;
+        self.energy_tolerance = energy;
+        self
+    }
+}
+
+/// A body as the drift correction sees it.
+struct DriftBody<'a> {
+    position: Vec2,
+    mass: f32,
+    velocity: Mut<'a, Velocity>,
+}
+
+/// Invariants of a world with the bodies they were measured for.
+#[derive(Debug, Clone, Copy)]
+struct Reference {
+    invariants: Invariants,
+    bodies: usize,
+    total_mass: f32,
+}
+
+impl Reference {
+    /// Whether the `bodies` are still the ones the reference was measured
+    /// for, as far as their count and total mass tell.
+    fn matches(&self, bodies: &[DriftBody]) -> bool {
+        let total_mass: f32 = bodies.iter().map(|body| body.mass).sum();
+        self.bodies == bodies.len()
+            && (total_mass - self.total_mass).abs() <= self.total_mass.abs() * 1e-6
+    }
+}
+
+fn potential(bodies: &[DriftBody], theta_threshold: f32, g: f32) -> f32 {
+    let bodies: Vec<(Vec2, f32)> = bodies
+        .iter()
+        .map(|body| (body.position, body.mass))
+        .collect();
+    potential_energy(&bodies, theta_threshold, g)
+}
+
+fn kinetic_energy(bodies: &[DriftBody]) -> f32 {
+    bodies
+        .iter()
+        .map(|body| 0.5 * body.mass * body.velocity.0.length_squared())
+        .sum()
+}
+
+fn momentum(bodies: &[DriftBody]) -> Vec2 {
+    bodies.iter().map(|body| body.velocity.0 * body.mass).sum()
+}
+
+/// Restores the `initial` invariants of the `bodies` if they drifted out of
+/// the tolerances, returning the velocity shift and scale applied.
+fn correct(
+    correction: &DriftCorrection,
+    initial: Invariants,
+    bodies: &mut [DriftBody],
+    theta_threshold: f32,
+    g: f32,
+) -> (Vec2, f32) {
+    let total_mass: f32 = bodies.iter().map(|body| body.mass).sum();
+    let momentum_scale: f32 = bodies
+        .iter()
+        .map(|body| body.mass * body.velocity.0.length())
+        .sum();
+    let momentum_drift = momentum(bodies) - initial.momentum;
+    let mut shift = Vec2::ZERO;
+    if momentum_drift.length() > correction.momentum_tolerance * momentum_scale {
+        shift = -momentum_drift / total_mass;
+        for body in bodies.iter_mut() {
+            body.velocity.0 += shift;
+        }
+    }
+
+    let energy = kinetic_energy(bodies) + potential(bodies, theta_threshold, g);
+    let mut scale = 1.;
+    if (energy - initial.energy).abs() > correction.energy_tolerance * initial.energy.abs() {
+        // Scaling relative to the center of mass keeps the momentum.
+        let center_velocity = momentum(bodies) / total_mass;
+        let internal: f32 = bodies
+            .iter()
+            .map(|body| 0.5 * body.mass * (body.velocity.0 - center_velocity).length_squared())
+            .sum();
+        // Without enough motion to take the energy from, only part of the
+        // drift can be corrected.
+        let target = (internal + initial.energy - energy).max(0.);
+        if internal > 0. {
+            scale = (target / internal).sqrt();
+            for body in bodies.iter_mut() {
+                body.velocity.0 = center_velocity + (body.velocity.0 - center_velocity) * scale;
+            }
+        }
+    }
+    (shift, scale)
+}
+
+pub fn correct_drift(
+    time: Res<Time>,
+    quality: PhysicsQuality,
+    settings: Res<PhysicsSettings>,
+    mut correction: ResMut<DriftCorrection>,
+    mut query: Query<(&Transform, &Mass, &mut Velocity, Option<&SimWorld>)>,
+) {
+    let first = correction.initial.is_empty();
+    if !correction.timer.tick(time.delta()).just_finished() && !first {
+        return;
+    }
+
+    let theta_threshold = quality.settings().theta_threshold;
+    let mut worlds: HashMap<SimWorld, Vec<DriftBody>> = HashMap::default();
+    for (transform, mass, velocity, world) in &mut query {
+        worlds
+            .entry(world.copied().unwrap_or_default())
+            .or_default()
+            .push(DriftBody {
+                position: transform.translation.xy(),
+                mass: mass.0,
+                velocity,
+            });
+    }
+
+    for (world, mut bodies) in worlds {
+        if bodies.is_empty() {
+            continue;
+        }
+        let Some(&initial) = correction
+            .initial
+            .get(&world)
+            .filter(|initial| initial.matches(&bodies))
+        else {
+            let initial = Reference {
+                invariants: Invariants {
+                    momentum: momentum(&bodies),
+                    energy: kinetic_energy(&bodies)
+                        + potential(&bodies, theta_threshold, settings.g),
+                },
+                bodies: bodies.len(),
+                total_mass: bodies.iter().map(|body| body.mass).sum(),
+            };
+            correction.initial.insert(world, initial);
+            continue;
+        };
+        let (shift, scale) = correct(
+            &correction,
+            initial.invariants,
+            &mut bodies,
+            theta_threshold,
+            settings.g,
+        );
+        if shift != Vec2::ZERO || scale != 1. {
+            info!(
+                "Corrected drift of world {}: velocities shifted by {shift}, scaled by {scale}",
+                world.0
+            );
+        }
+    }
+}
+
+/// Forgets the invariants of the previous scenario, the next ones are
+/// measured once it is loaded.
+pub fn reset_drift_reference(mut correction: ResMut<DriftCorrection>) {
+    correction.initial.clear();
+    correction.timer.reset();
+}
@@ -0,0 +1,91 @@
+use crate::input::InputMap;
+use crate::physics_plugin::{MainCamera, PhysicsPlugin};
+use bevy::prelude::*;
+use bevy::render::camera::{CameraUpdateSystem, RenderTarget};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+
+/// Image of `size` pixels a camera can render into and the image can be
+/// shown or copied from, black until something is rendered.
+pub fn render_target(size: UVec2) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size.x.max(1),
+            height: size.y.max(1),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST
+        | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+/// Where an embedded simulation renders to. Replacing the `target`, e.g.
+/// with a bigger image when the widget showing it is resized, moves the
+/// rendering over to it.
+#[derive(Resource, Debug, Clone)]
+pub struct EmbeddedView {
+    pub target: Handle<Image>,
+}
+
+/// Runs the simulation inside an app which owns the window and the event
+/// loop, such as an editor or an egui application showing the simulation
+/// as a widget. The host adds its own `DefaultPlugins`, creates a
+/// [`render_target`] image for the simulation to render into, and shows
+/// that image wherever it likes. The simulation steps along with the
+/// host's updates, from loading the selected scenario on.
+///
+/// The keys and mouse buttons of the simulation are off unless asked for,
+/// so typing elsewhere in the host doesn't pause or restart it.
+pub struct EmbeddedSimulationPlugin {
+    pub target: Handle<Image>,
+    pub input: bool,
+}
+
+impl EmbeddedSimulationPlugin {
+    pub fn new(target: Handle<Image>) -> Self {
+        EmbeddedSimulationPlugin {
+            target,
+            input: false,
+        }
+    }
+
+    pub fn with_input(mut self, input: bool) -> Self {
+        self.input = input;
+        self
+    }
+}
+
+/// Points the main camera at the [`EmbeddedView`] instead of the window.
+fn retarget_main_camera(
+    view: Res<EmbeddedView>,
+    mut cameras: Query<&mut Camera, With<MainCamera>>,
+) {
+    for mut camera in &mut cameras {
+        if !view.is_changed() && !camera.is_added() {
+            continue;
+        }
+        camera.target = RenderTarget::Image(view.target.clone());
+    }
+}
+
+impl Plugin for EmbeddedSimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(PhysicsPlugin::default())
+            .insert_resource(EmbeddedView {
+                target: self.target.clone(),
+            })
+            .add_systems(PostUpdate, retarget_main_camera.before(CameraUpdateSystem));
+        if !self.input {
+            app.insert_resource(InputMap {
+                bindings: Vec::new(),
+            });
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use crate::bookmarks::{Bookmark, Bookmarks};
+use crate::forces::Drag;
+use crate::inspector::Inspector;
+use crate::integrator::IntegratorKind;
+use crate::localization::Localization;
+use crate::physics_config::{PhysicsConfig, PhysicsQuality};
+use crate::physics_plugin::{Mass, PhysicsSettings, Velocity};
+use crate::preview::RELEVANT_BODIES;
+use crate::quality::Quality;
+use crate::simulation::{Simulation, SimulationConfig};
+use crate::state::SimState;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+
+/// Number of upcoming encounters listed in the timeline.
+const TIMELINE_LENGTH: usize = 5;
+
+/// Marks a body whose close approaches are predicted, on top of the one
+/// selected in the [`Inspector`].
+#[derive(Component, Debug, Default)]
+pub struct WatchEncounters;
+
+/// A watched body is predicted to pass `other` at `miss_distance`, `time`
+/// simulated seconds from when the prediction was made.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PredictedEncounterEvent {
+    pub body: Entity,
+    pub other: Entity,
+    pub time: f32,
+    pub miss_distance: f32,
+}
+
+/// Predicts the closest approaches of the watched bodies every second by
+/// stepping a [`Simulation`] ahead, sending a [`PredictedEncounterEvent`]
+/// for each one closer than `max_miss_distance` within the `horizon`.
+#[derive(Resource, Debug)]
+pub struct EncounterPrediction {
+    /// How far ahead to predict in simulated seconds
+    pub horizon: f32,
+    pub steps: usize,
+    pub max_miss_distance: f32,
+    timer: Timer,
+    /// Encounters of the last prediction, soonest first
+    upcoming: Vec<PredictedEncounterEvent>,
+}
+
+impl Default for EncounterPrediction {
+    fn default() -> Self {
+        EncounterPrediction {
+            horizon: 10.,
+            steps: 200,
+            max_miss_distance: 20.,
+            timer: Timer::from_seconds(1., TimerMode::Repeating),
+            upcoming: Vec::new(),
+        }
+    }
+}
+
+impl EncounterPrediction {
+    pub fn upcoming(&self) -> &[PredictedEncounterEvent] {
+        &self.upcoming
+    }
+}
+
+/// Steps a [`Simulation`] with every watched body, the bodies watched in the
+/// same world and the heaviest ones of it.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn predict_encounters(
+    time: Res<Time<Real>>,
+    quality: PhysicsQuality,
+    integrator: Res<IntegratorKind>,
+    settings: Res<PhysicsSettings>,
+    inspector: Option<Res<Inspector>>,
+    mut prediction: ResMut<EncounterPrediction>,
+    mut encounters: EventWriter<PredictedEncounterEvent>,
+    watched: Query<Entity, With<WatchEncounters>>,
+    bodies: Query<(
+        Entity,
+        &Transform,
+        &Velocity,
+        Option<&Mass>,
+        Option<&Drag>,
+        Option<&SimWorld>,
+    )>,
+) {
+    if !prediction.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let mut targets: Vec<Entity> = watched.iter().collect();
+    targets.extend(inspector.and_then(|inspector| inspector.target));
+    targets.sort();
+    targets.dedup();
+
+    let dt = prediction.horizon / prediction.steps.max(1) as f32;
+    let mut upcoming = Vec::new();
+    for target in targets {
+        let Ok((.., target_world)) = bodies.get(target) else {
+            continue;
+        };
+        let mut relevant: Vec<_> = bodies
+            .iter()
+            .filter(|(entity, _, _, mass, _, world)| {
+                *entity != target && mass.is_some() && *world == target_world
+            })
+            .collect();
+        relevant.sort_by(|a, b| {
+            let mass = |mass: Option<&Mass>| mass.map_or(0., |mass| mass.0);
+            mass(b.3).total_cmp(&mass(a.3))
+        });
+        relevant.truncate(RELEVANT_BODIES);
+        relevant.extend(
+            watched
+                .iter()
+                .filter(|&entity| entity != target)
+                .filter_map(|entity| bodies.get(entity).ok())
+                .filter(|(.., world)| *world == target_world),
+        );
+        relevant.sort_by_key(|(entity, ..)| *entity);
+        relevant.dedup_by_key(|(entity, ..)| *entity);
+
+        let mut shadow = Simulation::capture(
+            relevant.into_iter().chain(bodies.get(target).ok()).map(
+                |(entity, transform, velocity, mass, drag, _)| {
+                    (entity, transform, velocity, mass, drag)
+                },
+            ),
+            SimulationConfig {
+                g: settings.g,
+                theta_threshold: quality.settings().theta_threshold,
+                integrator: *integrator,
+                softening: settings.softening,
+            },
+        );
+        for (other, time, miss_distance) in shadow.closest_approaches(target, prediction.steps, dt)
+        {
+            if miss_distance <= prediction.max_miss_distance {
+                upcoming.push(PredictedEncounterEvent {
+                    body: target,
+                    other,
+                    time,
+                    miss_distance,
+                });
+            }
+        }
+    }
+
+    upcoming.sort_by(|a, b| a.time.total_cmp(&b.time));
+    encounters.send_batch(upcoming.iter().copied());
+    prediction.upcoming = upcoming;
+}
+
+/// Marks the text listing the upcoming encounters.
+#[derive(Component)]
+struct TimelineText;
+
+fn spawn_timeline_text(mut commands: Commands) {
+    commands.spawn((
+        TimelineText,
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            right: Val::Px(10.),
+            ..Default::default()
+        },
+    ));
+}
+
+/// Lists the upcoming encounters, then the [`Bookmarks`] with the one being
+/// noted.
+fn update_timeline_text(
+    prediction: Res<EncounterPrediction>,
+    bookmarks: Option<Res<Bookmarks>>,
+    localization: Res<Localization>,
+    mut texts: Query<&mut Text, With<TimelineText>>,
+) {
+    let mut lines: Vec<String> = prediction
+        .upcoming
+        .iter()
+        .take(TIMELINE_LENGTH)
+        .map(|encounter| {
+            localization.text(
+                "timeline-encounter",
+                &[
+                    ("time", ((encounter.time * 10.).round() / 10.).into()),
+                    ("body", encounter.body.index().into()),
+                    ("other", encounter.other.index().into()),
+                    (
+                        "distance",
+                        ((encounter.miss_distance * 10.).round() / 10.).into(),
+                    ),
+                ],
+            )
+        })
+        .collect();
+    if let Some(bookmarks) = bookmarks {
+        let time = |bookmark: &Bookmark| ((bookmark.time * 10.).round() / 10.).into();
+        for (index, bookmark) in bookmarks.entries.iter().enumerate() {
+            let marker = if bookmarks.current == Some(index) {
+                "> "
+            } else {
+                ""
+            };
+            let line = localization.text(
+                "timeline-bookmark",
+                &[("index", (index + 1) as f64), ("time", time(bookmark))],
+            );
+            lines.push(format!("{marker}{line} {}", bookmark.note));
+        }
+        if let Some(draft) = bookmarks.draft() {
+            let line = localization.text("timeline-bookmark-draft", &[("time", time(draft))]);
+            lines.push(format!("{line} {}_", draft.note));
+        }
+    }
+    for mut text in &mut texts {
+        text.0 = lines.join("\n");
+    }
+}
+
+/// Predicts the close approaches of the watched and the inspected bodies
+/// and lists them in a timeline.
+pub struct EncounterPlugin;
+
+impl Plugin for EncounterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EncounterPrediction>()
+            .init_resource::<Quality>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<IntegratorKind>()
+            .init_resource::<PhysicsSettings>()
+            .init_resource::<Localization>()
+            .add_event::<PredictedEncounterEvent>()
+            .add_systems(Startup, spawn_timeline_text)
+            .add_systems(
+                Update,
+                (
+                    predict_encounters.run_if(in_state(SimState::Running)),
+                    update_timeline_text.run_if(
+                        resource_changed::<EncounterPrediction>
+                            .or(resource_exists_and_changed::<Bookmarks>),
+                    ),
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,89 @@
+use crate::orbits::{AnalyticOrbit, OrbitParent};
+use bevy::prelude::*;
+
+/// Deviation of a body's simulated position from the analytic one at a
+/// point in time.
+#[derive(Debug, Clone)]
+pub struct EphemerisError {
+    /// Elapsed app time of the comparison
+    pub time: f32,
+    pub entity: Entity,
+    /// Name of the body, or its entity if it has none
+    pub body: String,
+    /// Distance between the simulated and the predicted position
+    pub error: f32,
+}
+
+/// When present, the simulated positions of bodies placed on relative
+/// orbits are periodically compared with their two-body solution, which
+/// shows the error the integrator and the tree approximation accumulate
+/// (along with the real perturbations from the other bodies).
+#[derive(Resource, Debug)]
+pub struct EphemerisComparison {
+    timer: Timer,
+    /// Every comparison made so far, oldest first
+    pub errors: Vec<EphemerisError>,
+}
+
+impl EphemerisComparison {
+    /// Compares the positions every `interval` seconds.
+    pub fn new(interval: f32) -> Self {
+        EphemerisComparison {
+            timer: Timer::from_seconds(interval, TimerMode::Repeating),
+            errors: Vec::new(),
+        }
+    }
+
+    /// The latest comparison of `entity`.
+    pub fn latest(&self, entity: Entity) -> Option<&EphemerisError> {
+        self.errors
+            .iter()
+            .rev()
+            .find(|error| error.entity == entity)
+    }
+}
+
+impl Default for EphemerisComparison {
+    /// Compares the positions every second.
+    fn default() -> Self {
+        EphemerisComparison::new(1.)
+    }
+}
+
+pub fn compare_ephemerides(
+    time: Res<Time>,
+    mut comparison: ResMut<EphemerisComparison>,
+    bodies: Query<(
+        Entity,
+        Option<&Name>,
+        &Transform,
+        &OrbitParent,
+        &AnalyticOrbit,
+    )>,
+    parents: Query<&Transform>,
+) {
+    if !comparison.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let now = time.elapsed_secs();
+    for (entity, name, transform, parent, orbit) in &bodies {
+        let (Some(predicted), Ok(parent_transform)) =
+            (orbit.predicted_offset(now), parents.get(parent.0))
+        else {
+            continue;
+        };
+        let offset = transform.translation.xy() - parent_transform.translation.xy();
+        let error = EphemerisError {
+            time: now,
+            entity,
+            body: name.map_or_else(|| entity.to_string(), |name| name.to_string()),
+            error: offset.distance(predicted),
+        };
+        info!(
+            "Ephemeris error of {} at {:.1} s: {}",
+            error.body, error.time, error.error
+        );
+        comparison.errors.push(error);
+    }
+}
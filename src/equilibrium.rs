@@ -0,0 +1,173 @@
+use crate::physics_config::PhysicsQuality;
+use crate::physics_plugin::{
+    build_fitted_tree, potential_energy, tree_acceleration, Mass, PhysicsSettings, Velocity,
+};
+use crate::quadtree::QuadTree;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Adjusts the velocities of freshly spawned bodies so they start close to
+/// equilibrium instead of collapsing or flying apart right away.
+///
+/// All the bodies of a world with the same request are adjusted together as
+/// one group, the component is removed once that's done.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub enum Equilibrate {
+    /// Scales the velocities relative to the group's center of mass so the
+    /// virial ratio `2K/|U|` of its kinetic and potential energy becomes the
+    /// given one: 1 is in equilibrium, below 1 the group contracts, above 1
+    /// it expands.
+    VirialRatio(f32),
+    /// Puts every body on a circular orbit around the group's center of
+    /// mass, with the speed the actual gravity at the body calls for. Bodies
+    /// keep the direction they orbit in, counterclockwise if they didn't
+    /// orbit before.
+    CircularOrbits,
+}
+
+/// A body being adjusted.
+struct GroupBody<'a> {
+    position: Vec2,
+    mass: f32,
+    velocity: Mut<'a, Velocity>,
+}
+
+/// Center of mass position and velocity of the `bodies`.
+fn center_of_mass(bodies: &[GroupBody]) -> (Vec2, Vec2) {
+    let total_mass: f32 = bodies.iter().map(|body| body.mass).sum();
+    if total_mass == 0. {
+        return (Vec2::ZERO, Vec2::ZERO);
+    }
+    let position: Vec2 = bodies.iter().map(|body| body.position * body.mass).sum();
+    let velocity: Vec2 = bodies.iter().map(|body| body.velocity.0 * body.mass).sum();
+    (position / total_mass, velocity / total_mass)
+}
+
+fn set_virial_ratio(bodies: &mut [GroupBody], ratio: f32, theta_threshold: f32, g: f32) {
+    let (_, center_velocity) = center_of_mass(bodies);
+    let sources: Vec<(Vec2, f32)> = bodies
+        .iter()
+        .map(|body| (body.position, body.mass))
+        .collect();
+    let potential = potential_energy(&sources, theta_threshold, g);
+    let kinetic: f32 = bodies
+        .iter()
+        .map(|body| 0.5 * body.mass * (body.velocity.0 - center_velocity).length_squared())
+        .sum();
+    if kinetic == 0. || potential == 0. {
+        return;
+    }
+    let scale = (ratio * potential.abs() / (2. * kinetic)).sqrt();
+    for body in bodies {
+        body.velocity.0 = center_velocity + (body.velocity.0 - center_velocity) * scale;
+    }
+}
+
+/// Speed of a circular orbit around `center` at `position`, given the
+/// actual gravity of the bodies in `q_tree` there with the gravitational
+/// constant `g`.
+pub fn circular_speed(
+    q_tree: &QuadTree,
+    position: Vec2,
+    center: Vec2,
+    theta_threshold: f32,
+    g: f32,
+) -> f32 {
+    let offset = position - center;
+    let distance = offset.length();
+    if distance == 0. {
+        return 0.;
+    }
+    // Only the pull towards the center keeps the body on the orbit.
+    let inward = -tree_acceleration(q_tree, position, theta_threshold, g).dot(offset / distance);
+    (inward.max(0.) * distance).sqrt()
+}
+
+/// Gives every body of the group the speed of a circular orbit under the
+/// gravity of all the `sources` of its world.
+fn set_circular_orbits(
+    bodies: &mut [GroupBody],
+    sources: &[(Vec2, f32)],
+    theta_threshold: f32,
+    g: f32,
+) {
+    let (center, center_velocity) = center_of_mass(bodies);
+    let q_tree = build_fitted_tree(sources);
+    for body in bodies {
+        let offset = body.position - center;
+        let distance = offset.length();
+        if distance == 0. {
+            continue;
+        }
+        let radial = offset / distance;
+        let speed = circular_speed(&q_tree, body.position, center, theta_threshold, g);
+        let clockwise = radial.perp_dot(body.velocity.0 - center_velocity) < 0.;
+        let tangent = if clockwise {
+            -radial.perp()
+        } else {
+            radial.perp()
+        };
+        body.velocity.0 = center_velocity + tangent * speed;
+    }
+}
+
+/// Handles the [`Equilibrate`] requests of the bodies spawned since the
+/// last step.
+#[allow(clippy::type_complexity)]
+pub fn equilibrate(
+    mut commands: Commands,
+    quality: PhysicsQuality,
+    settings: Res<PhysicsSettings>,
+    sources: Query<(&Transform, &Mass, Option<&SimWorld>)>,
+    mut requests: Query<(
+        Entity,
+        &Equilibrate,
+        &Transform,
+        &Mass,
+        &mut Velocity,
+        Option<&SimWorld>,
+    )>,
+) {
+    if requests.is_empty() {
+        return;
+    }
+    let theta_threshold = quality.settings().theta_threshold;
+
+    let mut groups: Vec<(SimWorld, Equilibrate, Vec<GroupBody>)> = Vec::new();
+    for (entity, request, transform, mass, velocity, world) in &mut requests {
+        commands.entity(entity).remove::<Equilibrate>();
+        let world = world.copied().unwrap_or_default();
+        let body = GroupBody {
+            position: transform.translation.xy(),
+            mass: mass.0,
+            velocity,
+        };
+        match groups.iter_mut().find(|(group_world, group_request, _)| {
+            *group_world == world && group_request == request
+        }) {
+            Some((_, _, bodies)) => bodies.push(body),
+            None => groups.push((world, *request, vec![body])),
+        }
+    }
+
+    let mut world_sources: HashMap<SimWorld, Vec<(Vec2, f32)>> = HashMap::default();
+    for (transform, mass, world) in &sources {
+        world_sources
+            .entry(world.copied().unwrap_or_default())
+            .or_default()
+            .push((transform.translation.xy(), mass.0));
+    }
+
+    for (world, request, mut bodies) in groups {
+        match request {
+            Equilibrate::VirialRatio(ratio) => {
+                set_virial_ratio(&mut bodies, ratio, theta_threshold, settings.g);
+            }
+            Equilibrate::CircularOrbits => {
+                let sources = world_sources.get(&world).map_or(&[][..], Vec::as_slice);
+                set_circular_orbits(&mut bodies, sources, theta_threshold, settings.g);
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use crate::embed::render_target;
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::MainCamera;
+use bevy::core_pipeline::bloom::Bloom;
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::view::screenshot::{save_to_disk, Screenshot, ScreenshotCaptured};
+use bevy::render::view::ColorGrading;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Largest side of an exported image, what most GPUs can still render to.
+const MAX_SIDE: u32 = 8192;
+/// Resolution multiple of exports not asking for a specific one.
+pub const DEFAULT_SCALE: u32 = 4;
+
+/// Frames the export camera renders before its image is captured, so the
+/// new render target is ready.
+const WARMUP_FRAMES: u8 = 2;
+
+/// Asks for the view of the main camera to be rendered offscreen at `scale`
+/// times the window resolution and saved to `path`, whose extension picks
+/// the image format. Thousands of tiny bodies look muddy at the window
+/// resolution.
+#[derive(Event, Debug, Clone)]
+pub struct ExportImage {
+    pub path: PathBuf,
+    pub scale: u32,
+    /// Quit the app once the image is saved
+    pub exit_when_saved: bool,
+}
+
+/// Exports the frame after the simulation ran for `at` seconds, quitting
+/// once it's saved, for rendering from scripts.
+#[derive(Resource, Debug, Clone)]
+pub struct ScheduledExport {
+    pub at: f32,
+    pub path: PathBuf,
+    pub scale: u32,
+    /// Simulated time so far
+    elapsed: f32,
+}
+
+impl ScheduledExport {
+    pub fn new(at: f32, path: impl Into<PathBuf>) -> Self {
+        ScheduledExport {
+            at,
+            path: path.into(),
+            scale: DEFAULT_SCALE,
+            elapsed: 0.,
+        }
+    }
+}
+
+/// Camera rendering an export into its own image.
+#[derive(Component)]
+struct ExportCamera {
+    path: PathBuf,
+    image: Handle<Image>,
+    frames_left: u8,
+    exit_when_saved: bool,
+}
+
+/// PNG file in `directory` named after the current time.
+pub fn timestamped_path(directory: &Path) -> PathBuf {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    directory.join(format!("spacesim-{seconds}.png"))
+}
+
+fn export_frame(actions: Actions, mut exports: EventWriter<ExportImage>) {
+    if actions.just_pressed(Action::ExportFrame) {
+        exports.send(ExportImage {
+            path: timestamped_path(Path::new(".")),
+            scale: DEFAULT_SCALE,
+            exit_when_saved: false,
+        });
+    }
+}
+
+/// Counts the simulated time and sends the export when it's reached, only
+/// once since the resource is removed.
+pub fn run_scheduled_export(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut scheduled: ResMut<ScheduledExport>,
+    mut exports: EventWriter<ExportImage>,
+) {
+    scheduled.elapsed += time.delta_secs();
+    if scheduled.elapsed < scheduled.at {
+        return;
+    }
+    exports.send(ExportImage {
+        path: scheduled.path.clone(),
+        scale: scheduled.scale,
+        exit_when_saved: true,
+    });
+    commands.remove_resource::<ScheduledExport>();
+}
+
+/// Spawns a camera for every requested export, copying the main camera
+/// with the same view over a bigger target.
+#[allow(clippy::type_complexity)]
+fn start_exports(
+    mut commands: Commands,
+    mut events: EventReader<ExportImage>,
+    mut images: ResMut<Assets<Image>>,
+    windows: Query<&Window>,
+    cameras: Query<
+        (
+            &Camera,
+            &Transform,
+            &OrthographicProjection,
+            &Tonemapping,
+            Option<&Bloom>,
+            Option<&ColorGrading>,
+        ),
+        With<MainCamera>,
+    >,
+) {
+    for event in events.read() {
+        let (Ok(window), Ok((camera, transform, projection, tonemapping, bloom, grading))) =
+            (windows.get_single(), cameras.get_single())
+        else {
+            warn!("Nothing to export to {:?}", event.path);
+            continue;
+        };
+
+        let window_size = window.physical_size().max(UVec2::ONE);
+        let scale = event
+            .scale
+            .clamp(1, MAX_SIDE / window_size.max_element().max(1));
+        if scale < event.scale {
+            warn!(
+                "Exporting at {scale}x instead of {}x, the image would be too big",
+                event.scale
+            );
+        }
+        let size = window_size * scale;
+        let image = images.add(render_target(size));
+
+        let mut export_camera = commands.spawn((
+            ExportCamera {
+                path: event.path.clone(),
+                image: image.clone(),
+                frames_left: WARMUP_FRAMES,
+                exit_when_saved: event.exit_when_saved,
+            },
+            Camera2d,
+            Camera {
+                target: RenderTarget::Image(image),
+                hdr: camera.hdr,
+                order: -100,
+                ..Default::default()
+            },
+            *transform,
+            // The bigger target would show more of the world at the same
+            // projection scale.
+            OrthographicProjection {
+                scale: projection.scale / scale as f32,
+                ..projection.clone()
+            },
+            *tonemapping,
+        ));
+        if let Some(bloom) = bloom {
+            export_camera.insert(bloom.clone());
+        }
+        if let Some(grading) = grading {
+            export_camera.insert(grading.clone());
+        }
+    }
+}
+
+/// Captures the images of the export cameras once they rendered, saving
+/// them and despawning the cameras.
+fn capture_exports(mut commands: Commands, mut cameras: Query<(Entity, &mut ExportCamera)>) {
+    for (entity, mut export) in &mut cameras {
+        if export.frames_left > 0 {
+            export.frames_left -= 1;
+            continue;
+        }
+        commands.entity(entity).remove::<ExportCamera>();
+        let mut save = save_to_disk(export.path.clone());
+        let exit_when_saved = export.exit_when_saved;
+        commands
+            .spawn(Screenshot::image(export.image.clone()))
+            .observe(
+                move |trigger: Trigger<ScreenshotCaptured>,
+                      mut commands: Commands,
+                      mut exit: EventWriter<AppExit>| {
+                    save(trigger);
+                    commands.entity(entity).despawn();
+                    if exit_when_saved {
+                        exit.send(AppExit::Success);
+                    }
+                },
+            );
+    }
+}
+
+/// Renders [`ExportImage`] requests offscreen, and the current frame when
+/// F12 is pressed.
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap>()
+            .add_event::<ExportImage>()
+            .add_systems(
+                Update,
+                (export_frame, start_exports, capture_exports).chain(),
+            );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::forces::Acceleration;
+use crate::physics_plugin::{Mass, Velocity};
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+use std::any::Any;
+
+/// What a [`Force`] or an [`Analysis`] sees of a body.
+#[derive(Debug, Clone, Copy)]
+pub struct BodySample {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// 0 for massless bodies
+    pub mass: f32,
+    pub world: SimWorld,
+}
+
+/// A force added to the simulation from outside of it, registered with
+/// [`ExtendSimulation::add_force`]. It is evaluated once every fixed step
+/// along with the drag and the tethers, and integrated with the gravity.
+pub trait Force: Send + Sync + 'static {
+    /// Acceleration of the `body` at the simulated `time`.
+    fn acceleration(&self, body: &BodySample, time: f32) -> Vec2;
+}
+
+/// A measurement of the simulation, registered with
+/// [`ExtendSimulation::add_analysis`]. It observes every body after every
+/// fixed step, once the collisions were handled, and keeps whatever it
+/// found for [`AnalysisRegistry::get`].
+pub trait Analysis: Send + Sync + 'static {
+    fn observe(&mut self, bodies: &[BodySample], time: f32);
+}
+
+/// The registered forces, in the order they were added.
+#[derive(Resource, Default)]
+pub struct ForceRegistry {
+    forces: Vec<Box<dyn Force>>,
+}
+
+struct RegisteredAnalysis {
+    analysis: Box<dyn Any + Send + Sync>,
+    /// Observes with the analysis, knowing its type
+    observe: fn(&mut (dyn Any + Send + Sync), &[BodySample], f32),
+}
+
+/// The registered analyses, in the order they were added.
+#[derive(Resource, Default)]
+pub struct AnalysisRegistry {
+    analyses: Vec<RegisteredAnalysis>,
+}
+
+impl AnalysisRegistry {
+    /// The registered analysis of type `A`, with what it found so far.
+    pub fn get<A: Analysis>(&self) -> Option<&A> {
+        self.analyses
+            .iter()
+            .find_map(|registered| registered.analysis.downcast_ref())
+    }
+
+    pub fn get_mut<A: Analysis>(&mut self) -> Option<&mut A> {
+        self.analyses
+            .iter_mut()
+            .find_map(|registered| registered.analysis.downcast_mut())
+    }
+}
+
+fn observe_as<A: Analysis>(
+    analysis: &mut (dyn Any + Send + Sync),
+    bodies: &[BodySample],
+    time: f32,
+) {
+    if let Some(analysis) = analysis.downcast_mut::<A>() {
+        analysis.observe(bodies, time);
+    }
+}
+
+/// Extending the simulation from other crates without forking it.
+pub trait ExtendSimulation {
+    fn add_force<F: Force + Default>(&mut self) -> &mut Self;
+
+    fn add_analysis<A: Analysis + Default>(&mut self) -> &mut Self;
+}
+
+impl ExtendSimulation for App {
+    fn add_force<F: Force + Default>(&mut self) -> &mut Self {
+        self.init_resource::<ForceRegistry>();
+        self.world_mut()
+            .resource_mut::<ForceRegistry>()
+            .forces
+            .push(Box::new(F::default()));
+        self
+    }
+
+    fn add_analysis<A: Analysis + Default>(&mut self) -> &mut Self {
+        self.init_resource::<AnalysisRegistry>();
+        self.world_mut()
+            .resource_mut::<AnalysisRegistry>()
+            .analyses
+            .push(RegisteredAnalysis {
+                analysis: Box::new(A::default()),
+                observe: observe_as::<A>,
+            });
+        self
+    }
+}
+
+type SampledBody<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Velocity,
+    Option<&'a Mass>,
+    Option<&'a SimWorld>,
+);
+
+fn sample((entity, transform, velocity, mass, world): SampledBody) -> BodySample {
+    BodySample {
+        entity,
+        position: transform.translation.xy(),
+        velocity: velocity.0,
+        mass: mass.map_or(0., |mass| mass.0),
+        world: world.copied().unwrap_or_default(),
+    }
+}
+
+/// Sums the accelerations of the registered forces into
+/// [`Acceleration::custom`].
+#[allow(clippy::type_complexity)]
+pub fn apply_custom_forces(
+    time: Res<Time>,
+    registry: Res<ForceRegistry>,
+    mut bodies: Query<(
+        Entity,
+        &Transform,
+        &Velocity,
+        Option<&Mass>,
+        Option<&SimWorld>,
+        &mut Acceleration,
+    )>,
+) {
+    if registry.forces.is_empty() {
+        return;
+    }
+    let now = time.elapsed_secs();
+    for (entity, transform, velocity, mass, world, mut acceleration) in &mut bodies {
+        let body = sample((entity, transform, velocity, mass, world));
+        acceleration.custom = registry
+            .forces
+            .iter()
+            .map(|force| force.acceleration(&body, now))
+            .sum();
+    }
+}
+
+/// Lets every registered analysis observe the bodies, which are sorted by
+/// their entities so they are seen in the same order on every run.
+pub fn run_analyses(
+    time: Res<Time>,
+    mut registry: ResMut<AnalysisRegistry>,
+    bodies: Query<SampledBody, Without<Parent>>,
+) {
+    if registry.analyses.is_empty() {
+        return;
+    }
+    let mut samples: Vec<BodySample> = bodies.iter().map(sample).collect();
+    samples.sort_by_key(|body| body.entity);
+    let now = time.elapsed_secs();
+    for registered in &mut registry.analyses {
+        (registered.observe)(registered.analysis.as_mut(), &samples, now);
+    }
+}
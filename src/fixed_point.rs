@@ -0,0 +1,144 @@
+use crate::forces::Acceleration;
+use crate::integrator::{IntegratorKind, LastAcceleration};
+use crate::physics_plugin::Velocity;
+use crate::timings::PhysicsTimings;
+use bevy::math::I64Vec2;
+use bevy::prelude::*;
+use bevy::utils::Instant;
+
+/// When present, the bodies are moved in integer fixed-point steps instead
+/// of floats, so machines running in lockstep can't drift apart through
+/// differently rounded float arithmetic.
+///
+/// The fixed-point state is the one the integration works with, the
+/// `Transform` and [`Velocity`] are converted from it after every substep
+/// for rendering and the force calculation. Changes made to them by other
+/// systems are picked up before the next step.
+///
+/// Only the integrators evaluating the forces once a step work in fixed
+/// point, RK4 always moves the bodies in floats.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct FixedPoint {
+    /// Bits below the binary point, a position is kept to `2^-fraction_bits`
+    /// units
+    pub fraction_bits: u32,
+}
+
+impl Default for FixedPoint {
+    fn default() -> Self {
+        FixedPoint { fraction_bits: 16 }
+    }
+}
+
+impl FixedPoint {
+    pub fn to_fixed(&self, value: Vec2) -> I64Vec2 {
+        let one = (1_i64 << self.fraction_bits) as f64;
+        I64Vec2::new(
+            (value.x as f64 * one).round() as i64,
+            (value.y as f64 * one).round() as i64,
+        )
+    }
+
+    pub fn to_float(&self, value: I64Vec2) -> Vec2 {
+        let one = (1_i64 << self.fraction_bits) as f64;
+        Vec2::new((value.x as f64 / one) as f32, (value.y as f64 / one) as f32)
+    }
+
+    /// `value` times the fixed-point `factor`, rounded towards negative
+    /// infinity.
+    fn scale(&self, value: I64Vec2, factor: i64) -> I64Vec2 {
+        I64Vec2::new(
+            (value.x * factor) >> self.fraction_bits,
+            (value.y * factor) >> self.fraction_bits,
+        )
+    }
+
+    /// Length of the step in fixed-point seconds.
+    fn step(&self, time: &Time) -> i64 {
+        ((time.delta().as_nanos() << self.fraction_bits) / 1_000_000_000) as i64
+    }
+}
+
+/// Position and velocity of a body in the fixed-point units of
+/// [`FixedPoint`].
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FixedState {
+    pub position: I64Vec2,
+    pub velocity: I64Vec2,
+}
+
+/// Converts the float position and velocity to fixed point for the bodies
+/// which have none yet, or whose float state was changed since the last
+/// step, e.g. by an impulse or a relative spawn.
+#[allow(clippy::type_complexity)]
+pub fn load_fixed_states(
+    mut commands: Commands,
+    fixed_point: Res<FixedPoint>,
+    mut bodies: Query<(Entity, &Transform, &Velocity, Option<&mut FixedState>)>,
+) {
+    for (entity, transform, velocity, state) in &mut bodies {
+        let position = transform.translation.xy();
+        let loaded = FixedState {
+            position: fixed_point.to_fixed(position),
+            velocity: fixed_point.to_fixed(velocity.0),
+        };
+        match state {
+            Some(mut state) => {
+                if fixed_point.to_float(state.position) != position
+                    || fixed_point.to_float(state.velocity) != velocity.0
+                {
+                    *state = loaded;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(loaded);
+            }
+        }
+    }
+}
+
+pub fn update_fixed_position(
+    time: Res<Time>,
+    fixed_point: Res<FixedPoint>,
+    integrator: Res<IntegratorKind>,
+    mut timings: ResMut<PhysicsTimings>,
+    mut bodies: Query<(&mut Transform, &mut FixedState, &LastAcceleration), With<Velocity>>,
+) {
+    let start = Instant::now();
+    let step = fixed_point.step(&time);
+    for (mut transform, mut state, last) in &mut bodies {
+        let mut moved = fixed_point.scale(state.velocity, step);
+        if let (IntegratorKind::VelocityVerlet, Some(acceleration)) = (*integrator, last.0) {
+            let acceleration = fixed_point.to_fixed(acceleration);
+            moved += fixed_point.scale(fixed_point.scale(acceleration, step), step) / 2;
+        }
+        state.position += moved;
+        let position = fixed_point.to_float(state.position);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+    timings.integration += start.elapsed();
+}
+
+/// Adds the summed acceleration of every body to its fixed-point velocity.
+#[allow(clippy::type_complexity)]
+pub fn integrate_fixed_acceleration(
+    time: Res<Time>,
+    fixed_point: Res<FixedPoint>,
+    integrator: Res<IntegratorKind>,
+    mut bodies: Query<(
+        &mut Velocity,
+        &mut FixedState,
+        &Acceleration,
+        &mut LastAcceleration,
+    )>,
+) {
+    let step = fixed_point.step(&time);
+    for (mut velocity, mut state, acceleration, mut last) in &mut bodies {
+        let total = acceleration.total();
+        let acceleration = fixed_point.to_fixed(integrator.velocity_acceleration(total, &last));
+        state.velocity += fixed_point.scale(acceleration, step);
+        velocity.0 = fixed_point.to_float(state.velocity);
+        last.0 = Some(total);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::physics_plugin::Velocity;
+use bevy::prelude::*;
+
+/// How many physics steps are run per second of simulated time, every step
+/// advancing the bodies by the same `1 / hz` seconds no matter the frame
+/// rate.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct TickRate {
+    pub hz: f64,
+}
+
+impl Default for TickRate {
+    fn default() -> Self {
+        TickRate { hz: 60. }
+    }
+}
+
+/// Positions of a body at the last two physics steps, which the rendered
+/// position is interpolated between.
+///
+/// Physics keeps writing to the `Transform`, the interpolated position is
+/// only put there between the steps and swapped back out before the next
+/// one.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct InterpolatedTranslation {
+    /// Position after the step before the last one
+    previous: Vec3,
+    /// Position after the last step
+    current: Vec3,
+    /// Position last put into the `Transform` for rendering
+    rendered: Vec3,
+}
+
+impl InterpolatedTranslation {
+    /// Position after the last step, where the physics has the body.
+    pub fn current(&self) -> Vec3 {
+        self.current
+    }
+
+    fn at(translation: Vec3) -> Self {
+        InterpolatedTranslation {
+            previous: translation,
+            current: translation,
+            rendered: translation,
+        }
+    }
+}
+
+/// Sets the fixed timestep to the [`TickRate`].
+pub fn apply_tick_rate(tick_rate: Res<TickRate>, mut time: ResMut<Time<Fixed>>) {
+    time.set_timestep_hz(tick_rate.hz);
+}
+
+/// Puts the position of the last step back before the next step runs.
+///
+/// A body whose `Transform` no longer holds the interpolated position was
+/// moved by something else in between, the move is kept and the body jumps
+/// there without interpolating.
+pub fn restore_physics_translations(
+    mut bodies: Query<(&mut Transform, &mut InterpolatedTranslation)>,
+) {
+    for (mut transform, mut interpolated) in &mut bodies {
+        if transform.translation == interpolated.rendered {
+            transform.translation = interpolated.current;
+        } else {
+            *interpolated = InterpolatedTranslation::at(transform.translation);
+        }
+    }
+}
+
+/// Remembers the positions the step moved the bodies to, starting the
+/// interpolation for the bodies which joined since the last step.
+#[allow(clippy::type_complexity)]
+pub fn record_physics_translations(
+    mut commands: Commands,
+    mut bodies: Query<
+        (Entity, &Transform, Option<&mut InterpolatedTranslation>),
+        (With<Velocity>, Without<Parent>),
+    >,
+) {
+    for (entity, transform, interpolated) in &mut bodies {
+        match interpolated {
+            Some(mut interpolated) => {
+                interpolated.previous = interpolated.current;
+                interpolated.current = transform.translation;
+                interpolated.rendered = transform.translation;
+            }
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(InterpolatedTranslation::at(transform.translation));
+            }
+        }
+    }
+}
+
+/// Moves the bodies to where they are between the last two steps, by how
+/// far the clock has gotten towards the next one.
+pub fn interpolate_translations(
+    time: Res<Time<Fixed>>,
+    mut bodies: Query<(&mut Transform, &mut InterpolatedTranslation)>,
+) {
+    let fraction = time.overstep_fraction();
+    for (mut transform, mut interpolated) in &mut bodies {
+        transform.translation = interpolated.previous.lerp(interpolated.current, fraction);
+        interpolated.rendered = transform.translation;
+    }
+}
@@ -0,0 +1,78 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::inspector::BodyPicker;
+use crate::physics_plugin::MainCamera;
+use crate::state::SimState;
+use bevy::prelude::*;
+
+/// Fraction of the remaining distance to the followed body the camera
+/// covers per second.
+const FOLLOW_SPEED: f32 = 5.;
+
+/// The body the main camera keeps centered, picked with a middle click
+/// (with the default input map).
+#[derive(Resource, Debug, Default)]
+pub struct CameraFollow {
+    pub target: Option<Entity>,
+}
+
+/// Follows the body closest to the cursor, clicking away from all of them
+/// keeps the current one.
+fn pick_followed_body(actions: Actions, picker: BodyPicker, mut follow: ResMut<CameraFollow>) {
+    if !actions.just_pressed(Action::FollowBody) {
+        return;
+    }
+    if let Some(Some(target)) = picker.pick() {
+        follow.target = Some(target);
+    }
+}
+
+fn release_camera(actions: Actions, mut follow: ResMut<CameraFollow>) {
+    if actions.just_pressed(Action::ReleaseCamera) {
+        follow.target = None;
+    }
+}
+
+fn reset_camera_follow(mut follow: ResMut<CameraFollow>) {
+    follow.target = None;
+}
+
+/// Eases the main camera toward the followed body. A body which docked to
+/// another one is followed along with it, one which was removed releases
+/// the camera.
+fn follow_body(
+    time: Res<Time<Real>>,
+    mut follow: ResMut<CameraFollow>,
+    bodies: Query<&GlobalTransform, Without<MainCamera>>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+) {
+    let Some(target) = follow.target else {
+        return;
+    };
+    let Ok(body) = bodies.get(target) else {
+        follow.target = None;
+        return;
+    };
+    // Real time, so the camera keeps up while the simulation is paused or
+    // sped up.
+    let t = 1. - (-FOLLOW_SPEED * time.delta_secs()).exp();
+    for mut transform in &mut cameras {
+        let position = transform.translation.xy().lerp(body.translation().xy(), t);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Keeps the camera on the [`CameraFollow`] target, picked with the middle
+/// mouse button and released with F by default.
+pub struct FollowCameraPlugin;
+
+impl Plugin for FollowCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraFollow>()
+            .init_resource::<InputMap>()
+            .add_systems(OnEnter(SimState::Loading), reset_camera_follow)
+            .add_systems(
+                Update,
+                (pick_followed_body, release_camera, follow_body).chain(),
+            );
+    }
+}
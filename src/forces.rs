@@ -0,0 +1,91 @@
+use crate::integrator::{IntegratorKind, LastAcceleration};
+use crate::physics_plugin::Velocity;
+use bevy::prelude::*;
+
+/// Acceleration of a body in the current step, kept apart by what causes
+/// it so it can be inspected. The force systems fill it in and
+/// [`integrate_acceleration`] applies the sum to the velocity.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct Acceleration {
+    /// Gravity of the other bodies, as the tree approximates it
+    pub gravity: Vec2,
+    pub drag: Vec2,
+    /// Autopilot engines
+    pub thrust: Vec2,
+    /// Springs of the tethers the body is attached to
+    pub tether: Vec2,
+    /// Forces registered by other crates, see [`crate::extensions::Force`]
+    pub custom: Vec2,
+    /// Velocity changes from [`Impulse`]s this frame, applied right away
+    /// instead of integrated
+    pub impulse: Vec2,
+}
+
+impl Acceleration {
+    /// The acceleration integrated into the velocity, without the impulses.
+    pub fn total(&self) -> Vec2 {
+        self.gravity + self.drag + self.thrust + self.tether + self.custom
+    }
+}
+
+/// Slows the body down proportionally to its speed.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Drag {
+    /// Deceleration per unit of speed
+    pub coefficient: f32,
+}
+
+/// Instant change of a body's velocity, e.g. from a scripted event.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Impulse {
+    pub body: Entity,
+    pub delta_velocity: Vec2,
+}
+
+/// Resets the contributions of the last frame, the force systems only fill
+/// in the ones that act on a body now. The gravity is kept, the force
+/// calculation replaces it unless the [`Watchdog`](crate::watchdog::Watchdog)
+/// cuts it short.
+pub fn clear_accelerations(mut accelerations: Query<&mut Acceleration>) {
+    for mut acceleration in &mut accelerations {
+        *acceleration = Acceleration {
+            gravity: acceleration.gravity,
+            ..Default::default()
+        };
+    }
+}
+
+pub fn apply_drag(mut bodies: Query<(&Velocity, &Drag, &mut Acceleration)>) {
+    for (velocity, drag, mut acceleration) in &mut bodies {
+        acceleration.drag = -velocity.0 * drag.coefficient;
+    }
+}
+
+pub fn apply_impulses(
+    mut impulses: EventReader<Impulse>,
+    mut bodies: Query<(&mut Velocity, &mut Acceleration)>,
+) {
+    for impulse in impulses.read() {
+        if let Ok((mut velocity, mut acceleration)) = bodies.get_mut(impulse.body) {
+            velocity.0 += impulse.delta_velocity;
+            acceleration.impulse += impulse.delta_velocity;
+        }
+    }
+}
+
+/// Adds the summed acceleration of every body to its velocity, the way the
+/// [`IntegratorKind`] asks for.
+pub fn integrate_acceleration(
+    time: Res<Time>,
+    integrator: Res<IntegratorKind>,
+    mut bodies: Query<(&mut Velocity, &Acceleration, &mut LastAcceleration)>,
+) {
+    for (mut velocity, acceleration, mut last) in &mut bodies {
+        integrator.kick(
+            &mut velocity.0,
+            acceleration.total(),
+            &mut last,
+            time.delta_secs(),
+        );
+    }
+}
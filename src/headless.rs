@@ -0,0 +1,233 @@
+//! `--headless <seconds>`: runs the simulation for a given simulated time
+//! without a window, as fast as the machine allows, printing the progress
+//! along the way. Ctrl+C stops the run gracefully, writing the state it got
+//! to into a checkpoint.
+
+use crate::fixed_step::TickRate;
+use crate::physics_plugin::{potential_energy, Mass, PhysicsSettings, Velocity};
+use crate::state::SimState;
+use crate::worlds::SimWorld;
+use bevy::app::{PluginGroupBuilder, ScheduleRunnerPlugin};
+use bevy::prelude::*;
+use bevy::render::settings::{RenderCreation, WgpuSettings};
+use bevy::render::RenderPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::utils::{Duration, HashMap, Instant};
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Real time between two progress reports.
+const REPORT_INTERVAL: f32 = 5.;
+
+/// The default plugins without a window or a renderer, updating in a loop
+/// as fast as it can.
+pub fn headless_plugins() -> PluginGroupBuilder {
+    DefaultPlugins
+        .set(WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            ..Default::default()
+        })
+        .set(RenderPlugin {
+            render_creation: RenderCreation::Automatic(WgpuSettings {
+                backends: None,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .disable::<WinitPlugin>()
+        .add(ScheduleRunnerPlugin::run_loop(Duration::ZERO))
+}
+
+/// Progress of a headless run.
+#[derive(Resource, Debug)]
+pub struct HeadlessRun {
+    /// Simulated seconds to run for
+    pub duration: f32,
+    /// Where the state is written when the run is interrupted
+    pub checkpoint_path: PathBuf,
+    steps: u64,
+    elapsed: f32,
+    started: Instant,
+    report_timer: Timer,
+    initial_energy: Option<f32>,
+}
+
+impl HeadlessRun {
+    pub fn new(duration: f32) -> Self {
+        HeadlessRun {
+            duration,
+            checkpoint_path: PathBuf::from("checkpoint.toml"),
+            steps: 0,
+            elapsed: 0.,
+            started: Instant::now(),
+            report_timer: Timer::from_seconds(REPORT_INTERVAL, TimerMode::Repeating),
+            initial_energy: None,
+        }
+    }
+}
+
+/// State of a body when the run was interrupted.
+#[derive(Serialize)]
+struct CheckpointBody {
+    world: u32,
+    position: Vec2,
+    velocity: Vec2,
+    mass: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct Checkpoint {
+    /// Simulated seconds the run got through
+    elapsed: f32,
+    steps: u64,
+    bodies: Vec<CheckpointBody>,
+}
+
+/// Kinetic plus potential energy of all the worlds with the gravitational
+/// constant `g`, every world only attracted by its own bodies.
+fn total_energy<'a>(
+    bodies: impl Iterator<
+        Item = (
+            &'a Transform,
+            &'a Velocity,
+            Option<&'a Mass>,
+            Option<&'a SimWorld>,
+        ),
+    >,
+    g: f32,
+) -> f32 {
+    let mut kinetic = 0.;
+    let mut sources: HashMap<SimWorld, Vec<(Vec2, f32)>> = HashMap::default();
+    for (transform, velocity, mass, world) in bodies {
+        let Some(mass) = mass else {
+            continue;
+        };
+        kinetic += 0.5 * mass.0 * velocity.0.length_squared();
+        sources
+            .entry(world.copied().unwrap_or_default())
+            .or_default()
+            .push((transform.translation.xy(), mass.0));
+    }
+    // Exact pair sums, the drift should show the integration error, not the
+    // tree's.
+    kinetic
+        + sources
+            .values()
+            .map(|bodies| potential_energy(bodies, 0., g))
+            .sum::<f32>()
+}
+
+/// Steps every frame by exactly one fixed step, so the simulation runs as
+/// fast as the steps can be computed rather than following the clock.
+fn step_per_update(mut commands: Commands, tick_rate: Res<TickRate>) {
+    commands.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        1. / tick_rate.hz,
+    )));
+}
+
+/// There is no one to pick a scenario, the selected one is loaded right
+/// away.
+fn skip_menu(mut next_state: ResMut<NextState<SimState>>) {
+    next_state.set(SimState::Loading);
+}
+
+fn count_step(time: Res<Time>, mut run: ResMut<HeadlessRun>) {
+    run.steps += 1;
+    run.elapsed += time.delta_secs();
+}
+
+/// Prints the progress every few seconds and stops the app once the
+/// simulated time is reached.
+#[allow(clippy::type_complexity)]
+fn report_progress(
+    time: Res<Time<Real>>,
+    settings: Res<PhysicsSettings>,
+    mut run: ResMut<HeadlessRun>,
+    mut exit: EventWriter<AppExit>,
+    bodies: Query<(&Transform, &Velocity, Option<&Mass>, Option<&SimWorld>)>,
+) {
+    let energy = || total_energy(bodies.iter(), settings.g);
+    if run.initial_energy.is_none() {
+        run.initial_energy = Some(energy());
+    }
+
+    let finished = run.elapsed >= run.duration;
+    if !run.report_timer.tick(time.delta()).just_finished() && !finished {
+        return;
+    }
+    let wall = run.started.elapsed().as_secs_f32();
+    let steps_per_second = run.steps as f32 / wall.max(f32::EPSILON);
+    let sim_per_second = run.elapsed / wall.max(f32::EPSILON);
+    let eta = (run.duration - run.elapsed).max(0.) / sim_per_second.max(f32::EPSILON);
+    let initial = run.initial_energy.unwrap_or_default();
+    let drift = (energy() - initial) / initial.abs().max(f32::EPSILON);
+    println!(
+        "step {}, {:.1} of {:.1} s simulated, {steps_per_second:.0} steps/s, ETA {eta:.0} s, energy drift {drift:+.2e}",
+        run.steps, run.elapsed, run.duration
+    );
+    if finished {
+        println!("Done in {wall:.1} s");
+        exit.send(AppExit::Success);
+    }
+}
+
+/// Writes the checkpoint when the app is asked to exit before the run is
+/// done, which Bevy does on Ctrl+C.
+#[allow(clippy::type_complexity)]
+fn write_checkpoint_on_exit(
+    mut exits: EventReader<AppExit>,
+    run: Res<HeadlessRun>,
+    bodies: Query<(&Transform, &Velocity, Option<&Mass>, Option<&SimWorld>)>,
+) {
+    if exits.is_empty() || run.elapsed >= run.duration {
+        return;
+    }
+    exits.clear();
+
+    let checkpoint = Checkpoint {
+        elapsed: run.elapsed,
+        steps: run.steps,
+        bodies: bodies
+            .iter()
+            .map(|(transform, velocity, mass, world)| CheckpointBody {
+                world: world.map_or(0, |world| world.0),
+                position: transform.translation.xy(),
+                velocity: velocity.0,
+                mass: mass.map(|mass| mass.0),
+            })
+            .collect(),
+    };
+    let written = toml::to_string(&checkpoint)
+        .map_err(|err| err.to_string())
+        .and_then(|text| std::fs::write(&run.checkpoint_path, text).map_err(|err| err.to_string()));
+    match written {
+        Ok(()) => println!(
+            "Interrupted after {:.1} s, state written to {}",
+            run.elapsed,
+            run.checkpoint_path.display()
+        ),
+        Err(err) => println!("Interrupted, couldn't write the checkpoint: {err}"),
+    }
+}
+
+/// Runs the loaded scenario for the [`HeadlessRun`]'s duration, to be
+/// used with [`headless_plugins`].
+pub struct HeadlessPlugin {
+    pub duration: f32,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HeadlessRun::new(self.duration))
+            .init_resource::<TickRate>()
+            .init_resource::<PhysicsSettings>()
+            .add_systems(Startup, step_per_update)
+            .add_systems(OnEnter(SimState::Menu), skip_menu)
+            .add_systems(FixedUpdate, count_step.run_if(in_state(SimState::Running)))
+            .add_systems(Update, report_progress.run_if(in_state(SimState::Running)))
+            .add_systems(Last, write_checkpoint_on_exit);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::camera_path::CameraDirector;
+use crate::comparison::AccuracyComparison;
+use crate::contours::PotentialContours;
+use crate::cost_heatmap::CostHeatmap;
+use crate::follow_camera::CameraFollow;
+use crate::input::{Action, Actions, InputMap};
+use crate::localization::Localization;
+use crate::long_exposure::LongExposure;
+use crate::measurement::Measurement;
+use crate::orbit_prediction::OrbitPrediction;
+use crate::populations::{Population, PopulationVisibility};
+use crate::probe::Probe;
+use crate::reference_frame::ReferenceFrame;
+use crate::starfield::Starfield;
+use crate::state::SimState;
+use crate::streamlines::StreamlineOverlay;
+use crate::tidal::TidalStressOverlay;
+use crate::trails::Trails;
+use crate::tree_overlay::TreeOverlay;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+/// Marks the help overlay.
+#[derive(Component)]
+struct HelpOverlay;
+
+fn spawn_help_overlay(mut commands: Commands) {
+    commands.spawn((
+        HelpOverlay,
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            right: Val::Px(10.),
+            padding: UiRect::all(Val::Px(10.)),
+            ..Default::default()
+        },
+        BackgroundColor(Color::srgba(0., 0., 0., 0.8)),
+        Visibility::Hidden,
+    ));
+}
+
+fn toggle_help(actions: Actions, mut overlays: Query<&mut Visibility, With<HelpOverlay>>) {
+    if !actions.just_pressed(Action::ToggleHelp) {
+        return;
+    }
+    for mut visibility in &mut overlays {
+        visibility.toggle_visible_hidden();
+    }
+}
+
+/// The state of everything an action toggles, when it is present.
+#[derive(SystemParam)]
+struct Modes<'w> {
+    probe: Option<Res<'w, Probe>>,
+    comparison: Option<Res<'w, AccuracyComparison>>,
+    state: Option<Res<'w, State<SimState>>>,
+    streamlines: Option<Res<'w, StreamlineOverlay>>,
+    contours: Option<Res<'w, PotentialContours>>,
+    long_exposure: Option<Res<'w, LongExposure>>,
+    tidal_stress: Option<Res<'w, TidalStressOverlay>>,
+    camera_director: Option<Res<'w, CameraDirector>>,
+    reference_frame: Option<Res<'w, ReferenceFrame>>,
+    cost_heatmap: Option<Res<'w, CostHeatmap>>,
+    camera_follow: Option<Res<'w, CameraFollow>>,
+    starfield: Option<Res<'w, Starfield>>,
+    measurement: Option<Res<'w, Measurement>>,
+    tree_overlay: Option<Res<'w, TreeOverlay>>,
+    trails: Option<Res<'w, Trails>>,
+    orbit_prediction: Option<Res<'w, OrbitPrediction>>,
+    populations: Option<Res<'w, PopulationVisibility>>,
+}
+
+impl Modes<'_> {
+    /// Whether the mode the `action` toggles is on, `None` for actions
+    /// which don't toggle anything.
+    fn active(&self, action: Action) -> Option<bool> {
+        match action {
+            Action::ToggleProbe => self.probe.as_ref().map(|probe| probe.active),
+            Action::ToggleComparison => self.comparison.as_ref().map(|c| c.is_running()),
+            Action::TogglePause => self.state.as_ref().map(|s| *s.get() == SimState::Paused),
+            Action::ToggleEditing => self.state.as_ref().map(|s| *s.get() == SimState::Editing),
+            Action::TogglePhotoMode => self.state.as_ref().map(|s| *s.get() == SimState::Photo),
+            Action::CycleStreamlines => self.streamlines.as_ref().map(|s| s.field.is_some()),
+            Action::ToggleContours => self.contours.as_ref().map(|c| c.active),
+            Action::ToggleLongExposure => self.long_exposure.as_ref().map(|l| l.is_active()),
+            Action::ToggleTidalStress => self.tidal_stress.as_ref().map(|t| t.active),
+            Action::ToggleCameraRecording => {
+                self.camera_director.as_ref().map(|d| d.is_recording())
+            }
+            Action::ToggleReferenceFrame => self
+                .reference_frame
+                .as_ref()
+                .map(|f| **f == ReferenceFrame::DominantBody),
+            Action::CycleCostHeatmap => self.cost_heatmap.as_ref().map(|c| c.metric.is_some()),
+            Action::FollowBody => self.camera_follow.as_ref().map(|f| f.target.is_some()),
+            Action::ToggleStarfield => self.starfield.as_ref().map(|s| s.enabled),
+            Action::CycleMeasureTool => self.measurement.as_ref().map(|m| m.tool.is_some()),
+            Action::ToggleTreeOverlay => self.tree_overlay.as_ref().map(|t| t.active),
+            Action::ToggleTrails => self.trails.as_ref().map(|t| t.all),
+            Action::ToggleOrbitPrediction => self.orbit_prediction.as_ref().map(|o| o.active),
+            Action::ToggleMassive => self.population_visible(Population::Massive),
+            Action::ToggleMassless => self.population_visible(Population::Massless),
+            Action::ToggleTracers => self.population_visible(Population::Tracers),
+            Action::CycleSolo => self.populations.as_ref().map(|p| p.solo.is_some()),
+            _ => None,
+        }
+    }
+
+    fn population_visible(&self, population: Population) -> Option<bool> {
+        self.populations
+            .as_ref()
+            .map(|populations| populations.is_visible(population))
+    }
+}
+
+/// Lists every binding of the input map, and which of the toggleable
+/// modes are on.
+fn update_help_overlay(
+    input_map: Res<InputMap>,
+    localization: Res<Localization>,
+    modes: Modes,
+    mut overlays: Query<(&mut Text, &Visibility), With<HelpOverlay>>,
+) {
+    let mode = |active: Option<bool>| match active {
+        Some(true) => format!(" [{}]", localization.text("mode-on", &[])),
+        Some(false) => format!(" [{}]", localization.text("mode-off", &[])),
+        None => String::new(),
+    };
+
+    for (mut text, visibility) in &mut overlays {
+        if visibility == Visibility::Hidden {
+            continue;
+        }
+        let mut lines = vec![localization.text("help-title", &[])];
+        for (action, binding) in &input_map.bindings {
+            lines.push(format!(
+                "{binding}: {}{}",
+                localization.text(action.message_id(), &[]),
+                mode(modes.active(*action))
+            ));
+        }
+        text.0 = lines.join("\n");
+    }
+}
+
+/// Overlay listing the key bindings, toggled with F1 by default.
+pub struct HelpPlugin;
+
+impl Plugin for HelpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap>()
+            .init_resource::<Localization>()
+            .add_systems(Startup, spawn_help_overlay)
+            .add_systems(Update, (toggle_help, update_help_overlay).chain());
+    }
+}
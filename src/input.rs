@@ -0,0 +1,252 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Something the user can trigger with a key or mouse button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    ToggleHelp,
+    ToggleProbe,
+    PlaceProbe,
+    ToggleComparison,
+    CyclePalette,
+    NextLessonStep,
+    TogglePause,
+    ToggleEditing,
+    Restart,
+    CycleStreamlines,
+    ToggleContours,
+    ToggleTimings,
+    TogglePhotoMode,
+    CapturePhoto,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ZoomIn,
+    ZoomOut,
+    BloomUp,
+    BloomDown,
+    ExposureUp,
+    ExposureDown,
+    ExportFrame,
+    ToggleLongExposure,
+    SaveLongExposure,
+    CycleQuality,
+    InspectBody,
+    ToggleTidalStress,
+    ToggleCameraRecording,
+    ToggleReferenceFrame,
+    CycleCostHeatmap,
+    FollowBody,
+    ReleaseCamera,
+    ToggleStarfield,
+    CycleMeasureTool,
+    PlaceMeasurePoint,
+    ToggleTreeOverlay,
+    AddBookmark,
+    JumpToBookmark,
+    ToggleTrails,
+    ToggleOrbitPrediction,
+    ToggleMassive,
+    ToggleMassless,
+    ToggleTracers,
+    CycleSolo,
+    ExportTree,
+    FitAll,
+    FitSelection,
+    PlaceBody,
+    DeleteBody,
+    ClearBodies,
+    Undock,
+}
+
+/// Physical input an action is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// Which input triggers which action, in the order they are listed in the
+/// help overlay.
+#[derive(Resource, Debug, Clone)]
+pub struct InputMap {
+    pub bindings: Vec<(Action, Binding)>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        InputMap {
+            bindings: vec![
+                (Action::ToggleHelp, Binding::Key(KeyCode::F1)),
+                (Action::ToggleProbe, Binding::Key(KeyCode::KeyP)),
+                (Action::PlaceProbe, Binding::Mouse(MouseButton::Left)),
+                (Action::ToggleComparison, Binding::Key(KeyCode::KeyG)),
+                (Action::CyclePalette, Binding::Key(KeyCode::KeyT)),
+                (Action::NextLessonStep, Binding::Key(KeyCode::KeyN)),
+                (Action::TogglePause, Binding::Key(KeyCode::Space)),
+                (Action::ToggleEditing, Binding::Key(KeyCode::KeyE)),
+                (Action::Restart, Binding::Key(KeyCode::KeyR)),
+                (Action::CycleStreamlines, Binding::Key(KeyCode::KeyL)),
+                (Action::ToggleContours, Binding::Key(KeyCode::KeyC)),
+                (Action::ToggleTimings, Binding::Key(KeyCode::F3)),
+                (Action::TogglePhotoMode, Binding::Key(KeyCode::KeyO)),
+                (Action::CapturePhoto, Binding::Key(KeyCode::Enter)),
+                (Action::PanUp, Binding::Key(KeyCode::ArrowUp)),
+                (Action::PanDown, Binding::Key(KeyCode::ArrowDown)),
+                (Action::PanLeft, Binding::Key(KeyCode::ArrowLeft)),
+                (Action::PanRight, Binding::Key(KeyCode::ArrowRight)),
+                (Action::ZoomIn, Binding::Key(KeyCode::PageUp)),
+                (Action::ZoomOut, Binding::Key(KeyCode::PageDown)),
+                (Action::BloomUp, Binding::Key(KeyCode::BracketRight)),
+                (Action::BloomDown, Binding::Key(KeyCode::BracketLeft)),
+                (Action::ExposureUp, Binding::Key(KeyCode::Equal)),
+                (Action::ExposureDown, Binding::Key(KeyCode::Minus)),
+                (Action::ExportFrame, Binding::Key(KeyCode::F12)),
+                (Action::ToggleLongExposure, Binding::Key(KeyCode::KeyX)),
+                (Action::SaveLongExposure, Binding::Key(KeyCode::KeyZ)),
+                (Action::CycleQuality, Binding::Key(KeyCode::KeyQ)),
+                (Action::InspectBody, Binding::Mouse(MouseButton::Right)),
+                (Action::ToggleTidalStress, Binding::Key(KeyCode::KeyS)),
+                (Action::ToggleCameraRecording, Binding::Key(KeyCode::KeyK)),
+                (Action::ToggleReferenceFrame, Binding::Key(KeyCode::KeyH)),
+                (Action::CycleCostHeatmap, Binding::Key(KeyCode::KeyM)),
+                (Action::FollowBody, Binding::Mouse(MouseButton::Middle)),
+                (Action::ReleaseCamera, Binding::Key(KeyCode::KeyF)),
+                (Action::ToggleStarfield, Binding::Key(KeyCode::KeyB)),
+                (Action::CycleMeasureTool, Binding::Key(KeyCode::KeyU)),
+                (Action::PlaceMeasurePoint, Binding::Mouse(MouseButton::Left)),
+                (Action::ToggleTreeOverlay, Binding::Key(KeyCode::KeyV)),
+                (Action::AddBookmark, Binding::Key(KeyCode::KeyY)),
+                (Action::JumpToBookmark, Binding::Key(KeyCode::KeyJ)),
+                (Action::ToggleTrails, Binding::Key(KeyCode::KeyW)),
+                (Action::ToggleOrbitPrediction, Binding::Key(KeyCode::KeyI)),
+                (Action::ToggleMassive, Binding::Key(KeyCode::Digit1)),
+                (Action::ToggleMassless, Binding::Key(KeyCode::Digit2)),
+                (Action::ToggleTracers, Binding::Key(KeyCode::Digit3)),
+                (Action::CycleSolo, Binding::Key(KeyCode::Digit0)),
+                (Action::ExportTree, Binding::Key(KeyCode::F2)),
+                (Action::FitAll, Binding::Key(KeyCode::Home)),
+                (Action::FitSelection, Binding::Key(KeyCode::End)),
+                (Action::PlaceBody, Binding::Mouse(MouseButton::Left)),
+                (Action::DeleteBody, Binding::Key(KeyCode::Delete)),
+                (Action::ClearBodies, Binding::Key(KeyCode::Backspace)),
+                (Action::Undock, Binding::Key(KeyCode::KeyD)),
+            ],
+        }
+    }
+}
+
+impl Action {
+    /// Id of the message describing the action.
+    pub fn message_id(&self) -> &'static str {
+        match self {
+            Action::ToggleHelp => "action-toggle-help",
+            Action::ToggleProbe => "action-toggle-probe",
+            Action::PlaceProbe => "action-place-probe",
+            Action::ToggleComparison => "action-toggle-comparison",
+            Action::CyclePalette => "action-cycle-palette",
+            Action::NextLessonStep => "action-next-lesson-step",
+            Action::TogglePause => "action-toggle-pause",
+            Action::ToggleEditing => "action-toggle-editing",
+            Action::Restart => "action-restart",
+            Action::CycleStreamlines => "action-cycle-streamlines",
+            Action::ToggleContours => "action-toggle-contours",
+            Action::ToggleTimings => "action-toggle-timings",
+            Action::TogglePhotoMode => "action-toggle-photo-mode",
+            Action::CapturePhoto => "action-capture-photo",
+            Action::PanUp => "action-pan-up",
+            Action::PanDown => "action-pan-down",
+            Action::PanLeft => "action-pan-left",
+            Action::PanRight => "action-pan-right",
+            Action::ZoomIn => "action-zoom-in",
+            Action::ZoomOut => "action-zoom-out",
+            Action::BloomUp => "action-bloom-up",
+            Action::BloomDown => "action-bloom-down",
+            Action::ExposureUp => "action-exposure-up",
+            Action::ExposureDown => "action-exposure-down",
+            Action::ExportFrame => "action-export-frame",
+            Action::ToggleLongExposure => "action-toggle-long-exposure",
+            Action::SaveLongExposure => "action-save-long-exposure",
+            Action::CycleQuality => "action-cycle-quality",
+            Action::InspectBody => "action-inspect-body",
+            Action::ToggleTidalStress => "action-toggle-tidal-stress",
+            Action::ToggleCameraRecording => "action-toggle-camera-recording",
+            Action::ToggleReferenceFrame => "action-toggle-reference-frame",
+            Action::CycleCostHeatmap => "action-cycle-cost-heatmap",
+            Action::FollowBody => "action-follow-body",
+            Action::ReleaseCamera => "action-release-camera",
+            Action::ToggleStarfield => "action-toggle-starfield",
+            Action::CycleMeasureTool => "action-cycle-measure-tool",
+            Action::PlaceMeasurePoint => "action-place-measure-point",
+            Action::ToggleTreeOverlay => "action-toggle-tree-overlay",
+            Action::AddBookmark => "action-add-bookmark",
+            Action::JumpToBookmark => "action-jump-to-bookmark",
+            Action::ToggleTrails => "action-toggle-trails",
+            Action::ToggleOrbitPrediction => "action-toggle-orbit-prediction",
+            Action::ToggleMassive => "action-toggle-massive",
+            Action::ToggleMassless => "action-toggle-massless",
+            Action::ToggleTracers => "action-toggle-tracers",
+            Action::CycleSolo => "action-cycle-solo",
+            Action::ExportTree => "action-export-tree",
+            Action::FitAll => "action-fit-all",
+            Action::FitSelection => "action-fit-selection",
+            Action::PlaceBody => "action-place-body",
+            Action::DeleteBody => "action-delete-body",
+            Action::ClearBodies => "action-clear-bodies",
+            Action::Undock => "action-undock",
+        }
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{key:?}");
+                // `KeyP` reads better as `P`, `Digit1` as `1`.
+                let name = name
+                    .strip_prefix("Key")
+                    .or_else(|| name.strip_prefix("Digit"))
+                    .unwrap_or(&name);
+                write!(f, "{name}")
+            }
+            Binding::Mouse(button) => write!(f, "Mouse {button:?}"),
+        }
+    }
+}
+
+/// Checks actions against the current input through the [`InputMap`].
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    map: Res<'w, InputMap>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    buttons: Res<'w, ButtonInput<MouseButton>>,
+}
+
+impl Actions<'_> {
+    /// Whether any input bound to the `action` is held down.
+    pub fn pressed(&self, action: Action) -> bool {
+        self.map
+            .bindings
+            .iter()
+            .filter(|(bound, _)| *bound == action)
+            .any(|(_, binding)| match binding {
+                Binding::Key(key) => self.keys.pressed(*key),
+                Binding::Mouse(button) => self.buttons.pressed(*button),
+            })
+    }
+
+    /// Whether any input bound to the `action` was pressed this frame.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.map
+            .bindings
+            .iter()
+            .filter(|(bound, _)| *bound == action)
+            .any(|(_, binding)| match binding {
+                Binding::Key(key) => self.keys.just_pressed(*key),
+                Binding::Mouse(button) => self.buttons.just_pressed(*button),
+            })
+    }
+}
@@ -0,0 +1,188 @@
+use crate::ephemeris::EphemerisComparison;
+use crate::forces::Acceleration;
+use crate::input::{Action, Actions, InputMap};
+use crate::localization::Localization;
+use crate::physics_plugin::{build_fitted_tree, MainCamera, Velocity};
+use crate::quadtree::leaf_key;
+use crate::radius::Radius;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Distance in pixels from the cursor within which a click picks a body.
+const PICK_RADIUS: f32 = 20.;
+
+/// The body whose forces are listed, picked with a right click (with the
+/// default input map).
+#[derive(Resource, Debug, Default)]
+pub struct Inspector {
+    pub target: Option<Entity>,
+}
+
+/// Marks the text listing the forces.
+#[derive(Component)]
+struct InspectorText;
+
+fn spawn_inspector_text(mut commands: Commands) {
+    commands.spawn((
+        InspectorText,
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.),
+            left: Val::Px(10.),
+            ..Default::default()
+        },
+    ));
+}
+
+/// The body of `bodies`, given as entities, positions and radii, whose
+/// disc is closest to `point`, if it is at most `reach` away.
+///
+/// The candidates are looked up in a quadtree of the bodies, so picking
+/// stays cheap with many of them.
+pub fn nearest_body(bodies: &[(Entity, Vec2, f32)], point: Vec2, reach: f32) -> Option<Entity> {
+    if bodies.is_empty() {
+        return None;
+    }
+    let positions: Vec<(Vec2, f32)> = bodies.iter().map(|body| (body.1, 1.)).collect();
+    let tree = build_fitted_tree(&positions);
+    let mut by_position: HashMap<(u32, u32), Vec<usize>> = HashMap::default();
+    for (index, body) in bodies.iter().enumerate() {
+        by_position.entry(leaf_key(body.1)).or_default().push(index);
+    }
+    // Big bodies can be picked anywhere on their disc.
+    let max_radius = bodies.iter().map(|body| body.2).fold(0., f32::max);
+
+    let mut nearest: Option<(Entity, f32)> = None;
+    tree.for_each_leaf_within(point, reach + max_radius, |leaf| {
+        let indices = tree
+            .leaf_bodies(leaf)
+            .filter_map(|(position, _)| by_position.get(&leaf_key(position)))
+            .flatten();
+        for &index in indices {
+            let (entity, position, radius) = bodies[index];
+            let distance = position.distance(point) - radius;
+            if distance <= reach && nearest.is_none_or(|(_, nearest)| distance < nearest) {
+                nearest = Some((entity, distance));
+            }
+        }
+    });
+    nearest.map(|(entity, _)| entity)
+}
+
+/// Picks bodies with the cursor of the main camera.
+#[derive(SystemParam)]
+pub struct BodyPicker<'w, 's> {
+    windows: Query<'w, 's, &'static Window>,
+    cameras: Query<
+        'w,
+        's,
+        (
+            &'static Camera,
+            &'static GlobalTransform,
+            &'static OrthographicProjection,
+        ),
+        With<MainCamera>,
+    >,
+    bodies: Query<'w, 's, (Entity, &'static Transform, Option<&'static Radius>), With<Velocity>>,
+}
+
+impl BodyPicker<'_, '_> {
+    /// The body closest to the cursor, `Some(None)` when no body is close
+    /// enough and `None` when the cursor is outside of the window.
+    pub fn pick(&self) -> Option<Option<Entity>> {
+        let (Ok(window), Ok((camera, camera_transform, projection))) =
+            (self.windows.get_single(), self.cameras.get_single())
+        else {
+            return None;
+        };
+        let cursor = window
+            .cursor_position()
+            .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())?;
+
+        let bodies: Vec<(Entity, Vec2, f32)> = self
+            .bodies
+            .iter()
+            .map(|(entity, transform, radius)| {
+                let radius = radius.map_or(transform.scale.x, |radius| radius.0);
+                (entity, transform.translation.xy(), radius)
+            })
+            .collect();
+        Some(nearest_body(
+            &bodies,
+            cursor,
+            PICK_RADIUS * projection.scale,
+        ))
+    }
+}
+
+/// Picks the body closest to the cursor, clicking away from all of them
+/// clears the selection.
+fn pick_body(actions: Actions, picker: BodyPicker, mut inspector: ResMut<Inspector>) {
+    if !actions.just_pressed(Action::InspectBody) {
+        return;
+    }
+    if let Some(target) = picker.pick() {
+        inspector.target = target;
+    }
+}
+
+fn update_inspector_text(
+    inspector: Res<Inspector>,
+    localization: Res<Localization>,
+    ephemerides: Option<Res<EphemerisComparison>>,
+    bodies: Query<&Acceleration>,
+    mut texts: Query<&mut Text, With<InspectorText>>,
+) {
+    let acceleration = inspector.target.and_then(|target| bodies.get(target).ok());
+    let ephemeris = ephemerides
+        .as_ref()
+        .zip(inspector.target)
+        .and_then(|(ephemerides, target)| ephemerides.latest(target));
+    let mut content = match acceleration {
+        Some(acceleration) => {
+            let lines = [
+                ("inspector-gravity", acceleration.gravity),
+                ("inspector-drag", acceleration.drag),
+                ("inspector-thrust", acceleration.thrust),
+                ("inspector-tether", acceleration.tether),
+                ("inspector-custom", acceleration.custom),
+                ("inspector-impulse", acceleration.impulse),
+                ("inspector-total", acceleration.total()),
+            ];
+            lines
+                .iter()
+                .map(|(id, value)| {
+                    let magnitude = (value.length() as f64 * 100.).round() / 100.;
+                    localization.text(id, &[("value", magnitude)])
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        None => String::new(),
+    };
+    if let Some(ephemeris) = ephemeris {
+        let error = (ephemeris.error as f64 * 100.).round() / 100.;
+        content.push('\n');
+        content.push_str(&localization.text("inspector-ephemeris", &[("value", error)]));
+    }
+    for mut text in &mut texts {
+        if text.0 != content {
+            text.0.clone_from(&content);
+        }
+    }
+}
+
+/// Lists what accelerates the body picked by the [`Inspector`].
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inspector>()
+            .init_resource::<Localization>()
+            .init_resource::<InputMap>()
+            .add_systems(Startup, spawn_inspector_text)
+            .add_systems(Update, (pick_body, update_inspector_text).chain());
+    }
+}
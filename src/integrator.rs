@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How the accelerations are turned into motion every substep.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IntegratorKind {
+    /// Second order and symplectic, orbits keep their energy over long
+    /// runs instead of spiraling
+    #[default]
+    VelocityVerlet,
+    /// Semi-implicit Euler, moves with the old velocity and then
+    /// accelerates, kept for comparison
+    Euler,
+    /// Classic fourth order Runge-Kutta, the most accurate per step but
+    /// evaluates the forces four times a step and isn't symplectic, see
+    /// [`rk4_step`]
+    Rk4,
+}
+
+/// Acceleration the body had at the end of the last substep, which velocity
+/// Verlet needs both to move the body and to average with the new one.
+/// `None` until the body's first substep.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct LastAcceleration(pub Option<Vec2>);
+
+impl IntegratorKind {
+    /// How far a body with `velocity` moves in `dt`.
+    ///
+    /// This and [`IntegratorKind::velocity_acceleration`] describe the
+    /// integrators evaluating the forces once a step, RK4 is stepped by
+    /// [`rk4_step`] instead and falls back to Euler here.
+    pub fn displacement(&self, velocity: Vec2, last: &LastAcceleration, dt: f32) -> Vec2 {
+        match self {
+            IntegratorKind::VelocityVerlet => {
+                velocity * dt + 0.5 * last.0.unwrap_or_default() * dt * dt
+            }
+            IntegratorKind::Euler | IntegratorKind::Rk4 => velocity * dt,
+        }
+    }
+
+    /// Acceleration the velocity changes with over `dt`, given the
+    /// `acceleration` at the new position.
+    pub fn velocity_acceleration(&self, acceleration: Vec2, last: &LastAcceleration) -> Vec2 {
+        match self {
+            IntegratorKind::VelocityVerlet => {
+                // The very first substep has nothing to average with.
+                0.5 * (last.0.unwrap_or(acceleration) + acceleration)
+            }
+            IntegratorKind::Euler | IntegratorKind::Rk4 => acceleration,
+        }
+    }
+
+    /// Accelerates `velocity` over `dt` by the `acceleration` at the new
+    /// position and keeps it as the `last` one for the next step.
+    pub fn kick(
+        &self,
+        velocity: &mut Vec2,
+        acceleration: Vec2,
+        last: &mut LastAcceleration,
+        dt: f32,
+    ) {
+        *velocity += self.velocity_acceleration(acceleration, last) * dt;
+        last.0 = Some(acceleration);
+    }
+}
+
+/// Advances the bodies by one classic fourth order Runge-Kutta step of `dt`.
+/// `accelerations` returns the acceleration of every body with the bodies
+/// at the positions it is given, it is called four times.
+///
+/// Returns the accelerations at the start of the step.
+pub fn rk4_step(
+    positions: &mut [Vec2],
+    velocities: &mut [Vec2],
+    dt: f32,
+    mut accelerations: impl FnMut(&[Vec2]) -> Vec<Vec2>,
+) -> Vec<Vec2> {
+    let advance = |start: &[Vec2], rates: &[Vec2], by: f32| -> Vec<Vec2> {
+        start
+            .iter()
+            .zip(rates)
+            .map(|(start, rate)| *start + *rate * by)
+            .collect()
+    };
+
+    let velocities_1 = velocities.to_vec();
+    let accelerations_1 = accelerations(positions);
+    let velocities_2 = advance(velocities, &accelerations_1, dt / 2.);
+    let accelerations_2 = accelerations(&advance(positions, &velocities_1, dt / 2.));
+    let velocities_3 = advance(velocities, &accelerations_2, dt / 2.);
+    let accelerations_3 = accelerations(&advance(positions, &velocities_2, dt / 2.));
+    let velocities_4 = advance(velocities, &accelerations_3, dt);
+    let accelerations_4 = accelerations(&advance(positions, &velocities_3, dt));
+
+    for (index, (position, velocity)) in positions.iter_mut().zip(velocities).enumerate() {
+        *position += dt / 6.
+            * (velocities_1[index]
+                + 2. * velocities_2[index]
+                + 2. * velocities_3[index]
+                + velocities_4[index]);
+        *velocity += dt / 6.
+            * (accelerations_1[index]
+                + 2. * accelerations_2[index]
+                + 2. * accelerations_3[index]
+                + accelerations_4[index]);
+    }
+    accelerations_1
+}
@@ -0,0 +1,87 @@
+use crate::physics_plugin::MainCamera;
+use crate::scenario::{RestartScenario, Scenarios};
+use crate::state::SimState;
+use bevy::prelude::*;
+
+/// How far the camera zooms out over a scenario.
+const ZOOM_OUT: f32 = 0.6;
+/// How far the camera turns over a scenario in radians.
+const TURN: f32 = std::f32::consts::FRAC_PI_4;
+
+/// Runs the simulation unattended: every scenario plays for a fixed time
+/// with a slow camera move, then the next one is loaded. Any input holds
+/// the cycle off, so visitors can play with the current scenario until
+/// they leave it alone for that long.
+#[derive(Resource, Debug)]
+pub struct Kiosk {
+    timer: Timer,
+}
+
+impl Kiosk {
+    /// Kiosk showing every scenario for `scenario_duration` seconds.
+    pub fn new(scenario_duration: f32) -> Self {
+        Kiosk {
+            timer: Timer::from_seconds(scenario_duration, TimerMode::Repeating),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cycle_scenarios(
+    time: Res<Time<Real>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
+    mut kiosk: ResMut<Kiosk>,
+    mut scenarios: ResMut<Scenarios>,
+    mut restarts: EventWriter<RestartScenario>,
+) {
+    if keys.get_just_pressed().next().is_some() || buttons.get_just_pressed().next().is_some() {
+        kiosk.timer.reset();
+        return;
+    }
+    match state.get() {
+        SimState::Menu => {
+            scenarios.selected = 0;
+            next_state.set(SimState::Loading);
+            kiosk.timer.reset();
+        }
+        SimState::Loading => {}
+        _ => {
+            if kiosk.timer.tick(time.delta()).just_finished() && !scenarios.entries.is_empty() {
+                scenarios.selected = (scenarios.selected + 1) % scenarios.entries.len();
+                restarts.send(RestartScenario);
+            }
+        }
+    }
+}
+
+/// Slowly zooms out and turns the camera over the time of a scenario.
+fn move_kiosk_camera(
+    kiosk: Res<Kiosk>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let t = kiosk.timer.fraction();
+    // Eases in and out, so the jump back at the next scenario is the only
+    // sudden move.
+    let eased = t * t * (3. - 2. * t);
+    for (mut transform, mut projection) in &mut cameras {
+        projection.scale = 1. + ZOOM_OUT * eased;
+        transform.rotation = Quat::from_rotation_z(TURN * eased);
+    }
+}
+
+/// Cycles through the scenarios when the [`Kiosk`] resource is inserted.
+pub struct KioskPlugin;
+
+impl Plugin for KioskPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (cycle_scenarios, move_kiosk_camera)
+                .chain()
+                .run_if(resource_exists::<Kiosk>),
+        );
+    }
+}
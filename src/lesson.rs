@@ -0,0 +1,168 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::localization::Localization;
+use bevy::prelude::*;
+
+/// How quickly the camera moves towards the target of the current step, as
+/// the fraction of the remaining distance covered per second.
+const CAMERA_SPEED: f32 = 3.;
+
+/// A single text card of a lesson.
+#[derive(Debug, Clone, Default)]
+pub struct LessonStep {
+    pub text: String,
+    /// Point the camera moves to while the step is shown
+    pub camera_target: Option<Vec2>,
+    /// Parameter change or action suggested to the student
+    pub suggestion: Option<String>,
+}
+
+/// A guided tour through the simulation, one step is shown at a time and
+/// the next step action advances to the next one.
+#[derive(Resource, Debug, Default)]
+pub struct Lesson {
+    pub steps: Vec<LessonStep>,
+    /// Index of the shown step
+    pub current: usize,
+}
+
+/// Marks the text card of the lesson.
+#[derive(Component)]
+struct LessonText;
+
+impl Lesson {
+    /// Parses a lesson from text. Steps are separated by lines containing
+    /// only `---`, a `camera: x y` line sets the camera target of the step,
+    /// a `try: ...` line its suggestion, lines starting with `#` are
+    /// comments and everything else is the text of the step.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut steps = Vec::new();
+        let mut step = LessonStep::default();
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim_end();
+            if line.trim() == "---" {
+                steps.push(std::mem::take(&mut step));
+            } else if line.starts_with('#') {
+                continue;
+            } else if let Some(target) = line.strip_prefix("camera:") {
+                let coords: Vec<f32> = target
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|err| format!("line {}: {err}", number + 1))?;
+                let [x, y] = coords[..] else {
+                    return Err(format!("line {}: expected `camera: x y`", number + 1));
+                };
+                step.camera_target = Some(Vec2::new(x, y));
+            } else if let Some(suggestion) = line.strip_prefix("try:") {
+                step.suggestion = Some(suggestion.trim().to_string());
+            } else {
+                if !step.text.is_empty() {
+                    step.text.push('\n');
+                }
+                step.text.push_str(line);
+            }
+        }
+        steps.push(step);
+        // Separators at the start or end leave empty steps behind.
+        steps.retain(|step| {
+            !step.text.trim().is_empty()
+                || step.camera_target.is_some()
+                || step.suggestion.is_some()
+        });
+
+        Ok(Lesson { steps, current: 0 })
+    }
+
+    fn current_step(&self) -> Option<&LessonStep> {
+        self.steps.get(self.current)
+    }
+}
+
+fn spawn_lesson_text(mut commands: Commands) {
+    commands.spawn((
+        LessonText,
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.),
+            left: Val::Px(10.),
+            max_width: Val::Percent(60.),
+            ..Default::default()
+        },
+    ));
+}
+
+fn advance_lesson(actions: Actions, mut lesson: ResMut<Lesson>) {
+    if actions.just_pressed(Action::NextLessonStep) && lesson.current < lesson.steps.len() {
+        lesson.current += 1;
+    }
+}
+
+fn update_lesson_text(
+    lesson: Res<Lesson>,
+    localization: Res<Localization>,
+    mut query: Query<&mut Text, With<LessonText>>,
+) {
+    if !lesson.is_changed() {
+        return;
+    }
+    let card = match lesson.current_step() {
+        Some(step) => {
+            let position = localization.text(
+                "lesson-step",
+                &[
+                    ("current", (lesson.current + 1) as f64),
+                    ("total", lesson.steps.len() as f64),
+                ],
+            );
+            let mut card = format!("[{position}] {}", step.text);
+            if let Some(suggestion) = &step.suggestion {
+                card.push_str(&format!(
+                    "\n{} {suggestion}",
+                    localization.text("lesson-try", &[])
+                ));
+            }
+            card.push('\n');
+            card.push_str(&localization.text("lesson-continue", &[]));
+            card
+        }
+        None => String::new(),
+    };
+    for mut text in &mut query {
+        text.0 = card.clone();
+    }
+}
+
+fn move_lesson_camera(
+    time: Res<Time>,
+    lesson: Res<Lesson>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+) {
+    let Some(target) = lesson.current_step().and_then(|step| step.camera_target) else {
+        return;
+    };
+    let t = (CAMERA_SPEED * time.delta_secs()).min(1.);
+    for mut transform in &mut cameras {
+        let position = transform.translation.xy().lerp(target, t);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Shows the [`Lesson`] resource, when one is inserted, as text cards with
+/// camera moves.
+pub struct LessonPlugin;
+
+impl Plugin for LessonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Localization>()
+            .init_resource::<InputMap>()
+            .add_systems(Startup, spawn_lesson_text)
+            .add_systems(
+                Update,
+                (advance_lesson, update_lesson_text, move_lesson_camera)
+                    .chain()
+                    .run_if(resource_exists::<Lesson>),
+            );
+    }
+}
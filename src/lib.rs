@@ -0,0 +1,87 @@
+pub mod autopilot;
+pub mod background;
+pub mod bodies;
+pub mod body_count;
+pub mod bookmarks;
+pub mod budget;
+pub mod cadence;
+pub mod camera_fit;
+pub mod camera_path;
+pub mod clustering;
+pub mod collision_response;
+pub mod comparison;
+pub mod contours;
+#[cfg(feature = "egui")]
+pub mod control_panel;
+pub mod convergence;
+pub mod cost_heatmap;
+pub mod deletion;
+pub mod determinism;
+pub mod disc;
+#[cfg(feature = "distributed")]
+pub mod distributed;
+pub mod distributions;
+pub mod docking;
+pub mod domain_decomposition;
+pub mod drift;
+pub mod embed;
+pub mod encounters;
+pub mod ephemeris;
+pub mod equilibrium;
+pub mod export;
+pub mod extensions;
+pub mod fixed_point;
+pub mod fixed_step;
+pub mod follow_camera;
+pub mod forces;
+pub mod headless;
+pub mod help;
+pub mod input;
+pub mod inspector;
+pub mod integrator;
+pub mod kiosk;
+pub mod lesson;
+pub mod localization;
+pub mod long_exposure;
+pub mod measurement;
+pub mod menu;
+pub mod mission;
+pub mod mouse_spawn;
+pub mod orbit_prediction;
+pub mod orbits;
+#[cfg(feature = "osc")]
+pub mod osc;
+pub mod photo;
+pub mod physics_config;
+pub mod physics_plugin;
+pub mod populations;
+pub mod presets;
+pub mod preview;
+pub mod probe;
+pub mod quadtree;
+pub mod quality;
+pub mod radius;
+pub mod reference_frame;
+pub mod scenario;
+pub mod scenario_file;
+pub mod settings;
+pub mod sim_rate;
+pub mod simulation;
+pub mod spatial_index;
+pub mod stability;
+pub mod starfield;
+pub mod state;
+pub mod statistics;
+pub mod streamlines;
+pub mod summation;
+pub mod tether;
+pub mod theme;
+pub mod tidal;
+pub mod timings;
+pub mod trails;
+pub mod tree_export;
+pub mod tree_failure;
+pub mod tree_overlay;
+pub mod view_relaxation;
+pub mod watchdog;
+pub mod worlds;
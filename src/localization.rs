@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+
+/// Message files compiled into the binary, by language identifier. The
+/// first one is the fallback for unknown languages.
+const BUNDLES: &[(&str, &str)] = &[("en-US", include_str!("../locales/en-US/main.ftl"))];
+
+/// Translated UI strings, looked up by message id.
+///
+/// With the `fluent` feature the messages are resolved by Fluent, so
+/// translations can use its plural and selector syntax. Without it only
+/// plain `id = text` messages with `{ $arg }` placeables are understood.
+#[derive(Resource)]
+pub struct Localization {
+    #[cfg(feature = "fluent")]
+    bundle: fluent::concurrent::FluentBundle<fluent::FluentResource>,
+    #[cfg(not(feature = "fluent"))]
+    messages: std::collections::HashMap<String, String>,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Localization::new(BUNDLES[0].0)
+    }
+}
+
+impl Localization {
+    /// Localization for the `language` (e.g. `en-US`), falling back to the
+    /// first bundled language if there are no messages for it.
+    pub fn new(language: &str) -> Self {
+        let (language, source) = BUNDLES
+            .iter()
+            .find(|(id, _)| *id == language)
+            .unwrap_or(&BUNDLES[0]);
+        Localization::from_source(language, source)
+    }
+
+    #[cfg(feature = "fluent")]
+    fn from_source(language: &str, source: &str) -> Self {
+        let language: unic_langid::LanguageIdentifier =
+            language.parse().expect("Invalid bundled language id");
+        let resource = fluent::FluentResource::try_new(source.to_string())
+            .unwrap_or_else(|(_, errors)| panic!("Invalid bundled messages: {errors:?}"));
+        let mut bundle = fluent::concurrent::FluentBundle::new_concurrent(vec![language]);
+        // The isolation marks around arguments show up as boxes in the UI
+        // font.
+        bundle.set_use_isolating(false);
+        bundle
+            .add_resource(resource)
+            .unwrap_or_else(|errors| panic!("Duplicate bundled messages: {errors:?}"));
+        Localization { bundle }
+    }
+
+    #[cfg(not(feature = "fluent"))]
+    fn from_source(_language: &str, source: &str) -> Self {
+        let messages = source
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(id, text)| (id.trim().to_string(), text.trim().to_string()))
+            .collect();
+        Localization { messages }
+    }
+
+    /// Text of the message `id` with the numeric `args` filled in, or the id
+    /// itself if there is no such message.
+    #[cfg(feature = "fluent")]
+    pub fn text(&self, id: &str, args: &[(&str, f64)]) -> String {
+        let Some(pattern) = self.bundle.get_message(id).and_then(|m| m.value()) else {
+            return id.to_string();
+        };
+        let mut fluent_args = fluent::FluentArgs::new();
+        for &(name, value) in args {
+            fluent_args.set(name, value);
+        }
+        let mut errors = Vec::new();
+        self.bundle
+            .format_pattern(pattern, Some(&fluent_args), &mut errors)
+            .into_owned()
+    }
+
+    /// Text of the message `id` with the numeric `args` filled in, or the id
+    /// itself if there is no such message.
+    #[cfg(not(feature = "fluent"))]
+    pub fn text(&self, id: &str, args: &[(&str, f64)]) -> String {
+        let Some(text) = self.messages.get(id) else {
+            return id.to_string();
+        };
+        args.iter().fold(text.clone(), |text, (name, value)| {
+            text.replace(&format!("{{ ${name} }}"), &value.to_string())
+        })
+    }
+}
@@ -0,0 +1,203 @@
+use crate::export::timestamped_path;
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::{MainCamera, Velocity};
+use crate::theme::Theme;
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use std::path::Path;
+
+/// Width of the canvas in pixels, the height follows the view.
+const CANVAS_WIDTH: u32 = 1024;
+
+/// Accumulates the positions of the bodies into a canvas that is never
+/// cleared, drawing star trails like a long-exposure photograph. The canvas
+/// covers the view at the time the mode is turned on and stays in place
+/// when the camera moves.
+#[derive(Resource, Debug)]
+pub struct LongExposure {
+    /// Seconds after which a trail fades to half its brightness, `None`
+    /// keeps everything for as long as the mode is on
+    pub half_life: Option<f32>,
+    /// Brightness a body adds to its pixel every step
+    pub intensity: f32,
+    canvas: Option<Canvas>,
+}
+
+#[derive(Debug)]
+struct Canvas {
+    image: Handle<Image>,
+    sprite: Entity,
+    /// Area of the world the canvas covers
+    view: Rect,
+    size: UVec2,
+    /// Light collected by every pixel, row by row from the top
+    light: Vec<Vec3>,
+}
+
+impl Default for LongExposure {
+    fn default() -> Self {
+        LongExposure {
+            half_life: None,
+            intensity: 0.05,
+            canvas: None,
+        }
+    }
+}
+
+impl LongExposure {
+    /// Fades the trails to half their brightness every `half_life` seconds.
+    pub fn with_half_life(mut self, half_life: f32) -> Self {
+        self.half_life = Some(half_life);
+        self
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.canvas.is_some()
+    }
+}
+
+fn toggle_long_exposure(
+    mut commands: Commands,
+    actions: Actions,
+    mut exposure: ResMut<LongExposure>,
+    mut images: ResMut<Assets<Image>>,
+    cameras: Query<(&GlobalTransform, &OrthographicProjection), With<MainCamera>>,
+) {
+    if !actions.just_pressed(Action::ToggleLongExposure) {
+        return;
+    }
+    if let Some(canvas) = exposure.canvas.take() {
+        commands.entity(canvas.sprite).despawn();
+        images.remove(&canvas.image);
+        return;
+    }
+    let Ok((camera_transform, projection)) = cameras.get_single() else {
+        return;
+    };
+
+    let view = Rect::from_center_size(
+        camera_transform.translation().xy() + projection.area.center(),
+        projection.area.size(),
+    );
+    let size = UVec2::new(
+        CANVAS_WIDTH,
+        ((CANVAS_WIDTH as f32 * view.height() / view.width()) as u32).max(1),
+    );
+    let image = images.add(Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ));
+    // Behind the bodies, so the live ones stay visible over their trails.
+    let sprite = commands
+        .spawn((
+            Sprite {
+                image: image.clone(),
+                custom_size: Some(view.size()),
+                ..Default::default()
+            },
+            Transform::from_translation(view.center().extend(-2.)),
+        ))
+        .id();
+    exposure.canvas = Some(Canvas {
+        image,
+        sprite,
+        view,
+        size,
+        light: vec![Vec3::ZERO; (size.x * size.y) as usize],
+    });
+}
+
+/// Fades the canvas and adds the light of every body at its position.
+fn expose(
+    time: Res<Time>,
+    theme: Res<Theme>,
+    mut exposure: ResMut<LongExposure>,
+    mut images: ResMut<Assets<Image>>,
+    bodies: Query<&Transform, With<Velocity>>,
+) {
+    let half_life = exposure.half_life;
+    let intensity = exposure.intensity;
+    let Some(canvas) = exposure.canvas.as_mut() else {
+        return;
+    };
+    if time.delta_secs() == 0. {
+        // Paused, nothing moved.
+        return;
+    }
+
+    if let Some(half_life) = half_life {
+        let retained = 0.5f32.powf(time.delta_secs() / half_life);
+        for light in &mut canvas.light {
+            *light *= retained;
+        }
+    }
+    let color = theme.body().to_srgba();
+    let light = Vec3::new(color.red, color.green, color.blue) * intensity;
+    for transform in &bodies {
+        let uv = (transform.translation.xy() - canvas.view.min) / canvas.view.size();
+        if uv.cmplt(Vec2::ZERO).any() || uv.cmpge(Vec2::ONE).any() {
+            continue;
+        }
+        // Rows go from the top, world y goes up.
+        let x = (uv.x * canvas.size.x as f32) as u32;
+        let y = ((1. - uv.y) * canvas.size.y as f32) as u32;
+        canvas.light[(y.min(canvas.size.y - 1) * canvas.size.x + x) as usize] += light;
+    }
+
+    if let Some(image) = images.get_mut(&canvas.image) {
+        for (pixel, light) in image.data.chunks_exact_mut(4).zip(&canvas.light) {
+            let light = light.min(Vec3::ONE);
+            pixel[0] = (light.x * 255.) as u8;
+            pixel[1] = (light.y * 255.) as u8;
+            pixel[2] = (light.z * 255.) as u8;
+            pixel[3] = (light.max_element() * 255.) as u8;
+        }
+    }
+}
+
+/// Saves the canvas as it is, at its own resolution.
+fn save_long_exposure(actions: Actions, exposure: Res<LongExposure>, images: Res<Assets<Image>>) {
+    if !actions.just_pressed(Action::SaveLongExposure) {
+        return;
+    }
+    let Some(image) = exposure
+        .canvas
+        .as_ref()
+        .and_then(|canvas| images.get(&canvas.image))
+    else {
+        return;
+    };
+    let path = timestamped_path(Path::new("."));
+    let saved = image
+        .clone()
+        .try_into_dynamic()
+        .map_err(|err| err.to_string())
+        .and_then(|image| image.to_rgb8().save(&path).map_err(|err| err.to_string()));
+    match saved {
+        Ok(()) => info!("Long exposure saved to {}", path.display()),
+        Err(err) => error!("Couldn't save the long exposure: {err}"),
+    }
+}
+
+/// Long-exposure rendering of the star trails.
+pub struct LongExposurePlugin;
+
+impl Plugin for LongExposurePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LongExposure>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .add_systems(
+                Update,
+                (toggle_long_exposure, expose, save_long_exposure).chain(),
+            );
+    }
+}
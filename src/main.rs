@@ -1,12 +1,497 @@
-use crate::physics_plugin::PhysicsPlugin;
 use bevy::prelude::*;
-
-mod physics_plugin;
-mod quadtree;
+use spacesim::body_count::BodyCountController;
+use spacesim::bookmarks::{BookmarkPlugin, Bookmarks};
+use spacesim::budget::{Budget, OverBudget};
+use spacesim::cadence::Cadences;
+use spacesim::camera_fit::CameraFitPlugin;
+use spacesim::camera_path::{CameraDirector, CameraPath, CameraPathPlugin};
+use spacesim::clustering::ClusteringStatistics;
+use spacesim::collision_response::CollisionMode;
+use spacesim::comparison::ComparisonPlugin;
+use spacesim::contours::ContourPlugin;
+use spacesim::convergence;
+use spacesim::cost_heatmap::CostHeatmapPlugin;
+use spacesim::deletion::DeletionPlugin;
+use spacesim::determinism::Determinism;
+use spacesim::domain_decomposition::DomainDecomposition;
+use spacesim::drift::DriftCorrection;
+use spacesim::encounters::EncounterPlugin;
+use spacesim::ephemeris::EphemerisComparison;
+use spacesim::export::{ExportPlugin, ScheduledExport};
+use spacesim::fixed_point::FixedPoint;
+use spacesim::fixed_step::TickRate;
+use spacesim::follow_camera::FollowCameraPlugin;
+use spacesim::headless::{headless_plugins, HeadlessPlugin};
+use spacesim::help::HelpPlugin;
+use spacesim::inspector::InspectorPlugin;
+use spacesim::integrator::IntegratorKind;
+use spacesim::kiosk::{Kiosk, KioskPlugin};
+use spacesim::lesson::{Lesson, LessonPlugin};
+use spacesim::localization::Localization;
+use spacesim::long_exposure::{LongExposure, LongExposurePlugin};
+use spacesim::measurement::{MeasurementPlugin, RealUnit, RealUnits};
+use spacesim::menu::MenuPlugin;
+use spacesim::mission::MissionPlugin;
+use spacesim::mouse_spawn::MouseSpawnPlugin;
+use spacesim::orbit_prediction::OrbitPredictionPlugin;
+use spacesim::photo::PhotoPlugin;
+use spacesim::physics_plugin::{GravityTrees, PhysicsPlugin};
+use spacesim::populations::PopulationPlugin;
+use spacesim::presets::{Preset, PresetsPlugin};
+use spacesim::preview::PreviewPlugin;
+use spacesim::probe::ProbePlugin;
+use spacesim::quality::QualityPlugin;
+use spacesim::radius::Density;
+use spacesim::reference_frame::{ReferenceFrame, ReferenceFramePlugin};
+use spacesim::scenario::{Scenarios, SimRng};
+use spacesim::scenario_file::{RegisterScenarioFile, ScenarioFile};
+use spacesim::settings::SettingsPlugin;
+use spacesim::sim_rate::SimRate;
+use spacesim::stability::StabilityProbe;
+use spacesim::starfield::{Starfield, StarfieldPlugin};
+use spacesim::state::SimState;
+use spacesim::statistics::StatisticsPlugin;
+use spacesim::streamlines::StreamlinePlugin;
+use spacesim::summation;
+use spacesim::theme::ThemePlugin;
+use spacesim::tidal::TidalPlugin;
+use spacesim::timings::TimingsPlugin;
+use spacesim::trails::TrailPlugin;
+use spacesim::tree_export::TreeExportPlugin;
+use spacesim::tree_failure::{TreeFailure, TreeFailurePlugin};
+use spacesim::tree_overlay::TreeOverlayPlugin;
+use spacesim::view_relaxation::ViewRelaxation;
+use spacesim::watchdog::Watchdog;
+use spacesim::worlds::{SimWorlds, WorldsPlugin};
+use std::time::Duration;
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_plugins(PhysicsPlugin)
-        .run();
+    // Subcommands run on their own, without opening the window.
+    if std::env::args().nth(1).as_deref() == Some("convergence") {
+        convergence::run();
+        return;
+    }
+    if std::env::args().nth(1).as_deref() == Some("summation") {
+        summation::run();
+        return;
+    }
+    #[cfg(feature = "distributed")]
+    if std::env::args().nth(1).as_deref() == Some("coordinator") {
+        spacesim::distributed::run_coordinator(std::env::args().skip(2));
+        return;
+    }
+    #[cfg(feature = "distributed")]
+    if std::env::args().nth(1).as_deref() == Some("worker") {
+        spacesim::distributed::run_worker(std::env::args().skip(2));
+        return;
+    }
+
+    let mut app = App::new();
+    if std::env::args().any(|arg| arg == "--headless") {
+        app.add_plugins(headless_plugins());
+    } else {
+        app.add_plugins(DefaultPlugins);
+    }
+    app.add_plugins(PhysicsPlugin::default())
+        .add_plugins(PresetsPlugin)
+        .add_plugins(MenuPlugin)
+        .add_plugins(MissionPlugin)
+        .add_plugins(ProbePlugin)
+        .add_plugins(ComparisonPlugin)
+        .add_plugins(LessonPlugin)
+        .add_plugins(ThemePlugin)
+        .add_plugins(HelpPlugin)
+        .add_plugins(WorldsPlugin)
+        .add_plugins(PreviewPlugin)
+        .add_plugins(EncounterPlugin)
+        .add_plugins(BookmarkPlugin)
+        .add_plugins(StreamlinePlugin)
+        .add_plugins(TidalPlugin)
+        .add_plugins(ContourPlugin)
+        .add_plugins(CostHeatmapPlugin)
+        .add_plugins(TreeOverlayPlugin)
+        .add_plugins(TreeExportPlugin)
+        .add_plugins(TrailPlugin)
+        .add_plugins(PopulationPlugin)
+        .add_plugins(OrbitPredictionPlugin)
+        .add_plugins(TimingsPlugin)
+        .add_plugins(StatisticsPlugin)
+        .add_plugins(TreeFailurePlugin)
+        .add_plugins(KioskPlugin)
+        .add_plugins(CameraPathPlugin)
+        .add_plugins(ReferenceFramePlugin)
+        .add_plugins(FollowCameraPlugin)
+        .add_plugins(CameraFitPlugin)
+        .add_plugins(StarfieldPlugin)
+        .add_plugins(MeasurementPlugin)
+        .add_plugins(MouseSpawnPlugin)
+        .add_plugins(DeletionPlugin)
+        .add_plugins(ExportPlugin)
+        .add_plugins(PhotoPlugin)
+        .add_plugins(LongExposurePlugin)
+        .add_plugins(QualityPlugin)
+        .add_plugins(SettingsPlugin)
+        .add_plugins(InspectorPlugin);
+    #[cfg(feature = "osc")]
+    app.add_plugins(spacesim::osc::OscPlugin);
+    #[cfg(feature = "egui")]
+    app.add_plugins(spacesim::control_panel::ControlPanelPlugin);
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // Split the force calculation into n×n tiles solved on separate
+            // threads
+            "--domain-tiles" => {
+                let tiles = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .expect("--domain-tiles expects a number of tiles per side");
+                app.insert_resource(DomainDecomposition::new(tiles));
+            }
+            // Guided lesson to show over the simulation
+            "--lesson" => {
+                let path = args.next().expect("--lesson expects a path");
+                let source = std::fs::read_to_string(&path)
+                    .unwrap_or_else(|err| panic!("Couldn't read lesson `{path}`: {err}"));
+                let lesson = Lesson::parse(&source)
+                    .unwrap_or_else(|err| panic!("Invalid lesson `{path}`: {err}"));
+                app.insert_resource(lesson);
+            }
+            // Camera path to play back from the start of every scenario
+            "--camera-path" => {
+                let path = args.next().expect("--camera-path expects a path");
+                let source = std::fs::read_to_string(&path)
+                    .unwrap_or_else(|err| panic!("Couldn't read camera path `{path}`: {err}"));
+                let camera_path = CameraPath::parse(&source)
+                    .unwrap_or_else(|err| panic!("Invalid camera path `{path}`: {err}"));
+                app.insert_resource(CameraDirector::default().with_playback(camera_path));
+            }
+            // Preset to start with: `solar-system`, `binary-star` or
+            // `galaxy-disc`
+            "--preset" => {
+                let preset = args
+                    .next()
+                    .and_then(|id| Preset::parse(&id))
+                    .expect("--preset expects `solar-system`, `binary-star` or `galaxy-disc`");
+                let registered = app
+                    .world_mut()
+                    .resource_mut::<Scenarios>()
+                    .select(preset.name());
+                assert!(registered, "Preset `{}` isn't registered", preset.name());
+                // Load it right away instead of waiting in the menu.
+                app.insert_state(SimState::Loading);
+            }
+            // Scenario to start with, read from a RON or TOML file
+            "--scenario" => {
+                let path = args.next().expect("--scenario expects a path");
+                let file = ScenarioFile::load(path.as_ref())
+                    .unwrap_or_else(|err| panic!("Couldn't load scenario `{path}`: {err}"));
+                app.register_scenario_file(file)
+                    .insert_state(SimState::Loading);
+            }
+            // Language of the UI, e.g. `en-US`
+            "--language" => {
+                let language = args.next().expect("--language expects a language id");
+                app.insert_resource(Localization::new(&language));
+            }
+            // Number of independent copies of the scenario to simulate side
+            // by side
+            "--worlds" => {
+                let count = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&count| count > 0)
+                    .expect("--worlds expects a positive number of worlds");
+                app.insert_resource(SimWorlds { count });
+            }
+            // CSV file to export nearest-neighbour distances and the
+            // two-point correlation function to every second
+            "--clustering-stats" => {
+                let path = args.next().expect("--clustering-stats expects a path");
+                app.insert_resource(ClusteringStatistics::new(1., 100., 20).with_export(path));
+            }
+            // Compare the bodies placed on orbits with their two-body
+            // solution every given number of seconds instead of every second
+            "--ephemeris" => {
+                let interval = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&interval: &f32| interval > 0.)
+                    .expect("--ephemeris expects a positive number of seconds");
+                app.insert_resource(EphemerisComparison::new(interval));
+            }
+            // Frame rate to keep by adding and removing massless tracer
+            // bodies
+            "--target-fps" => {
+                let fps = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&fps: &f32| fps > 0.)
+                    .expect("--target-fps expects a positive frame rate");
+                app.insert_resource(BodyCountController::new(fps));
+            }
+            // Cycle through the scenarios unattended, showing each for the
+            // given number of seconds
+            "--kiosk" => {
+                let duration = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&duration: &f32| duration > 0.)
+                    .expect("--kiosk expects a positive number of seconds");
+                app.insert_resource(Kiosk::new(duration));
+            }
+            // Save a high resolution image after simulating the given number
+            // of seconds, then quit
+            "--export-at" => {
+                let at = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .expect("--export-at expects a number of seconds and a path");
+                let path = args
+                    .next()
+                    .expect("--export-at expects a number of seconds and a path");
+                app.insert_resource(ScheduledExport::new(at, path));
+            }
+            // Seconds after which long-exposure trails fade to half their
+            // brightness, they never fade without it
+            "--exposure-half-life" => {
+                let half_life = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&half_life: &f32| half_life > 0.)
+                    .expect("--exposure-half-life expects a positive number of seconds");
+                app.insert_resource(LongExposure::default().with_half_life(half_life));
+            }
+            // Restore the initial momentum and energy every given number of
+            // seconds if they drifted
+            "--correct-drift" => {
+                let interval = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&interval: &f32| interval > 0.)
+                    .expect("--correct-drift expects a positive number of seconds");
+                app.insert_resource(DriftCorrection::new(interval));
+            }
+            // Seed of the random generator the scenarios are spawned with,
+            // random by default
+            "--seed" => {
+                let seed = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .expect("--seed expects a number");
+                app.insert_resource(SimRng::new(seed));
+            }
+            // Probe every scenario after spawning it and spawn it again from
+            // the next seed, up to the given number of times, while it
+            // comes out unstable
+            "--reroll-unstable" => {
+                let attempts = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&attempts| attempts > 0)
+                    .expect("--reroll-unstable expects a positive number of attempts");
+                app.insert_resource(StabilityProbe::new(attempts));
+            }
+            // Make runs with the same seed and config reproducible
+            "--deterministic" => {
+                app.insert_resource(Determinism);
+            }
+            // Physics steps per second of simulated time
+            "--tick-rate" => {
+                let hz = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&hz: &f64| hz > 0.)
+                    .expect("--tick-rate expects a positive number of steps per second");
+                app.insert_resource(TickRate { hz });
+            }
+            // Physics steps between the runs of the expensive subsystems,
+            // e.g. `forces=1,collisions=2,statistics=30,heatmap=60`
+            "--cadence" => {
+                let cadences = args
+                    .next()
+                    .and_then(|value| Cadences::parse(&value))
+                    .expect(
+                        "--cadence expects `forces`, `collisions`, `statistics` or `heatmap` \
+                         followed by `=` and a positive number of steps, separated by commas",
+                    );
+                app.insert_resource(cadences);
+            }
+            // Integrator moving the bodies, `verlet` by default
+            "--integrator" => {
+                let integrator = match args.next().as_deref() {
+                    Some("verlet") => IntegratorKind::VelocityVerlet,
+                    Some("euler") => IntegratorKind::Euler,
+                    Some("rk4") => IntegratorKind::Rk4,
+                    _ => panic!("--integrator expects `verlet`, `euler` or `rk4`"),
+                };
+                app.insert_resource(integrator);
+            }
+            // Simulated time per real second, e.g. `1y` or `30d`, instead of
+            // following the clock
+            "--sim-rate" => {
+                let rate = args
+                    .next()
+                    .and_then(|value| SimRate::parse(&value))
+                    .expect("--sim-rate expects a positive rate like `1y`, `30d` or `90s`");
+                app.insert_resource(SimRate::new(rate));
+            }
+            // Cut the force calculation short once the physics of a frame
+            // would take longer than the given milliseconds
+            "--watchdog" => {
+                let budget = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&budget: &f64| budget > 0.)
+                    .expect("--watchdog expects a positive number of milliseconds");
+                app.insert_resource(Watchdog::new(Duration::from_secs_f64(budget / 1000.)));
+            }
+            // Update the gravity trees by moving the bodies which crossed
+            // into another cell, rebuilding them every given number of
+            // updates
+            "--incremental-tree" => {
+                let rebuild_every = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&updates: &u32| updates > 0)
+                    .expect("--incremental-tree expects a positive number of updates");
+                app.insert_resource(GravityTrees::incremental(rebuild_every));
+            }
+            // Walk the tree with this many times the theta threshold for the
+            // bodies outside the view
+            "--relax-off-view" => {
+                let factor = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&factor: &f32| factor >= 1.)
+                    .expect("--relax-off-view expects a factor of at least 1");
+                app.insert_resource(ViewRelaxation::new(factor));
+            }
+            // Run for the given simulated seconds without a window, printing
+            // the progress
+            "--headless" => {
+                let duration = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&duration: &f32| duration > 0.)
+                    .expect("--headless expects a positive number of seconds");
+                app.add_plugins(HeadlessPlugin { duration });
+            }
+            // Cap on the number of bodies, spawns that don't fit are refused
+            "--max-bodies" => {
+                let max_bodies = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .expect("--max-bodies expects a number");
+                let budget = app
+                    .world()
+                    .get_resource::<Budget>()
+                    .copied()
+                    .unwrap_or_default();
+                app.insert_resource(budget.with_max_bodies(max_bodies));
+            }
+            // Stay within the budget by dropping the lightest bodies instead
+            // of refusing spawns
+            "--drop-lightest" => {
+                let budget = app
+                    .world()
+                    .get_resource::<Budget>()
+                    .copied()
+                    .unwrap_or_default();
+                app.insert_resource(budget.with_on_exceeded(OverBudget::DropLightest));
+            }
+            // How touching bodies respond, `merge` by default, `bounce`
+            // followed by the restitution, or `none`
+            "--collisions" => {
+                let mode = args.next().unwrap_or_default();
+                let restitution = (mode == "bounce").then(|| args.next()).flatten();
+                let mode = CollisionMode::parse(&mode, restitution.as_deref()).expect(
+                    "--collisions expects `merge`, `bounce` with a restitution from 0 to 1, or `none`",
+                );
+                app.insert_resource(mode);
+            }
+            // Mass per unit of area the bodies are made of, which sizes them
+            "--density" => {
+                let density = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&density: &f32| density > 0.)
+                    .expect("--density expects a positive mass per unit of area");
+                app.insert_resource(Density(density));
+            }
+            // Also report measured lengths in a real unit, given as the
+            // length of one simulation unit and the unit's name, e.g.
+            // `"1.5e6 km"`
+            "--length-unit" => {
+                let unit = args
+                    .next()
+                    .and_then(|unit| RealUnit::parse(&unit))
+                    .expect("--length-unit expects a length and a unit, e.g. \"1.5e6 km\"");
+                app.world_mut().get_resource_or_init::<RealUnits>().length = Some(unit);
+            }
+            // Also report measured masses in a real unit, e.g. `"1e20 kg"`
+            "--mass-unit" => {
+                let unit = args
+                    .next()
+                    .and_then(|unit| RealUnit::parse(&unit))
+                    .expect("--mass-unit expects a mass and a unit, e.g. \"1e20 kg\"");
+                app.world_mut().get_resource_or_init::<RealUnits>().mass = Some(unit);
+            }
+            // Load the bookmarks from the file and save new ones to it
+            "--bookmarks" => {
+                let path = args.next().expect("--bookmarks expects a path");
+                let bookmarks = Bookmarks::load(path.as_ref())
+                    .unwrap_or_else(|err| panic!("Couldn't load bookmarks `{path}`: {err}"));
+                app.insert_resource(bookmarks);
+            }
+            // Show the starfield in the background, generated from the
+            // given seed
+            "--starfield" => {
+                let seed = args
+                    .next()
+                    .and_then(|seed| seed.parse().ok())
+                    .expect("--starfield expects a seed");
+                app.insert_resource(Starfield {
+                    enabled: true,
+                    seed,
+                });
+            }
+            // Frame to show the bodies in, `inertial` by default or
+            // `dominant-body` to move along with the heaviest body
+            "--frame" => {
+                let frame = args
+                    .next()
+                    .and_then(|frame| ReferenceFrame::parse(&frame))
+                    .expect("--frame expects `inertial` or `dominant-body`");
+                app.insert_resource(frame);
+            }
+            // Pause when the gravity tree can't be built instead of skipping
+            // the physics until it can
+            "--pause-on-tree-failure" => {
+                app.insert_resource(TreeFailure {
+                    auto_pause: true,
+                    ..Default::default()
+                });
+            }
+            // Send the statistics as OSC messages to the given address
+            #[cfg(feature = "osc")]
+            "--osc" => {
+                let target = args
+                    .next()
+                    .expect("--osc expects an address like `127.0.0.1:9000`");
+                let output = spacesim::osc::OscOutput::new(&target)
+                    .unwrap_or_else(|err| panic!("Couldn't send OSC to `{target}`: {err}"));
+                app.insert_resource(output);
+            }
+            // Integrate in fixed point so lockstep peers stay bit for bit
+            // in sync
+            "--fixed-point" => {
+                app.insert_resource(FixedPoint::default());
+            }
+            _ => panic!("Unknown argument `{arg}`"),
+        }
+    }
+
+    app.run();
 }
@@ -0,0 +1,284 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::localization::Localization;
+use crate::physics_plugin::{GravityTrees, MainCamera};
+use crate::theme::Theme;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+
+/// Radius in pixels of the markers on the measured points.
+const MARKER_RADIUS: f32 = 4.;
+/// Radius in pixels of the arc marking a measured angle.
+const ARC_RADIUS: f32 = 30.;
+
+/// What clicking in the view measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeasureTool {
+    /// Distance between two points
+    Ruler,
+    /// Angle at the second of three points
+    Angle,
+    /// Mass and average density within a circle dragged out from its center
+    Region,
+}
+
+impl MeasureTool {
+    /// Points it takes to measure.
+    fn points(self) -> usize {
+        match self {
+            MeasureTool::Ruler => 2,
+            MeasureTool::Angle => 3,
+            MeasureTool::Region => 2,
+        }
+    }
+}
+
+/// The measurement in progress, with the tool cycled with U and the points
+/// placed with a left click (with the default input map).
+#[derive(Resource, Debug, Default)]
+pub struct Measurement {
+    pub tool: Option<MeasureTool>,
+    /// Points placed so far, for a region its center and a point on its
+    /// edge
+    pub points: Vec<Vec2>,
+    dragging: bool,
+}
+
+/// A real unit some quantity of the simulation is also reported in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RealUnit {
+    /// How many of the unit one unit of the simulation is
+    pub per_sim_unit: f32,
+    pub name: String,
+}
+
+impl RealUnit {
+    /// Parses the size of a simulation unit followed by the name of the
+    /// unit, e.g. `1.5e6 km`.
+    pub fn parse(unit: &str) -> Option<Self> {
+        let (value, name) = unit.trim().split_once(char::is_whitespace)?;
+        Some(RealUnit {
+            per_sim_unit: value.parse().ok()?,
+            name: name.trim().to_string(),
+        })
+    }
+
+    fn format(&self, value: f32) -> String {
+        format!("{:.3e} {}", value * self.per_sim_unit, self.name)
+    }
+}
+
+/// Units the measurements are reported in besides the simulation's own.
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct RealUnits {
+    pub length: Option<RealUnit>,
+    pub mass: Option<RealUnit>,
+}
+
+impl RealUnits {
+    /// Unit of surface density, when both the mass and the length have a
+    /// real unit.
+    fn density(&self) -> Option<RealUnit> {
+        let (mass, length) = self.mass.as_ref().zip(self.length.as_ref())?;
+        Some(RealUnit {
+            per_sim_unit: mass.per_sim_unit / (length.per_sim_unit * length.per_sim_unit),
+            name: format!("{}/{}²", mass.name, length.name),
+        })
+    }
+}
+
+/// Marks the text reporting the measurement.
+#[derive(Component)]
+struct MeasurementText;
+
+fn spawn_measurement_text(mut commands: Commands) {
+    commands.spawn((
+        MeasurementText,
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.),
+            right: Val::Px(10.),
+            ..Default::default()
+        },
+    ));
+}
+
+fn cycle_measure_tool(actions: Actions, mut measurement: ResMut<Measurement>) {
+    if actions.just_pressed(Action::CycleMeasureTool) {
+        measurement.tool = match measurement.tool {
+            None => Some(MeasureTool::Ruler),
+            Some(MeasureTool::Ruler) => Some(MeasureTool::Angle),
+            Some(MeasureTool::Angle) => Some(MeasureTool::Region),
+            Some(MeasureTool::Region) => None,
+        };
+        measurement.points.clear();
+        measurement.dragging = false;
+    }
+}
+
+/// Places the points of the measurement, a click after the last one
+/// starts a new measurement. A region follows the cursor while the button
+/// is held.
+fn place_measure_points(
+    actions: Actions,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut measurement: ResMut<Measurement>,
+) {
+    let Some(tool) = measurement.tool else {
+        return;
+    };
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), cameras.get_single())
+    else {
+        return;
+    };
+    let Some(cursor) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return;
+    };
+
+    if actions.just_pressed(Action::PlaceMeasurePoint) {
+        if measurement.points.len() >= tool.points() {
+            measurement.points.clear();
+        }
+        measurement.points.push(cursor);
+        if tool == MeasureTool::Region {
+            measurement.points.push(cursor);
+            measurement.dragging = true;
+        }
+    } else if measurement.dragging {
+        if actions.pressed(Action::PlaceMeasurePoint) {
+            measurement.points[1] = cursor;
+        } else {
+            measurement.dragging = false;
+        }
+    }
+}
+
+/// Points along the arc around `vertex` from the direction of `from` to
+/// the direction of `to`, the short way round.
+fn arc_points(vertex: Vec2, from: Vec2, to: Vec2, radius: f32) -> Vec<Vec2> {
+    let start = (from - vertex).to_angle();
+    let angle = (from - vertex).angle_to(to - vertex);
+    (0..=16)
+        .map(|step| vertex + Vec2::from_angle(start + angle * step as f32 / 16.) * radius)
+        .collect()
+}
+
+fn draw_measurement(
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+    measurement: Res<Measurement>,
+    cameras: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    let Some(tool) = measurement.tool else {
+        return;
+    };
+    let scale = cameras
+        .get_single()
+        .map_or(1., |projection| projection.scale);
+    let points = &measurement.points;
+    for &point in points {
+        gizmos.circle_2d(point, MARKER_RADIUS * scale, theme.accent());
+    }
+    match tool {
+        MeasureTool::Ruler | MeasureTool::Angle => {
+            gizmos.linestrip_2d(points.iter().copied(), theme.accent());
+            if let [from, vertex, to] = points[..] {
+                gizmos.linestrip_2d(
+                    arc_points(vertex, from, to, ARC_RADIUS * scale),
+                    theme.highlight(),
+                );
+            }
+        }
+        MeasureTool::Region => {
+            if let [center, edge] = points[..] {
+                gizmos.circle_2d(center, center.distance(edge), theme.highlight());
+            }
+        }
+    }
+}
+
+/// Rounds to two decimals, like the rest of the reported quantities.
+fn rounded(value: f32) -> f64 {
+    (value as f64 * 100.).round() / 100.
+}
+
+/// Reports what was measured, in the real units as well when they are set.
+fn update_measurement_text(
+    measurement: Res<Measurement>,
+    units: Res<RealUnits>,
+    localization: Res<Localization>,
+    trees: Res<GravityTrees>,
+    mut texts: Query<&mut Text, With<MeasurementText>>,
+) {
+    let with_unit = |id: &str, value: f32, unit: Option<&RealUnit>| {
+        let text = localization.text(id, &[("value", rounded(value))]);
+        match unit {
+            Some(unit) => format!("{text} ({})", unit.format(value)),
+            None => text,
+        }
+    };
+    let points = &measurement.points;
+    let content = match (measurement.tool, &points[..]) {
+        (None, _) => String::new(),
+        (Some(MeasureTool::Ruler), &[from, to]) => {
+            with_unit("measure-distance", from.distance(to), units.length.as_ref())
+        }
+        (Some(MeasureTool::Angle), &[from, vertex, to]) => {
+            let angle = (from - vertex).angle_to(to - vertex).abs().to_degrees();
+            localization.text("measure-angle", &[("value", rounded(angle))])
+        }
+        (Some(MeasureTool::Region), &[center, edge]) => {
+            let radius = center.distance(edge);
+            // The tree of the last physics step, so nothing is found while
+            // the domain decomposition computes the forces.
+            let mass = trees
+                .tree(SimWorld::default())
+                .map_or(0., |tree| tree.mass_within(center, radius));
+            let area = std::f32::consts::PI * radius * radius;
+            let density = if area > 0. { mass / area } else { 0. };
+            [
+                with_unit("measure-radius", radius, units.length.as_ref()),
+                with_unit("measure-mass", mass, units.mass.as_ref()),
+                with_unit("measure-density", density, units.density().as_ref()),
+            ]
+            .join("\n")
+        }
+        (Some(MeasureTool::Ruler), _) => localization.text("measure-ruler-hint", &[]),
+        (Some(MeasureTool::Angle), _) => localization.text("measure-angle-hint", &[]),
+        (Some(MeasureTool::Region), _) => localization.text("measure-region-hint", &[]),
+    };
+    for mut text in &mut texts {
+        if text.0 != content {
+            text.0.clone_from(&content);
+        }
+    }
+}
+
+/// Measures distances, angles and the mass within regions of the view.
+pub struct MeasurementPlugin;
+
+impl Plugin for MeasurementPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Measurement>()
+            .init_resource::<RealUnits>()
+            .init_resource::<GravityTrees>()
+            .init_resource::<Theme>()
+            .init_resource::<Localization>()
+            .init_resource::<InputMap>()
+            .add_systems(Startup, spawn_measurement_text)
+            .add_systems(
+                Update,
+                (
+                    cycle_measure_tool,
+                    place_measure_points,
+                    draw_measurement,
+                    update_measurement_text,
+                )
+                    .chain(),
+            );
+    }
+}
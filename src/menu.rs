@@ -0,0 +1,251 @@
+use crate::localization::Localization;
+use crate::mission::Mission;
+use crate::physics_plugin::{BodyMaterial, Mass, Velocity};
+use crate::scenario::{Scenarios, SimRng};
+use crate::scenario_file::{scenario_dirs, RegisterScenarioFile};
+use crate::state::SimState;
+use crate::theme::Theme;
+use bevy::ecs::component::ComponentId;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy::window::PrimaryWindow;
+
+/// Side of the square the preview of a scenario is drawn in, in pixels.
+const PREVIEW_SIZE: f32 = 90.;
+/// Side of the dot of a body in a preview, in pixels.
+const PREVIEW_DOT: f32 = 2.;
+/// Most bodies drawn in a preview, every so many of the others are left out.
+const PREVIEW_BODIES: usize = 400;
+
+/// Button loading the scenario at this index of [`Scenarios::entries`].
+#[derive(Component)]
+struct ScenarioButton(usize);
+
+/// Where in its preview each drawn body of a scenario is, by the index of
+/// the scenario in [`Scenarios::entries`].
+#[derive(Resource, Debug, Default)]
+struct ScenarioPreviews(HashMap<usize, Vec<Vec2>>);
+
+/// Positions of the dots of the `positions` in a preview, fitted into it
+/// around the middle of their bounding box with y pointing down.
+fn preview_dots(positions: &[Vec2]) -> Vec<Vec2> {
+    let (min, max) = positions
+        .iter()
+        .fold((Vec2::MAX, Vec2::MIN), |(min, max), &position| {
+            (min.min(position), max.max(position))
+        });
+    let middle = (min + max) / 2.;
+    let size = (max - min).max_element().max(f32::MIN_POSITIVE);
+    let stride = positions.len().div_ceil(PREVIEW_BODIES).max(1);
+    positions
+        .iter()
+        .step_by(stride)
+        .map(|&position| {
+            let relative = (position - middle) / size;
+            Vec2::new(0.5 + relative.x, 0.5 - relative.y) * (PREVIEW_SIZE - PREVIEW_DOT)
+        })
+        .collect()
+}
+
+/// Spawns every scenario without a preview yet to see where its bodies
+/// start out, and despawns it again. The scenarios are spawned from the
+/// seed they are loaded with, so the previews show what is loaded.
+///
+/// Whatever the spawn systems leave behind is cleaned up again: the
+/// resources they insert are removed, the ones they replace put back, and
+/// the meshes and materials they add are freed with the last handle to
+/// them.
+fn capture_previews(world: &mut World) {
+    let previews = world.resource::<ScenarioPreviews>();
+    let missing: Vec<_> = world
+        .resource::<Scenarios>()
+        .entries
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !previews.0.contains_key(index))
+        .map(|(index, scenario)| (index, scenario.spawn))
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+    let seed = world.resource::<SimRng>().seed;
+    // The resources the spawn systems replace, taken out so they aren't lost
+    let body_material = world.remove_resource::<BodyMaterial>();
+    let mission = world.remove_resource::<Mission>();
+    let resources: HashSet<ComponentId> =
+        world.iter_resources().map(|(info, _)| info.id()).collect();
+
+    for (index, spawn) in missing {
+        *world.resource_mut::<SimRng>() = SimRng::new(seed);
+        let existing: HashSet<Entity> = world.iter_entities().map(|entity| entity.id()).collect();
+        if let Err(err) = world.run_system(spawn) {
+            warn!("Couldn't spawn the scenario to preview: {err}");
+            continue;
+        }
+        let positions: Vec<Vec2> = world
+            .query_filtered::<(Entity, &Transform), (Or<(With<Mass>, With<Velocity>)>, Without<Parent>)>()
+            .iter(world)
+            .filter(|(entity, _)| !existing.contains(entity))
+            .map(|(_, transform)| transform.translation.xy())
+            .collect();
+        let spawned: Vec<Entity> = world
+            .iter_entities()
+            .map(|entity| entity.id())
+            .filter(|entity| !existing.contains(entity))
+            .collect();
+        for entity in spawned {
+            world.despawn(entity);
+        }
+        world
+            .resource_mut::<ScenarioPreviews>()
+            .0
+            .insert(index, preview_dots(&positions));
+    }
+    *world.resource_mut::<SimRng>() = SimRng::new(seed);
+    let inserted: Vec<ComponentId> = world
+        .iter_resources()
+        .map(|(info, _)| info.id())
+        .filter(|id| !resources.contains(id))
+        .collect();
+    for id in inserted {
+        world.remove_resource_by_id(id);
+    }
+    if let Some(body_material) = body_material {
+        world.insert_resource(body_material);
+    }
+    if let Some(mission) = mission {
+        world.insert_resource(mission);
+    }
+}
+
+/// Lists the scenarios, with their descriptions and previews, as buttons in
+/// the middle of the screen.
+fn spawn_menu(
+    mut commands: Commands,
+    scenarios: Res<Scenarios>,
+    previews: Res<ScenarioPreviews>,
+    theme: Res<Theme>,
+    localization: Res<Localization>,
+) {
+    commands
+        .spawn((
+            StateScoped(SimState::Menu),
+            Node {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(10.),
+                ..Default::default()
+            },
+        ))
+        .with_children(|menu| {
+            menu.spawn(Text::new(localization.text("menu-title", &[])));
+            for (index, scenario) in scenarios.entries.iter().enumerate() {
+                menu.spawn(Node {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(10.),
+                    ..Default::default()
+                })
+                .with_children(|row| {
+                    row.spawn((
+                        Node {
+                            width: Val::Px(PREVIEW_SIZE),
+                            height: Val::Px(PREVIEW_SIZE),
+                            ..Default::default()
+                        },
+                        BackgroundColor(Color::srgba(0., 0., 0., 0.8)),
+                    ))
+                    .with_children(|preview| {
+                        let dots = previews.0.get(&index).into_iter().flatten();
+                        for dot in dots {
+                            preview.spawn((
+                                Node {
+                                    position_type: PositionType::Absolute,
+                                    left: Val::Px(dot.x),
+                                    top: Val::Px(dot.y),
+                                    width: Val::Px(PREVIEW_DOT),
+                                    height: Val::Px(PREVIEW_DOT),
+                                    ..Default::default()
+                                },
+                                BackgroundColor(theme.body()),
+                            ));
+                        }
+                    });
+                    row.spawn((
+                        ScenarioButton(index),
+                        Button,
+                        Node {
+                            width: Val::Px(400.),
+                            flex_direction: FlexDirection::Column,
+                            padding: UiRect::all(Val::Px(10.)),
+                            ..Default::default()
+                        },
+                        BackgroundColor(Color::srgba(0., 0., 0., 0.8)),
+                    ))
+                    .with_children(|button| {
+                        let (name, description) = match &scenario.message {
+                            Some(message) => (
+                                localization.text(message, &[]),
+                                localization.text(&format!("{message}-description"), &[]),
+                            ),
+                            None => (scenario.name.clone(), scenario.description.clone()),
+                        };
+                        button.spawn(Text::new(name));
+                        button.spawn((Text::new(description), TextFont::from_font_size(14.)));
+                    });
+                });
+            }
+        });
+}
+
+/// Highlights the hovered button and loads the scenario of the pressed one.
+fn choose_scenario(
+    theme: Res<Theme>,
+    mut scenarios: ResMut<Scenarios>,
+    mut next_state: ResMut<NextState<SimState>>,
+    mut buttons: Query<(&Interaction, &ScenarioButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, button, mut background) in &mut buttons {
+        match interaction {
+            Interaction::Pressed => {
+                scenarios.selected = button.0;
+                next_state.set(SimState::Loading);
+            }
+            Interaction::Hovered => background.0 = theme.highlight(),
+            Interaction::None => background.0 = Color::srgba(0., 0., 0., 0.8),
+        }
+    }
+}
+
+/// Starts the app in a menu to pick the scenario from, instead of loading
+/// the first one right away. Besides the scenarios of the plugins it lists
+/// the scenario files of the [`scenario_dirs`].
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Theme>()
+            .init_resource::<Localization>()
+            .init_resource::<Scenarios>()
+            .init_resource::<ScenarioPreviews>()
+            .init_resource::<SimRng>()
+            .insert_state(SimState::Menu)
+            .enable_state_scoped_entities::<SimState>()
+            .add_systems(
+                OnEnter(SimState::Menu),
+                (
+                    // Without a window there is no one to show them to.
+                    capture_previews.run_if(any_with_component::<PrimaryWindow>),
+                    spawn_menu,
+                )
+                    .chain(),
+            )
+            .add_systems(Update, choose_scenario.run_if(in_state(SimState::Menu)));
+        for dir in scenario_dirs() {
+            app.register_scenario_dir(&dir);
+        }
+    }
+}
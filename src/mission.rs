@@ -0,0 +1,337 @@
+use crate::localization::Localization;
+use crate::physics_plugin::{Mass, PhysicsSettings, Velocity};
+use bevy::prelude::*;
+
+/// Eccentricity below which an orbit counts as circular for
+/// [`Objective::ReachOrbit`].
+const MAX_ECCENTRICITY: f32 = 0.05;
+
+/// A single goal the player has to reach.
+#[derive(Debug, Clone, Copy)]
+pub enum Objective {
+    /// Get `body` on a (nearly) circular orbit around `center` with a
+    /// semi-major axis within `tolerance` of `radius`.
+    ReachOrbit {
+        body: Entity,
+        center: Entity,
+        radius: f32,
+        tolerance: f32,
+    },
+    /// Get `body` within `distance` of `target`.
+    Rendezvous {
+        body: Entity,
+        target: Entity,
+        distance: f32,
+    },
+    /// Keep `body` in the simulation for `duration` seconds.
+    Survive { body: Entity, duration: f32 },
+}
+
+/// Tracking of how far along an objective is.
+#[derive(Debug, Clone, Copy)]
+pub struct ObjectiveState {
+    pub objective: Objective,
+    /// Progress from 0 to 1
+    pub progress: f32,
+    pub completed: bool,
+    /// Set when the objective can no longer be completed, e.g. because a
+    /// body it needs is gone
+    pub failed: bool,
+}
+
+/// The objectives of the currently played mission, in the order they are
+/// shown.
+#[derive(Resource, Debug, Default)]
+pub struct Mission {
+    pub objectives: Vec<ObjectiveState>,
+    /// Seconds since the mission started
+    pub elapsed: f32,
+}
+
+/// Sent once when the objective at `index` in [`Mission::objectives`] is
+/// completed.
+#[derive(Event, Debug)]
+pub struct ObjectiveCompleted {
+    pub index: usize,
+}
+
+/// Marks the text listing the objectives.
+#[derive(Component)]
+struct MissionText;
+
+impl Mission {
+    /// Mission consisting of the `objectives`, none of them started yet.
+    pub fn new(objectives: impl IntoIterator<Item = Objective>) -> Self {
+        Mission {
+            objectives: objectives
+                .into_iter()
+                .map(|objective| ObjectiveState {
+                    objective,
+                    progress: 0.,
+                    completed: false,
+                    failed: false,
+                })
+                .collect(),
+            elapsed: 0.,
+        }
+    }
+}
+
+impl Objective {
+    /// Short description shown in the objective list.
+    fn describe(&self, localization: &Localization) -> String {
+        match *self {
+            Objective::ReachOrbit { radius, .. } => {
+                localization.text("objective-reach-orbit", &[("radius", radius.into())])
+            }
+            Objective::Rendezvous { distance, .. } => {
+                localization.text("objective-rendezvous", &[("distance", distance.into())])
+            }
+            Objective::Survive { duration, .. } => {
+                localization.text("objective-survive", &[("duration", duration.into())])
+            }
+        }
+    }
+}
+
+fn update_objectives(
+    time: Res<Time>,
+    mut mission: ResMut<Mission>,
+    settings: Res<PhysicsSettings>,
+    mut completed: EventWriter<ObjectiveCompleted>,
+    bodies: Query<(&Transform, &Velocity, Option<&Mass>)>,
+) {
+    mission.elapsed += time.delta_secs();
+    let elapsed = mission.elapsed;
+    let state = |entity: Entity| {
+        bodies.get(entity).ok().map(|(transform, velocity, mass)| {
+            // Massless bodies still orbit, they just don't pull back
+            let mass = mass.map_or(0., |mass| mass.0);
+            (transform.translation.xy(), velocity.0, mass)
+        })
+    };
+
+    for (index, objective_state) in mission.objectives.iter_mut().enumerate() {
+        if objective_state.completed || objective_state.failed {
+            continue;
+        }
+
+        let (progress, done) = match objective_state.objective {
+            Objective::ReachOrbit {
+                body,
+                center,
+                radius,
+                tolerance,
+            } => {
+                let (
+                    Some((position, velocity, mass)),
+                    Some((center_position, center_velocity, center_mass)),
+                ) = (state(body), state(center))
+                else {
+                    objective_state.failed = true;
+                    continue;
+                };
+                let offset = position - center_position;
+                let relative_velocity = velocity - center_velocity;
+                let distance = offset.length();
+                let mu = settings.g * (mass + center_mass);
+                // Specific orbital energy, unbound orbits have no
+                // semi-major axis to compare
+                let energy = relative_velocity.length_squared() / 2. - mu / distance;
+                if energy >= 0. || !energy.is_finite() {
+                    objective_state.progress = 0.;
+                    continue;
+                }
+                let semi_major_axis = -mu / (2. * energy);
+                let eccentricity = ((relative_velocity.length_squared() - mu / distance) * offset
+                    - offset.dot(relative_velocity) * relative_velocity)
+                    .length()
+                    / mu;
+                let axis_error = (semi_major_axis - radius).abs();
+                (
+                    (1. - axis_error / radius) * (1. - eccentricity),
+                    axis_error <= tolerance && eccentricity < MAX_ECCENTRICITY,
+                )
+            }
+            Objective::Rendezvous {
+                body,
+                target,
+                distance,
+            } => {
+                let (Some((position, ..)), Some((target_position, ..))) =
+                    (state(body), state(target))
+                else {
+                    objective_state.failed = true;
+                    continue;
+                };
+                let current = position.distance(target_position);
+                (distance / current, current <= distance)
+            }
+            Objective::Survive { body, duration } => {
+                if state(body).is_none() {
+                    objective_state.failed = true;
+                    continue;
+                }
+                (elapsed / duration, elapsed >= duration)
+            }
+        };
+
+        objective_state.progress = progress.clamp(0., 1.);
+        if done {
+            objective_state.progress = 1.;
+            objective_state.completed = true;
+            completed.send(ObjectiveCompleted { index });
+        }
+    }
+}
+
+fn spawn_mission_text(mut commands: Commands) {
+    commands.spawn((
+        MissionText,
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            ..Default::default()
+        },
+    ));
+}
+
+fn update_mission_text(
+    mission: Res<Mission>,
+    localization: Res<Localization>,
+    mut query: Query<&mut Text, With<MissionText>>,
+) {
+    let lines: Vec<String> = mission
+        .objectives
+        .iter()
+        .map(|state| {
+            let status = if state.completed {
+                localization.text("objective-done", &[])
+            } else if state.failed {
+                localization.text("objective-failed", &[])
+            } else {
+                let percent = (state.progress * 100.).round();
+                localization.text("objective-progress", &[("percent", percent.into())])
+            };
+            format!("{} [{status}]", state.objective.describe(&localization))
+        })
+        .collect();
+    for mut text in &mut query {
+        text.0 = lines.join("\n");
+    }
+}
+
+/// Tracks the objectives of the [`Mission`] resource, when one is inserted,
+/// and lists their progress on screen.
+pub struct MissionPlugin;
+
+impl Plugin for MissionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Localization>()
+            .add_event::<ObjectiveCompleted>()
+            .add_systems(Startup, spawn_mission_text)
+            .add_systems(
+                Update,
+                (update_objectives, update_mission_text)
+                    .chain()
+                    .run_if(resource_exists::<Mission>),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// World with a [`Objective::ReachOrbit`] mission, a center of mass 1000 at the origin and a
+    /// body of mass 1 at `position` moving with `velocity`, with G = 1.
+    fn orbiting(position: Vec2, velocity: Vec2) -> (World, Entity, Entity) {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        world.insert_resource(PhysicsSettings { g: 1., ..default() });
+        world.init_resource::<Events<ObjectiveCompleted>>();
+        let center = world
+            .spawn((Transform::default(), Velocity(Vec2::ZERO), Mass(1000.)))
+            .id();
+        let body = world
+            .spawn((
+                Transform::from_translation(position.extend(0.)),
+                Velocity(velocity),
+                Mass(1.),
+            ))
+            .id();
+        world.insert_resource(Mission::new([Objective::ReachOrbit {
+            body,
+            center,
+            radius: 100.,
+            tolerance: 5.,
+        }]));
+        (world, body, center)
+    }
+
+    fn update(world: &mut World) -> &ObjectiveState {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_objectives);
+        schedule.run(world);
+        &world.resource::<Mission>().objectives[0]
+    }
+
+    fn completions(world: &World) -> usize {
+        let events = world.resource::<Events<ObjectiveCompleted>>();
+        events.get_cursor().read(events).count()
+    }
+
+    /// Speed of a circular orbit of `radius` around the center.
+    fn circular_speed(radius: f32) -> f32 {
+        (1001. / radius).sqrt()
+    }
+
+    #[test]
+    fn reaching_the_radius_without_circularizing_is_progress() {
+        // At the radius with no radial speed, but at the apoapsis of an
+        // ellipse plunging towards the center
+        let (mut world, _, _) = orbiting(Vec2::new(100., 0.), Vec2::Y * 0.5 * circular_speed(100.));
+        let state = update(&mut world);
+        assert!(!state.completed && !state.failed);
+        assert!(
+            state.progress > 0. && state.progress < 1.,
+            "{}",
+            state.progress
+        );
+
+        // A rounder orbit of the same apoapsis is further along
+        let (mut closer, _, _) =
+            orbiting(Vec2::new(100., 0.), Vec2::Y * 0.9 * circular_speed(100.));
+        assert!(update(&mut closer).progress > state.progress);
+
+        // Escaping makes no progress at all
+        let (mut escaping, _, _) =
+            orbiting(Vec2::new(100., 0.), Vec2::Y * 2. * circular_speed(100.));
+        assert_eq!(update(&mut escaping).progress, 0.);
+    }
+
+    #[test]
+    fn circular_orbit_completes_once() {
+        let (mut world, _, _) = orbiting(Vec2::new(0., 102.), Vec2::X * circular_speed(102.));
+        let state = update(&mut world);
+        assert!(state.completed);
+        assert_eq!(state.progress, 1.);
+        update(&mut world);
+        assert_eq!(completions(&world), 1);
+    }
+
+    #[test]
+    fn despawned_body_fails_the_objective() {
+        let (mut world, body, _) = orbiting(Vec2::new(100., 0.), Vec2::Y * circular_speed(100.));
+        world.despawn(body);
+        world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        let state = update(&mut world);
+        assert!(state.failed && !state.completed);
+        assert_eq!(completions(&world), 0);
+    }
+}
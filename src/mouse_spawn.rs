@@ -0,0 +1,166 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::{BodyMaterial, BodyMesh, MainCamera, Mass, Velocity};
+use crate::radius::{radius_of, Density, Radius};
+use crate::scenario::ScenarioEntity;
+use crate::state::SimState;
+use crate::theme::Theme;
+use bevy::input::mouse::{AccumulatedMouseScroll, MouseScrollUnit};
+use bevy::prelude::*;
+
+/// Factor the mass changes by per line scrolled.
+const MASS_STEP: f32 = 1.25;
+/// Pixels of scrolling counted as a line, for touchpads scrolling by the
+/// pixel.
+const PIXELS_PER_LINE: f32 = 20.;
+
+/// Body placed with a left click while editing (with the default input
+/// map). Dragging away from where the button went down sets its velocity,
+/// and scrolling while dragging its mass.
+#[derive(Resource, Debug)]
+pub struct MouseSpawn {
+    /// Mass of the next body
+    pub mass: f32,
+    /// Velocity per unit dragged
+    pub velocity_per_unit: f32,
+    /// Where the drag started
+    start: Option<Vec2>,
+}
+
+impl Default for MouseSpawn {
+    fn default() -> Self {
+        MouseSpawn {
+            mass: 10_000_000.,
+            velocity_per_unit: 1.,
+            start: None,
+        }
+    }
+}
+
+impl MouseSpawn {
+    /// Velocity of a body dragged from where it was placed to `cursor`.
+    fn velocity(&self, cursor: Vec2) -> Vec2 {
+        self.start.map_or(Vec2::ZERO, |start| {
+            (cursor - start) * self.velocity_per_unit
+        })
+    }
+}
+
+/// Starts placing a body on a press, changes its mass on scrolling and
+/// spawns it when the button is released.
+#[allow(clippy::too_many_arguments)]
+fn spawn_with_mouse(
+    mut commands: Commands,
+    actions: Actions,
+    scroll: Res<AccumulatedMouseScroll>,
+    density: Res<Density>,
+    material: Option<Res<BodyMaterial>>,
+    mesh: Option<Res<BodyMesh>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut spawn: ResMut<MouseSpawn>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), cameras.get_single())
+    else {
+        return;
+    };
+    let Some(cursor) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return;
+    };
+
+    if actions.just_pressed(Action::PlaceBody) {
+        spawn.start = Some(cursor);
+    }
+    let Some(start) = spawn.start else {
+        return;
+    };
+    if actions.pressed(Action::PlaceBody) {
+        let lines = match scroll.unit {
+            MouseScrollUnit::Line => scroll.delta.y,
+            MouseScrollUnit::Pixel => scroll.delta.y / PIXELS_PER_LINE,
+        };
+        if lines != 0. {
+            spawn.mass *= MASS_STEP.powf(lines);
+        }
+        return;
+    }
+
+    let velocity = spawn.velocity(cursor);
+    spawn.start = None;
+    // The scenario creates the material, without it there is nothing the
+    // bodies are drawn with.
+    let Some(material) = material else {
+        return;
+    };
+    let mesh = match mesh {
+        Some(mesh) => mesh.0.clone(),
+        None => {
+            let mesh = meshes.add(Circle::new(1.));
+            commands.insert_resource(BodyMesh(mesh.clone()));
+            mesh
+        }
+    };
+    // Sized right away, the radii are only updated while running.
+    let radius = radius_of(spawn.mass, density.0);
+    commands.spawn((
+        ScenarioEntity,
+        Velocity(velocity),
+        Mass(spawn.mass),
+        Radius(radius),
+        Mesh2d(mesh),
+        MeshMaterial2d(material.0.clone()),
+        Transform::from_translation(start.extend(0.)).with_scale(Vec3::new(radius, radius, 1.)),
+    ));
+}
+
+/// Drops the body being placed when editing ends before it is released.
+fn cancel_mouse_spawn(mut spawn: ResMut<MouseSpawn>) {
+    spawn.start = None;
+}
+
+/// Outlines the body being placed and draws its velocity as an arrow.
+fn draw_mouse_spawn(
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+    density: Res<Density>,
+    spawn: Res<MouseSpawn>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+) {
+    let Some(start) = spawn.start else {
+        return;
+    };
+    gizmos.circle_2d(start, radius_of(spawn.mass, density.0), theme.accent());
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), cameras.get_single())
+    else {
+        return;
+    };
+    if let Some(cursor) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    {
+        gizmos.arrow_2d(start, cursor, theme.accent());
+    }
+}
+
+/// Places bodies with the mouse while editing, see [`MouseSpawn`].
+pub struct MouseSpawnPlugin;
+
+impl Plugin for MouseSpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MouseSpawn>()
+            .init_resource::<Density>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .add_systems(
+                Update,
+                (spawn_with_mouse, draw_mouse_spawn)
+                    .chain()
+                    .run_if(in_state(SimState::Editing)),
+            )
+            .add_systems(OnExit(SimState::Editing), cancel_mouse_spawn);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::inspector::Inspector;
+use crate::integrator::{IntegratorKind, LastAcceleration};
+use crate::physics_config::{PhysicsConfig, PhysicsQuality};
+use crate::physics_plugin::{GravityTrees, MainCamera, Mass, PhysicsSettings, Velocity};
+use crate::quality::Quality;
+use crate::simulation::{path_through_tree, SimBody, SimulationConfig};
+use crate::theme::Theme;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+
+/// Length in pixels of the dashes of the predicted path and of the gaps
+/// between them.
+const DASH_LENGTH: f32 = 8.;
+
+/// Path the body picked in the inspector is predicted to take, drawn as a
+/// dashed line ahead of it and toggled with I (with the default input map).
+///
+/// Unlike the [`TrajectoryPreview`](crate::preview::TrajectoryPreview),
+/// the body moves through the gravity tree of the last step with every
+/// other body held in place, which is cheap enough for any number of
+/// bodies but only right while they don't move much. Nothing is drawn
+/// while the domain decomposition, which builds its own trees, computes
+/// the forces.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct OrbitPrediction {
+    pub active: bool,
+    /// How far ahead to predict in seconds
+    pub duration: f32,
+    pub steps: usize,
+}
+
+impl Default for OrbitPrediction {
+    fn default() -> Self {
+        OrbitPrediction {
+            active: false,
+            duration: 20.,
+            steps: 400,
+        }
+    }
+}
+
+fn toggle_orbit_prediction(actions: Actions, mut prediction: ResMut<OrbitPrediction>) {
+    if actions.just_pressed(Action::ToggleOrbitPrediction) {
+        prediction.active = !prediction.active;
+    }
+}
+
+/// Splits the line through `points` into dashes of `length`, with gaps of
+/// the same length between them.
+fn dashes(points: &[Vec2], length: f32) -> Vec<(Vec2, Vec2)> {
+    let mut dashes = Vec::new();
+    let mut drawn = true;
+    let mut left = length;
+    for pair in points.windows(2) {
+        let (mut from, to) = (pair[0], pair[1]);
+        let direction = (to - from).normalize_or_zero();
+        let mut remaining = from.distance(to);
+        while remaining > 0. {
+            let step = left.min(remaining);
+            let next = from + direction * step;
+            if drawn {
+                dashes.push((from, next));
+            }
+            from = next;
+            remaining -= step;
+            left -= step;
+            if left <= 0. {
+                drawn = !drawn;
+                left = length;
+            }
+        }
+    }
+    dashes
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn draw_orbit_prediction(
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+    quality: PhysicsQuality,
+    settings: Res<PhysicsSettings>,
+    integrator: Res<IntegratorKind>,
+    prediction: Res<OrbitPrediction>,
+    inspector: Res<Inspector>,
+    trees: Res<GravityTrees>,
+    cameras: Query<&OrthographicProjection, With<MainCamera>>,
+    bodies: Query<(
+        &Transform,
+        &Velocity,
+        Option<&Mass>,
+        Option<&LastAcceleration>,
+        Option<&SimWorld>,
+    )>,
+) {
+    if !prediction.active {
+        return;
+    }
+    let Some((transform, velocity, mass, last, world)) =
+        inspector.target.and_then(|target| bodies.get(target).ok())
+    else {
+        return;
+    };
+    let Some(tree) = trees.tree(world.copied().unwrap_or_default()) else {
+        return;
+    };
+
+    let position = transform.translation.xy();
+    let body = SimBody {
+        last_acceleration: last.copied().unwrap_or_default(),
+        ..SimBody::new(position, velocity.0, mass.map_or(0., |mass| mass.0))
+    };
+    let config = SimulationConfig {
+        g: settings.g,
+        theta_threshold: quality.settings().theta_threshold,
+        integrator: *integrator,
+        softening: settings.softening,
+    };
+    let dt = prediction.duration / prediction.steps as f32;
+    let path: Vec<Vec2> = std::iter::once(position)
+        .chain(path_through_tree(tree, body, &config, prediction.steps, dt))
+        .collect();
+
+    let scale = cameras
+        .get_single()
+        .map_or(1., |projection| projection.scale);
+    for (from, to) in dashes(&path, DASH_LENGTH * scale) {
+        gizmos.line_2d(from, to, theme.highlight());
+    }
+}
+
+/// Draws the [`OrbitPrediction`] of the inspected body.
+pub struct OrbitPredictionPlugin;
+
+impl Plugin for OrbitPredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OrbitPrediction>()
+            .init_resource::<Inspector>()
+            .init_resource::<GravityTrees>()
+            .init_resource::<Quality>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<IntegratorKind>()
+            .init_resource::<PhysicsSettings>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .add_systems(
+                Update,
+                (toggle_orbit_prediction, draw_orbit_prediction).chain(),
+            );
+    }
+}
@@ -0,0 +1,309 @@
+use crate::physics_plugin::{Mass, PhysicsSettings, Velocity};
+use bevy::prelude::*;
+
+/// Largest eccentricity of [`RelativeOrbit::Elements`], at 1 and above
+/// the orbit is no longer an ellipse.
+pub const MAX_ECCENTRICITY: f32 = 0.999;
+
+/// The body this body orbits (moon → planet → star).
+///
+/// The link is only used for authoring and grouping, gravity still acts
+/// between all the bodies.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct OrbitParent(pub Entity);
+
+/// How a body is placed relative to its parent.
+#[derive(Debug, Clone, Copy)]
+pub enum RelativeOrbit {
+    /// Circular orbit with `radius`, starting at `angle` radians from the
+    /// parent's x axis, going counterclockwise.
+    Circular { radius: f32, angle: f32 },
+    /// Elliptic orbit given by its Keplerian elements. The simulation is
+    /// planar, so the inclination only leaves whether the orbit is
+    /// retrograde and the ascending node is folded into the argument of
+    /// periapsis.
+    Elements {
+        semi_major_axis: f32,
+        /// Clamped into `0..=MAX_ECCENTRICITY`
+        eccentricity: f32,
+        /// Angle of the periapsis from the parent's x axis in radians
+        argument_of_periapsis: f32,
+        /// Mean anomaly at spawn in radians
+        mean_anomaly: f32,
+        /// Whether the body goes around clockwise
+        retrograde: bool,
+    },
+    /// Raw position and velocity relative to the parent.
+    State { offset: Vec2, velocity: Vec2 },
+}
+
+/// Simulation time between the epoch the relative orbits are given at and
+/// the start of the simulation. Orbits are propagated analytically by this
+/// much before the bodies are placed, raw relative states are used as is.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct SpawnEpoch {
+    pub elapsed: f32,
+}
+
+/// Initial state of a body given relative to its `parent`, converted to an
+/// absolute position and velocity before the body is simulated and replaced
+/// by an [`OrbitParent`] link.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct RelativeSpawn {
+    pub parent: Entity,
+    pub orbit: RelativeOrbit,
+}
+
+/// Solves Kepler's equation `E - e sin E = M` for the eccentric anomaly `E`
+/// using Newton's method, with `M` wrapped into `0..2π` first.
+fn eccentric_anomaly(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    let mean_anomaly = mean_anomaly.rem_euclid(std::f32::consts::TAU);
+    let mut anomaly = if eccentricity < 0.8 {
+        mean_anomaly
+    } else {
+        std::f32::consts::PI
+    };
+    for _ in 0..50 {
+        let step = (anomaly - eccentricity * anomaly.sin() - mean_anomaly)
+            / (1. - eccentricity * anomaly.cos());
+        anomaly -= step;
+        if step.abs() < 1e-6 {
+            break;
+        }
+    }
+    anomaly
+}
+
+/// Two-body solution of a body's orbit around its parent, kept from its
+/// relative spawn to compare the simulated motion against.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct AnalyticOrbit {
+    pub orbit: RelativeOrbit,
+    /// Mass of the parent and the body together
+    pub total_mass: f32,
+    /// Gravitational constant the orbit was solved with
+    pub g: f32,
+    /// Time since the orbit's epoch at which the body was placed
+    pub epoch_elapsed: f32,
+    /// Elapsed app time at which the body was placed
+    pub spawned_at: f32,
+}
+
+impl AnalyticOrbit {
+    /// Position relative to the parent the two-body solution predicts at
+    /// elapsed app time `now`, `None` for raw states which can't be
+    /// propagated.
+    pub fn predicted_offset(&self, now: f32) -> Option<Vec2> {
+        if let RelativeOrbit::State { .. } = self.orbit {
+            return None;
+        }
+        let elapsed = self.epoch_elapsed + now - self.spawned_at;
+        Some(
+            self.orbit
+                .relative_state(self.total_mass, self.g, elapsed)
+                .0,
+        )
+    }
+}
+
+impl RelativeOrbit {
+    /// Position and velocity relative to the parent `elapsed` time after the
+    /// epoch, `total_mass` is the mass of the parent and the body together
+    /// and `g` the gravitational constant.
+    pub(crate) fn relative_state(&self, total_mass: f32, g: f32, elapsed: f32) -> (Vec2, Vec2) {
+        match *self {
+            RelativeOrbit::Circular { radius, angle } => {
+                let speed = (g * total_mass / radius).sqrt();
+                let dir = Vec2::from_angle(angle + speed / radius * elapsed);
+                (dir * radius, dir.perp() * speed)
+            }
+            RelativeOrbit::Elements {
+                semi_major_axis: a,
+                eccentricity: e,
+                argument_of_periapsis,
+                mean_anomaly,
+                retrograde,
+            } => {
+                let e = e.clamp(0., MAX_ECCENTRICITY);
+                let mean_motion = (g * total_mass / (a * a * a)).sqrt();
+                let anomaly = eccentric_anomaly(mean_anomaly + mean_motion * elapsed, e);
+                let (sin, cos) = anomaly.sin_cos();
+                let minor_ratio = (1. - e * e).sqrt();
+                // Rate of change of the eccentric anomaly
+                let rate = mean_motion / (1. - e * cos);
+                // Position and velocity with the periapsis on the x axis
+                let mut offset = Vec2::new(a * (cos - e), a * minor_ratio * sin);
+                let mut velocity = Vec2::new(-a * sin, a * minor_ratio * cos) * rate;
+                if retrograde {
+                    // Mirrored across the line of apsides
+                    offset.y = -offset.y;
+                    velocity.y = -velocity.y;
+                }
+                let rotation = Vec2::from_angle(argument_of_periapsis);
+                (rotation.rotate(offset), rotation.rotate(velocity))
+            }
+            RelativeOrbit::State { offset, velocity } => (offset, velocity),
+        }
+    }
+}
+
+/// Converts the relative spawns to absolute positions and velocities,
+/// parents before their children so whole chains resolve in one go.
+pub fn resolve_relative_spawns(
+    time: Res<Time>,
+    settings: Res<PhysicsSettings>,
+    mut commands: Commands,
+    epoch: Option<Res<SpawnEpoch>>,
+    spawns: Query<(Entity, &RelativeSpawn)>,
+    mut bodies: Query<(&mut Transform, &mut Velocity, Option<&Mass>)>,
+) {
+    let elapsed = epoch.map_or(0., |epoch| epoch.elapsed);
+    let mut pending: Vec<(Entity, RelativeSpawn)> = spawns
+        .iter()
+        .map(|(entity, spawn)| (entity, *spawn))
+        .collect();
+
+    while !pending.is_empty() {
+        let waiting: Vec<Entity> = pending.iter().map(|(entity, _)| *entity).collect();
+        let before = pending.len();
+
+        pending.retain(|(entity, spawn)| {
+            if waiting.contains(&spawn.parent) {
+                // The parent itself is not placed yet.
+                return true;
+            }
+            let Ok((parent_transform, parent_velocity, parent_mass)) = bodies.get(spawn.parent)
+            else {
+                // The parent is gone, keep the body where it is.
+                commands.entity(*entity).remove::<RelativeSpawn>();
+                return false;
+            };
+            let parent_position = parent_transform.translation;
+            let parent_velocity = parent_velocity.0;
+            let parent_mass = parent_mass.map_or(0., |mass| mass.0);
+
+            let mut entity_commands = commands.entity(*entity);
+            if let Ok((mut transform, mut velocity, mass)) = bodies.get_mut(*entity) {
+                let total_mass = parent_mass + mass.map_or(0., |mass| mass.0);
+                let (offset, relative_velocity) =
+                    spawn.orbit.relative_state(total_mass, settings.g, elapsed);
+                transform.translation = parent_position + offset.extend(0.);
+                velocity.0 = parent_velocity + relative_velocity;
+                entity_commands.insert(AnalyticOrbit {
+                    orbit: spawn.orbit,
+                    total_mass,
+                    g: settings.g,
+                    epoch_elapsed: elapsed,
+                    spawned_at: time.elapsed_secs(),
+                });
+            }
+            entity_commands
+                .remove::<RelativeSpawn>()
+                .insert(OrbitParent(spawn.parent));
+            false
+        });
+
+        if pending.len() == before {
+            // Only cycles are left, those can never be resolved.
+            for (entity, _) in pending.drain(..) {
+                commands.entity(entity).remove::<RelativeSpawn>();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{PI, TAU};
+
+    const G: f32 = 1.;
+    const TOTAL_MASS: f32 = 1000.;
+
+    fn elements(semi_major_axis: f32, eccentricity: f32, mean_anomaly: f32) -> RelativeOrbit {
+        RelativeOrbit::Elements {
+            semi_major_axis,
+            eccentricity,
+            argument_of_periapsis: 0.7,
+            mean_anomaly,
+            retrograde: false,
+        }
+    }
+
+    #[test]
+    fn zero_mean_anomaly_is_at_periapsis() {
+        for e in [0., 0.3, 0.9] {
+            let (offset, velocity) = elements(50., e, 0.).relative_state(TOTAL_MASS, G, 0.);
+            assert!(
+                (offset.length() - 50. * (1. - e)).abs() < 1e-3,
+                "{e} {offset}"
+            );
+            assert!((offset.to_angle() - 0.7).abs() < 1e-5, "{e} {offset}");
+            assert!(offset.dot(velocity).abs() < 1e-4 * offset.length() * velocity.length());
+        }
+    }
+
+    #[test]
+    fn speed_follows_vis_viva() {
+        let a = 80.;
+        for e in [0.1, 0.5, 0.95] {
+            for mean_anomaly in [0., 0.5, 2., PI, 4., 6.] {
+                let (offset, velocity) =
+                    elements(a, e, mean_anomaly).relative_state(TOTAL_MASS, G, 0.);
+                let expected = (G * TOTAL_MASS * (2. / offset.length() - 1. / a)).sqrt();
+                assert!(
+                    (velocity.length() - expected).abs() <= expected * 1e-4,
+                    "{e} {mean_anomaly}: {} {expected}",
+                    velocity.length()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn circular_elements_match_a_circular_orbit() {
+        let circular = RelativeOrbit::Circular {
+            radius: 60.,
+            angle: 0.7,
+        };
+        for e in [0., 1e-5] {
+            for elapsed in [0., 3., 17.] {
+                let (offset, velocity) = circular.relative_state(TOTAL_MASS, G, elapsed);
+                let (elements_offset, elements_velocity) =
+                    elements(60., e, 0.).relative_state(TOTAL_MASS, G, elapsed);
+                assert!(offset.distance(elements_offset) < 1e-2, "{e} {elapsed}");
+                assert!(
+                    velocity.distance(elements_velocity) < velocity.length() * 1e-3,
+                    "{e} {elapsed}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn kepler_converges_close_to_parabolic() {
+        for e in [0.9, 0.99, MAX_ECCENTRICITY] {
+            for step in 0..64 {
+                let mean_anomaly = step as f32 / 64. * TAU;
+                let anomaly = eccentric_anomaly(mean_anomaly, e);
+                let residual = anomaly - e * anomaly.sin() - mean_anomaly;
+                assert!(residual.abs() < 1e-4, "{e} {mean_anomaly}: {residual}");
+            }
+        }
+        // Many orbits after the epoch
+        let anomaly = eccentric_anomaly(1000., 0.99);
+        let residual = anomaly - 0.99 * anomaly.sin() - 1000f32.rem_euclid(TAU);
+        assert!(residual.abs() < 1e-4, "{residual}");
+    }
+
+    #[test]
+    fn eccentricity_is_clamped_to_an_ellipse() {
+        for e in [-0.2, 1., 1.5] {
+            let (offset, velocity) = elements(50., e, 1.).relative_state(TOTAL_MASS, G, 2.);
+            assert!(offset.is_finite() && velocity.is_finite(), "{e}");
+        }
+        let clamped = elements(50., 1.5, 1.).relative_state(TOTAL_MASS, G, 2.);
+        let most = elements(50., MAX_ECCENTRICITY, 1.).relative_state(TOTAL_MASS, G, 2.);
+        assert_eq!(clamped, most);
+    }
+}
@@ -0,0 +1,111 @@
+//! `--osc <host:port>`: sends the [`SimStatistics`] and the merges as OSC
+//! messages over UDP, so the simulation can drive music or visuals running
+//! elsewhere. Only built with the `osc` feature.
+
+use crate::state::SimState;
+use crate::statistics::{MergeEvent, SimStatistics};
+use bevy::prelude::*;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// Appends an OSC string, null terminated and padded to 4 bytes.
+fn push_string(packet: &mut Vec<u8>, string: &str) {
+    packet.extend_from_slice(string.as_bytes());
+    packet.extend(std::iter::repeat_n(0, 4 - string.len() % 4));
+}
+
+/// OSC message to `address` with float arguments.
+pub fn encode_message(address: &str, arguments: &[f32]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    push_string(&mut packet, address);
+    let tags: String = std::iter::once(',')
+        .chain(arguments.iter().map(|_| 'f'))
+        .collect();
+    push_string(&mut packet, &tags);
+    for argument in arguments {
+        packet.extend_from_slice(&argument.to_be_bytes());
+    }
+    packet
+}
+
+/// Where the messages go. Sending never blocks, messages which can't be
+/// sent are dropped rather than holding up the frame.
+///
+/// The statistics go out every time they are updated:
+///
+/// - `/spacesim/kinetic_energy <energy>`
+/// - `/spacesim/merge_rate <merges per second>`
+/// - `/spacesim/density_peak <x> <y> <density>`
+///
+/// and every merge right when it happens, as
+/// `/spacesim/merge <x> <y> <mass>`.
+#[derive(Resource, Debug)]
+pub struct OscOutput {
+    socket: UdpSocket,
+    target: SocketAddr,
+}
+
+impl OscOutput {
+    /// Output to `target`, e.g. `127.0.0.1:9000`.
+    pub fn new(target: &str) -> std::io::Result<Self> {
+        let target = target.to_socket_addrs()?.next().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no address to send to")
+        })?;
+        let any = if target.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(any)?;
+        socket.set_nonblocking(true)?;
+        Ok(OscOutput { socket, target })
+    }
+
+    pub fn send(&self, address: &str, arguments: &[f32]) {
+        let packet = encode_message(address, arguments);
+        if let Err(err) = self.socket.send_to(&packet, self.target) {
+            debug!("Dropped OSC message `{address}`: {err}");
+        }
+    }
+}
+
+fn send_statistics(output: Res<OscOutput>, statistics: Res<SimStatistics>) {
+    output.send("/spacesim/kinetic_energy", &[statistics.kinetic_energy]);
+    output.send("/spacesim/merge_rate", &[statistics.merge_rate]);
+    output.send(
+        "/spacesim/density_peak",
+        &[
+            statistics.density_peak.x,
+            statistics.density_peak.y,
+            statistics.peak_density,
+        ],
+    );
+}
+
+fn send_merges(output: Res<OscOutput>, mut merges: EventReader<MergeEvent>) {
+    for merge in merges.read() {
+        output.send(
+            "/spacesim/merge",
+            &[merge.position.x, merge.position.y, merge.mass],
+        );
+    }
+}
+
+/// Sends the statistics over OSC when the [`OscOutput`] resource is
+/// inserted.
+pub struct OscPlugin;
+
+impl Plugin for OscPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimStatistics>()
+            .add_event::<MergeEvent>()
+            .add_systems(
+                Update,
+                (
+                    send_merges,
+                    send_statistics.run_if(resource_changed::<SimStatistics>),
+                )
+                    .run_if(resource_exists::<OscOutput>)
+                    .run_if(in_state(SimState::Running)),
+            );
+    }
+}
@@ -0,0 +1,191 @@
+use crate::export::{timestamped_path, ExportImage};
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::MainCamera;
+use crate::settings::CameraSensitivity;
+use crate::state::{pause_time, resume_time, SimState};
+use bevy::core_pipeline::bloom::Bloom;
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::prelude::*;
+use bevy::render::view::{ColorGrading, ColorGradingGlobal};
+use std::path::PathBuf;
+
+/// Fraction of the view the camera pans per second.
+const PAN_SPEED: f32 = 0.25;
+/// Rate of zooming, the scale changes by e^rate per second.
+const ZOOM_RATE: f32 = 0.5;
+/// Change of bloom intensity per second.
+const BLOOM_RATE: f32 = 0.2;
+/// Change of exposure in stops per second.
+const EXPOSURE_RATE: f32 = 1.;
+
+/// Look of the photos and where they are saved.
+#[derive(Resource, Debug, Clone)]
+pub struct PhotoSettings {
+    pub bloom: f32,
+    /// Exposure in stops, 0 leaves the colors as they are
+    pub exposure: f32,
+    /// Captures are rendered at this many times the window resolution
+    pub supersampling: u32,
+    pub directory: PathBuf,
+}
+
+impl Default for PhotoSettings {
+    fn default() -> Self {
+        PhotoSettings {
+            bloom: 0.15,
+            exposure: 0.,
+            supersampling: 4,
+            directory: PathBuf::from("."),
+        }
+    }
+}
+
+/// Visibility a UI root had before photo mode hid it.
+#[derive(Component)]
+struct HiddenForPhoto(Visibility);
+
+fn toggle_photo_mode(
+    actions: Actions,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    if !actions.just_pressed(Action::TogglePhotoMode) {
+        return;
+    }
+    match state.get() {
+        SimState::Running | SimState::Paused | SimState::Editing => next_state.set(SimState::Photo),
+        SimState::Photo => next_state.set(SimState::Running),
+        _ => {}
+    }
+}
+
+/// Hides the UI and the overlays, and switches the camera to HDR so bloom
+/// and exposure apply.
+#[allow(clippy::type_complexity)]
+fn enter_photo_mode(
+    mut commands: Commands,
+    settings: Res<PhotoSettings>,
+    mut gizmos: ResMut<GizmoConfigStore>,
+    ui_roots: Query<(Entity, &Visibility), (With<Node>, Without<Parent>)>,
+    mut cameras: Query<(Entity, &mut Camera), With<MainCamera>>,
+) {
+    for (entity, visibility) in &ui_roots {
+        commands
+            .entity(entity)
+            .insert((HiddenForPhoto(*visibility), Visibility::Hidden));
+    }
+    gizmos.config_mut::<DefaultGizmoConfigGroup>().0.enabled = false;
+    for (entity, mut camera) in &mut cameras {
+        camera.hdr = true;
+        commands.entity(entity).insert((
+            Bloom {
+                intensity: settings.bloom,
+                ..Default::default()
+            },
+            ColorGrading::with_identical_sections(
+                ColorGradingGlobal {
+                    exposure: settings.exposure,
+                    ..Default::default()
+                },
+                Default::default(),
+            ),
+            Tonemapping::TonyMcMapface,
+        ));
+    }
+}
+
+fn exit_photo_mode(
+    mut commands: Commands,
+    mut gizmos: ResMut<GizmoConfigStore>,
+    hidden: Query<(Entity, &HiddenForPhoto)>,
+    mut cameras: Query<(Entity, &mut Camera), With<MainCamera>>,
+) {
+    for (entity, hidden) in &hidden {
+        commands
+            .entity(entity)
+            .insert(hidden.0)
+            .remove::<HiddenForPhoto>();
+    }
+    gizmos.config_mut::<DefaultGizmoConfigGroup>().0.enabled = true;
+    for (entity, mut camera) in &mut cameras {
+        camera.hdr = false;
+        commands
+            .entity(entity)
+            .remove::<(Bloom, ColorGrading)>()
+            .insert(Tonemapping::None);
+    }
+}
+
+/// Fine camera, bloom and exposure control while holding the keys, and
+/// capturing the photo.
+#[allow(clippy::type_complexity)]
+fn control_photo(
+    time: Res<Time<Real>>,
+    actions: Actions,
+    sensitivity: Res<CameraSensitivity>,
+    mut settings: ResMut<PhotoSettings>,
+    mut exports: EventWriter<ExportImage>,
+    mut cameras: Query<
+        (
+            &mut Transform,
+            &mut OrthographicProjection,
+            &mut Bloom,
+            &mut ColorGrading,
+        ),
+        With<MainCamera>,
+    >,
+) {
+    let dt = time.delta_secs();
+    let axis = |negative, positive| {
+        actions.pressed(positive) as i32 as f32 - actions.pressed(negative) as i32 as f32
+    };
+    let pan = Vec2::new(
+        axis(Action::PanLeft, Action::PanRight),
+        axis(Action::PanDown, Action::PanUp),
+    );
+    let zoom = axis(Action::ZoomIn, Action::ZoomOut);
+    settings.bloom =
+        (settings.bloom + axis(Action::BloomDown, Action::BloomUp) * BLOOM_RATE * dt).clamp(0., 1.);
+    settings.exposure += axis(Action::ExposureDown, Action::ExposureUp) * EXPOSURE_RATE * dt;
+
+    for (mut transform, mut projection, mut bloom, mut grading) in &mut cameras {
+        let view_size = projection.area.size();
+        let offset =
+            transform.rotation * (pan * view_size * PAN_SPEED * sensitivity.0 * dt).extend(0.);
+        transform.translation += offset;
+        projection.scale *= (zoom * ZOOM_RATE * sensitivity.0 * dt).exp();
+        bloom.intensity = settings.bloom;
+        grading.global.exposure = settings.exposure;
+    }
+
+    if actions.just_pressed(Action::CapturePhoto) {
+        exports.send(ExportImage {
+            path: timestamped_path(&settings.directory),
+            scale: settings.supersampling,
+            exit_when_saved: false,
+        });
+    }
+}
+
+/// Photo mode: the simulation pauses, the UI and overlays hide, and the
+/// camera can be moved finely and the image tuned before capturing it at
+/// a multiple of the window resolution.
+pub struct PhotoPlugin;
+
+impl Plugin for PhotoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhotoSettings>()
+            .init_resource::<CameraSensitivity>()
+            .init_resource::<InputMap>()
+            .add_systems(OnEnter(SimState::Photo), (pause_time, enter_photo_mode))
+            .add_systems(OnExit(SimState::Photo), (resume_time, exit_photo_mode))
+            .add_systems(
+                Update,
+                (
+                    toggle_photo_mode,
+                    control_photo.run_if(in_state(SimState::Photo)),
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::fixed_step::TickRate;
+use crate::integrator::IntegratorKind;
+use crate::physics_plugin::PhysicsSettings;
+use crate::quality::{Quality, QualitySettings};
+use crate::scenario::Scenarios;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Physics parameters one layer of configuration sets, the ones left at
+/// `None` are inherited from the layer below.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PhysicsOverrides {
+    /// Barnes-Hut threshold, replacing the one of the [`Quality`] preset
+    pub theta_threshold: Option<f32>,
+    /// Physics substeps per fixed step, replacing the preset's
+    pub substeps: Option<u32>,
+    pub integrator: Option<IntegratorKind>,
+    /// Fixed steps per second, see [`TickRate`]
+    pub tick_rate: Option<f64>,
+}
+
+impl PhysicsOverrides {
+    /// These overrides on top of `below`.
+    pub fn over(self, below: PhysicsOverrides) -> Self {
+        PhysicsOverrides {
+            theta_threshold: self.theta_threshold.or(below.theta_threshold),
+            substeps: self.substeps.or(below.substeps),
+            integrator: self.integrator.or(below.integrator),
+            tick_rate: self.tick_rate.or(below.tick_rate),
+        }
+    }
+}
+
+/// The layers the physics parameters are resolved from when a scenario is
+/// loaded: the built-in defaults, overridden by the [`PhysicsSettings`] the
+/// app was built with, overridden by the user settings, overridden by the
+/// scenario. Resolving the same scenario again always gives the same
+/// parameters, whatever was loaded before it.
+#[derive(Resource, Debug, Default)]
+pub struct PhysicsConfig {
+    /// What the user settings override
+    pub user: PhysicsOverrides,
+    /// Integrator and tick rate from before the first scenario was loaded,
+    /// the built-in ones or the ones given on the command line
+    defaults: Option<(IntegratorKind, f64)>,
+    /// The app's, the user's and the loaded scenario's overrides together
+    active: PhysicsOverrides,
+}
+
+impl PhysicsConfig {
+    /// Config with the `user` settings' overrides and nothing resolved yet.
+    pub fn new(user: PhysicsOverrides) -> Self {
+        PhysicsConfig {
+            user,
+            ..Default::default()
+        }
+    }
+
+    pub fn active(&self) -> PhysicsOverrides {
+        self.active
+    }
+
+    /// Replaces the theta threshold until the next scenario is loaded,
+    /// which resolves it from the layers again.
+    pub fn set_theta_threshold(&mut self, theta_threshold: f32) {
+        self.active.theta_threshold = Some(theta_threshold);
+    }
+
+    /// The settings of the `quality` preset with the active overrides
+    /// applied.
+    pub fn quality_settings(&self, quality: Quality) -> QualitySettings {
+        let mut settings = quality.settings();
+        if let Some(theta_threshold) = self.active.theta_threshold {
+            settings.theta_threshold = theta_threshold;
+        }
+        if let Some(substeps) = self.active.substeps {
+            settings.substeps = substeps;
+        }
+        settings
+    }
+}
+
+/// The [`Quality`] preset as the physics should follow it, with the
+/// overrides of the [`PhysicsConfig`].
+#[derive(SystemParam)]
+pub struct PhysicsQuality<'w> {
+    quality: Res<'w, Quality>,
+    config: Res<'w, PhysicsConfig>,
+}
+
+impl PhysicsQuality<'_> {
+    pub fn settings(&self) -> QualitySettings {
+        self.config.quality_settings(*self.quality)
+    }
+}
+
+/// Which layer a resolved parameter came from, for the log.
+fn layer<T: Copy>(scenario: Option<T>, user: Option<T>, default: T) -> (T, &'static str) {
+    match (scenario, user) {
+        (Some(value), _) => (value, "scenario"),
+        (None, Some(value)) => (value, "user settings"),
+        (None, None) => (default, "default"),
+    }
+}
+
+/// Resolves the physics parameters of the scenario about to be loaded and
+/// applies them, logging each with where it came from.
+pub fn resolve_physics_config(
+    scenarios: Res<Scenarios>,
+    quality: Res<Quality>,
+    settings: Res<PhysicsSettings>,
+    mut config: ResMut<PhysicsConfig>,
+    mut integrator: ResMut<IntegratorKind>,
+    mut tick_rate: ResMut<TickRate>,
+) {
+    let Some(scenario) = scenarios.entries.get(scenarios.selected) else {
+        return;
+    };
+    let (default_integrator, default_tick_rate) =
+        *config.defaults.get_or_insert((*integrator, tick_rate.hz));
+    let user = config.user;
+    let overrides = scenario.physics;
+    let built = PhysicsOverrides {
+        theta_threshold: settings.theta_threshold,
+        ..Default::default()
+    };
+
+    let preset = quality.settings();
+    let (theta_threshold, theta_from) = layer(
+        overrides.theta_threshold,
+        user.theta_threshold,
+        settings.theta_threshold.unwrap_or(preset.theta_threshold),
+    );
+    let (substeps, substeps_from) = layer(overrides.substeps, user.substeps, preset.substeps);
+    let (resolved_integrator, integrator_from) =
+        layer(overrides.integrator, user.integrator, default_integrator);
+    let (hz, tick_rate_from) = layer(overrides.tick_rate, user.tick_rate, default_tick_rate);
+    info!(
+        "Physics of `{}`: theta {theta_threshold} ({theta_from}), {substeps} substeps ({substeps_from}), {resolved_integrator:?} ({integrator_from}), {hz} Hz ({tick_rate_from})",
+        scenario.name
+    );
+
+    config.active = overrides.over(user).over(built);
+    integrator.set_if_neq(resolved_integrator);
+    if tick_rate.hz != hz {
+        tick_rate.hz = hz;
+    }
+}
@@ -1,117 +1,1309 @@
-use crate::quadtree::{Node, QuadTree};
-use bevy::prelude::{Circle, Color, *};
+use crate::autopilot::steer_autopilots;
+use crate::background::{spawn_halo, super_particles, Background, BackgroundAggregation};
+use crate::body_count::{scale_body_count, BodyCountController};
+use crate::budget::{enforce_budget, Budget, BudgetExceeded};
+use crate::cadence::{count_step, on_cadence, Cadences, Subsystem};
+use crate::clustering::{measure_clustering, ClusteringStatistics};
+use crate::collision_response::{
+    bounce_bodies, bounces_collisions, merge_bodies, merges_collisions, CollisionMode,
+};
+use crate::determinism::Determinism;
+use crate::disc::{spawn_stable_disc, StableDisc};
+use crate::distributions::Distribution;
+use crate::docking::{
+    detect_collisions, dock_bodies, request_undock, undock_bodies, Collision, Undock,
+};
+use crate::domain_decomposition::DomainDecomposition;
+use crate::drift::{correct_drift, reset_drift_reference, DriftCorrection};
+use crate::ephemeris::{compare_ephemerides, EphemerisComparison};
+use crate::equilibrium::equilibrate;
+use crate::export::{run_scheduled_export, ScheduledExport};
+use crate::extensions::{apply_custom_forces, run_analyses, AnalysisRegistry, ForceRegistry};
+use crate::fixed_point::{
+    integrate_fixed_acceleration, load_fixed_states, update_fixed_position, FixedPoint,
+};
+use crate::fixed_step::{
+    apply_tick_rate, interpolate_translations, record_physics_translations,
+    restore_physics_translations, TickRate,
+};
+use crate::forces::{
+    apply_drag, apply_impulses, clear_accelerations, integrate_acceleration, Acceleration, Impulse,
+};
+use crate::input::InputMap;
+use crate::inspector::Inspector;
+use crate::integrator::{rk4_step, IntegratorKind, LastAcceleration};
+use crate::orbits::resolve_relative_spawns;
+use crate::physics_config::{resolve_physics_config, PhysicsConfig, PhysicsQuality};
+use crate::quadtree::{morton_order, morton_sort, BodyMove, NodeHandle, QuadTree, TreeError};
+use crate::quality::Quality;
+use crate::radius::{update_radii, BodyDensity, Density, Radius};
+use crate::scenario::{
+    load_scenario, request_restart, restart_scenario, RegisterScenario, RestartScenario, Scenarios,
+    SimRng,
+};
+use crate::sim_rate::{adjust_sim_rate, SimRate};
+use crate::state::{pause_time, resume_time, toggle_editing, toggle_pause, SimState};
+use crate::tether::{apply_tethers, draw_tethers};
+use crate::theme::Theme;
+use crate::timings::PhysicsTimings;
+use crate::tree_failure::{tree_built, TreeFailure};
+use crate::view_relaxation::{relaxed_theta, track_view, ViewRelaxation};
+use crate::watchdog::{start_watchdog_frame, Watchdog};
+use crate::worlds::SimWorld;
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::{Circle, *};
+#[cfg(not(feature = "rayon"))]
+use bevy::tasks::{ComputeTaskPool, ParallelSlice, TaskPool};
+use bevy::utils::{Duration, HashMap, Instant};
 use rand::distr::StandardUniform;
-use rand::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-const G: f32 = 0.000_1;
+/// Gravitational constant the [`PhysicsSettings`] start with.
+pub const G: f32 = 0.000_1;
+/// Nodes with theta below this value are treated as a single body, the
+/// threshold of the high [`Quality`] preset.
+pub const THETA_THRESHOLD: f32 = 3.;
+/// Targets a thread walks the trees for at a time.
+#[cfg(not(feature = "rayon"))]
+const TRAVERSAL_CHUNK: usize = 256;
+/// Bodies whose gravity is computed between the checks of the
+/// [`Watchdog`].
+const WATCHDOG_CHUNK: usize = 1024;
 
+/// Mass of a body, the body attracts others only if it has one.
 #[derive(Component)]
-struct Mass(f32);
+#[require(Radius)]
+pub struct Mass(pub f32);
 
+/// Material shared by all the bodies, its color follows the theme.
+#[derive(Resource)]
+pub struct BodyMaterial(pub Handle<ColorMaterial>);
+
+/// Circle mesh of radius 1 shared by the bodies spawned during a run, which
+/// are scaled to their radius like the rest.
+#[derive(Resource)]
+pub struct BodyMesh(pub Handle<Mesh>);
+
+/// The camera showing world 0, which the UI is drawn on.
 #[derive(Component)]
-struct Velocity(Vec2);
+pub struct MainCamera;
+
+/// Velocity of a body in units per second.
+#[derive(Component)]
+#[require(Acceleration, LastAcceleration)]
+pub struct Velocity(pub Vec2);
+
+/// Schedule moving the bodies, updating their gravity and integrating their
+/// accelerations, run as many times a step as the [`Quality`] asks for
+/// with the step time split between the runs.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhysicsSubstep;
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn((MainCamera, Camera2d));
+}
+
+/// Parameters of the "Random disc" scenario.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RandomDisc {
+    pub count: usize,
+    pub central_mass: f32,
+    /// Distance of the bodies from the center
+    pub offset: Distribution,
+    pub speed: Distribution,
+    pub mass: Distribution,
+}
+
+impl Default for RandomDisc {
+    fn default() -> Self {
+        RandomDisc {
+            count: 2_000,
+            central_mass: 100_000_000_000.,
+            offset: Distribution::Uniform {
+                min: 100.,
+                max: 300.,
+            },
+            speed: Distribution::Uniform {
+                min: 100.,
+                max: 200.,
+            },
+            mass: Distribution::Uniform {
+                min: 1_000_000.,
+                max: 20_000_000.,
+            },
+        }
+    }
+}
 
 fn spawn_objects(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+    disc: Res<RandomDisc>,
+    mut rng: ResMut<SimRng>,
 ) {
-    commands.spawn(Camera2d);
-
+    let rng = &mut rng.rng;
     let circle = meshes.add(Circle::new(1.));
-    let material = materials.add(ColorMaterial::from(Color::srgb_u8(255, 0, 0)));
-
-    let min_offset = 100.;
-    let offset_random_margin = 200.;
-    let count = 2_000;
-    let min_speed = 100.0;
-    let speed_random_margin = 100.0;
-    let min_mass = 1_000_000.;
-    let mass_random_margin = 19_000_000.;
+    let material = materials.add(ColorMaterial::from(theme.body()));
+    commands.insert_resource(BodyMaterial(material.clone()));
 
     commands.spawn((
         Velocity(Vec2::ZERO),
-        Mass(100_000_000_000.),
+        Mass(disc.central_mass),
+        BodyDensity::sized(disc.central_mass, 50.),
         Mesh2d(circle.clone()),
         MeshMaterial2d(material.clone()),
-        Transform {
-            translation: Vec3::new(0., 0., 0.),
-            scale: Vec3::new(50., 50., 1.),
-            ..Default::default()
-        },
+        Transform::default(),
     ));
 
-    let increment_angle = 360. / count as f32;
-    for i in 0..count {
+    let increment_angle = 360. / disc.count as f32;
+    for i in 0..disc.count {
         let angle: f32 = (increment_angle * i as f32)
-            + rand::rng().sample::<f32, StandardUniform>(StandardUniform) * increment_angle;
+            + rng.sample::<f32, StandardUniform>(StandardUniform) * increment_angle;
         let dir = Vec2::from_angle(angle.to_radians());
-        let offset = min_offset
-            + offset_random_margin * rand::rng().sample::<f32, StandardUniform>(StandardUniform);
-        let speed = min_speed
-            + speed_random_margin * rand::rng().sample::<f32, StandardUniform>(StandardUniform);
-        let mass_addition =
-            mass_random_margin * rand::rng().sample::<f32, StandardUniform>(StandardUniform);
-        let mass = min_mass + mass_addition;
-
-        let direction = Vec2::new(
-            rand::rng().random_range(-1.0..1.0),
-            rand::rng().random_range(-1.0..1.0),
-        )
-        .normalize();
+        let offset = disc.offset.sample(rng);
+        let speed = disc.speed.sample(rng);
+        let mass = disc.mass.sample(rng);
+
+        let direction =
+            Vec2::new(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0)).normalize();
 
         commands.spawn((
             Velocity(direction * speed),
             Mass(mass),
             Mesh2d(circle.clone()),
             MeshMaterial2d(material.clone()),
-            Transform {
-                translation: Vec3::new(dir.x * offset, dir.y * offset, 0.), // Offset them a bit
-                scale: Vec3::new(
-                    3. + mass_addition / 800_000.0,
-                    3. + mass_addition / 800_000.0,
-                    1.,
-                ),
-                ..Default::default()
-            },
+            // Offset them a bit
+            Transform::from_xyz(dir.x * offset, dir.y * offset, 0.),
         ));
     }
 }
 
-fn update_position(time: Res<Time>, mut query: Query<(&mut Transform, &Velocity)>) {
-    for (mut pos, vel) in &mut query {
-        pos.translation.x += vel.0.x * time.delta_secs();
-        pos.translation.y += vel.0.y * time.delta_secs();
+pub(crate) fn update_position(
+    time: Res<Time>,
+    integrator: Res<IntegratorKind>,
+    mut timings: ResMut<PhysicsTimings>,
+    mut query: Query<(&mut Transform, &Velocity, &LastAcceleration)>,
+) {
+    let start = Instant::now();
+    for (mut pos, vel, last) in &mut query {
+        let moved = integrator.displacement(vel.0, last, time.delta_secs());
+        pos.translation.x += moved.x;
+        pos.translation.y += moved.y;
     }
+    timings.integration += start.elapsed();
 }
 
-fn apply_acceleration(
-    time: Res<Time>,
-    subquery: Query<(Entity, &Mass, &Transform)>,
-    mut query: Query<(Entity, &Transform, &mut Velocity)>,
-) {
+/// Gravitational acceleration a point mass at `center_of_mass` causes at
+/// `position` with the gravitational constant `g`.
+pub fn point_mass_acceleration(position: Vec2, center_of_mass: Vec2, mass: f32, g: f32) -> Vec2 {
+    if center_of_mass == position {
+        return Vec2::ZERO;
+    }
+    let dir_vec = center_of_mass - position;
+    g * (mass / dir_vec.length_squared()) * dir_vec.normalize()
+}
+
+/// Sums the acceleration at `position` from all the bodies the Barnes-Hut
+/// traversal of `tree` yields for the given `theta_threshold`, with the
+/// gravitational constant `g`.
+pub fn tree_acceleration(tree: &QuadTree, position: Vec2, theta_threshold: f32, g: f32) -> Vec2 {
+    tree.accumulate_acceleration(position, theta_threshold, g, 0.)
+}
+
+/// Gravity gradient `∂aᵢ/∂xⱼ` a point mass at `center_of_mass` causes at
+/// `position` with the gravitational constant `g`, Plummer softened by
+/// `softening` like the force.
+pub fn point_mass_tidal_tensor(
+    position: Vec2,
+    center_of_mass: Vec2,
+    mass: f32,
+    g: f32,
+    softening: f32,
+) -> Mat2 {
+    let r = center_of_mass - position;
+    let softened = r.length_squared() + softening * softening;
+    if softened == 0. {
+        return Mat2::ZERO;
+    }
+    // G M (3 r rᵀ - s² I) / s⁵ with s² = r² + ε²
+    let scale = g * mass / (softened * softened * softened.sqrt());
+    let xy = 3. * r.x * r.y;
+    scale
+        * Mat2::from_cols(
+            Vec2::new(3. * r.x * r.x - softened, xy),
+            Vec2::new(xy, 3. * r.y * r.y - softened),
+        )
+}
+
+/// Gravity gradient `∂aᵢ/∂xⱼ` at `position` from the bodies the Barnes-Hut
+/// traversal of `tree` yields, summed analytically over the accepted
+/// nodes with the gravitational constant `g` and the force's `softening`.
+/// Stretching directions have positive eigenvalues.
+///
+/// For the field at a body in the tree, `exclude` is its mass, which is
+/// taken out of the node closest to `position` so the body doesn't feel
+/// itself. That node is the body's own leaf even when `position` is a
+/// little off the point the tree has it at.
+pub fn tree_tidal_tensor(
+    tree: &QuadTree,
+    position: Vec2,
+    theta_threshold: f32,
+    g: f32,
+    softening: f32,
+    exclude: Option<f32>,
+) -> Mat2 {
+    let mut sources = Vec::new();
+    tree.for_each_body(position, theta_threshold, |mass, center_of_mass| {
+        sources.push((mass, center_of_mass));
+    });
+    if let Some(own_mass) = exclude {
+        let own = sources.iter_mut().min_by(|a, b| {
+            a.1.distance_squared(position)
+                .total_cmp(&b.1.distance_squared(position))
+        });
+        if let Some((mass, _)) = own {
+            // Whatever shares the body's bucket stays.
+            *mass = (*mass - own_mass).max(0.);
+        }
+    }
+    sources
+        .into_iter()
+        .fold(Mat2::ZERO, |tensor, (mass, center_of_mass)| {
+            tensor + point_mass_tidal_tensor(position, center_of_mass, mass, g, softening)
+        })
+}
+
+/// Gravitational potential a point mass at `center_of_mass` causes at
+/// `position` with the gravitational constant `g`, Plummer softened by
+/// `softening` like the force.
+pub fn point_mass_potential(
+    position: Vec2,
+    center_of_mass: Vec2,
+    mass: f32,
+    g: f32,
+    softening: f32,
+) -> f32 {
+    let softened = center_of_mass.distance_squared(position) + softening * softening;
+    if softened == 0. {
+        return 0.;
+    }
+    -g * mass / softened.sqrt()
+}
+
+/// Sums the potential at `position` from the nodes the Barnes-Hut traversal
+/// of `tree` accepts with the gravitational constant `g` and the force's
+/// `softening`, optionally including their quadrupole moments on top of
+/// the monopoles.
+pub fn tree_potential(
+    tree: &QuadTree,
+    position: Vec2,
+    theta_threshold: f32,
+    g: f32,
+    softening: f32,
+    quadrupole: bool,
+) -> f32 {
+    let mut potential = 0.;
+    tree.for_each_accepted(position, theta_threshold, |node| {
+        let monopole = point_mass_potential(position, node.center_of_mass, node.mass, g, softening);
+        let r = position - node.center_of_mass;
+        let distance = (r.length_squared() + softening * softening).sqrt();
+        if !quadrupole || distance == 0. {
+            potential += monopole;
+            return;
+        }
+        // -G (3 rᵀIr - tr(I) s²) / 2s⁵ for the second moment I about the
+        // center of mass, with s² = r² + ε²
+        let moment = node.central_second_moment();
+        let r_moment_r = moment.x * r.x * r.x + 2. * moment.y * r.x * r.y + moment.z * r.y * r.y;
+        let trace = moment.x + moment.z;
+        potential += monopole
+            - g * (3. * r_moment_r - trace * distance * distance) / (2. * distance.powi(5));
+    });
+    potential
+}
+
+/// Builds the quadtree the force calculation uses from the positions and
+/// masses of the bodies.
+pub fn build_tree(bodies: impl IntoIterator<Item = (Vec2, f32)>) -> QuadTree {
     let mut q_tree = QuadTree::new(Vec2::new(0., 0.), 1000.);
-    for (_, mass, transform) in &subquery {
-        q_tree.add_node(transform.translation.xy(), mass.0);
+    for (position, mass) in bodies {
+        // Bodies the tree can't take are left out, which only makes the
+        // estimates drawn from it rougher.
+        let _ = q_tree.add_node(position, mass);
+    }
+    q_tree
+}
+
+/// Builds a quadtree sized to fit all of the `bodies`, for calculations
+/// which can't rely on the bodies staying inside the fixed bounds of
+/// [`build_tree`].
+pub fn build_fitted_tree(bodies: &[(Vec2, f32)]) -> QuadTree {
+    QuadTree::build(bodies)
+}
+
+/// Total potential energy of the `bodies` with the gravitational constant
+/// `g`, approximated by the tree with `theta_threshold`.
+pub fn potential_energy(bodies: &[(Vec2, f32)], theta_threshold: f32, g: f32) -> f32 {
+    if bodies.is_empty() {
+        return 0.;
+    }
+    let q_tree = build_fitted_tree(bodies);
+    let potential: f32 = bodies
+        .iter()
+        .map(|&(position, mass)| {
+            mass * tree_potential(&q_tree, position, theta_threshold, g, 0., false)
+        })
+        .sum();
+    // Every pair is counted from both sides.
+    potential / 2.
+}
+
+/// Builds in a row the bodies of a world have to stay in one quadrant of
+/// the root of its tree before it is shrunk, so bodies passing through the
+/// middle don't have the tree shrunk and grown again over and over.
+const SHRINK_AFTER: u32 = 30;
+
+/// The trees of the last force calculation, one per world, rebuilt in place
+/// every time so their memory is reused instead of allocated anew.
+///
+/// Once all the bodies of a world stayed in one quadrant of the root for
+/// [`SHRINK_AFTER`] builds, the root is shrunk to that quadrant, see
+/// [`QuadTree::shrink_root`].
+///
+/// Built with [`GravityTrees::incremental`] the trees are updated by moving
+/// the bodies instead, see [`QuadTree::move_bodies`].
+#[derive(Resource, Debug, Default, Clone)]
+pub struct GravityTrees {
+    trees: HashMap<SimWorld, QuadTree>,
+    /// Updates after which the trees are rebuilt from scratch, when they
+    /// are updated incrementally
+    rebuild_every: Option<u32>,
+    /// Bodies in the tree of every world, when updated incrementally
+    tracked: HashMap<SimWorld, TrackedBodies>,
+    /// Builds in a row all the bodies of every world were in one quadrant
+    /// of the root
+    confined: HashMap<SimWorld, u32>,
+}
+
+/// Bodies of a tree updated incrementally, in the Morton order they were
+/// inserted in.
+#[derive(Debug, Default, Clone)]
+struct TrackedBodies {
+    bodies: Vec<(Vec2, f32)>,
+    /// Index of each body in the sources the tree was built from
+    order: Vec<usize>,
+    handles: Vec<NodeHandle>,
+    /// Updates since the tree was last rebuilt
+    updates: u32,
+}
+
+impl GravityTrees {
+    /// Trees updated by moving the bodies which crossed into another cell
+    /// since the last force calculation, and rebuilt from scratch after
+    /// `rebuild_every` updates so they don't wear down into long chains of
+    /// nodes. The bodies are matched up with those of the last update by
+    /// their order, so the tree is also rebuilt whenever their masses
+    /// differ, e.g. after a merge.
+    pub fn incremental(rebuild_every: u32) -> Self {
+        GravityTrees {
+            rebuild_every: Some(rebuild_every),
+            ..Default::default()
+        }
+    }
+
+    /// Drops the trees, for when bodies they were built from are gone
+    /// before the next force calculation builds them again.
+    pub fn clear(&mut self) {
+        self.trees.clear();
+        self.tracked.clear();
+        self.confined.clear();
+    }
+
+    /// Nodes of the trees of all the worlds together.
+    pub fn node_count(&self) -> usize {
+        self.trees.values().map(QuadTree::node_count).sum()
+    }
+
+    /// The tree last built for `world`, `None` when it had no sources.
+    pub fn tree(&self, world: SimWorld) -> Option<&QuadTree> {
+        self.trees.get(&world)
+    }
+
+    /// Rebuilds the tree of every world from its sources, inserted in Morton
+    /// order, or updates it when the trees are incremental, dropping the
+    /// trees of the worlds without any. The bounds of the trees are grown
+    /// to hold the sources first, see [`QuadTree::fit_bounds`].
+    fn rebuild(&mut self, by_world: HashMap<SimWorld, Vec<(Vec2, f32)>>) -> Result<(), TreeError> {
+        self.trees.retain(|world, _| by_world.contains_key(world));
+        self.tracked.retain(|world, _| by_world.contains_key(world));
+        self.confined.retain(|world, _| by_world.contains_key(world));
+        for (world, mut world_sources) in by_world {
+            let q_tree = self.trees.entry(world).or_insert_with(|| build_tree([]));
+            q_tree.fit_bounds(&world_sources);
+            if let Some(rebuild_every) = self.rebuild_every {
+                let tracked = self.tracked.entry(world).or_default();
+                if tracked.updates >= rebuild_every || !tracked.update(q_tree, &world_sources)? {
+                    tracked.rebuild(q_tree, &world_sources)?;
+                }
+            } else {
+                morton_sort(&mut world_sources);
+                q_tree.rebuild_from(world_sources)?;
+            }
+            let confined = self.confined.entry(world).or_default();
+            *confined = if q_tree.can_shrink_root() {
+                *confined + 1
+            } else {
+                0
+            };
+            if *confined >= SHRINK_AFTER {
+                q_tree.shrink_root();
+                *confined = 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Acceleration at `position` in `world` from the tree last built for
+    /// it with the gravity of the `settings`, zero in a world without any
+    /// sources.
+    fn acceleration_at(
+        &self,
+        world: SimWorld,
+        position: Vec2,
+        theta_threshold: f32,
+        settings: &PhysicsSettings,
+    ) -> Vec2 {
+        self.trees.get(&world).map_or(Vec2::ZERO, |q_tree| {
+            q_tree.accumulate_acceleration(
+                position,
+                theta_threshold,
+                settings.g,
+                settings.softening,
+            )
+        })
+    }
+}
+
+impl TrackedBodies {
+    /// Moves the bodies of the `q_tree` to the `sources`, `false` when the
+    /// tree has to be rebuilt instead.
+    fn update(
+        &mut self,
+        q_tree: &mut QuadTree,
+        sources: &[(Vec2, f32)],
+    ) -> Result<bool, TreeError> {
+        let same_bodies = self.order.len() == sources.len()
+            && self
+                .bodies
+                .iter()
+                .zip(&self.order)
+                .all(|(body, &index)| body.1 == sources[index].1);
+        if !same_bodies {
+            return Ok(false);
+        }
+        let mut moves: Vec<BodyMove> = self
+            .bodies
+            .iter()
+            .zip(&self.order)
+            .zip(&self.handles)
+            .map(|((&(from, mass), &index), &handle)| BodyMove {
+                handle,
+                from,
+                to: sources[index].0,
+                mass,
+            })
+            .collect();
+        match q_tree.move_bodies(&mut moves) {
+            Ok(true) => {}
+            Ok(false) | Err(TreeError::NotFound { .. }) => return Ok(false),
+            Err(error) => return Err(error),
+        }
+        self.handles.clear();
+        self.handles.extend(moves.iter().map(|body| body.handle));
+        for (body, &index) in self.bodies.iter_mut().zip(&self.order) {
+            body.0 = sources[index].0;
+        }
+        self.updates += 1;
+        Ok(true)
+    }
+
+    /// Rebuilds the `q_tree` from the `sources`, inserted in Morton order,
+    /// and looks up the leaves they ended up in.
+    fn rebuild(&mut self, q_tree: &mut QuadTree, sources: &[(Vec2, f32)]) -> Result<(), TreeError> {
+        self.order = morton_order(sources);
+        self.bodies.clear();
+        self.bodies
+            .extend(self.order.iter().map(|&index| sources[index]));
+        q_tree.rebuild_from(self.bodies.iter().copied())?;
+        self.updates = 0;
+        match self
+            .bodies
+            .iter()
+            .map(|&(position, _)| q_tree.handle(position))
+            .collect()
+        {
+            Some(handles) => self.handles = handles,
+            // Bodies outside of the tree's bounds have no leaf, so it can't
+            // be updated.
+            None => self.order.clear(),
+        }
+        Ok(())
+    }
+}
+
+/// A body attracting others, as the force calculation sees it.
+#[derive(Debug, Clone, Copy)]
+pub struct GravitySource {
+    pub world: SimWorld,
+    pub position: Vec2,
+    pub mass: f32,
+    /// Merged with the other background bodies of its world into super
+    /// particles, see [`BackgroundAggregation`]
+    pub background: bool,
+}
+
+/// Splits the `sources` by world, with the background bodies of every
+/// world merged into `super_particle_count` super particles.
+fn sources_by_world(
+    sources: &[GravitySource],
+    super_particle_count: usize,
+) -> HashMap<SimWorld, Vec<(Vec2, f32)>> {
+    let mut by_world: HashMap<SimWorld, Vec<(Vec2, f32)>> = HashMap::default();
+    let mut background: HashMap<SimWorld, Vec<(Vec2, f32)>> = HashMap::default();
+    for source in sources {
+        let population = if source.background {
+            &mut background
+        } else {
+            &mut by_world
+        };
+        population
+            .entry(source.world)
+            .or_default()
+            .push((source.position, source.mass));
+    }
+    for (world, bodies) in background {
+        by_world
+            .entry(world)
+            .or_default()
+            .extend(super_particles(&bodies, super_particle_count));
+    }
+    by_world
+}
+
+/// Gravitational acceleration the `sources` cause at each of the `targets`,
+/// every target only attracted by the sources of its own world.
+///
+/// This is the whole force calculation of a step, integrators needing the
+/// forces at more than one state per step call it once for each. Fails when
+/// a world's tree can't be built, e.g. after the bodies blew up into NaN.
+/// The `relaxation` coarsens the gravity of the targets outside the view,
+/// except with the `decomposition`.
+#[allow(clippy::too_many_arguments)]
+pub fn gravity_accelerations(
+    sources: &[GravitySource],
+    targets: &[(SimWorld, Vec2)],
+    theta_threshold: f32,
+    relaxation: Option<&ViewRelaxation>,
+    settings: &PhysicsSettings,
+    super_particle_count: usize,
+    decomposition: Option<&DomainDecomposition>,
+    trees: &mut GravityTrees,
+    timings: &mut PhysicsTimings,
+) -> Result<Vec<Vec2>, TreeError> {
+    let by_world = sources_by_world(sources, super_particle_count);
+
+    if let Some(decomposition) = decomposition {
+        let start = Instant::now();
+        let mut accelerations = vec![Vec2::ZERO; targets.len()];
+        for (world, world_sources) in &by_world {
+            let (indices, positions): (Vec<usize>, Vec<Vec2>) = targets
+                .iter()
+                .enumerate()
+                .filter(|(_, (target_world, _))| target_world == world)
+                .map(|(index, (_, position))| (index, *position))
+                .unzip();
+            let world_accelerations =
+                decomposition.accelerations(world_sources, &positions, theta_threshold, settings);
+            for (index, acceleration) in indices.into_iter().zip(world_accelerations) {
+                accelerations[index] = acceleration;
+            }
+        }
+        timings.traversal += start.elapsed();
+        return Ok(accelerations);
+    }
+
+    let start = Instant::now();
+    trees.rebuild(by_world)?;
+    timings.tree_build += start.elapsed();
+
+    let start = Instant::now();
+    let accelerations = traverse_trees(trees, targets, theta_threshold, relaxation, settings);
+    timings.traversal += start.elapsed();
+    Ok(accelerations)
+}
+
+/// Acceleration at each of the `targets` from the `trees`. The traversals
+/// only read the trees, so the targets are split between the threads of
+/// Bevy's compute task pool.
+#[cfg(not(feature = "rayon"))]
+fn traverse_trees(
+    trees: &GravityTrees,
+    targets: &[(SimWorld, Vec2)],
+    theta_threshold: f32,
+    relaxation: Option<&ViewRelaxation>,
+    settings: &PhysicsSettings,
+) -> Vec<Vec2> {
+    targets
+        .par_chunk_map(
+            ComputeTaskPool::get_or_init(TaskPool::default),
+            TRAVERSAL_CHUNK,
+            |_, chunk| {
+                chunk
+                    .iter()
+                    .map(|&(world, position)| {
+                        let theta_threshold = relaxed_theta(relaxation, position, theta_threshold);
+                        trees.acceleration_at(world, position, theta_threshold, settings)
+                    })
+                    .collect::<Vec<_>>()
+            },
+        )
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Acceleration at each of the `targets` from the `trees`, with the targets
+/// split between the threads of rayon's pool, which steals work instead of
+/// handing out fixed chunks.
+#[cfg(feature = "rayon")]
+fn traverse_trees(
+    trees: &GravityTrees,
+    targets: &[(SimWorld, Vec2)],
+    theta_threshold: f32,
+    relaxation: Option<&ViewRelaxation>,
+    settings: &PhysicsSettings,
+) -> Vec<Vec2> {
+    use rayon::prelude::*;
+
+    targets
+        .par_iter()
+        .map(|&(world, position)| {
+            let theta_threshold = relaxed_theta(relaxation, position, theta_threshold);
+            trees.acceleration_at(world, position, theta_threshold, settings)
+        })
+        .collect()
+}
+
+/// Fills in the gravity part of the [`Acceleration`] of every body.
+///
+/// The trees are built once into [`GravityTrees`], after which the bodies
+/// walk them in parallel.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn apply_gravity(
+    quality: PhysicsQuality,
+    settings: Res<PhysicsSettings>,
+    mut timings: ResMut<PhysicsTimings>,
+    mut trees: ResMut<GravityTrees>,
+    mut failure: ResMut<TreeFailure>,
+    decomposition: Option<Res<DomainDecomposition>>,
+    determinism: Option<Res<Determinism>>,
+    watchdog: Option<ResMut<Watchdog>>,
+    relaxation: Option<Res<ViewRelaxation>>,
+    aggregation: Res<BackgroundAggregation>,
+    subquery: Query<(
+        Entity,
+        &Mass,
+        &Transform,
+        Option<&SimWorld>,
+        Has<Background>,
+    )>,
+    mut query: Query<(&Transform, &mut Acceleration, Option<&SimWorld>)>,
+) {
+    // Where the camera looks differs between runs
+    let relaxation = relaxation.filter(|_| determinism.is_none());
+    let mut ordered: Vec<_> = subquery.iter().collect();
+    if determinism.is_some() {
+        ordered.sort_by_key(|(entity, ..)| *entity);
     }
-    for (_, transform, mut velocity) in &mut query {
-        let bodies = q_tree.collect_bodies(transform.translation.xy(), 3.);
+    let sources: Vec<GravitySource> = ordered
+        .into_iter()
+        .map(|(_, mass, transform, world, background)| GravitySource {
+            world: world.copied().unwrap_or_default(),
+            position: transform.translation.xy(),
+            mass: mass.0,
+            background,
+        })
+        .collect();
+    let theta_threshold = quality.settings().theta_threshold;
 
-        for body in bodies {
-            if body.center_of_mass != transform.translation.xy() {
-                let dir_vec = body.center_of_mass - transform.translation.xy();
-                velocity.0 += (G * (body.mass / dir_vec.length_squared()) * dir_vec.normalize())
-                    * time.delta_secs();
+    // The tiles already spread the work between threads themselves.
+    if let Some(decomposition) = decomposition {
+        let mut bodies: Vec<_> = query.iter_mut().collect();
+        let targets: Vec<(SimWorld, Vec2)> = bodies
+            .iter()
+            .map(|(transform, _, world)| {
+                (
+                    world.copied().unwrap_or_default(),
+                    transform.translation.xy(),
+                )
+            })
+            .collect();
+        let accelerations = gravity_accelerations(
+            &sources,
+            &targets,
+            theta_threshold,
+            None,
+            &settings,
+            aggregation.super_particles,
+            Some(&decomposition),
+            &mut trees,
+            &mut timings,
+        );
+        if let Ok(accelerations) = accelerations {
+            failure.clear();
+            for ((_, acceleration, _), gravity) in bodies.iter_mut().zip(accelerations) {
+                acceleration.gravity = gravity;
             }
         }
+        return;
+    }
+
+    // Skipping bodies by the wall clock would make the runs differ.
+    if let Some(mut watchdog) = watchdog.filter(|_| determinism.is_none()) {
+        if !watchdog.allows(watchdog.tree_build) {
+            let count = query.iter().count();
+            watchdog.record(0, count);
+            return;
+        }
+        let start = Instant::now();
+        let built = trees.rebuild(sources_by_world(&sources, aggregation.super_particles));
+        watchdog.tree_build = start.elapsed();
+        timings.tree_build += watchdog.tree_build;
+        if let Err(error) = built {
+            failure.record(error);
+            return;
+        }
+        failure.clear();
+        apply_gravity_within(
+            &trees,
+            theta_threshold,
+            relaxation.as_deref(),
+            &settings,
+            &mut watchdog,
+            &mut timings,
+            query,
+        );
+        return;
+    }
+
+    let start = Instant::now();
+    let built = trees.rebuild(sources_by_world(&sources, aggregation.super_particles));
+    timings.tree_build += start.elapsed();
+    if let Err(error) = built {
+        failure.record(error);
+        return;
+    }
+    failure.clear();
+
+    // The positions are read out first, so the traversals don't hold on to
+    // the query and the results are written back in a second pass.
+    let start = Instant::now();
+    let mut bodies: Vec<_> = query.iter_mut().collect();
+    let targets: Vec<(SimWorld, Vec2)> = bodies
+        .iter()
+        .map(|(transform, _, world)| {
+            (
+                world.copied().unwrap_or_default(),
+                transform.translation.xy(),
+            )
+        })
+        .collect();
+    let accelerations = traverse_trees(
+        &trees,
+        &targets,
+        theta_threshold,
+        relaxation.as_deref(),
+        &settings,
+    );
+    for ((_, acceleration, _), gravity) in bodies.iter_mut().zip(accelerations) {
+        acceleration.gravity = gravity;
+    }
+    timings.traversal += start.elapsed();
+}
+
+/// Walks the trees for the gravity of the bodies in chunks, as long as the
+/// [`Watchdog`] expects the next chunk to fit into the frame.
+fn apply_gravity_within(
+    trees: &GravityTrees,
+    theta_threshold: f32,
+    relaxation: Option<&ViewRelaxation>,
+    settings: &PhysicsSettings,
+    watchdog: &mut Watchdog,
+    timings: &mut PhysicsTimings,
+    mut query: Query<(&Transform, &mut Acceleration, Option<&SimWorld>)>,
+) {
+    let mut bodies: Vec<_> = query.iter_mut().collect();
+    let targets: Vec<(SimWorld, Vec2)> = bodies
+        .iter()
+        .map(|(transform, _, world)| {
+            (
+                world.copied().unwrap_or_default(),
+                transform.translation.xy(),
+            )
+        })
+        .collect();
+    let order: Vec<usize> = watchdog.order(targets.len()).collect();
+
+    let mut computed = 0;
+    // Chunks take about as long as the last one.
+    let mut chunk_cost = Duration::ZERO;
+    for chunk in order.chunks(WATCHDOG_CHUNK) {
+        if !watchdog.allows(chunk_cost) {
+            break;
+        }
+        let start = Instant::now();
+        let chunk_targets: Vec<_> = chunk.iter().map(|&index| targets[index]).collect();
+        let accelerations =
+            traverse_trees(trees, &chunk_targets, theta_threshold, relaxation, settings);
+        for (&index, gravity) in chunk.iter().zip(accelerations) {
+            bodies[index].1.gravity = gravity;
+        }
+        chunk_cost = start.elapsed();
+        timings.traversal += chunk_cost;
+        computed += chunk.len();
+    }
+    watchdog.record(computed, targets.len());
+}
+
+/// Moves the bodies by one classic fourth order Runge-Kutta step of `dt`,
+/// see [`rk4_step`], with the `gravity` at every stage plus the `others`
+/// accelerations held for the whole step. Bodies without `others` don't
+/// move.
+///
+/// Returns the gravity and the whole acceleration of every body at the
+/// start of the step. Fails with the first error of the `gravity`, leaving
+/// the `positions` and `velocities` half stepped, so they should be copies.
+pub fn rk4_gravity_step(
+    positions: &mut [Vec2],
+    velocities: &mut [Vec2],
+    dt: f32,
+    others: &[Option<Vec2>],
+    mut gravity: impl FnMut(&[Vec2]) -> Result<Vec<Vec2>, TreeError>,
+) -> Result<(Vec<Vec2>, Vec<Vec2>), TreeError> {
+    let mut gravities = Vec::new();
+    let mut error = None;
+    let first = rk4_step(positions, velocities, dt, |positions| {
+        let gravity = gravity(positions).unwrap_or_else(|tree_error| {
+            // The remaining stages are wasted, but the step is thrown away.
+            error.get_or_insert(tree_error);
+            vec![Vec2::ZERO; positions.len()]
+        });
+        if gravities.is_empty() {
+            gravities.clone_from(&gravity);
+        }
+        gravity
+            .into_iter()
+            .zip(others)
+            .map(|(gravity, other)| other.map_or(Vec2::ZERO, |other| gravity + other))
+            .collect()
+    });
+    match error {
+        Some(error) => Err(error),
+        None => Ok((gravities, first)),
+    }
+}
+
+/// Moves the bodies by a classic fourth order Runge-Kutta step, evaluating
+/// the gravity of all of them four times. The other accelerations are held
+/// at what the force systems set for the whole step.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub(crate) fn integrate_rk4(
+    time: Res<Time>,
+    settings: (
+        PhysicsQuality,
+        Res<PhysicsSettings>,
+        Res<BackgroundAggregation>,
+    ),
+    mut timings: ResMut<PhysicsTimings>,
+    mut trees: ResMut<GravityTrees>,
+    mut failure: ResMut<TreeFailure>,
+    decomposition: Option<Res<DomainDecomposition>>,
+    determinism: Option<Res<Determinism>>,
+    relaxation: Option<Res<ViewRelaxation>>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Transform,
+            Option<&Mass>,
+            Option<(&mut Velocity, &mut Acceleration, &mut LastAcceleration)>,
+            Option<&SimWorld>,
+            Has<Background>,
+        ),
+        Or<(With<Mass>, With<Velocity>)>,
+    >,
+) {
+    let (quality, physics, aggregation) = settings;
+    // Where the camera looks differs between runs
+    let relaxation = relaxation.filter(|_| determinism.is_none());
+    let mut bodies: Vec<_> = query.iter_mut().collect();
+    if determinism.is_some() {
+        bodies.sort_by_key(|(entity, ..)| *entity);
+    }
+    let worlds: Vec<SimWorld> = bodies
+        .iter()
+        .map(|body| body.4.copied().unwrap_or_default())
+        .collect();
+    let mut positions: Vec<Vec2> = bodies.iter().map(|body| body.1.translation.xy()).collect();
+    let mut velocities: Vec<Vec2> = bodies
+        .iter()
+        .map(|body| body.3.as_ref().map_or(Vec2::ZERO, |moving| moving.0 .0))
+        .collect();
+    // Bodies which don't move get no acceleration, so they stay in place.
+    let others: Vec<Option<Vec2>> = bodies
+        .iter()
+        .map(|body| {
+            body.3
+                .as_ref()
+                .map(|(_, acceleration, _)| acceleration.total() - acceleration.gravity)
+        })
+        .collect();
+    let sources = |positions: &[Vec2]| -> Vec<GravitySource> {
+        bodies
+            .iter()
+            .zip(positions)
+            .filter_map(|(body, &position)| {
+                body.2.map(|mass| GravitySource {
+                    world: body.4.copied().unwrap_or_default(),
+                    position,
+                    mass: mass.0,
+                    background: body.5,
+                })
+            })
+            .collect()
+    };
+
+    let theta_threshold = quality.settings().theta_threshold;
+    let stepped = rk4_gravity_step(
+        &mut positions,
+        &mut velocities,
+        time.delta_secs(),
+        &others,
+        |positions| {
+            let targets: Vec<(SimWorld, Vec2)> = worlds
+                .iter()
+                .copied()
+                .zip(positions.iter().copied())
+                .collect();
+            gravity_accelerations(
+                &sources(positions),
+                &targets,
+                theta_threshold,
+                relaxation.as_deref(),
+                &physics,
+                aggregation.super_particles,
+                decomposition.as_deref(),
+                &mut trees,
+                &mut timings,
+            )
+        },
+    );
+    let (gravities, first) = match stepped {
+        Ok(accelerations) => accelerations,
+        Err(error) => {
+            failure.record(error);
+            return;
+        }
+    };
+    failure.clear();
+
+    for (((body, position), velocity), (gravity, first)) in bodies
+        .iter_mut()
+        .zip(positions)
+        .zip(velocities)
+        .zip(gravities.into_iter().zip(first))
+    {
+        let Some((moving_velocity, acceleration, last)) = &mut body.3 else {
+            continue;
+        };
+        body.1.translation.x = position.x;
+        body.1.translation.y = position.y;
+        moving_velocity.0 = velocity;
+        acceleration.gravity = gravity;
+        last.0 = Some(first);
+    }
+}
+
+/// Runs the [`PhysicsSubstep`] schedule, each run seeing an equal part of
+/// the fixed step's time. The remaining substeps are skipped once the
+/// gravity trees fail to build.
+fn run_substeps(world: &mut World) {
+    let quality = *world.resource::<Quality>();
+    let substeps = world
+        .resource::<PhysicsConfig>()
+        .quality_settings(quality)
+        .substeps
+        .max(1);
+    let mut timings = world.resource_mut::<PhysicsTimings>();
+    timings.tree_build = Duration::ZERO;
+    timings.traversal = Duration::ZERO;
+    timings.integration = Duration::ZERO;
+    // The collision systems add to it on the steps they run in.
+    timings.collision = Duration::ZERO;
+
+    let step_time = *world.resource::<Time>();
+    let step_start = step_time.elapsed() - step_time.delta();
+    for step in 1..=substeps {
+        let mut time = Time::<()>::default();
+        time.advance_to(step_start + step_time.delta() * (step - 1) / substeps);
+        time.advance_to(step_start + step_time.delta() * step / substeps);
+        world.insert_resource(time);
+        world.run_schedule(PhysicsSubstep);
+        if world.resource::<TreeFailure>().error.is_some() {
+            break;
+        }
+    }
+    world.insert_resource(step_time);
+}
+
+/// Whether the bodies are stepped by [`integrate_rk4`] rather than the
+/// systems evaluating the forces once a step.
+fn uses_rk4(integrator: Res<IntegratorKind>) -> bool {
+    *integrator == IntegratorKind::Rk4
+}
+
+/// Tunable parameters of the simulation, set up with the builder methods
+/// of the [`PhysicsPlugin`].
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsSettings {
+    /// Gravitational constant of the force calculation, and of the orbits
+    /// and energies computed from it
+    pub g: f32,
+    /// Barnes-Hut threshold replacing the one of the [`Quality`] preset,
+    /// below the user settings and the scenario in the [`PhysicsConfig`]
+    pub theta_threshold: Option<f32>,
+    /// Plummer softening length of the force calculation, zero for plain
+    /// Newtonian gravity
+    pub softening: f32,
+    /// Positions a [`Trail`](crate::trails::Trail) keeps
+    pub trail_length: usize,
+    /// Simulated seconds between the positions a trail records
+    pub trail_interval: f32,
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        PhysicsSettings {
+            g: G,
+            theta_threshold: None,
+            softening: 0.,
+            trail_length: 120,
+            trail_interval: 0.05,
+        }
     }
 }
 
-pub struct PhysicsPlugin;
+/// Simulates the bodies, moving them with the integrator it was built with,
+/// velocity Verlet by default, and the [`PhysicsSettings`] it was built
+/// with.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PhysicsPlugin {
+    integrator: IntegratorKind,
+    settings: PhysicsSettings,
+}
+
+impl PhysicsPlugin {
+    pub fn with_integrator(mut self, integrator: IntegratorKind) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    /// Gravitational constant, [`G`] by default.
+    pub fn with_g(mut self, g: f32) -> Self {
+        self.settings.g = g;
+        self
+    }
+
+    /// Barnes-Hut threshold, lower is more accurate and slower. By default
+    /// the [`Quality`] preset's.
+    pub fn with_theta(mut self, theta_threshold: f32) -> Self {
+        self.settings.theta_threshold = Some(theta_threshold);
+        self
+    }
+}
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_objects)
-            .add_systems(Update, (update_position, apply_acceleration).chain());
+        app.init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .init_resource::<Inspector>()
+            .init_resource::<Scenarios>()
+            .init_resource::<SimRng>()
+            .init_resource::<PhysicsTimings>()
+            .init_resource::<GravityTrees>()
+            .init_resource::<TreeFailure>()
+            .init_resource::<Quality>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<BackgroundAggregation>()
+            .init_resource::<RandomDisc>()
+            .init_resource::<StableDisc>()
+            .init_resource::<TickRate>()
+            .init_resource::<CollisionMode>()
+            .init_resource::<Density>()
+            .init_resource::<ForceRegistry>()
+            .init_resource::<AnalysisRegistry>()
+            .init_resource::<Cadences>()
+            .insert_resource(self.settings)
+            .insert_resource(self.integrator)
+            .init_state::<SimState>()
+            .add_event::<Undock>()
+            .add_event::<Collision>()
+            .add_event::<Impulse>()
+            .add_event::<RestartScenario>()
+            .add_event::<BudgetExceeded>()
+            .register_scenario(
+                "Random disc",
+                "2000 bodies circling a heavy central body",
+                spawn_objects,
+            )
+            .register_scenario(
+                "Stable disc",
+                "2000 bodies on the orbits the gravity of the whole disc calls for",
+                spawn_stable_disc,
+            )
+            .register_scenario(
+                "Disc in a halo",
+                "500 bodies circling a heavy central body inside a halo of 20000 background bodies",
+                spawn_halo,
+            )
+            .localize_scenario("Random disc", "scenario-random-disc")
+            .localize_scenario("Stable disc", "scenario-stable-disc")
+            .localize_scenario("Disc in a halo", "scenario-halo")
+            .add_systems(Startup, spawn_camera)
+            .add_systems(
+                PhysicsSubstep,
+                (
+                    (
+                        update_position.run_if(not(resource_exists::<FixedPoint>)),
+                        update_fixed_position.run_if(resource_exists::<FixedPoint>),
+                    ),
+                    apply_gravity.run_if(on_cadence(Subsystem::Forces)),
+                    (
+                        integrate_acceleration.run_if(not(resource_exists::<FixedPoint>)),
+                        integrate_fixed_acceleration.run_if(resource_exists::<FixedPoint>),
+                    )
+                        .run_if(tree_built),
+                )
+                    .chain()
+                    .run_if(not(uses_rk4)),
+            )
+            .add_systems(PhysicsSubstep, integrate_rk4.run_if(uses_rk4))
+            .add_systems(
+                OnEnter(SimState::Loading),
+                (resolve_physics_config, load_scenario).chain(),
+            )
+            .add_systems(OnEnter(SimState::Paused), pause_time)
+            .add_systems(OnExit(SimState::Paused), resume_time)
+            .add_systems(OnEnter(SimState::Editing), pause_time)
+            .add_systems(OnExit(SimState::Editing), resume_time)
+            .add_systems(
+                Update,
+                (
+                    toggle_pause,
+                    toggle_editing,
+                    (request_restart, restart_scenario).chain(),
+                    request_undock,
+                ),
+            )
+            .add_systems(Update, apply_tick_rate.run_if(resource_changed::<TickRate>))
+            .add_systems(Update, adjust_sim_rate.run_if(resource_exists::<SimRate>))
+            .add_systems(FixedFirst, restore_physics_translations)
+            .add_systems(
+                FixedUpdate,
+                (
+                    count_step,
+                    resolve_relative_spawns,
+                    equilibrate,
+                    clear_accelerations,
+                    apply_impulses,
+                    apply_drag,
+                    steer_autopilots,
+                    apply_tethers,
+                    apply_custom_forces,
+                    load_fixed_states.run_if(resource_exists::<FixedPoint>),
+                    run_substeps,
+                    detect_collisions.run_if(on_cadence(Subsystem::Collisions)),
+                    dock_bodies.run_if(merges_collisions),
+                    merge_bodies.run_if(merges_collisions),
+                    bounce_bodies.run_if(bounces_collisions.and(on_cadence(Subsystem::Collisions))),
+                    undock_bodies,
+                    update_radii,
+                    run_analyses,
+                )
+                    .chain()
+                    .run_if(in_state(SimState::Running)),
+            )
+            .add_systems(FixedLast, record_physics_translations)
+            .add_systems(
+                RunFixedMainLoop,
+                interpolate_translations.in_set(RunFixedMainLoopSystem::AfterFixedMainLoop),
+            )
+            .add_systems(Update, draw_tethers)
+            .add_systems(PostUpdate, enforce_budget.run_if(resource_exists::<Budget>))
+            .add_systems(
+                First,
+                start_watchdog_frame.run_if(resource_exists::<Watchdog>),
+            )
+            .add_systems(
+                PostUpdate,
+                track_view
+                    .run_if(resource_exists::<ViewRelaxation>)
+                    .after(TransformSystem::TransformPropagate),
+            )
+            .add_systems(
+                PostUpdate,
+                update_radii.before(TransformSystem::TransformPropagate),
+            )
+            .add_systems(
+                FixedUpdate,
+                compare_ephemerides
+                    .after(run_substeps)
+                    .run_if(resource_exists::<EphemerisComparison>)
+                    .run_if(in_state(SimState::Running)),
+            )
+            .add_systems(
+                FixedUpdate,
+                measure_clustering
+                    .after(run_substeps)
+                    .run_if(resource_exists::<ClusteringStatistics>)
+                    .run_if(in_state(SimState::Running)),
+            )
+            .add_systems(
+                OnEnter(SimState::Loading),
+                reset_drift_reference.run_if(resource_exists::<DriftCorrection>),
+            )
+            .add_systems(
+                FixedUpdate,
+                correct_drift
+                    .after(run_substeps)
+                    .run_if(resource_exists::<DriftCorrection>)
+                    .run_if(in_state(SimState::Running)),
+            )
+            .add_systems(
+                Update,
+                scale_body_count
+                    .run_if(resource_exists::<BodyCountController>)
+                    .run_if(not(resource_exists::<Determinism>))
+                    .run_if(in_state(SimState::Running)),
+            )
+            .add_systems(
+                FixedUpdate,
+                run_scheduled_export
+                    .after(run_substeps)
+                    .run_if(resource_exists::<ScheduledExport>)
+                    .run_if(in_state(SimState::Running)),
+            );
     }
 }
@@ -0,0 +1,122 @@
+use crate::body_count::Tracer;
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::{Mass, Velocity};
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+/// Group of bodies that are hidden or shown together, told apart by their
+/// components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Population {
+    /// Bodies with a [`Mass`], the ones attracting the others
+    Massive,
+    /// Bodies without a mass placed by the scenario
+    Massless,
+    /// [`Tracer`]s added to keep the frame rate
+    Tracers,
+}
+
+impl Population {
+    pub const ALL: [Population; 3] = [
+        Population::Massive,
+        Population::Massless,
+        Population::Tracers,
+    ];
+
+    /// Population of a body with or without a mass, which is a [`Tracer`]
+    /// or not.
+    pub fn of(massive: bool, tracer: bool) -> Self {
+        match (massive, tracer) {
+            (true, _) => Population::Massive,
+            (false, true) => Population::Tracers,
+            (false, false) => Population::Massless,
+        }
+    }
+}
+
+/// Which populations are drawn. Each is hidden or shown with 1, 2 and 3,
+/// and 0 solos them one after the other (with the default input map).
+///
+/// Only the drawing is affected, hidden bodies are simulated and attract
+/// the others as before.
+#[derive(Resource, Debug, Default)]
+pub struct PopulationVisibility {
+    pub hidden: HashSet<Population>,
+    /// Population drawn alone, whatever is hidden
+    pub solo: Option<Population>,
+}
+
+impl PopulationVisibility {
+    pub fn is_visible(&self, population: Population) -> bool {
+        match self.solo {
+            Some(solo) => solo == population,
+            None => !self.hidden.contains(&population),
+        }
+    }
+
+    /// Hides the `population` if it is shown, shows it otherwise.
+    pub fn toggle(&mut self, population: Population) {
+        if !self.hidden.remove(&population) {
+            self.hidden.insert(population);
+        }
+    }
+
+    /// Solos the next population, or none after the last.
+    pub fn cycle_solo(&mut self) {
+        let next = match self.solo {
+            None => 0,
+            Some(solo) => Population::ALL.iter().position(|&p| p == solo).unwrap() + 1,
+        };
+        self.solo = Population::ALL.get(next).copied();
+    }
+}
+
+fn control_populations(actions: Actions, mut visibility: ResMut<PopulationVisibility>) {
+    for (action, population) in [
+        (Action::ToggleMassive, Population::Massive),
+        (Action::ToggleMassless, Population::Massless),
+        (Action::ToggleTracers, Population::Tracers),
+    ] {
+        if actions.just_pressed(action) {
+            visibility.toggle(population);
+        }
+    }
+    if actions.just_pressed(Action::CycleSolo) {
+        visibility.cycle_solo();
+    }
+}
+
+/// Hides the bodies of the hidden populations, including the ones spawned
+/// since. Docked parts follow the composite they are a child of.
+#[allow(clippy::type_complexity)]
+fn apply_population_visibility(
+    populations: Res<PopulationVisibility>,
+    mut bodies: Query<
+        (&mut Visibility, Has<Mass>, Has<Tracer>),
+        (Or<(With<Mass>, With<Velocity>)>, Without<Parent>),
+    >,
+) {
+    for (mut visibility, massive, tracer) in &mut bodies {
+        let shown = populations.is_visible(Population::of(massive, tracer));
+        visibility.set_if_neq(if shown {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+/// Shows or hides whole populations of bodies, see
+/// [`PopulationVisibility`].
+pub struct PopulationPlugin;
+
+impl Plugin for PopulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PopulationVisibility>()
+            .init_resource::<InputMap>()
+            .add_systems(
+                Update,
+                (control_populations, apply_population_visibility).chain(),
+            );
+    }
+}
@@ -0,0 +1,344 @@
+use crate::distributions::Distribution;
+use crate::equilibrium::circular_speed;
+use crate::physics_config::{PhysicsOverrides, PhysicsQuality};
+use crate::physics_plugin::{build_fitted_tree, BodyMaterial, Mass, PhysicsSettings, Velocity};
+use crate::radius::BodyDensity;
+use crate::scenario::{RegisterScenario, SimRng};
+use crate::theme::Theme;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Planets of the solar system as their distance from the sun in AU, their
+/// mass as a fraction of the sun's and the radius they are drawn at.
+const PLANETS: [(f32, f32, f32); 8] = [
+    (0.387, 1.66e-7, 2.),
+    (0.723, 2.45e-6, 3.),
+    (1., 3.0e-6, 3.),
+    (1.524, 3.2e-7, 2.),
+    (5.2, 9.55e-4, 8.),
+    (9.58, 2.86e-4, 7.),
+    (19.2, 4.37e-5, 5.),
+    (30.1, 5.15e-5, 5.),
+];
+
+/// Ready-made initial conditions, each registered as a scenario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Preset {
+    SolarSystem,
+    BinaryStar,
+    GalaxyDisc,
+}
+
+impl Preset {
+    /// Name of the scenario the preset is registered as.
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::SolarSystem => "Solar system",
+            Preset::BinaryStar => "Binary star",
+            Preset::GalaxyDisc => "Galaxy disc",
+        }
+    }
+
+    /// Parses the id used on the command line, e.g. `solar-system`.
+    pub fn parse(id: &str) -> Option<Self> {
+        match id {
+            "solar-system" => Some(Preset::SolarSystem),
+            "binary-star" => Some(Preset::BinaryStar),
+            "galaxy-disc" => Some(Preset::GalaxyDisc),
+            _ => None,
+        }
+    }
+}
+
+/// Parameters of the "Solar system" preset. Distances are compressed, a
+/// planet `d` AU out orbits at `scale * sqrt(d)`, so the outer planets
+/// still fit the view, with the speed of a circular orbit there.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SolarSystem {
+    pub sun_mass: f32,
+    pub scale: f32,
+    /// Asteroids between Mars and Jupiter
+    pub asteroids: usize,
+}
+
+impl Default for SolarSystem {
+    fn default() -> Self {
+        SolarSystem {
+            sun_mass: 100_000_000_000.,
+            scale: 100.,
+            asteroids: 300,
+        }
+    }
+}
+
+/// Parameters of the "Binary star" preset, two stars on circular orbits
+/// around each other with a disc circling both further out.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BinaryStar {
+    pub primary_mass: f32,
+    /// Mass of the secondary star as a fraction of the primary's
+    pub mass_ratio: f32,
+    pub separation: f32,
+    pub disc_count: usize,
+    /// Distance of the disc bodies from the center of mass, in
+    /// separations. Orbits closer than about 2.5 aren't stable.
+    pub disc_radius: Distribution,
+    pub disc_mass: Distribution,
+}
+
+impl Default for BinaryStar {
+    fn default() -> Self {
+        BinaryStar {
+            primary_mass: 60_000_000_000.,
+            mass_ratio: 0.5,
+            separation: 60.,
+            disc_count: 1_500,
+            disc_radius: Distribution::PowerLaw {
+                min: 2.5,
+                max: 6.,
+                exponent: -1.,
+            },
+            disc_mass: Distribution::Uniform {
+                min: 1_000_000.,
+                max: 3_000_000.,
+            },
+        }
+    }
+}
+
+/// Parameters of the "Galaxy disc" preset, a bulge inside a disc whose
+/// surface density falls off exponentially, every body on the circular
+/// orbit the gravity of the whole galaxy calls for.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GalaxyDisc {
+    pub count: usize,
+    pub bulge_mass: f32,
+    /// Mass of the disc, split evenly between its bodies
+    pub disc_mass: f32,
+    /// Distance over which the surface density falls by a factor of e
+    pub scale_length: f32,
+    /// Distance the disc is cut off at, in scale lengths
+    pub cutoff: f32,
+}
+
+impl Default for GalaxyDisc {
+    fn default() -> Self {
+        GalaxyDisc {
+            count: 3_000,
+            bulge_mass: 30_000_000_000.,
+            disc_mass: 60_000_000_000.,
+            scale_length: 80.,
+            cutoff: 4.,
+        }
+    }
+}
+
+/// The body material and the circle mesh every preset draws its bodies
+/// with.
+fn body_assets(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    theme: &Theme,
+) -> (Handle<Mesh>, Handle<ColorMaterial>) {
+    let circle = meshes.add(Circle::new(1.));
+    let material = materials.add(ColorMaterial::from(theme.body()));
+    commands.insert_resource(BodyMaterial(material.clone()));
+    (circle, material)
+}
+
+/// Counterclockwise velocity of a circular orbit at `position` around a
+/// `mass` at the origin, with the gravitational constant `g`.
+fn orbital_velocity(position: Vec2, mass: f32, g: f32) -> Vec2 {
+    let distance = position.length();
+    if distance == 0. {
+        return Vec2::ZERO;
+    }
+    position.perp() / distance * (g * mass / distance).sqrt()
+}
+
+fn spawn_solar_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+    settings: Res<PhysicsSettings>,
+    system: Res<SolarSystem>,
+    mut rng: ResMut<SimRng>,
+) {
+    let rng = &mut rng.rng;
+    let (circle, material) = body_assets(&mut commands, &mut meshes, &mut materials, &theme);
+    let mut spawn = |position: Vec2, mass: f32, radius: f32| {
+        commands.spawn((
+            Velocity(orbital_velocity(position, system.sun_mass, settings.g)),
+            Mass(mass),
+            BodyDensity::sized(mass, radius),
+            Mesh2d(circle.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::from_translation(position.extend(0.)),
+        ));
+    };
+
+    spawn(Vec2::ZERO, system.sun_mass, 25.);
+    for (distance, mass_fraction, radius) in PLANETS {
+        let direction = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU));
+        let position = direction * system.scale * distance.sqrt();
+        spawn(position, system.sun_mass * mass_fraction, radius);
+    }
+    for _ in 0..system.asteroids {
+        let direction = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU));
+        let distance = rng.random_range(2.2_f32..3.3).sqrt() * system.scale;
+        let mass = system.sun_mass * rng.random_range(1e-9..1e-8);
+        spawn(direction * distance, mass, 1.);
+    }
+}
+
+fn spawn_binary_star(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+    settings: Res<PhysicsSettings>,
+    binary: Res<BinaryStar>,
+    mut rng: ResMut<SimRng>,
+) {
+    let rng = &mut rng.rng;
+    let (circle, material) = body_assets(&mut commands, &mut meshes, &mut materials, &theme);
+
+    // Both stars circle the center of mass at the origin.
+    let primary_mass = binary.primary_mass;
+    let secondary_mass = binary.primary_mass * binary.mass_ratio;
+    let total_mass = primary_mass + secondary_mass;
+    let relative_speed = (settings.g * total_mass / binary.separation).sqrt();
+    for (mass, side) in [(primary_mass, -1.), (secondary_mass, 1.)] {
+        let other_fraction = (total_mass - mass) / total_mass;
+        commands.spawn((
+            Velocity(Vec2::Y * side * relative_speed * other_fraction),
+            Mass(mass),
+            BodyDensity::sized(mass, binary.separation / 5. * (mass / primary_mass).sqrt()),
+            Mesh2d(circle.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::from_translation(
+                (Vec2::X * side * binary.separation * other_fraction).extend(0.),
+            ),
+        ));
+    }
+
+    for _ in 0..binary.disc_count {
+        let direction = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU));
+        let position = direction * binary.disc_radius.sample(rng) * binary.separation;
+        commands.spawn((
+            Velocity(orbital_velocity(position, total_mass, settings.g)),
+            Mass(binary.disc_mass.sample(rng)),
+            Mesh2d(circle.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::from_translation(position.extend(0.)),
+        ));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_galaxy_disc(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    theme: Res<Theme>,
+    quality: PhysicsQuality,
+    settings: Res<PhysicsSettings>,
+    galaxy: Res<GalaxyDisc>,
+    mut rng: ResMut<SimRng>,
+) {
+    let rng = &mut rng.rng;
+    let (circle, material) = body_assets(&mut commands, &mut meshes, &mut materials, &theme);
+
+    let max_radius = galaxy.cutoff * galaxy.scale_length;
+    let mass = galaxy.disc_mass / galaxy.count.max(1) as f32;
+    let bodies: Vec<(Vec2, f32)> = (0..galaxy.count)
+        .map(|_| {
+            // An exponential surface density puts the radii on a gamma
+            // distribution of shape 2, the sum of two exponential draws.
+            let radius = loop {
+                let (u1, u2): (f32, f32) = (1. - rng.random::<f32>(), 1. - rng.random::<f32>());
+                let radius = -galaxy.scale_length * (u1 * u2).ln();
+                if radius <= max_radius {
+                    break radius;
+                }
+            };
+            let direction = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU));
+            (direction * radius, mass)
+        })
+        .collect();
+
+    // The inner disc weighs as much as the bulge, its own gravity shapes
+    // the rotation curve.
+    let theta_threshold = quality.settings().theta_threshold;
+    let q_tree = build_fitted_tree(
+        &bodies
+            .iter()
+            .copied()
+            .chain([(Vec2::ZERO, galaxy.bulge_mass)])
+            .collect::<Vec<_>>(),
+    );
+
+    commands.spawn((
+        Velocity(Vec2::ZERO),
+        Mass(galaxy.bulge_mass),
+        BodyDensity::sized(galaxy.bulge_mass, galaxy.scale_length / 8.),
+        Mesh2d(circle.clone()),
+        MeshMaterial2d(material.clone()),
+        Transform::default(),
+    ));
+    for (position, mass) in bodies {
+        let speed = circular_speed(&q_tree, position, Vec2::ZERO, theta_threshold, settings.g);
+        commands.spawn((
+            Velocity(position.perp().normalize_or_zero() * speed),
+            Mass(mass),
+            // The bodies stand for many stars each, drawn as points.
+            BodyDensity::sized(mass, 1.5),
+            Mesh2d(circle.clone()),
+            MeshMaterial2d(material.clone()),
+            Transform::from_translation(position.extend(0.)),
+        ));
+    }
+}
+
+/// Registers the [`Preset`]s as scenarios.
+pub struct PresetsPlugin;
+
+impl Plugin for PresetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SolarSystem>()
+            .init_resource::<BinaryStar>()
+            .init_resource::<GalaxyDisc>()
+            .init_resource::<Theme>()
+            .init_resource::<SimRng>()
+            .register_scenario(
+                Preset::SolarSystem.name(),
+                "The sun and its planets, the distances compressed to fit the view",
+                spawn_solar_system,
+            )
+            .register_scenario(
+                Preset::BinaryStar.name(),
+                "Two stars circling each other inside a disc",
+                spawn_binary_star,
+            )
+            .register_scenario(
+                Preset::GalaxyDisc.name(),
+                "A bulge in an exponential disc on its circular orbits",
+                spawn_galaxy_disc,
+            )
+            .localize_scenario(Preset::SolarSystem.name(), "scenario-solar-system")
+            .localize_scenario(Preset::BinaryStar.name(), "scenario-binary-star")
+            .localize_scenario(Preset::GalaxyDisc.name(), "scenario-galaxy-disc")
+            // The stars orbit each other in about a second, which takes
+            // more than one step a frame to follow.
+            .override_scenario_physics(
+                Preset::BinaryStar.name(),
+                PhysicsOverrides {
+                    substeps: Some(4),
+                    ..Default::default()
+                },
+            );
+    }
+}
@@ -0,0 +1,108 @@
+use crate::forces::Drag;
+use crate::integrator::IntegratorKind;
+use crate::physics_config::{PhysicsConfig, PhysicsQuality};
+use crate::physics_plugin::{Mass, PhysicsSettings, Velocity};
+use crate::quality::Quality;
+use crate::simulation::{Simulation, SimulationConfig};
+use crate::theme::Theme;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+
+/// Number of the most massive bodies copied into the simulation of a
+/// trajectory preview besides the previewed body, lighter ones barely
+/// change its path.
+pub const RELEVANT_BODIES: usize = 32;
+
+/// Path the `target` body is predicted to take, drawn ahead of it. The
+/// prediction runs in a [`Simulation`] with the target and the heaviest
+/// bodies of its world.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct TrajectoryPreview {
+    pub target: Option<Entity>,
+    /// How far ahead to predict in seconds
+    pub duration: f32,
+    pub steps: usize,
+}
+
+impl Default for TrajectoryPreview {
+    fn default() -> Self {
+        TrajectoryPreview {
+            target: None,
+            duration: 5.,
+            steps: 100,
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn draw_trajectory_preview(
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+    quality: PhysicsQuality,
+    settings: Res<PhysicsSettings>,
+    integrator: Res<IntegratorKind>,
+    preview: Res<TrajectoryPreview>,
+    bodies: Query<(
+        Entity,
+        &Transform,
+        &Velocity,
+        Option<&Mass>,
+        Option<&Drag>,
+        Option<&SimWorld>,
+    )>,
+) {
+    let Some(target) = preview.target else {
+        return;
+    };
+    let Ok(target_body) = bodies.get(target) else {
+        return;
+    };
+    let (_, target_transform, .., target_world) = target_body;
+
+    let mut relevant: Vec<_> = bodies
+        .iter()
+        .filter(|(entity, _, _, mass, _, world)| {
+            *entity != target && mass.is_some() && *world == target_world
+        })
+        .collect();
+    relevant.sort_by(|a, b| {
+        let mass = |mass: Option<&Mass>| mass.map_or(0., |mass| mass.0);
+        mass(b.3).total_cmp(&mass(a.3))
+    });
+    relevant.truncate(RELEVANT_BODIES);
+
+    let mut shadow = Simulation::capture(
+        relevant.into_iter().chain([target_body]).map(
+            |(entity, transform, velocity, mass, drag, _)| {
+                (entity, transform, velocity, mass, drag)
+            },
+        ),
+        SimulationConfig {
+            g: settings.g,
+            theta_threshold: quality.settings().theta_threshold,
+            integrator: *integrator,
+            softening: settings.softening,
+        },
+    );
+    let dt = preview.duration / preview.steps as f32;
+    let path = shadow.trajectory(target, preview.steps, dt);
+    gizmos.linestrip_2d(
+        std::iter::once(target_transform.translation.xy()).chain(path),
+        theme.accent(),
+    );
+}
+
+/// Draws the [`TrajectoryPreview`] once a target is set.
+pub struct PreviewPlugin;
+
+impl Plugin for PreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrajectoryPreview>()
+            .init_resource::<Quality>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<IntegratorKind>()
+            .init_resource::<PhysicsSettings>()
+            .init_resource::<Theme>()
+            .add_systems(Update, draw_trajectory_preview);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_config::{PhysicsConfig, PhysicsQuality};
+use crate::physics_plugin::{point_mass_acceleration, MainCamera, Mass, PhysicsSettings};
+use crate::quadtree::QuadTree;
+use crate::quality::Quality;
+use crate::theme::Theme;
+use bevy::prelude::*;
+
+/// Length of the drawn acceleration arrow, the arrow only shows direction.
+const ARROW_LENGTH: f32 = 60.;
+
+/// Point whose Barnes-Hut traversal is shown, toggled with `P` and placed
+/// with a left click while active (with the default input map).
+#[derive(Resource, Debug, Default)]
+pub struct Probe {
+    pub active: bool,
+    pub position: Option<Vec2>,
+}
+
+/// Marks the filled squares drawn for the accepted cells.
+#[derive(Component)]
+struct ProbeCell;
+
+fn control_probe(
+    actions: Actions,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut probe: ResMut<Probe>,
+) {
+    if actions.just_pressed(Action::ToggleProbe) {
+        probe.active = !probe.active;
+        probe.position = None;
+    }
+    if !probe.active || !actions.just_pressed(Action::PlaceProbe) {
+        return;
+    }
+
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), cameras.get_single())
+    else {
+        return;
+    };
+    if let Some(cursor) = window.cursor_position() {
+        probe.position = camera.viewport_to_world_2d(camera_transform, cursor).ok();
+    }
+}
+
+/// Redraws the cells the tree traversal for the probed point accepted
+/// (filled) and opened (outlined), and the resulting acceleration.
+#[allow(clippy::too_many_arguments)]
+fn draw_probe(
+    mut commands: Commands,
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+    quality: PhysicsQuality,
+    settings: Res<PhysicsSettings>,
+    probe: Res<Probe>,
+    cells: Query<Entity, With<ProbeCell>>,
+    bodies: Query<(&Mass, &Transform)>,
+) {
+    for entity in &cells {
+        commands.entity(entity).despawn();
+    }
+    let Some(position) = probe.position else {
+        return;
+    };
+
+    let sources: Vec<(Vec2, f32)> = bodies
+        .iter()
+        .map(|(mass, transform)| (transform.translation.xy(), mass.0))
+        .collect();
+    let q_tree = QuadTree::build(&sources);
+    let mut acceleration = Vec2::ZERO;
+    let theta_threshold = quality.settings().theta_threshold;
+    q_tree.trace_traversal(position, theta_threshold, |node, accepted| {
+        let size = Vec2::splat(node.half_size * 2.);
+        if accepted {
+            acceleration +=
+                point_mass_acceleration(position, node.center_of_mass, node.mass, settings.g);
+            // Behind the bodies so they stay visible.
+            commands.spawn((
+                ProbeCell,
+                Sprite {
+                    color: theme.highlight().with_alpha(0.25),
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                Transform::from_translation(node.center.extend(-1.)),
+            ));
+            gizmos.line_2d(
+                position,
+                node.center_of_mass,
+                theme.highlight().with_alpha(0.5),
+            );
+        } else {
+            gizmos.rect_2d(
+                Isometry2d::from_translation(node.center),
+                size,
+                theme.outline(),
+            );
+        }
+    });
+
+    gizmos.circle_2d(position, 4., theme.accent());
+    gizmos.arrow_2d(
+        position,
+        position + acceleration.normalize_or_zero() * ARROW_LENGTH,
+        theme.accent(),
+    );
+}
+
+/// Lets the user inspect the Barnes-Hut traversal for a clicked point.
+pub struct ProbePlugin;
+
+impl Plugin for ProbePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Probe>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .init_resource::<Quality>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<PhysicsSettings>()
+            .add_systems(Update, (control_probe, draw_probe).chain());
+    }
+}
@@ -1,4 +1,5 @@
-use bevy::prelude::Vec2;
+use bevy::math::DVec2;
+use bevy::prelude::{Vec2, Vec3};
 use core::panic;
 use std::vec;
 
@@ -12,16 +13,78 @@ pub struct Node {
     /// Mass of the node
     pub mass: f32,
     /// Center of the region the node is representing
-    center: Vec2,
+    pub center: Vec2,
     /// Center of mass of the node (equal to position if the node is a
     /// leaf node)
     pub center_of_mass: Vec2,
     /// Distance from center to the side of the square
-    half_size: f32,
+    pub half_size: f32,
+    /// Sum of `m·(x², xy, y²)` over the bodies in the node, kept so the
+    /// quadrupole moment can be derived
+    pub second_moment: Vec3,
+    /// Mass and mass weighted sum of the positions in double precision,
+    /// which `mass` and `center_of_mass` are derived from so the rounding
+    /// doesn't build up as bodies are added
+    total_mass: f64,
+    weighted_position: DVec2,
+    /// Index of the bucket in [`QuadTree::buckets`] listing the bodies of a
+    /// leaf at [`MAX_DEPTH`] holding more than one
+    bucket: Option<usize>,
+}
+
+/// How the contributions of the bodies to an acceleration are added up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Summation {
+    /// In single precision, losing the small contributions of the many
+    /// distant bodies next to the large ones of the near bodies
+    Single,
+    /// In double precision, only rounded to single precision at the end
+    #[default]
+    Double,
+}
+
+/// Depth below the root at which the cells stop being split. Bodies ending
+/// up in the same cell this deep share a leaf, their mass aggregated into
+/// it and each of them listed in its bucket. With the tree 2000 units
+/// across this only happens to bodies closer than a millionth of a unit.
+pub const MAX_DEPTH: usize = 32;
+
+/// Fraction of the half size of a cell along its edges, inside which
+/// [`QuadTree::move_bodies`] looks up which cell a body is in.
+const EDGE_MARGIN: f32 = 1e-3;
+
+/// In which order [`QuadTree::build_with`] inserts the bodies.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InsertionOrder {
+    /// In the order they are given
+    Given,
+    /// Sorted by their Morton code, so bodies close to each other are
+    /// inserted one after the other and the nodes of a cell end up close
+    /// to each other in memory, which the traversals walk faster
+    #[default]
+    Morton,
+}
+
+/// Leaf a body was placed in. It stays valid while bodies are added,
+/// removed and moved, until the tree is cleared, rebuilt or compacted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeHandle(usize);
+
+/// A body of the tree moving to a new position, see
+/// [`QuadTree::move_bodies`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyMove {
+    /// Leaf of the body, updated to the leaf it ends up in
+    pub handle: NodeHandle,
+    /// Where the body is in the tree
+    pub from: Vec2,
+    pub to: Vec2,
+    pub mass: f32,
 }
 
 /// Stores information about the quadtree.
 #[readonly::make]
+#[derive(Debug, Clone)]
 pub struct QuadTree {
     /// The inner vector, storing the nodes
     vec: Vec<Node>,
@@ -33,34 +96,234 @@ pub struct QuadTree {
     bounds: [Vec2; 2],
     /// The index of root node
     pub root: usize,
+    /// Center and half size the tree was created with, which
+    /// [`QuadTree::clear`] goes back to
+    initial: (Vec2, f32),
+    /// Positions and masses of the bodies of the leaves holding more than
+    /// one
+    buckets: Vec<Vec<(Vec2, f32)>>,
+    /// Memory [`QuadTree::compact`] lays the nodes out in before swapping
+    /// it with `vec`, kept so compacting doesn't allocate every time
+    spare: Vec<Node>,
+}
+
+/// Why a body couldn't be added to, removed from or moved in a
+/// [`QuadTree`].
+///
+/// The tree is left incomplete when adding fails, it has to be rebuilt
+/// before it is used for anything that matters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TreeError {
+    /// The position isn't finite or the mass isn't finite and positive,
+    /// which is what a blown up simulation usually ends up with
+    InvalidBody { position: Vec2, mass: f32 },
+    /// There is no body with exactly this position and mass in the tree
+    NotFound { position: Vec2, mass: f32 },
+}
+
+impl std::fmt::Display for TreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TreeError::InvalidBody { position, mass } => {
+                write!(f, "body at {position} with mass {mass} can't be placed")
+            }
+            TreeError::NotFound { position, mass } => {
+                write!(f, "there is no body at {position} with mass {mass}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+/// `m·(x², xy, y²)` of a body, what it adds to the second moment of the
+/// nodes containing it.
+fn second_moment(position: Vec2, mass: f32) -> Vec3 {
+    mass * Vec3::new(
+        position.x * position.x,
+        position.x * position.y,
+        position.y * position.y,
+    )
 }
 
 impl Node {
+    /// Node covering the square at `center` without any bodies in it.
+    fn empty(center: Vec2, half_size: f32) -> Self {
+        Node {
+            children: [None; 4],
+            mass: 0.,
+            center,
+            center_of_mass: center,
+            half_size,
+            second_moment: Vec3::ZERO,
+            total_mass: 0.,
+            weighted_position: DVec2::ZERO,
+            bucket: None,
+        }
+    }
+
+    /// Leaf node of a single body.
+    fn body(center: Vec2, half_size: f32, position: Vec2, mass: f32) -> Self {
+        Node {
+            children: [None; 4],
+            mass,
+            center,
+            center_of_mass: position,
+            half_size,
+            second_moment: second_moment(position, mass),
+            total_mass: mass as f64,
+            weighted_position: position.as_dvec2() * mass as f64,
+            bucket: None,
+        }
+    }
+
+    /// Adds a body to the mass and center of mass of the node.
+    fn add_mass(&mut self, position: Vec2, mass: f32) {
+        self.total_mass += mass as f64;
+        self.weighted_position += position.as_dvec2() * mass as f64;
+        self.mass = self.total_mass as f32;
+        self.center_of_mass = (self.weighted_position / self.total_mass).as_vec2();
+        self.second_moment += second_moment(position, mass);
+    }
+
+    /// Takes a body out of the mass and center of mass of the node, the
+    /// center of mass going back to the center once the node is empty.
+    fn remove_mass(&mut self, position: Vec2, mass: f32) {
+        self.total_mass -= mass as f64;
+        self.weighted_position -= position.as_dvec2() * mass as f64;
+        self.second_moment -= second_moment(position, mass);
+        if self.total_mass > 0. {
+            self.mass = self.total_mass as f32;
+            self.center_of_mass = (self.weighted_position / self.total_mass).as_vec2();
+        } else {
+            *self = Node {
+                children: self.children,
+                ..Node::empty(self.center, self.half_size)
+            };
+        }
+    }
+
+    /// Whether `pos` is inside the node's region and clear of its edges,
+    /// where rounding could put it on either side.
+    fn well_inside(&self, pos: Vec2) -> bool {
+        let inner = self.half_size * (1. - EDGE_MARGIN);
+        (pos - self.center).abs().cmplt(Vec2::splat(inner)).all()
+    }
+
     // Returns the index of quadrant to which the position belongs.
     // WARNING!!! pos should be inside the bounds of this node, otherwise
     // the quadtree structure is invalid if
     // the index is then used to append stuff.
+    // The quadrants go clockwise from top-left with "top" being the side
+    // with smaller y, matching how the child centers are placed when
+    // splitting.
     fn get_quadrant(&self, pos: Vec2) -> usize {
         match (pos.x > self.center.x, pos.y > self.center.y) {
-            (false, true) => 0,  // top-left
-            (true, true) => 1,   // top-right
-            (false, false) => 2, // bottom-left
-            (true, false) => 3,  // bottom-right
+            (false, false) => 0, // top-left
+            (true, false) => 1,  // top-right
+            (true, true) => 2,   // bottom-right
+            (false, true) => 3,  // bottom-left
         }
     }
 
-    // Whether this node is a leaf node.
-    fn is_leaf(&self) -> bool {
+    /// Center of the child cell covering the `quadrant`.
+    fn quadrant_center(&self, quadrant: usize) -> Vec2 {
+        let offset = self.half_size / 2.;
+        match quadrant {
+            0 => Vec2::new(self.center.x - offset, self.center.y - offset),
+            1 => Vec2::new(self.center.x + offset, self.center.y - offset),
+            2 => Vec2::new(self.center.x + offset, self.center.y + offset),
+            3 => Vec2::new(self.center.x - offset, self.center.y + offset),
+            _ => panic!("Invalid quadrant index"),
+        }
+    }
+
+    /// Second moment of the mass about the center of mass as `(xx, xy, yy)`,
+    /// zero for a single body.
+    pub fn central_second_moment(&self) -> Vec3 {
+        let c = self.center_of_mass;
+        self.second_moment - self.mass * Vec3::new(c.x * c.x, c.x * c.y, c.y * c.y)
+    }
+
+    // Distance from `pos` to the closest point of the region the node
+    // covers, zero when `pos` is inside of it.
+    fn distance_to_region(&self, pos: Vec2) -> f32 {
+        ((pos - self.center).abs() - Vec2::splat(self.half_size))
+            .max(Vec2::ZERO)
+            .length()
+    }
+
+    /// Whether this node is a leaf node.
+    pub fn is_leaf(&self) -> bool {
         // Leaf nodes don't have any children.
         for n in self.children {
             if n.is_some() {
                 return false;
             }
         }
-        return true;
+        true
     }
 }
 
+/// Spreads the lower 16 bits of `x` out to the even bits.
+fn spread_bits(x: u32) -> u32 {
+    let x = x & 0x0000_ffff;
+    let x = (x | x << 8) & 0x00ff_00ff;
+    let x = (x | x << 4) & 0x0f0f_0f0f;
+    let x = (x | x << 2) & 0x3333_3333;
+    (x | x << 1) & 0x5555_5555
+}
+
+/// Morton code of `position` on a 65536 by 65536 grid over the square at
+/// `min` with sides `size` long. Sorting by it orders positions along a Z
+/// shaped curve, which keeps positions in the same cell of a quadtree
+/// together.
+pub fn morton_code(position: Vec2, min: Vec2, size: f32) -> u32 {
+    let cell = ((position - min) / size * 65535.)
+        .clamp(Vec2::ZERO, Vec2::splat(65535.))
+        .as_uvec2();
+    spread_bits(cell.x) | spread_bits(cell.y) << 1
+}
+
+/// Sorts the `bodies` by their [`morton_code`] within their bounding box.
+pub fn morton_sort(bodies: &mut [(Vec2, f32)]) {
+    let (min, max) = bounding_box(bodies);
+    let size = (max - min).max_element().max(f32::MIN_POSITIVE);
+    bodies.sort_by_cached_key(|&(position, _)| morton_code(position, min, size));
+}
+
+/// Indices of the `bodies` in the order [`morton_sort`] puts them in, for
+/// bodies that have to stay where they are.
+pub fn morton_order(bodies: &[(Vec2, f32)]) -> Vec<usize> {
+    let (min, max) = bounding_box(bodies);
+    let size = (max - min).max_element().max(f32::MIN_POSITIVE);
+    let mut order: Vec<usize> = (0..bodies.len()).collect();
+    order.sort_by_cached_key(|&index| morton_code(bodies[index].0, min, size));
+    order
+}
+
+/// Corners of the smallest box around the finite positions of the
+/// `bodies`, both at the origin when there are none.
+fn bounding_box(bodies: &[(Vec2, f32)]) -> (Vec2, Vec2) {
+    let (min, max) = bodies
+        .iter()
+        .map(|&(position, _)| position)
+        .filter(|position| position.is_finite())
+        .fold((Vec2::MAX, Vec2::MIN), |(min, max), position| {
+            (min.min(position), max.max(position))
+        });
+    if min.x > max.x {
+        return (Vec2::ZERO, Vec2::ZERO);
+    }
+    (min, max)
+}
+
+/// Key looking up the bodies at a leaf position of a tree, as leaves
+/// don't know which body they hold.
+pub fn leaf_key(position: Vec2) -> (u32, u32) {
+    (position.x.to_bits(), position.y.to_bits())
+}
+
 impl QuadTree {
     /// Construct a new Quadtree using center and half size, to construct a
     /// square bounding box.
@@ -68,18 +331,123 @@ impl QuadTree {
         let xy1 = Vec2::new(center.x - half_size, center.y - half_size);
         let xy2 = Vec2::new(center.x + half_size, center.y + half_size);
         QuadTree {
-            vec: vec![Node {
-                children: [None; 4],
-                mass: 0.,
-                center,
-                center_of_mass: center,
-                half_size,
-            }],
+            vec: vec![Node::empty(center, half_size)],
             bounds: [xy1, xy2],
             root: 0,
+            initial: (center, half_size),
+            buckets: Vec::new(),
+            spare: Vec::new(),
         }
     }
 
+    /// Tree sized to fit all of the `bodies`, with them inserted in Morton
+    /// order, see [`QuadTree::build_with`].
+    pub fn build(bodies: &[(Vec2, f32)]) -> Self {
+        Self::build_with(bodies, InsertionOrder::default())
+    }
+
+    /// Tree sized to fit all of the `bodies`, found in one pass over them,
+    /// with them inserted in the `order` and the nodes compacted, see
+    /// [`QuadTree::compact`]. Bodies the tree can't take are left out.
+    pub fn build_with(bodies: &[(Vec2, f32)], order: InsertionOrder) -> Self {
+        let (min, max) = bounding_box(bodies);
+        // Every body is inside the tree's initial bounds, so it doesn't have
+        // to grow while they are inserted.
+        let mut q_tree = QuadTree::new((min + max) / 2., (max - min).max_element() / 2. + 1.);
+        let add = |q_tree: &mut QuadTree, &(position, mass): &(Vec2, f32)| {
+            let _ = q_tree.add_node(position, mass);
+        };
+        match order {
+            InsertionOrder::Given => bodies.iter().for_each(|body| add(&mut q_tree, body)),
+            InsertionOrder::Morton => {
+                let mut sorted = bodies.to_vec();
+                morton_sort(&mut sorted);
+                sorted.iter().for_each(|body| add(&mut q_tree, body));
+            }
+        }
+        q_tree.compact();
+        q_tree
+    }
+
+    /// Removes all the bodies and shrinks the tree back to the bounds it was
+    /// created with. The memory of the nodes is kept, so building the tree
+    /// again doesn't allocate until it outgrows the last one.
+    pub fn clear(&mut self) {
+        let (center, half_size) = self.initial;
+        self.vec.clear();
+        self.buckets.clear();
+        self.vec.push(Node::empty(center, half_size));
+        self.bounds = [center - half_size, center + half_size];
+        self.root = 0;
+    }
+
+    /// Grows the bounds the tree is cleared to until they hold the finite
+    /// positions of all the `bodies`, so rebuilding the tree from them
+    /// doesn't have to grow it while inserting. The bounds are made twice
+    /// as large as the bodies need, so bodies drifting outwards don't have
+    /// them grown again every time, and only shrink with
+    /// [`QuadTree::shrink_root`].
+    pub fn fit_bounds(&mut self, bodies: &[(Vec2, f32)]) {
+        let (center, half_size) = self.initial;
+        let (min, max) = bounding_box(bodies);
+        let (lower, upper) = (center - half_size, center + half_size);
+        if min.cmpge(lower).all() && max.cmple(upper).all() {
+            return;
+        }
+        let (min, max) = (min.min(lower), max.max(upper));
+        self.initial = ((min + max) / 2., (max - min).max_element());
+    }
+
+    /// Number of nodes the tree is made of, inner ones included.
+    pub fn node_count(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// Lays the nodes out depth first, each node followed by the subtrees of
+    /// its children in order of their quadrant, with the root first. The
+    /// nodes are otherwise stored in the order they were created, which
+    /// scatters the children of a node over the whole tree, so a traversal
+    /// jumps around in memory.
+    pub fn compact(&mut self) {
+        let mut spare = std::mem::take(&mut self.spare);
+        spare.clear();
+        spare.reserve(self.vec.len());
+        // Nodes to copy over, with the index of their parent in the new
+        // layout and the quadrant they are in.
+        let mut to_visit: Vec<(usize, Option<(usize, usize)>)> = vec![(self.root, None)];
+        while let Some((node_idx, parent)) = to_visit.pop() {
+            let node = self.vec[node_idx];
+            let new_idx = spare.len();
+            if let Some((parent_idx, quadrant)) = parent {
+                spare[parent_idx].children[quadrant] = Some(new_idx);
+            }
+            // Pushed in reverse, so the first quadrant is visited first.
+            for (quadrant, child) in node.children.iter().enumerate().rev() {
+                if let Some(child) = *child {
+                    to_visit.push((child, Some((new_idx, quadrant))));
+                }
+            }
+            spare.push(node);
+        }
+        self.spare = std::mem::replace(&mut self.vec, spare);
+        self.root = 0;
+    }
+
+    /// Replaces the bodies of the tree with the `bodies`, reusing the memory
+    /// of the previous nodes, see [`QuadTree::clear`], and compacts it. Stops
+    /// at the first body which can't be added.
+    pub fn rebuild_from(
+        &mut self,
+        bodies: impl IntoIterator<Item = (Vec2, f32)>,
+    ) -> Result<(), TreeError> {
+        self.clear();
+        for (position, mass) in bodies {
+            self.add_node(position, mass)?;
+        }
+        self.compact();
+        Ok(())
+    }
+
     /// Returns true if the `position` is inside the bounds of this quadtree
     fn in_bounds(&mut self, position: Vec2) -> bool {
         // Out of bounds to the left.
@@ -99,231 +467,733 @@ impl QuadTree {
             return false;
         }
 
-        return true;
+        true
     }
 
-    /// Finds the leaf node that needs to be split to insert the new node and
-    /// splits it using recursion.
-    fn split_add_recursive(&mut self, node_idx: usize, position: Vec2, mass: f32) {
-        let child_quadrant;
-        let new_halfsize;
-        let center;
-
-        {
+    /// Walks down from the root to the leaf the body belongs in, adding its
+    /// mass to every node on the way, and splits the leaf already holding
+    /// a body there until the two are in different cells. Leaves at
+    /// [`MAX_DEPTH`] aren't split anymore, the body is added to the leaf's
+    /// bucket instead, so bodies on top of each other don't subdivide
+    /// forever. Returns the index of the leaf the body ends up in.
+    fn insert(&mut self, position: Vec2, mass: f32) -> usize {
+        let mut node_idx = self.root;
+        let mut depth = 0;
+        loop {
+            // Index the next pushed node will have
+            let idx = self.vec.len();
             let node = &mut self.vec[node_idx];
-            // Recalculate the center of mass and mass of this node with the
-            // passed arguments
-            node.center_of_mass =
-                (node.center_of_mass * node.mass + position * mass) / (node.mass + mass);
-            node.mass += mass;
-            // Get the quadrant where the position would belong and the center
-            // of that quadrant
-            child_quadrant = node.get_quadrant(position);
-            new_halfsize = node.half_size / 2.;
-            center = match child_quadrant {
-                0 => Vec2::new(node.center.x - new_halfsize, node.center.y - new_halfsize),
-                1 => Vec2::new(node.center.x + new_halfsize, node.center.y - new_halfsize),
-                2 => Vec2::new(node.center.x + new_halfsize, node.center.y + new_halfsize),
-                3 => Vec2::new(node.center.x - new_halfsize, node.center.y + new_halfsize),
-                _ => panic!("Invalid child quadrant"),
-            };
+            node.add_mass(position, mass);
+            let quadrant = node.get_quadrant(position);
+            let center = node.quadrant_center(quadrant);
+            let half_size = node.half_size / 2.;
+            let child = node.children[quadrant];
+
+            match child {
+                None => {
+                    // Empty slot, the body gets a leaf of its own.
+                    self.vec[node_idx].children[quadrant] = Some(idx);
+                    self.vec.push(Node::body(center, half_size, position, mass));
+                    return idx;
+                }
+                Some(child_idx) if !self.vec[child_idx].is_leaf() => {
+                    node_idx = child_idx;
+                }
+                Some(child_idx) if depth + 1 >= MAX_DEPTH => {
+                    self.add_to_bucket(child_idx, position, mass);
+                    return child_idx;
+                }
+                Some(child_idx) => {
+                    // The leaf is replaced by an internal node starting out
+                    // with everything of the leaf, and moves down into the
+                    // quadrant of it its body is in.
+                    self.vec[node_idx].children[quadrant] = Some(idx);
+                    let original = self.vec[child_idx];
+                    let new_quadrant = original.get_quadrant(original.center_of_mass);
+                    let mut internal = original;
+                    internal.children[new_quadrant] = Some(child_idx);
+                    self.vec.push(internal);
+
+                    let leaf = &mut self.vec[child_idx];
+                    leaf.center = original.quadrant_center(new_quadrant);
+                    leaf.half_size /= 2.;
+                    node_idx = idx;
+                }
+            }
+            depth += 1;
         }
-        // Get the index which the newly created node will be when pushed
-        let idx = self.vec.len();
-
-        match self.vec[node_idx].children[child_quadrant] {
-            None => {
-                // Empty slot, just push the node and add it to the slot.
-                self.vec.push(Node {
-                    children: [None; 4],
-                    mass,
-                    center,
-                    center_of_mass: position,
-                    half_size: new_halfsize,
-                });
-                self.vec[node_idx].children[child_quadrant] = Some(idx);
-            }
-            Some(child_idx) => {
-                if self.vec[child_idx].is_leaf() {
-                    // We'll be replacing the original leaf node with internal
-                    // node, we need to get some information from the original
-                    // node beforehand.
-                    let original_center_of_mass;
-                    let original_mass;
-                    let original_half_size;
-                    let original_center;
-                    {
-                        let original = &self.vec[child_idx];
-                        original_center_of_mass = original.center_of_mass;
-                        original_mass = original.mass;
-                        original_half_size = original.half_size;
-                        original_center = original.center;
-                    }
-
-                    // Push new internal node in the place of the original
-                    // leaf node and replace the original node's index in its
-                    // parrent with the new one
-                    self.vec.push(Node {
-                        children: [None; 4],
-                        mass: original_mass,
-                        center: original_center,
-                        center_of_mass: original_center_of_mass,
-                        half_size: original_half_size,
-                    });
-                    self.vec[node_idx].children[child_quadrant] = Some(idx);
-
-                    // Find which quadrant does the original node belong to in
-                    // the new internal node and record it in its children there.
-                    let new_quadrant = self.vec[idx].get_quadrant(original_center_of_mass);
-                    {
-                        let (new_node, original) = if child_idx < idx {
-                            let (first_half, second_half) = self.vec.split_at_mut(idx);
-                            // `idx` is at the beginning of `second_half`
-                            (&mut first_half[child_idx], &mut second_half[0])
-                        } else {
-                            let (first_half, second_half) = self.vec.split_at_mut(child_idx);
-                            // `child_idx` is at the beginning of `second_half`
-                            (&mut first_half[idx], &mut second_half[0])
-                        };
-
-                        // Adjust the half_size and center according to
-                        // the quadrant.
-                        original.half_size /= 2.;
-                        original.center = match new_quadrant {
-                            0 => Vec2::new(
-                                original.center.x - original.half_size,
-                                original.center.y - original.half_size,
-                            ),
-                            1 => Vec2::new(
-                                original.center.x + original.half_size,
-                                original.center.y - original.half_size,
-                            ),
-                            2 => Vec2::new(
-                                original.center.x + original.half_size,
-                                original.center.y + original.half_size,
-                            ),
-                            3 => Vec2::new(
-                                original.center.x - original.half_size,
-                                original.center.y + original.half_size,
-                            ),
-                            _ => panic!("Invalid quadrant index"),
-                        };
-
-                        new_node.children[new_quadrant] = Some(child_idx);
-                    }
-
-                    // Try to add the node to the newly created internal node
-                    self.split_add_recursive(idx, position, mass);
+    }
+
+    /// Adds a body to the leaf at `leaf_idx`, listing the body the leaf
+    /// held so far in a new bucket first if it has none yet.
+    fn add_to_bucket(&mut self, leaf_idx: usize, position: Vec2, mass: f32) {
+        let leaf = &mut self.vec[leaf_idx];
+        let bucket = *leaf.bucket.get_or_insert_with(|| {
+            self.buckets.push(vec![(leaf.center_of_mass, leaf.mass)]);
+            self.buckets.len() - 1
+        });
+        leaf.add_mass(position, mass);
+        self.buckets[bucket].push((position, mass));
+    }
+
+    /// Removes the body at exactly `position` with exactly `mass`, taking
+    /// its mass out of every node above it. Nodes left without any bodies
+    /// are cut off the tree and nodes left with a single body in a leaf
+    /// below them are replaced by the leaf, so the tree ends up the way
+    /// inserting the remaining bodies would have built it. The nodes cut
+    /// off stay in memory until the tree is compacted or cleared.
+    pub fn remove_node(&mut self, position: Vec2, mass: f32) -> Result<(), TreeError> {
+        let not_found = TreeError::NotFound { position, mass };
+        // Nodes from the root down to the parent of the leaf, with the
+        // quadrant the body is in.
+        let mut path = Vec::new();
+        let mut node_idx = self.root;
+        let leaf_idx = loop {
+            let node = &self.vec[node_idx];
+            let quadrant = node.get_quadrant(position);
+            let child_idx = node.children[quadrant].ok_or(not_found)?;
+            path.push((node_idx, quadrant));
+            if self.vec[child_idx].is_leaf() {
+                break child_idx;
+            }
+            node_idx = child_idx;
+        };
+
+        let leaf = &mut self.vec[leaf_idx];
+        let mut emptied = false;
+        match leaf.bucket {
+            Some(bucket) => {
+                let bodies = &mut self.buckets[bucket];
+                let index = bodies
+                    .iter()
+                    .position(|&body| body == (position, mass))
+                    .ok_or(not_found)?;
+                bodies.swap_remove(index);
+                if let [(remaining, remaining_mass)] = bodies[..] {
+                    // The bucket is dropped along with the second to last
+                    // body.
+                    *leaf = Node::body(leaf.center, leaf.half_size, remaining, remaining_mass);
                 } else {
-                    // Node is internal, try to add to it
-                    self.split_add_recursive(child_idx, position, mass);
+                    leaf.remove_mass(position, mass);
+                }
+            }
+            None if leaf.center_of_mass == position && leaf.mass == mass => emptied = true,
+            None => return Err(not_found),
+        }
+        for &(node_idx, _) in &path {
+            self.vec[node_idx].remove_mass(position, mass);
+        }
+
+        // From the parent of the leaf up to the children of the root, which
+        // stays even when it is empty.
+        for level in (0..path.len()).rev() {
+            let (node_idx, quadrant) = path[level];
+            if emptied {
+                self.vec[node_idx].children[quadrant] = None;
+            }
+            if level == 0 {
+                break;
+            }
+            let (parent_idx, parent_quadrant) = path[level - 1];
+            let node = self.vec[node_idx];
+            let mut children = node.children.iter().flatten();
+            match (children.next(), children.next()) {
+                (None, _) => emptied = true,
+                (Some(&child_idx), None)
+                    if self.vec[child_idx].is_leaf() && self.vec[child_idx].bucket.is_none() =>
+                {
+                    let leaf = &mut self.vec[child_idx];
+                    leaf.center = node.center;
+                    leaf.half_size = node.half_size;
+                    self.vec[parent_idx].children[parent_quadrant] = Some(child_idx);
+                    emptied = false;
                 }
+                // Nothing changes further up.
+                _ => break,
             }
         }
+        Ok(())
     }
 
-    /// Adds the node to the quadtree, subdividing or expanding the tree as
-    /// needed
-    pub fn add_node(&mut self, position: Vec2, mass: f32) {
-        if self.in_bounds(position) {
-            self.split_add_recursive(self.root, position, mass);
-            return;
+    /// Moves the body at exactly `old_position` with exactly `mass` to the
+    /// `new_position`, see [`QuadTree::remove_node`] and
+    /// [`QuadTree::add_node`]. The tree is left as it was when the body
+    /// can't be moved.
+    pub fn update_node(
+        &mut self,
+        old_position: Vec2,
+        new_position: Vec2,
+        mass: f32,
+    ) -> Result<(), TreeError> {
+        if !new_position.is_finite() {
+            return Err(TreeError::InvalidBody {
+                position: new_position,
+                mass,
+            });
+        }
+        self.remove_node(old_position, mass)?;
+        self.add_node(new_position, mass)
+    }
+
+    /// Index of the leaf in the cell `position` is in, `None` when that
+    /// cell is empty.
+    fn leaf_at(&self, position: Vec2) -> Option<usize> {
+        let mut node_idx = self.root;
+        loop {
+            let node = &self.vec[node_idx];
+            node_idx = node.children[node.get_quadrant(position)]?;
+            if self.vec[node_idx].is_leaf() {
+                return Some(node_idx);
+            }
+        }
+    }
+
+    /// Handle of the leaf holding a body at exactly `position`, `None` when
+    /// there is no body there.
+    pub fn handle(&self, position: Vec2) -> Option<NodeHandle> {
+        let leaf_idx = self.leaf_at(position)?;
+        self.leaf_bodies(&self.vec[leaf_idx])
+            .any(|(body, _)| body == position)
+            .then_some(NodeHandle(leaf_idx))
+    }
+
+    /// Moves the bodies to their new positions, updating the handles of
+    /// the `moves`. Bodies staying inside the cell of their leaf are moved
+    /// in place, only those crossing into another cell are removed and
+    /// inserted again, after which the masses of the nodes are summed up
+    /// again from the leaves. Cheaper than rebuilding the tree as long as
+    /// most bodies move less than the size of their cell.
+    ///
+    /// Returns `false` without changing anything when a body would leave
+    /// the bounds of the tree, which then has to be rebuilt. Fails when a
+    /// body isn't in the tree where it is said to be, leaving the tree
+    /// incomplete.
+    pub fn move_bodies(&mut self, moves: &mut [BodyMove]) -> Result<bool, TreeError> {
+        for body in moves.iter() {
+            if !body.to.is_finite() {
+                return Err(TreeError::InvalidBody {
+                    position: body.to,
+                    mass: body.mass,
+                });
+            }
+            if !self.in_bounds(body.to) {
+                return Ok(false);
+            }
         }
 
-        let mut center = self.vec[self.root].center;
-        let mut new_bounds = self.bounds;
-        // Calculate the half_size of the new boundin box
-        let half_size = new_bounds[1].x - new_bounds[0].x;
-        let prev_root_idx = self.root;
-        let mut children: [Option<usize>; 4] = [None; 4];
-
-        if position.x < center.x {
-            if position.y < center.y {
-                // To the top-left of the center
-                // Expand the bounds accordingly
-                new_bounds[0].x = new_bounds[0].x - half_size;
-                new_bounds[0].y = new_bounds[0].y - half_size;
-                // Note down the previous root as child of ther first one
-                children[2] = Some(prev_root_idx);
-                // Note down the new center of the root node
-                center.y = self.bounds[0].y;
+        let mut crossing = Vec::new();
+        for (index, body) in moves.iter().enumerate() {
+            let leaf = &self.vec[body.handle.0];
+            // Near the edges the cell is looked up the way inserting does,
+            // so bodies on an edge stay on the same side of it.
+            let in_place = leaf.is_leaf()
+                && leaf.bucket.is_none()
+                && leaf.center_of_mass == body.from
+                && leaf.mass == body.mass
+                && (leaf.well_inside(body.to) || self.leaf_at(body.to) == Some(body.handle.0));
+            if in_place {
+                let leaf = &mut self.vec[body.handle.0];
+                *leaf = Node::body(leaf.center, leaf.half_size, body.to, body.mass);
             } else {
-                // To the bottom-left of the center
-                // Other steps are the same as before
-                new_bounds[0].x = new_bounds[0].x - half_size;
-                new_bounds[1].y = new_bounds[1].y + half_size;
-                children[1] = Some(prev_root_idx);
-                center.y = self.bounds[1].y;
-            }
-            center.x = self.bounds[0].x;
-        } else {
-            if position.y < center.y {
-                // To the top-right of the center
-                new_bounds[1].x = new_bounds[1].x + half_size;
-                new_bounds[0].y = new_bounds[0].y - half_size;
-                children[3] = Some(prev_root_idx);
-                center.y = self.bounds[0].y;
+                crossing.push(index);
+            }
+        }
+        // Removing and inserting keeps the masses of the nodes on the way
+        // up to date, which are summed up again anyway.
+        for &index in &crossing {
+            self.remove_node(moves[index].from, moves[index].mass)?;
+        }
+        for &index in &crossing {
+            let body = &mut moves[index];
+            body.handle = NodeHandle(self.insert(body.to, body.mass));
+        }
+        self.sum_masses();
+        Ok(true)
+    }
+
+    /// Sets the mass, center of mass and second moment of every inner node
+    /// to the sum of those of its children, from the leaves up.
+    fn sum_masses(&mut self) {
+        let mut parents_first = Vec::with_capacity(self.vec.len());
+        let mut to_visit = vec![self.root];
+        while let Some(node_idx) = to_visit.pop() {
+            parents_first.push(node_idx);
+            to_visit.extend(self.vec[node_idx].children.iter().flatten());
+        }
+        for &node_idx in parents_first.iter().rev() {
+            let node = self.vec[node_idx];
+            if node.is_leaf() {
+                continue;
+            }
+            let mut summed = Node {
+                children: node.children,
+                ..Node::empty(node.center, node.half_size)
+            };
+            for &child_idx in node.children.iter().flatten() {
+                let child = &self.vec[child_idx];
+                summed.total_mass += child.total_mass;
+                summed.weighted_position += child.weighted_position;
+                summed.second_moment += child.second_moment;
+            }
+            if summed.total_mass > 0. {
+                summed.mass = summed.total_mass as f32;
+                summed.center_of_mass = (summed.weighted_position / summed.total_mass).as_vec2();
+            }
+            self.vec[node_idx] = summed;
+        }
+    }
+
+    /// Positions and masses of the bodies of the `leaf`, more than one only
+    /// for the leaves at [`MAX_DEPTH`] which bodies had to share.
+    pub fn leaf_bodies(&self, leaf: &Node) -> impl Iterator<Item = (Vec2, f32)> + '_ {
+        let bucket = leaf.bucket.map(|bucket| self.buckets[bucket].as_slice());
+        let single = bucket.is_none().then_some((leaf.center_of_mass, leaf.mass));
+        bucket.into_iter().flatten().copied().chain(single)
+    }
+
+    /// Adds the node to the quadtree, subdividing the cells or growing the
+    /// tree as needed, see [`QuadTree::grow_to_contain`].
+    pub fn add_node(&mut self, position: Vec2, mass: f32) -> Result<(), TreeError> {
+        if !position.is_finite() || !mass.is_finite() || mass <= 0. {
+            return Err(TreeError::InvalidBody { position, mass });
+        }
+        if !self.grow_to_contain(position) {
+            return Err(TreeError::InvalidBody { position, mass });
+        }
+        self.insert(position, mass);
+        Ok(())
+    }
+
+    /// Doubles the size of the tree towards `position` until it is inside
+    /// the tree's bounds, the old root becoming the child of the new one in
+    /// the opposite quadrant. An empty tree is moved instead. Returns
+    /// `false` and leaves the tree as it was for positions which aren't
+    /// finite or the bounds can't grow to hold, because they would
+    /// overflow or the tree has no size to double.
+    pub fn grow_to_contain(&mut self, position: Vec2) -> bool {
+        let inside = |[min, max]: [Vec2; 2]| position.cmpge(min).all() && position.cmple(max).all();
+        if inside(self.bounds) {
+            return true;
+        }
+        let old_root = self.vec[self.root];
+        if !position.is_finite() || old_root.half_size <= 0. {
+            return false;
+        }
+        // Centers and half sizes of the roots to add, worked out before
+        // the tree is touched.
+        let mut grown = Vec::new();
+        let (mut center, mut half_size) = (old_root.center, old_root.half_size);
+        let mut bounds = self.bounds;
+        while !inside(bounds) {
+            let direction = Vec2::new(
+                if position.x > center.x { 1. } else { -1. },
+                if position.y > center.y { 1. } else { -1. },
+            );
+            center += direction * half_size;
+            half_size *= 2.;
+            bounds = [center - half_size, center + half_size];
+            if !bounds[0].is_finite() || !bounds[1].is_finite() {
+                return false;
+            }
+            grown.push((center, half_size));
+        }
+
+        for (center, half_size) in grown {
+            let old_root = self.vec[self.root];
+            let mut root = Node {
+                center,
+                half_size,
+                ..old_root
+            };
+            // An empty old root would be taken for a leaf holding a body.
+            if old_root.is_leaf() {
+                self.vec[self.root] = root;
             } else {
-                // To the bottom-right of the center
-                new_bounds[1].x = new_bounds[1].x + half_size;
-                new_bounds[1].y = new_bounds[1].y + half_size;
-                children[0] = Some(prev_root_idx);
-                center.y = self.bounds[1].y;
+                root.children = [None; 4];
+                root.children[root.get_quadrant(old_root.center)] = Some(self.root);
+                self.root = self.vec.len();
+                self.vec.push(root);
             }
-            center.x = self.bounds[1].x;
         }
+        self.bounds = bounds;
+        true
+    }
 
-        // Calculate new center based on the new bounds
-        center.x = (new_bounds[0].x + new_bounds[1].x) / 2.0;
-        center.y = (new_bounds[0].y + new_bounds[1].y) / 2.0;
+    /// The only child of the root, when the bodies are all in one quadrant
+    /// of it and there are enough of them for the child to be an inner
+    /// node.
+    fn only_child_of_root(&self) -> Option<usize> {
+        let mut children = self.vec[self.root].children.iter().flatten();
+        match (children.next(), children.next()) {
+            (Some(&child_idx), None) if !self.vec[child_idx].is_leaf() => Some(child_idx),
+            _ => None,
+        }
+    }
 
-        // Create the new root node
-        self.bounds = new_bounds;
-        let new_root = self.vec.len();
-        self.vec.push(Node {
-            children,
-            center,
-            mass: self.vec[prev_root_idx].mass + mass,
-            center_of_mass: ((self.vec[prev_root_idx].mass
-                * self.vec[prev_root_idx].center_of_mass)
-                + (mass * position))
-                / (mass + self.vec[prev_root_idx].mass),
-            half_size,
-        });
-        self.root = new_root;
+    /// Whether [`QuadTree::shrink_root`] would shrink the tree.
+    pub fn can_shrink_root(&self) -> bool {
+        self.only_child_of_root().is_some()
+    }
+
+    /// Makes the only child of the root the root when all the bodies are
+    /// in one quadrant, halving the size of the tree, and returns whether
+    /// it did. The tree is cleared to the smaller bounds from then on, so
+    /// a root left too large after the bodies drew together doesn't take
+    /// a level of the walk down to every body. The nodes and their handles
+    /// stay as they are.
+    pub fn shrink_root(&mut self) -> bool {
+        let Some(child_idx) = self.only_child_of_root() else {
+            return false;
+        };
+        let root = &self.vec[child_idx];
+        self.bounds = [root.center - root.half_size, root.center + root.half_size];
+        self.initial = (root.center, root.half_size);
+        self.root = child_idx;
+        true
     }
 
     /// Calculates the 'theta', which is later used for setting the accuracy.
     fn calculate_theta(&self, node_idx: usize, position: Vec2) -> f32 {
         let node = &self.vec[node_idx];
         let distance = node.center_of_mass.distance(position);
-        return (node.half_size * 2.) / distance;
+        (node.half_size * 2.) / distance
+    }
+
+    /// Calls `visit` with the mass and center of mass of every body the
+    /// Barnes-Hut traversal for `position` yields: nodes with theta smaller
+    /// than `theta_threshold` as a single body, otherwise expanded until a
+    /// leaf node is encountered.
+    ///
+    /// Doesn't allocate and only reads the tree, so any number of systems
+    /// or threads can walk the same tree at once.
+    pub fn for_each_body(
+        &self,
+        position: Vec2,
+        theta_threshold: f32,
+        mut visit: impl FnMut(f32, Vec2),
+    ) {
+        self.for_each_accepted(position, theta_threshold, |node| {
+            visit(node.mass, node.center_of_mass);
+        });
+    }
+
+    /// Like [`QuadTree::for_each_body`], but with the whole nodes, for
+    /// calculations needing more of them than the mass.
+    pub fn for_each_accepted(
+        &self,
+        position: Vec2,
+        theta_threshold: f32,
+        mut visit: impl FnMut(&Node),
+    ) {
+        self.walk(
+            self.root,
+            position,
+            theta_threshold,
+            &mut |node, accepted| {
+                if accepted {
+                    visit(node);
+                }
+            },
+        );
+    }
+
+    /// Gravitational acceleration at `position` from the bodies the
+    /// Barnes-Hut traversal yields, summed while walking the tree with the
+    /// gravitational constant `g`. `softening` is the Plummer softening
+    /// length, zero for plain Newtonian gravity.
+    ///
+    /// The distance to every node is computed once, both for the opening
+    /// test and for the force.
+    pub fn accumulate_acceleration(
+        &self,
+        position: Vec2,
+        theta_threshold: f32,
+        g: f32,
+        softening: f32,
+    ) -> Vec2 {
+        self.accumulate_acceleration_with(
+            position,
+            theta_threshold,
+            g,
+            softening,
+            Summation::default(),
+        )
+    }
+
+    /// Like [`QuadTree::accumulate_acceleration`], adding the contributions
+    /// up the way `summation` says.
+    pub fn accumulate_acceleration_with(
+        &self,
+        position: Vec2,
+        theta_threshold: f32,
+        g: f32,
+        softening: f32,
+        summation: Summation,
+    ) -> Vec2 {
+        let softening_squared = softening * softening;
+        match summation {
+            Summation::Single => self.accumulate_from(
+                self.root,
+                position,
+                theta_threshold,
+                g,
+                softening_squared,
+                |acceleration| acceleration,
+            ),
+            Summation::Double => self
+                .accumulate_from(
+                    self.root,
+                    position,
+                    theta_threshold,
+                    g,
+                    softening_squared,
+                    |acceleration| acceleration.as_dvec2(),
+                )
+                .as_vec2(),
+        }
     }
 
-    /// Collect the bodies that can be used to calculate forces on body at
-    /// `position`. Only internal nodes with theta value smaller than
-    /// `theta_threshold` are returned, otherwise they are expanded until a
-    /// leaf node is encountered, which will then be returned.
-    pub fn collect_bodies(&mut self, position: Vec2, theta_threshold: f32) -> Vec<&Node> {
-        let mut bodies: Vec<&Node> = Vec::new();
+    /// Like [`QuadTree::accumulate_acceleration`], leaving out the bodies
+    /// in the box from `min` up to but not including `max`. Nodes
+    /// overlapping the box are always opened up rather than taken as a
+    /// single body, for far fields whose near part is summed up elsewhere.
+    pub fn accumulate_acceleration_outside(
+        &self,
+        position: Vec2,
+        theta_threshold: f32,
+        g: f32,
+        softening: f32,
+        [min, max]: [Vec2; 2],
+    ) -> Vec2 {
+        let softening_squared = softening * softening;
+        let mut acceleration = Vec2::ZERO;
+        let mut to_visit = vec![self.root];
+        while let Some(node_idx) = to_visit.pop() {
+            let node = &self.vec[node_idx];
+            let offset = node.center_of_mass - position;
+            let distance_squared = offset.length_squared();
+            if node.is_leaf() {
+                let com = node.center_of_mass;
+                if com.cmpge(min).all() && com.cmplt(max).all() {
+                    continue;
+                }
+            } else {
+                let overlaps = (node.center - node.half_size).cmple(max).all()
+                    && (node.center + node.half_size).cmpge(min).all();
+                let size = node.half_size * 2.;
+                if overlaps || size * size >= theta_threshold * theta_threshold * distance_squared {
+                    to_visit.extend(node.children.iter().flatten());
+                    continue;
+                }
+            }
+            let softened = distance_squared + softening_squared;
+            if softened > 0. {
+                acceleration += g * node.mass * offset / (softened * softened.sqrt());
+            }
+        }
+        acceleration
+    }
+
+    /// Sums the contributions after converting them with `widen` to the
+    /// type they are added up in.
+    fn accumulate_from<A: std::iter::Sum<A>>(
+        &self,
+        node_idx: usize,
+        position: Vec2,
+        theta_threshold: f32,
+        g: f32,
+        softening_squared: f32,
+        widen: fn(Vec2) -> A,
+    ) -> A {
+        let node = &self.vec[node_idx];
+        let offset = node.center_of_mass - position;
+        let distance_squared = offset.length_squared();
+        // Same test as `calculate_theta`, squared so no root is needed.
+        let size = node.half_size * 2.;
+        let accepted =
+            size * size < theta_threshold * theta_threshold * distance_squared || node.is_leaf();
+        if !accepted {
+            return node
+                .children
+                .iter()
+                .flatten()
+                .map(|&child| {
+                    self.accumulate_from(
+                        child,
+                        position,
+                        theta_threshold,
+                        g,
+                        softening_squared,
+                        widen,
+                    )
+                })
+                .sum();
+        }
+
+        let softened = distance_squared + softening_squared;
+        if softened == 0. {
+            return widen(Vec2::ZERO);
+        }
+        widen(g * node.mass * offset / (softened * softened.sqrt()))
+    }
+
+    /// Walks the tree the same way as [`QuadTree::for_each_body`] and calls
+    /// `visit` with every node it touches, along with whether the node was
+    /// accepted as a single body (`true`) or opened up (`false`).
+    pub fn trace_traversal(
+        &self,
+        position: Vec2,
+        theta_threshold: f32,
+        mut visit: impl FnMut(&Node, bool),
+    ) {
+        self.walk(self.root, position, theta_threshold, &mut visit);
+    }
+
+    // Recursion rather than a stack of nodes to visit, which would have to
+    // be allocated. The depth is bounded by how many times the tree can
+    // halve its cells.
+    fn walk(
+        &self,
+        node_idx: usize,
+        position: Vec2,
+        theta_threshold: f32,
+        visit: &mut impl FnMut(&Node, bool),
+    ) {
+        let node = &self.vec[node_idx];
+        let theta = self.calculate_theta(node_idx, position);
+        let accepted = theta < theta_threshold || node.is_leaf();
+        visit(node, accepted);
+        if !accepted {
+            for &child in node.children.iter().flatten() {
+                self.walk(child, position, theta_threshold, visit);
+            }
+        }
+    }
+
+    /// Calls `visit` with every node of the tree and its depth, the root
+    /// being at depth 0, parents before their children.
+    pub fn for_each_node(&self, mut visit: impl FnMut(&Node, usize)) {
+        let mut to_visit = vec![(self.root, 0)];
+
+        while let Some((node_idx, depth)) = to_visit.pop() {
+            let node = &self.vec[node_idx];
+            visit(node, depth);
+            to_visit.extend(
+                node.children
+                    .iter()
+                    .flatten()
+                    .map(|&child| (child, depth + 1)),
+            );
+        }
+    }
+
+    /// Calls `visit` with every leaf with a body within `radius` of
+    /// `position`, cells lying entirely outside of the radius are skipped
+    /// without visiting their children.
+    pub fn for_each_leaf_within(&self, position: Vec2, radius: f32, mut visit: impl FnMut(&Node)) {
+        let mut to_visit = vec![self.root];
+
+        while let Some(node_idx) = to_visit.pop() {
+            let node = &self.vec[node_idx];
+            if node.distance_to_region(position) > radius {
+                continue;
+            }
+            if node.is_leaf() {
+                let within = self
+                    .leaf_bodies(node)
+                    .any(|(body, _)| body.distance(position) <= radius);
+                if node.mass > 0. && within {
+                    visit(node);
+                }
+            } else {
+                to_visit.extend(node.children.iter().flatten());
+            }
+        }
+    }
+
+    /// Total mass within `radius` of `position`. Cells lying entirely inside
+    /// the circle count with their whole mass without visiting their
+    /// children, leaves count with the bodies which are inside.
+    pub fn mass_within(&self, position: Vec2, radius: f32) -> f32 {
+        let mut mass = 0.;
         let mut to_visit = vec![self.root];
 
         while let Some(node_idx) = to_visit.pop() {
             let node = &self.vec[node_idx];
-            let theta = self.calculate_theta(node_idx, position);
-            if theta < theta_threshold || node.is_leaf() {
-                // If node is under the threshold add it to the return vector.
-                bodies.push(node);
+            if node.distance_to_region(position) > radius {
+                continue;
+            }
+            let farthest_corner = (position - node.center).abs() + Vec2::splat(node.half_size);
+            if node.is_leaf() {
+                mass += self
+                    .leaf_bodies(node)
+                    .filter(|(body, _)| body.distance(position) <= radius)
+                    .map(|(_, mass)| mass)
+                    .sum::<f32>();
+            } else if farthest_corner.length() <= radius {
+                mass += node.mass;
             } else {
-                // Otherwise expand it by adding its children to the visit
-                // vector
-                for &child in node.children.iter().flatten() {
-                    to_visit.push(child);
+                to_visit.extend(node.children.iter().flatten());
+            }
+        }
+        mass
+    }
+
+    /// Finds the leaf closest to `position`, not counting leaves right at
+    /// `position`, so the nearest neighbour of a body in the tree can be
+    /// found with its own position.
+    pub fn nearest_leaf(&self, position: Vec2) -> Option<&Node> {
+        let mut nearest: Option<(f32, &Node)> = None;
+        let mut to_visit = vec![self.root];
+
+        while let Some(node_idx) = to_visit.pop() {
+            let node = &self.vec[node_idx];
+            if nearest.is_some_and(|(distance, _)| node.distance_to_region(position) >= distance) {
+                continue;
+            }
+            if node.is_leaf() {
+                let distance = node.center_of_mass.distance(position);
+                let closer =
+                    nearest.is_none_or(|(nearest_distance, _)| distance < nearest_distance);
+                if node.mass > 0. && distance > 0. && closer {
+                    nearest = Some((distance, node));
                 }
+            } else {
+                to_visit.extend(node.children.iter().flatten());
             }
         }
 
-        return bodies;
+        nearest.map(|(_, node)| node)
+    }
+
+    /// Cuts the tree into at least `count` nodes (fewer only if it has
+    /// fewer leaves) which together hold all of its mass, splitting the
+    /// largest nodes first so the cut nodes are of similar size.
+    pub fn aggregate(&self, count: usize) -> Vec<&Node> {
+        let mut cut = vec![self.root];
+        let mut to_split = std::collections::VecDeque::from([self.root]);
+
+        while cut.len() < count {
+            let Some(node_idx) = to_split.pop_front() else {
+                break;
+            };
+            let node = &self.vec[node_idx];
+            if node.is_leaf() {
+                continue;
+            }
+            // Breadth-first, so the nodes are split from largest to
+            // smallest.
+            cut.retain(|&cut_idx| cut_idx != node_idx);
+            for &child in node.children.iter().flatten() {
+                cut.push(child);
+                to_split.push_back(child);
+            }
+        }
+
+        cut.into_iter()
+            .map(|node_idx| &self.vec[node_idx])
+            .filter(|node| node.mass > 0.)
+            .collect()
     }
 
     pub fn debug_print(&self, node_idx: usize, indentation: usize) {
@@ -335,13 +1205,570 @@ impl QuadTree {
             node.center_of_mass.x,
             node.center_of_mass.y
         );
-        for child in node.children {
-            match child {
-                Some(child_idx) => {
-                    self.debug_print(child_idx, indentation + 1);
-                }
-                None => {}
+        for child_idx in node.children.into_iter().flatten() {
+            self.debug_print(child_idx, indentation + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// `count` bodies spread uniformly over the square at the origin with
+    /// the `half_size`, the same ones for the same `seed`.
+    fn random_bodies(count: usize, half_size: f32, seed: u64) -> Vec<(Vec2, f32)> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count)
+            .map(|_| {
+                let position = Vec2::new(
+                    rng.random_range(-half_size..half_size),
+                    rng.random_range(-half_size..half_size),
+                );
+                (position, rng.random_range(1.0..1000.0))
+            })
+            .collect()
+    }
+
+    fn tree_of(center: Vec2, half_size: f32, bodies: &[(Vec2, f32)]) -> QuadTree {
+        let mut q_tree = QuadTree::new(center, half_size);
+        for &(position, mass) in bodies {
+            q_tree.add_node(position, mass).unwrap();
+        }
+        q_tree
+    }
+
+    fn root(q_tree: &QuadTree) -> Node {
+        q_tree.vec[q_tree.root]
+    }
+
+    fn contains(q_tree: &QuadTree, position: Vec2) -> bool {
+        let [min, max] = q_tree.bounds;
+        position.cmpge(min).all() && position.cmple(max).all()
+    }
+
+    /// Cells, masses and centers of mass of the nodes reachable from the
+    /// root, ordered by cell so trees with the nodes laid out differently
+    /// compare equal.
+    fn cells(q_tree: &QuadTree) -> Vec<(Vec2, f32, f32, Vec2)> {
+        let mut cells = Vec::new();
+        q_tree.for_each_node(|node, _| {
+            cells.push((node.center, node.half_size, node.mass, node.center_of_mass));
+        });
+        cells.sort_by(|a, b| {
+            (a.0.x, a.0.y, a.1)
+                .partial_cmp(&(b.0.x, b.0.y, b.1))
+                .unwrap()
+        });
+        cells
+    }
+
+    fn assert_same_tree(q_tree: &QuadTree, expected: &QuadTree) {
+        let (cells, expected) = (cells(q_tree), cells(expected));
+        assert_eq!(cells.len(), expected.len());
+        for (cell, expected) in cells.iter().zip(&expected) {
+            assert_eq!((cell.0, cell.1), (expected.0, expected.1));
+            assert!(
+                (cell.2 - expected.2).abs() <= expected.2 * 1e-4,
+                "{cell:?} {expected:?}"
+            );
+            assert!(cell.3.distance(expected.3) < 1e-3, "{cell:?} {expected:?}");
+        }
+    }
+
+    /// Total mass and center of mass of the `bodies`, added up one by one
+    /// in double precision.
+    fn brute_force(bodies: &[(Vec2, f32)]) -> (f32, Vec2) {
+        let mass: f64 = bodies.iter().map(|&(_, mass)| mass as f64).sum();
+        let weighted: DVec2 = bodies
+            .iter()
+            .map(|&(position, mass)| position.as_dvec2() * mass as f64)
+            .sum();
+        (mass as f32, (weighted / mass).as_vec2())
+    }
+
+    /// Leaves reachable from the root, with their depth.
+    fn leaves(q_tree: &QuadTree) -> Vec<(Node, usize)> {
+        let mut leaves = Vec::new();
+        q_tree.for_each_node(|node, depth| {
+            if node.is_leaf() {
+                leaves.push((*node, depth));
             }
+        });
+        leaves
+    }
+
+    #[test]
+    fn build_fits_the_bodies_with_a_unit_of_padding() {
+        let bodies: Vec<_> = random_bodies(100, 50., 18)
+            .into_iter()
+            .map(|(position, mass)| (position * Vec2::new(1., 0.5) + Vec2::new(300., -20.), mass))
+            .collect();
+        let (min, max) = bodies
+            .iter()
+            .fold((Vec2::MAX, Vec2::MIN), |(min, max), &(position, _)| {
+                (min.min(position), max.max(position))
+            });
+        let q_tree = QuadTree::build(&bodies);
+
+        let (center, half_size) = ((min + max) / 2., (max - min).x / 2. + 1.);
+        assert_eq!(q_tree.bounds, [center - half_size, center + half_size]);
+        assert_eq!(q_tree.root, 0);
+        for &(position, _) in &bodies {
+            assert!(q_tree.handle(position).is_some());
+        }
+        let (mass, center_of_mass) = brute_force(&bodies);
+        assert!((root(&q_tree).mass - mass).abs() <= mass * 1e-6);
+        assert!(root(&q_tree).center_of_mass.distance(center_of_mass) < 1e-3);
+    }
+
+    #[test]
+    fn build_of_no_bodies_is_around_the_origin() {
+        let q_tree = QuadTree::build(&[]);
+        assert_eq!(q_tree.bounds, [Vec2::splat(-1.), Vec2::ONE]);
+        assert_eq!(q_tree.node_count(), 1);
+        assert_eq!(root(&q_tree).mass, 0.);
+    }
+
+    #[test]
+    fn build_leaves_out_bodies_it_cant_take() {
+        let valid = [(Vec2::new(-3., 4.), 2.), (Vec2::new(5., -1.), 3.)];
+        let mut bodies = valid.to_vec();
+        bodies.extend([
+            (Vec2::new(f32::NAN, 0.), 1.),
+            (Vec2::new(f32::INFINITY, 2.), 1.),
+            (Vec2::new(1., 1.), 0.),
+            (Vec2::new(0., 2.), -1.),
+            (Vec2::new(2., 0.), f32::NAN),
+        ]);
+        let q_tree = QuadTree::build(&bodies);
+
+        // Only the positions which aren't finite are left out of the
+        // bounds, the ones of the bodies with invalid masses still count.
+        assert_eq!(q_tree.bounds, [Vec2::new(-4., -3.5), Vec2::new(6., 6.5)]);
+        assert_eq!(leaves(&q_tree).len(), 2);
+        let (mass, center_of_mass) = brute_force(&valid);
+        assert_eq!(root(&q_tree).mass, mass);
+        assert!(root(&q_tree).center_of_mass.distance(center_of_mass) < 1e-6);
+    }
+
+    #[test]
+    fn build_in_either_order_gives_the_same_tree() {
+        let bodies = random_bodies(1000, 100., 19);
+        let morton = QuadTree::build_with(&bodies, InsertionOrder::Morton);
+        let given = QuadTree::build_with(&bodies, InsertionOrder::Given);
+        assert_same_tree(&morton, &given);
+        for &(position, _) in &random_bodies(50, 120., 20) {
+            let expected = given.accumulate_acceleration(position, 1., 1., 0.);
+            let acceleration = morton.accumulate_acceleration(position, 1., 1., 0.);
+            assert!(
+                acceleration.distance(expected) <= expected.length() * 1e-5,
+                "{acceleration} {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn grow_to_contain_towards_every_quadrant() {
+        let bodies = random_bodies(50, 1., 0);
+        for target in [
+            Vec2::new(-5., -7.),
+            Vec2::new(9., -3.),
+            Vec2::new(30., 12.),
+            Vec2::new(-2., 100.),
+        ] {
+            let mut q_tree = tree_of(Vec2::ZERO, 1., &bodies);
+            let before = root(&q_tree);
+            assert!(q_tree.grow_to_contain(target));
+            assert!(contains(&q_tree, target));
+            assert!(contains(&q_tree, Vec2::splat(-1.)) && contains(&q_tree, Vec2::ONE));
+
+            let after = root(&q_tree);
+            assert!(after.half_size > before.half_size);
+            assert_eq!(after.mass, before.mass);
+            assert_eq!(after.center_of_mass, before.center_of_mass);
+            for &(position, _) in &bodies {
+                assert!(q_tree.handle(position).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn grow_to_contain_moves_an_empty_tree() {
+        let mut q_tree = QuadTree::new(Vec2::ZERO, 1.);
+        assert!(q_tree.grow_to_contain(Vec2::new(-50., 70.)));
+        assert!(contains(&q_tree, Vec2::new(-50., 70.)));
+        assert_eq!(q_tree.node_count(), 1);
+        assert_eq!(q_tree.root, 0);
+
+        q_tree.add_node(Vec2::new(-50., 70.), 1.).unwrap();
+        assert!(q_tree.handle(Vec2::new(-50., 70.)).is_some());
+    }
+
+    #[test]
+    fn grow_to_contain_keeps_a_point_on_the_edge() {
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &random_bodies(10, 1., 1));
+        let before = (q_tree.bounds, q_tree.node_count());
+        assert!(q_tree.grow_to_contain(Vec2::new(1., -1.)));
+        assert_eq!((q_tree.bounds, q_tree.node_count()), before);
+    }
+
+    #[test]
+    fn grow_to_contain_fails_without_changing_the_tree() {
+        let mut q_tree = QuadTree::new(Vec2::ZERO, 0.);
+        assert!(!q_tree.grow_to_contain(Vec2::ONE));
+
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &random_bodies(10, 1., 2));
+        let (bounds, count, root_idx) = (q_tree.bounds, q_tree.node_count(), q_tree.root);
+        for position in [Vec2::new(f32::MAX, 0.), Vec2::new(f32::NAN, 0.)] {
+            assert!(!q_tree.grow_to_contain(position));
+            assert!(q_tree.add_node(position, 1.).is_err());
+            assert_eq!(
+                (q_tree.bounds, q_tree.node_count(), q_tree.root),
+                (bounds, count, root_idx)
+            );
+        }
+    }
+
+    #[test]
+    fn remove_node_drops_the_bucket_of_the_last_body() {
+        let position = Vec2::new(0.3, -0.4);
+        let mut q_tree = tree_of(
+            Vec2::ZERO,
+            1.,
+            &[(position, 1.), (position, 2.), (position, 3.)],
+        );
+        let leaf = q_tree.handle(position).unwrap().0;
+        assert!(q_tree.vec[leaf].bucket.is_some());
+
+        q_tree.remove_node(position, 2.).unwrap();
+        let leaf = q_tree.vec[q_tree.handle(position).unwrap().0];
+        assert_eq!(q_tree.leaf_bodies(&leaf).count(), 2);
+        assert_eq!(root(&q_tree).mass, 4.);
+
+        q_tree.remove_node(position, 1.).unwrap();
+        let leaf = q_tree.vec[q_tree.handle(position).unwrap().0];
+        assert!(leaf.bucket.is_none());
+        assert_eq!((leaf.center_of_mass, leaf.mass), (position, 3.));
+        assert_same_tree(&q_tree, &tree_of(Vec2::ZERO, 1., &[(position, 3.)]));
+    }
+
+    #[test]
+    fn remove_node_collapses_a_chain_of_single_children() {
+        // Close enough together to be split apart a dozen levels down.
+        let (kept, removed) = (Vec2::new(0.3, 0.3), Vec2::new(0.3001, 0.3001));
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &[(kept, 1.), (removed, 2.)]);
+        assert!(q_tree.node_count() > 10);
+
+        q_tree.remove_node(removed, 2.).unwrap();
+        let expected = tree_of(Vec2::ZERO, 1., &[(kept, 1.)]);
+        assert_same_tree(&q_tree, &expected);
+        assert_eq!(cells(&q_tree).len(), 2);
+    }
+
+    #[test]
+    fn remove_node_fails_for_a_body_not_in_the_tree() {
+        let bodies = random_bodies(20, 1., 3);
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &bodies);
+        let before = cells(&q_tree);
+        let (position, mass) = bodies[0];
+        for (position, mass) in [(position, mass + 1.), (position + 1e-4, mass)] {
+            assert_eq!(
+                q_tree.remove_node(position, mass),
+                Err(TreeError::NotFound { position, mass })
+            );
+        }
+        assert_eq!(
+            q_tree.update_node(position + 1e-4, Vec2::ZERO, mass),
+            Err(TreeError::NotFound {
+                position: position + 1e-4,
+                mass
+            })
+        );
+        assert_eq!(cells(&q_tree), before);
+    }
+
+    #[test]
+    fn remove_node_leaves_the_tree_the_remaining_bodies_build() {
+        let bodies = random_bodies(300, 100., 4);
+        let mut q_tree = tree_of(Vec2::ZERO, 100., &bodies);
+        for &(position, mass) in bodies.iter().step_by(2) {
+            q_tree.remove_node(position, mass).unwrap();
+        }
+        let remaining: Vec<_> = bodies.iter().copied().skip(1).step_by(2).collect();
+        assert_same_tree(&q_tree, &tree_of(Vec2::ZERO, 100., &remaining));
+
+        let built = QuadTree::build(&remaining);
+        let (root, built_root) = (root(&q_tree), root(&built));
+        assert!((root.mass - built_root.mass).abs() <= built_root.mass * 1e-5);
+        assert!(root.center_of_mass.distance(built_root.center_of_mass) < 1e-3);
+    }
+
+    #[test]
+    fn update_node_matches_building_the_moved_bodies() {
+        let mut bodies = random_bodies(200, 100., 5);
+        let mut q_tree = tree_of(Vec2::ZERO, 100., &bodies);
+        let targets = random_bodies(50, 100., 6);
+        for (body, &(target, _)) in bodies.iter_mut().zip(&targets) {
+            q_tree.update_node(body.0, target, body.1).unwrap();
+            body.0 = target;
+        }
+        assert_same_tree(&q_tree, &tree_of(Vec2::ZERO, 100., &bodies));
+    }
+
+    /// Moves of all the `bodies` by up to `step` in each direction, with
+    /// the handles of their leaves in the `q_tree`.
+    fn moves(q_tree: &QuadTree, bodies: &[(Vec2, f32)], step: f32, seed: u64) -> Vec<BodyMove> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        bodies
+            .iter()
+            .map(|&(from, mass)| BodyMove {
+                handle: q_tree.handle(from).unwrap(),
+                from,
+                to: from + Vec2::new(rng.random_range(-step..step), rng.random_range(-step..step)),
+                mass,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn move_bodies_within_their_cell() {
+        let bodies = [(Vec2::new(-0.5, -0.5), 1.), (Vec2::new(0.5, 0.5), 2.)];
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &bodies);
+        let handle = q_tree.handle(bodies[0].0).unwrap();
+        let count = q_tree.node_count();
+        let to = Vec2::new(-0.25, -0.75);
+        let mut moves = [BodyMove {
+            handle,
+            from: bodies[0].0,
+            to,
+            mass: 1.,
+        }];
+        assert_eq!(q_tree.move_bodies(&mut moves), Ok(true));
+        assert_eq!(moves[0].handle, handle);
+        assert_eq!(q_tree.handle(to), Some(handle));
+        assert_eq!(q_tree.node_count(), count);
+        assert_same_tree(&q_tree, &tree_of(Vec2::ZERO, 1., &[(to, 1.), bodies[1]]));
+    }
+
+    #[test]
+    fn move_bodies_across_a_cell_edge() {
+        let bodies = [(Vec2::new(-0.5, -0.5), 1.), (Vec2::new(0.5, 0.5), 2.)];
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &bodies);
+        let to = Vec2::new(0.6, -0.2);
+        let mut moves = [BodyMove {
+            handle: q_tree.handle(bodies[0].0).unwrap(),
+            from: bodies[0].0,
+            to,
+            mass: 1.,
+        }];
+        assert_eq!(q_tree.move_bodies(&mut moves), Ok(true));
+        assert_eq!(q_tree.handle(to), Some(moves[0].handle));
+        assert_eq!(q_tree.handle(bodies[0].0), None);
+        assert_same_tree(&q_tree, &tree_of(Vec2::ZERO, 1., &[(to, 1.), bodies[1]]));
+    }
+
+    #[test]
+    fn move_bodies_out_of_bounds_changes_nothing() {
+        let bodies = random_bodies(50, 1., 7);
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &bodies);
+        let before = (cells(&q_tree), q_tree.node_count());
+        let mut moves = moves(&q_tree, &bodies, 0.01, 8);
+        moves[30].to = Vec2::new(1.5, 0.);
+        let handles: Vec<_> = moves.iter().map(|body| body.handle).collect();
+        assert_eq!(q_tree.move_bodies(&mut moves), Ok(false));
+        assert_eq!((cells(&q_tree), q_tree.node_count()), before);
+        assert!(moves.iter().map(|body| body.handle).eq(handles));
+    }
+
+    #[test]
+    fn move_bodies_matches_a_rebuild() {
+        let mut bodies = random_bodies(2000, 95., 9);
+        let mut q_tree = tree_of(Vec2::ZERO, 100., &bodies);
+        let mut moves = moves(&q_tree, &bodies, 0.5, 10);
+        for step in 0..5 {
+            assert_eq!(q_tree.move_bodies(&mut moves), Ok(true));
+            for (body, moved) in bodies.iter_mut().zip(&moves) {
+                body.0 = moved.to;
+                assert_eq!(q_tree.handle(moved.to), Some(moved.handle));
+            }
+            assert_same_tree(&q_tree, &tree_of(Vec2::ZERO, 100., &bodies));
+
+            // The next moves start from the handles the last ones left.
+            let mut rng = StdRng::seed_from_u64(11 + step);
+            for body in &mut moves {
+                body.from = body.to;
+                body.to += Vec2::new(rng.random_range(-0.5..0.5), rng.random_range(-0.5..0.5));
+            }
+        }
+    }
+
+    #[test]
+    fn shrink_root_to_the_quadrant_of_the_bodies() {
+        let bodies: Vec<_> = random_bodies(100, 0.4, 12)
+            .into_iter()
+            .map(|(position, mass)| (position + Vec2::new(0.5, -0.5), mass))
+            .collect();
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &bodies);
+        let before = root(&q_tree);
+        let handles: Vec<_> = bodies
+            .iter()
+            .map(|&(position, _)| q_tree.handle(position))
+            .collect();
+
+        assert!(q_tree.can_shrink_root());
+        assert!(q_tree.shrink_root());
+        let after = root(&q_tree);
+        assert_eq!((after.center, after.half_size), (Vec2::new(0.5, -0.5), 0.5));
+        assert_eq!(
+            (after.mass, after.center_of_mass),
+            (before.mass, before.center_of_mass)
+        );
+        assert!(bodies
+            .iter()
+            .map(|&(position, _)| q_tree.handle(position))
+            .eq(handles));
+        assert_same_tree(&q_tree, &tree_of(Vec2::new(0.5, -0.5), 0.5, &bodies));
+
+        q_tree.clear();
+        assert_eq!(q_tree.bounds, [Vec2::new(0., -1.), Vec2::new(1., 0.)]);
+    }
+
+    #[test]
+    fn shrink_root_keeps_bodies_in_several_quadrants() {
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &random_bodies(100, 1., 13));
+        let before = q_tree.bounds;
+        assert!(!q_tree.shrink_root());
+        assert_eq!(q_tree.bounds, before);
+
+        // A single body has a leaf for a child, which can't be the root.
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &[(Vec2::new(0.5, 0.5), 1.)]);
+        assert!(!q_tree.shrink_root());
+    }
+
+    #[test]
+    fn coincident_bodies_share_one_bucket() {
+        let position = Vec2::new(0.3, -0.4);
+        let bodies: Vec<_> = (1..=20).map(|mass| (position, mass as f32)).collect();
+        let q_tree = tree_of(Vec2::ZERO, 1., &bodies);
+
+        let leaves = leaves(&q_tree);
+        assert_eq!(leaves.len(), 1);
+        let (leaf, depth) = leaves[0];
+        assert_eq!(depth, MAX_DEPTH);
+        assert!(leaf.bucket.is_some());
+        assert_eq!(q_tree.buckets.len(), 1);
+        assert!(q_tree.leaf_bodies(&leaf).eq(bodies.iter().copied()));
+
+        let (mass, center_of_mass) = brute_force(&bodies);
+        assert_eq!((leaf.mass, leaf.center_of_mass), (mass, position));
+        assert_eq!(
+            (root(&q_tree).mass, root(&q_tree).center_of_mass),
+            (mass, center_of_mass)
+        );
+    }
+
+    #[test]
+    fn buckets_add_up_like_the_bodies_in_them() {
+        let mut rng = StdRng::seed_from_u64(14);
+        let mut bodies = random_bodies(200, 100., 15);
+        let clusters: Vec<Vec<(Vec2, f32)>> = random_bodies(3, 100., 16)
+            .into_iter()
+            .map(|(position, _)| {
+                (0..5)
+                    .map(|_| (position, rng.random_range(1.0..1000.0)))
+                    .collect()
+            })
+            .collect();
+        bodies.extend(clusters.iter().flatten());
+        let q_tree = tree_of(Vec2::ZERO, 100., &bodies);
+
+        assert_eq!(q_tree.buckets.len(), clusters.len());
+        for cluster in &clusters {
+            let position = cluster[0].0;
+            let leaf = q_tree.vec[q_tree.handle(position).unwrap().0];
+            assert!(q_tree.leaf_bodies(&leaf).eq(cluster.iter().copied()));
+            let (mass, _) = brute_force(cluster);
+            assert!((leaf.mass - mass).abs() <= mass * 1e-6);
+            assert_eq!(leaf.center_of_mass, position);
+        }
+        let (mass, center_of_mass) = brute_force(&bodies);
+        assert!((root(&q_tree).mass - mass).abs() <= mass * 1e-6);
+        assert!(root(&q_tree).center_of_mass.distance(center_of_mass) < 1e-4);
+    }
+
+    #[test]
+    fn splitting_a_leaf_moves_its_body_down() {
+        let (first, second) = ((Vec2::new(0.5, 0.5), 2.), (Vec2::new(0.6, 0.6), 3.));
+        let mut q_tree = tree_of(Vec2::ZERO, 1., &[first]);
+        let handle = q_tree.handle(first.0).unwrap();
+        q_tree.add_node(second.0, second.1).unwrap();
+
+        // The leaf of the first body is kept, below the internal node which
+        // took its place.
+        assert_eq!(q_tree.handle(first.0), Some(handle));
+        for (position, mass) in [first, second] {
+            let leaf = q_tree.vec[q_tree.handle(position).unwrap().0];
+            assert!(leaf.is_leaf() && leaf.bucket.is_none());
+            assert_eq!((leaf.center_of_mass, leaf.mass), (position, mass));
+            assert!((position - leaf.center)
+                .abs()
+                .cmple(Vec2::splat(leaf.half_size))
+                .all());
+        }
+        let (mass, center_of_mass) = brute_force(&[first, second]);
+        q_tree.for_each_node(|node, _| {
+            if !node.is_leaf() {
+                assert_eq!(node.mass, mass);
+                assert!(node.center_of_mass.distance(center_of_mass) < 1e-6);
+            }
+        });
+        assert_same_tree(&q_tree, &tree_of(Vec2::ZERO, 1., &[second, first]));
+    }
+
+    #[test]
+    fn compact_keeps_the_tree_and_lays_it_out_depth_first() {
+        let bodies = random_bodies(500, 100., 17);
+        // Inserted in the given order without compacting, so the children
+        // of the nodes are scattered, and with the nodes of the removed
+        // bodies left behind.
+        let mut scattered = tree_of(Vec2::ZERO, 100., &bodies);
+        for &(position, mass) in bodies.iter().step_by(7) {
+            scattered.remove_node(position, mass).unwrap();
+        }
+        let mut compacted = scattered.clone();
+        compacted.compact();
+
+        assert_eq!(compacted.root, 0);
+        assert!(compacted.node_count() < scattered.node_count());
+        assert_eq!(cells(&compacted).len(), compacted.node_count());
+        for (idx, node) in compacted.vec.iter().enumerate() {
+            assert!(node.children.iter().flatten().all(|&child| child > idx));
+        }
+        assert_same_tree(&compacted, &scattered);
+
+        let visits = |q_tree: &QuadTree, position: Vec2| {
+            let mut visits = Vec::new();
+            q_tree.for_each_body(position, 1., |mass, center_of_mass| {
+                visits.push((mass, center_of_mass));
+            });
+            visits
+        };
+        for &(position, _) in &random_bodies(50, 120., 18) {
+            assert_eq!(
+                compacted.accumulate_acceleration(position, 1., 1., 0.),
+                scattered.accumulate_acceleration(position, 1., 1., 0.)
+            );
+            assert_eq!(visits(&compacted, position), visits(&scattered, position));
+        }
+
+        // Building in the given order compacts the tree the same way.
+        let built = QuadTree::build_with(&bodies, InsertionOrder::Given);
+        assert_eq!(built.root, 0);
+        for (idx, node) in built.vec.iter().enumerate() {
+            assert!(node.children.iter().flatten().all(|&child| child > idx));
         }
     }
 }
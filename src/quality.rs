@@ -0,0 +1,133 @@
+use crate::contours::PotentialContours;
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::{MainCamera, THETA_THRESHOLD};
+use crate::preview::TrajectoryPreview;
+use crate::state::SimState;
+use bevy::core_pipeline::bloom::Bloom;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Preset trading accuracy and looks for speed, picked once and applied to
+/// both the rendering and the physics.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quality {
+    Low,
+    Medium,
+    #[default]
+    High,
+    Ultra,
+}
+
+/// Everything a [`Quality`] preset decides.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualitySettings {
+    /// Bloom intensity outside photo mode, `None` turns bloom off
+    pub bloom: Option<f32>,
+    /// Number of steps of the trajectory preview
+    pub preview_steps: usize,
+    /// Seconds between refreshes of the resampled overlays
+    pub overlay_interval: f32,
+    /// Barnes-Hut threshold of the force calculation, see
+    /// [`THETA_THRESHOLD`]
+    pub theta_threshold: f32,
+    /// Physics substeps per fixed step
+    pub substeps: u32,
+}
+
+impl Quality {
+    pub const ALL: [Quality; 4] = [Quality::Low, Quality::Medium, Quality::High, Quality::Ultra];
+
+    pub fn settings(&self) -> QualitySettings {
+        match self {
+            Quality::Low => QualitySettings {
+                bloom: None,
+                preview_steps: 50,
+                overlay_interval: 1.,
+                theta_threshold: 1.5,
+                substeps: 1,
+            },
+            Quality::Medium => QualitySettings {
+                bloom: None,
+                preview_steps: 100,
+                overlay_interval: 0.5,
+                theta_threshold: 2.,
+                substeps: 1,
+            },
+            Quality::High => QualitySettings {
+                bloom: None,
+                preview_steps: 100,
+                overlay_interval: 0.5,
+                theta_threshold: THETA_THRESHOLD,
+                substeps: 1,
+            },
+            Quality::Ultra => QualitySettings {
+                bloom: Some(0.1),
+                preview_steps: 200,
+                overlay_interval: 0.25,
+                theta_threshold: 4.,
+                substeps: 2,
+            },
+        }
+    }
+
+    /// The next preset, wrapping around from the highest to the lowest.
+    pub fn next(&self) -> Quality {
+        let index = Quality::ALL.iter().position(|quality| quality == self);
+        Quality::ALL[index.map_or(0, |index| (index + 1) % Quality::ALL.len())]
+    }
+}
+
+fn cycle_quality(actions: Actions, mut quality: ResMut<Quality>) {
+    if actions.just_pressed(Action::CycleQuality) {
+        *quality = quality.next();
+        info!("Quality set to {:?}", *quality);
+    }
+}
+
+/// Applies the rendering side of the preset. Photo mode sets up the camera
+/// on its own, so the preset is applied again when it's left.
+fn apply_quality(
+    mut commands: Commands,
+    quality: Res<Quality>,
+    contours: Option<ResMut<PotentialContours>>,
+    preview: Option<ResMut<TrajectoryPreview>>,
+    mut cameras: Query<(Entity, &mut Camera), With<MainCamera>>,
+) {
+    let settings = quality.settings();
+    for (entity, mut camera) in &mut cameras {
+        camera.hdr = settings.bloom.is_some();
+        match settings.bloom {
+            Some(intensity) => commands.entity(entity).insert(Bloom {
+                intensity,
+                ..Default::default()
+            }),
+            None => commands.entity(entity).remove::<Bloom>(),
+        };
+    }
+    if let Some(mut contours) = contours {
+        contours.set_refresh_interval(settings.overlay_interval);
+    }
+    if let Some(mut preview) = preview {
+        preview.steps = settings.preview_steps;
+    }
+}
+
+/// Switches between the [`Quality`] presets.
+pub struct QualityPlugin;
+
+impl Plugin for QualityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Quality>()
+            .init_resource::<InputMap>()
+            .add_systems(
+                Update,
+                (
+                    cycle_quality,
+                    apply_quality
+                        .run_if(resource_changed::<Quality>.or(state_changed::<SimState>))
+                        .run_if(not(in_state(SimState::Photo))),
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,73 @@
+use crate::docking::DockedPart;
+use crate::physics_plugin::Mass;
+use bevy::prelude::*;
+
+/// Radius of a body, derived from its [`Mass`] and density by
+/// [`update_radii`]. It is what the body is drawn at and what collides.
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct Radius(pub f32);
+
+/// Mass per unit of area the bodies are made of, unless they have their own
+/// [`BodyDensity`]. The default makes a body of 1 000 000 mass 3 units
+/// large.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct Density(pub f32);
+
+impl Default for Density {
+    fn default() -> Self {
+        Density(35_000.)
+    }
+}
+
+/// Density of a body made of something else than the rest, such as the
+/// heavy center of a disc.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct BodyDensity(pub f32);
+
+impl BodyDensity {
+    /// Density which makes a body of `mass` `radius` large.
+    pub fn sized(mass: f32, radius: f32) -> Self {
+        BodyDensity(mass / (std::f32::consts::PI * radius * radius))
+    }
+}
+
+/// Radius of a disc of `mass` and `density`.
+pub fn radius_of(mass: f32, density: f32) -> f32 {
+    (mass.max(0.) / (std::f32::consts::PI * density)).sqrt()
+}
+
+/// Recomputes the [`Radius`] of the bodies whose mass or density changed,
+/// scaling them to it and keeping their docked parts where they were.
+#[allow(clippy::type_complexity)]
+pub fn update_radii(
+    density: Res<Density>,
+    mut bodies: Query<(
+        Ref<Mass>,
+        Option<Ref<BodyDensity>>,
+        &mut Radius,
+        &mut Transform,
+        Option<&Children>,
+    )>,
+    mut parts: Query<(&DockedPart, &mut Transform), Without<Mass>>,
+) {
+    for (mass, body_density, mut radius, mut transform, children) in &mut bodies {
+        let body_density_changed = body_density.as_ref().is_some_and(|d| d.is_changed());
+        if !(density.is_changed() || mass.is_changed() || body_density_changed) {
+            continue;
+        }
+        let size = radius_of(mass.0, body_density.map_or(density.0, |d| d.0));
+        if radius.0 == size {
+            continue;
+        }
+        radius.0 = size;
+        transform.scale = Vec3::new(size, size, 1.);
+
+        // Children are placed relative to the parent's scale.
+        for &child in children.into_iter().flatten() {
+            if let Ok((part, mut local)) = parts.get_mut(child) {
+                local.translation = (part.offset / size).extend(0.);
+                local.scale = part.scale / transform.scale;
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::{MainCamera, Mass};
+use crate::state::SimState;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Fraction of the total mass a single body has to hold to dominate the
+/// others.
+pub const DOMINANT_FRACTION: f32 = 0.5;
+
+/// Frame the bodies are shown in. The physics always runs in the inertial
+/// frame, only the view moves.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReferenceFrame {
+    #[default]
+    Inertial,
+    /// Moving along with the body which holds most of the mass, so its slow
+    /// drift doesn't carry the whole scene out of view over long runs.
+    /// Inertial while no body dominates.
+    DominantBody,
+}
+
+impl ReferenceFrame {
+    /// Parses `inertial` or `dominant-body`.
+    pub fn parse(frame: &str) -> Option<Self> {
+        match frame {
+            "inertial" => Some(ReferenceFrame::Inertial),
+            "dominant-body" => Some(ReferenceFrame::DominantBody),
+            _ => None,
+        }
+    }
+}
+
+/// The body holding more than [`DOMINANT_FRACTION`] of the total mass of
+/// the `bodies`, given as entities and masses.
+pub fn dominant_body(bodies: impl IntoIterator<Item = (Entity, f32)>) -> Option<Entity> {
+    let mut total = 0.;
+    let mut heaviest: Option<(Entity, f32)> = None;
+    for (entity, mass) in bodies {
+        total += mass;
+        if heaviest.is_none_or(|(_, heaviest_mass)| mass > heaviest_mass) {
+            heaviest = Some((entity, mass));
+        }
+    }
+    heaviest
+        .filter(|&(_, mass)| mass > total * DOMINANT_FRACTION)
+        .map(|(entity, _)| entity)
+}
+
+/// The body the view moves along with, and where it was when the camera
+/// last moved.
+#[derive(Resource, Debug, Default)]
+pub struct FrameAnchor {
+    pub body: Option<Entity>,
+    position: Option<Vec2>,
+}
+
+fn toggle_reference_frame(actions: Actions, mut frame: ResMut<ReferenceFrame>) {
+    if actions.just_pressed(Action::ToggleReferenceFrame) {
+        *frame = match *frame {
+            ReferenceFrame::Inertial => ReferenceFrame::DominantBody,
+            ReferenceFrame::DominantBody => ReferenceFrame::Inertial,
+        };
+    }
+}
+
+fn reset_frame_anchor(mut anchor: ResMut<FrameAnchor>) {
+    *anchor = FrameAnchor::default();
+}
+
+/// Moves the main camera by as much as the dominant body of world 0 moved
+/// since the last frame. A body which starts or stops dominating, e.g.
+/// after merging, only becomes the anchor, the view doesn't jump to it.
+fn follow_dominant_body(
+    frame: Res<ReferenceFrame>,
+    mut anchor: ResMut<FrameAnchor>,
+    bodies: Query<(Entity, &Mass, &Transform, Option<&SimWorld>), Without<MainCamera>>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+) {
+    if *frame != ReferenceFrame::DominantBody {
+        anchor.bypass_change_detection().position = None;
+        return;
+    }
+    let body = dominant_body(
+        bodies
+            .iter()
+            .filter(|(_, _, _, world)| world.copied().unwrap_or_default() == SimWorld(0))
+            .map(|(entity, mass, _, _)| (entity, mass.0)),
+    );
+    let position = body
+        .and_then(|body| bodies.get(body).ok())
+        .map(|(_, _, transform, _)| transform.translation.xy());
+    if body == anchor.body {
+        if let (Some(previous), Some(position)) = (anchor.position, position) {
+            for mut transform in &mut cameras {
+                transform.translation += (position - previous).extend(0.);
+            }
+        }
+    }
+    anchor.body = body;
+    anchor.position = position;
+}
+
+/// Shows the bodies in the [`ReferenceFrame`], toggled with H by default.
+pub struct ReferenceFramePlugin;
+
+impl Plugin for ReferenceFramePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReferenceFrame>()
+            .init_resource::<FrameAnchor>()
+            .init_resource::<InputMap>()
+            .add_systems(OnEnter(SimState::Loading), reset_frame_anchor)
+            .add_systems(
+                Update,
+                (toggle_reference_frame, follow_dominant_body).chain(),
+            );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::comparison::AccuracyComparison;
+use crate::ephemeris::EphemerisComparison;
+use crate::input::{Action, Actions};
+use crate::mission::Mission;
+use crate::physics_config::PhysicsOverrides;
+use crate::stability::reroll_unstable;
+use crate::state::{restart_time, SimState};
+use crate::statistics::SimStatistics;
+use crate::worlds::{SimWorld, SimWorlds};
+use bevy::ecs::system::SystemId;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// A way to populate the simulation.
+pub struct Scenario {
+    pub name: String,
+    /// One line shown under the name in the menu
+    pub description: String,
+    /// System spawning the bodies of the scenario
+    pub spawn: SystemId,
+    /// Physics parameters the scenario needs, on top of the user's
+    pub physics: PhysicsOverrides,
+    /// Message id of the name in the menu, with the description under
+    /// `<id>-description`. Without one the name and description are shown
+    /// as they are.
+    pub message: Option<String>,
+}
+
+/// Every scenario that can be loaded, and the one that is loaded when
+/// entering [`SimState::Loading`].
+#[derive(Resource, Default)]
+pub struct Scenarios {
+    pub entries: Vec<Scenario>,
+    pub selected: usize,
+}
+
+impl Scenarios {
+    /// Selects the scenario called `name`, returning whether there is one.
+    pub fn select(&mut self, name: &str) -> bool {
+        match self
+            .entries
+            .iter()
+            .position(|scenario| scenario.name == name)
+        {
+            Some(index) => {
+                self.selected = index;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Marks the entities spawned by the scenario, which are despawned when it
+/// is restarted.
+#[derive(Component)]
+pub struct ScenarioEntity;
+
+/// Source of randomness for spawning scenarios. It is reseeded with the
+/// same seed on restart, so a restarted scenario comes out the same.
+#[derive(Resource)]
+pub struct SimRng {
+    pub seed: u64,
+    pub rng: StdRng,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        SimRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        SimRng::new(rand::random())
+    }
+}
+
+/// Registering scenarios from plugins.
+pub trait RegisterScenario {
+    /// Adds a scenario whose bodies are spawned by the `spawn` system.
+    fn register_scenario<M>(
+        &mut self,
+        name: &str,
+        description: &str,
+        spawn: impl IntoSystem<(), (), M> + 'static,
+    ) -> &mut Self;
+
+    /// Makes the scenario called `name` run with the `physics` overrides,
+    /// see [`PhysicsConfig`](crate::physics_config::PhysicsConfig).
+    fn override_scenario_physics(&mut self, name: &str, physics: PhysicsOverrides) -> &mut Self;
+
+    /// Shows the scenario called `name` in the menu with the messages
+    /// `message` and `<message>-description` of the
+    /// [`Localization`](crate::localization::Localization).
+    fn localize_scenario(&mut self, name: &str, message: &str) -> &mut Self;
+}
+
+impl RegisterScenario for App {
+    fn register_scenario<M>(
+        &mut self,
+        name: &str,
+        description: &str,
+        spawn: impl IntoSystem<(), (), M> + 'static,
+    ) -> &mut Self {
+        let spawn = self.register_system(spawn);
+        self.init_resource::<Scenarios>();
+        self.world_mut()
+            .resource_mut::<Scenarios>()
+            .entries
+            .push(Scenario {
+                name: name.to_owned(),
+                description: description.to_owned(),
+                spawn,
+                physics: PhysicsOverrides::default(),
+                message: None,
+            });
+        self
+    }
+
+    fn override_scenario_physics(&mut self, name: &str, physics: PhysicsOverrides) -> &mut Self {
+        self.init_resource::<Scenarios>();
+        let mut scenarios = self.world_mut().resource_mut::<Scenarios>();
+        match scenarios
+            .entries
+            .iter_mut()
+            .find(|scenario| scenario.name == name)
+        {
+            Some(scenario) => scenario.physics = physics,
+            None => warn!("No scenario `{name}` to override the physics of"),
+        }
+        self
+    }
+
+    fn localize_scenario(&mut self, name: &str, message: &str) -> &mut Self {
+        self.init_resource::<Scenarios>();
+        let mut scenarios = self.world_mut().resource_mut::<Scenarios>();
+        match scenarios
+            .entries
+            .iter_mut()
+            .find(|scenario| scenario.name == name)
+        {
+            Some(scenario) => scenario.message = Some(message.to_owned()),
+            None => warn!("No scenario `{name}` to localize"),
+        }
+        self
+    }
+}
+
+/// Spawns the selected scenario once for every world of [`SimWorlds`],
+/// marking everything it spawned as a [`ScenarioEntity`] of that world, and
+/// starts simulating it. With a
+/// [`StabilityProbe`](crate::stability::StabilityProbe) the seed is moved on
+/// past the ones giving unstable systems first.
+pub fn load_scenario(world: &mut World) {
+    let scenarios = world.resource::<Scenarios>();
+    let spawn = scenarios
+        .entries
+        .get(scenarios.selected)
+        .map(|scenario| scenario.spawn);
+    let count = world
+        .get_resource::<SimWorlds>()
+        .map_or(1, |worlds| worlds.count);
+
+    if let Some(spawn) = spawn {
+        let seed = world.resource::<SimRng>().seed;
+        let seed = reroll_unstable(world, spawn, seed);
+        for sim_world in 0..count {
+            // Every world starts from the same random draws.
+            *world.resource_mut::<SimRng>() = SimRng::new(seed);
+            let existing: HashSet<Entity> =
+                world.iter_entities().map(|entity| entity.id()).collect();
+            if let Err(err) = world.run_system(spawn) {
+                error!("Couldn't spawn the scenario: {err}");
+            }
+            let spawned: Vec<Entity> = world
+                .iter_entities()
+                .map(|entity| entity.id())
+                .filter(|entity| !existing.contains(entity))
+                .collect();
+            for entity in spawned {
+                let mut entity = world.entity_mut(entity);
+                entity.insert(ScenarioEntity);
+                if count > 1 {
+                    entity.insert(SimWorld(sim_world));
+                }
+            }
+        }
+    }
+    world
+        .resource_mut::<NextState<SimState>>()
+        .set(SimState::Running);
+}
+
+/// Asks for the selected scenario to be loaded from scratch.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RestartScenario;
+
+pub fn request_restart(actions: Actions, mut restarts: EventWriter<RestartScenario>) {
+    if actions.just_pressed(Action::Restart) {
+        restarts.send(RestartScenario);
+    }
+}
+
+/// Despawns the scenario, resets what was accumulated while simulating it
+/// and loads the selected scenario again.
+///
+/// The mission refers to bodies of the old scenario, so it is removed and
+/// left for the scenario to insert again.
+#[allow(clippy::too_many_arguments)]
+pub fn restart_scenario(
+    mut restarts: EventReader<RestartScenario>,
+    mut commands: Commands,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut rng: ResMut<SimRng>,
+    comparison: Option<ResMut<AccuracyComparison>>,
+    ephemerides: Option<ResMut<EphemerisComparison>>,
+    statistics: Option<ResMut<SimStatistics>>,
+    entities: Query<Entity, (With<ScenarioEntity>, Without<Parent>)>,
+) {
+    if restarts.read().count() == 0 || matches!(state.get(), SimState::Menu | SimState::Loading) {
+        return;
+    }
+
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    restart_time(&mut time);
+    *rng = SimRng::new(rng.seed);
+    if let Some(mut comparison) = comparison {
+        comparison.stop();
+    }
+    if let Some(mut ephemerides) = ephemerides {
+        ephemerides.errors.clear();
+    }
+    if let Some(mut statistics) = statistics {
+        *statistics = SimStatistics::default();
+    }
+    commands.remove_resource::<Mission>();
+    next_state.set(SimState::Loading);
+}
@@ -0,0 +1,1165 @@
+use crate::autopilot::{Autopilot, AutopilotMode};
+use crate::distributions::Distribution;
+use crate::docking::Dockable;
+use crate::ephemeris::EphemerisComparison;
+use crate::mission::{Mission, Objective};
+use crate::orbits::{OrbitParent, RelativeOrbit, RelativeSpawn, SpawnEpoch};
+use crate::physics_config::PhysicsOverrides;
+use crate::physics_plugin::{BodyMaterial, Mass, PhysicsSettings, Velocity};
+use crate::radius::BodyDensity;
+use crate::scenario::{RegisterScenario, Scenarios, SimRng};
+use crate::sim_rate::parse_duration;
+use crate::tether::Tether;
+use crate::theme::Theme;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A single body of a [`ScenarioFile`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BodySpec {
+    /// Name the objectives refer to the body by
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub position: Vec2,
+    #[serde(default)]
+    pub velocity: Vec2,
+    pub mass: f32,
+    /// Orbit around another body, placing the body instead of its position
+    /// and velocity
+    #[serde(default)]
+    pub orbit: Option<OrbitSpec>,
+    /// Radius to draw and collide the body at instead of the one its mass
+    /// gives it
+    #[serde(default)]
+    pub radius: Option<f32>,
+    /// sRGB color from 0 to 1, the theme's body color by default
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+    /// Lets the body dock with other dockable bodies it touches
+    #[serde(default)]
+    pub dockable: Option<Dockable>,
+    #[serde(default)]
+    pub autopilot: Option<AutopilotSpec>,
+}
+
+/// An [`Autopilot`] steering the body relative to another one referred to
+/// by name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutopilotSpec {
+    pub mode: AutopilotModeSpec,
+    /// Largest acceleration the engines can produce
+    pub max_acceleration: f32,
+}
+
+/// What an autopilot is trying to achieve, see [`AutopilotMode`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AutopilotModeSpec {
+    HoldOrbit { center: String, radius: f32 },
+    HoldPosition { anchor: String, offset: Vec2 },
+    Intercept { target: String },
+}
+
+impl AutopilotSpec {
+    /// Name of the body the mode is relative to.
+    fn reference(&self) -> &str {
+        match &self.mode {
+            AutopilotModeSpec::HoldOrbit { center, .. } => center,
+            AutopilotModeSpec::HoldPosition { anchor, .. } => anchor,
+            AutopilotModeSpec::Intercept { target } => target,
+        }
+    }
+
+    /// The lengths and accelerations of the autopilot, by field name.
+    fn quantities(&self) -> Vec<(&str, f32)> {
+        let mut quantities = vec![("max_acceleration", self.max_acceleration)];
+        if let AutopilotModeSpec::HoldOrbit { radius, .. } = self.mode {
+            quantities.push(("radius", radius));
+        }
+        quantities
+    }
+
+    /// The autopilot relative to the body spawned as `reference`.
+    fn autopilot(&self, reference: Entity) -> Autopilot {
+        let mode = match self.mode {
+            AutopilotModeSpec::HoldOrbit { radius, .. } => AutopilotMode::HoldOrbit {
+                center: reference,
+                radius,
+            },
+            AutopilotModeSpec::HoldPosition { offset, .. } => AutopilotMode::HoldPosition {
+                anchor: reference,
+                offset,
+            },
+            AutopilotModeSpec::Intercept { .. } => AutopilotMode::Intercept { target: reference },
+        };
+        Autopilot {
+            mode,
+            max_acceleration: self.max_acceleration,
+        }
+    }
+}
+
+/// The Keplerian elements of a body's orbit around the body named `parent`,
+/// with the angles in radians.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrbitSpec {
+    pub parent: String,
+    /// Semi-major axis
+    pub a: f32,
+    /// Eccentricity, from 0 for a circle to below 1
+    #[serde(default)]
+    pub e: f32,
+    /// Inclination, the orbit is retrograde above a right angle
+    #[serde(default)]
+    pub i: f32,
+    #[serde(default)]
+    pub arg_periapsis: f32,
+    #[serde(default)]
+    pub ascending_node: f32,
+    #[serde(default)]
+    pub mean_anomaly: f32,
+}
+
+impl OrbitSpec {
+    /// The orbit in the plane of the simulation, where a retrograde orbit
+    /// measures the argument of periapsis clockwise from the ascending
+    /// node.
+    fn orbit(&self) -> RelativeOrbit {
+        let retrograde = self.i.cos() < 0.;
+        RelativeOrbit::Elements {
+            semi_major_axis: self.a,
+            eccentricity: self.e,
+            argument_of_periapsis: if retrograde {
+                self.ascending_node - self.arg_periapsis
+            } else {
+                self.ascending_node + self.arg_periapsis
+            },
+            mean_anomaly: self.mean_anomaly,
+            retrograde,
+        }
+    }
+}
+
+/// Bodies scattered around a center, each drawn from the distributions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupSpec {
+    pub count: usize,
+    #[serde(default)]
+    pub center: Vec2,
+    /// Velocity of the group as a whole
+    #[serde(default)]
+    pub velocity: Vec2,
+    /// Distance of the bodies from the center, in a random direction
+    pub distance: Distribution,
+    /// Speed of the bodies around the center, counterclockwise
+    #[serde(default = "no_speed")]
+    pub speed: Distribution,
+    pub mass: Distribution,
+    #[serde(default)]
+    pub color: Option<[f32; 3]>,
+}
+
+fn no_speed() -> Distribution {
+    Distribution::Constant(0.)
+}
+
+impl GroupSpec {
+    /// Position, velocity and mass of a body of the group drawn from `rng`.
+    fn sample(&self, rng: &mut impl Rng) -> (Vec2, Vec2, f32) {
+        let direction = Vec2::from_angle(rng.random_range(0.0..std::f32::consts::TAU));
+        let offset = direction * self.distance.sample(rng);
+        let speed = self.speed.sample(rng);
+        (
+            self.center + offset,
+            self.velocity + direction.perp() * speed,
+            // Wide distributions can draw masses the tree rejects.
+            self.mass.sample(rng).max(f32::MIN_POSITIVE),
+        )
+    }
+}
+
+/// A [`Tether`] between two bodies referred to by name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TetherSpec {
+    /// Body holding the tether, which holds no other
+    pub body: String,
+    pub other: String,
+    pub rest_length: f32,
+    pub stiffness: f32,
+    #[serde(default)]
+    pub damping: f32,
+    /// Force above which the tether snaps, unbreakable without one
+    #[serde(default)]
+    pub break_force: Option<f32>,
+}
+
+impl TetherSpec {
+    /// The lengths and forces of the tether, by field name, and whether
+    /// they can be zero.
+    fn quantities(&self) -> Vec<(&str, f32, bool)> {
+        let mut quantities = vec![
+            ("rest_length", self.rest_length, true),
+            ("stiffness", self.stiffness, false),
+            ("damping", self.damping, true),
+        ];
+        quantities.extend(self.break_force.map(|force| ("break_force", force, false)));
+        quantities
+    }
+
+    /// The tether to the body spawned as `other`.
+    fn tether(&self, other: Entity) -> Tether {
+        Tether {
+            other,
+            rest_length: self.rest_length,
+            stiffness: self.stiffness,
+            damping: self.damping,
+            break_force: self.break_force.unwrap_or(f32::INFINITY),
+        }
+    }
+}
+
+/// An objective of a scenario's mission, see [`Objective`], with the bodies
+/// referred to by name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ObjectiveSpec {
+    ReachOrbit {
+        body: String,
+        center: String,
+        radius: f32,
+        tolerance: f32,
+    },
+    Rendezvous {
+        body: String,
+        target: String,
+        distance: f32,
+    },
+    Survive {
+        body: String,
+        duration: f32,
+    },
+}
+
+impl ObjectiveSpec {
+    fn bodies(&self) -> Vec<&str> {
+        match self {
+            ObjectiveSpec::ReachOrbit { body, center, .. } => vec![body, center],
+            ObjectiveSpec::Rendezvous { body, target, .. } => vec![body, target],
+            ObjectiveSpec::Survive { body, .. } => vec![body],
+        }
+    }
+
+    /// The lengths and times of the objective, by field name.
+    fn quantities(&self) -> Vec<(&str, f32)> {
+        match *self {
+            ObjectiveSpec::ReachOrbit {
+                radius, tolerance, ..
+            } => vec![("radius", radius), ("tolerance", tolerance)],
+            ObjectiveSpec::Rendezvous { distance, .. } => vec![("distance", distance)],
+            ObjectiveSpec::Survive { duration, .. } => vec![("duration", duration)],
+        }
+    }
+
+    /// The objective for the bodies spawned under the names, which
+    /// [`ScenarioFile::check_links`] made sure are all there.
+    fn objective(&self, named: &HashMap<&str, Entity>) -> Objective {
+        let entity = |name: &String| {
+            *named
+                .get(name.as_str())
+                .expect("Objective refers to a body that wasn't checked")
+        };
+        match self {
+            ObjectiveSpec::ReachOrbit {
+                body,
+                center,
+                radius,
+                tolerance,
+            } => Objective::ReachOrbit {
+                body: entity(body),
+                center: entity(center),
+                radius: *radius,
+                tolerance: *tolerance,
+            },
+            ObjectiveSpec::Rendezvous {
+                body,
+                target,
+                distance,
+            } => Objective::Rendezvous {
+                body: entity(body),
+                target: entity(target),
+                distance: *distance,
+            },
+            ObjectiveSpec::Survive { body, duration } => Objective::Survive {
+                body: entity(body),
+                duration: *duration,
+            },
+        }
+    }
+}
+
+/// Seconds in a day.
+const DAY: f64 = 86_400.;
+
+/// A time in a scenario file: a UTC date such as `2025-03-01` or
+/// `2025-03-01T12:30:00`, or a duration such as `30d` or `1y` in the units
+/// of [`parse_duration`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ScenarioTime {
+    /// Seconds since 1970-01-01T00:00:00
+    Date(f64),
+    /// Seconds
+    Duration(f64),
+}
+
+impl TryFrom<String> for ScenarioTime {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        parse_duration(&text)
+            .map(ScenarioTime::Duration)
+            .or_else(|| parse_date(&text).map(ScenarioTime::Date))
+            .ok_or_else(|| format!("`{text}` is neither a date nor a duration"))
+    }
+}
+
+impl From<ScenarioTime> for String {
+    fn from(time: ScenarioTime) -> String {
+        match time {
+            ScenarioTime::Date(seconds) => {
+                let days = (seconds / DAY).floor();
+                let (year, month, day) = civil_from_days(days as i64);
+                let seconds = (seconds - days * DAY).round() as u32;
+                format!(
+                    "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                )
+            }
+            ScenarioTime::Duration(seconds) => format!("{seconds}s"),
+        }
+    }
+}
+
+/// Seconds since 1970-01-01 of a date such as `2025-03-01`, optionally
+/// followed by a time such as `T12:30` or `T12:30:15.5`.
+fn parse_date(date: &str) -> Option<f64> {
+    let date = date.trim().trim_end_matches('Z');
+    let (date, time) = date.split_once('T').unwrap_or((date, "00:00"));
+    let mut fields = date.splitn(3, '-');
+    let year: i64 = fields.next()?.parse().ok()?;
+    let month: i64 = fields.next()?.parse().ok()?;
+    let day: i64 = fields.next()?.parse().ok()?;
+    let mut fields = time.splitn(3, ':');
+    let hour: u32 = fields.next()?.parse().ok()?;
+    let minute: u32 = fields.next()?.parse().ok()?;
+    let second: f64 = fields
+        .next()
+        .map_or(Some(0.), |second| second.parse().ok())?;
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour >= 24
+        || minute >= 60
+        || !(0. ..60.).contains(&second)
+    {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    // Rejects days past the end of the month, which roll over.
+    (civil_from_days(days) == (year, month, day))
+        .then(|| days as f64 * DAY + f64::from(hour * 3600 + minute * 60) + second)
+}
+
+/// Days from 1970-01-01 to the date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Years starting in March, so the leap day comes last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Year, month and day `days` after 1970-01-01, the inverse of
+/// [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// A scenario described in a file rather than in code, so initial
+/// conditions can be tried out without recompiling. Read from RON when the
+/// file ends in `.ron`, from TOML otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioFile {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub physics: PhysicsOverrides,
+    #[serde(default)]
+    pub bodies: Vec<BodySpec>,
+    #[serde(default)]
+    pub groups: Vec<GroupSpec>,
+    #[serde(default)]
+    pub tethers: Vec<TetherSpec>,
+    /// Date the orbits are given at, which a start date is counted from
+    #[serde(default)]
+    pub reference_epoch: Option<ScenarioTime>,
+    /// When the simulation starts, a date or a duration after the
+    /// reference epoch, the bodies on orbits are moved along to it first
+    #[serde(default)]
+    pub start: Option<ScenarioTime>,
+    /// Objectives of the mission played in the scenario, none for a sandbox
+    #[serde(default)]
+    pub objectives: Vec<ObjectiveSpec>,
+}
+
+impl ScenarioFile {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let scenario: ScenarioFile = if path.extension().is_some_and(|ext| ext == "ron") {
+            ron::from_str(&source).map_err(|err| err.to_string())?
+        } else {
+            toml::from_str(&source).map_err(|err| err.to_string())?
+        };
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Checks that the bodies have positive masses, the start fits the
+    /// reference epoch and the orbits, autopilots, tethers and objectives
+    /// refer to bodies there are. Files are checked when loaded and again
+    /// when registered, as they can also be built or parsed by hand.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(body) = self
+            .bodies
+            .iter()
+            .find(|body| !(body.mass.is_finite() && body.mass > 0.))
+        {
+            return Err(format!(
+                "the body at {} needs a positive mass",
+                body.position
+            ));
+        }
+        self.start_elapsed()?;
+        self.check_links()
+    }
+
+    /// Checks that the orbits, autopilots, tethers and objectives refer to
+    /// bodies by names there are, with positive lengths, accelerations,
+    /// forces and times, elliptic orbits that don't go around in a circle
+    /// of parents and at most one tether held by a body.
+    fn check_links(&self) -> Result<(), String> {
+        let mut names = Vec::new();
+        for name in self.bodies.iter().filter_map(|body| body.name.as_deref()) {
+            if names.contains(&name) {
+                return Err(format!("there are several bodies named `{name}`"));
+            }
+            names.push(name);
+        }
+        let known = |name: &str| {
+            if names.contains(&name) {
+                Ok(())
+            } else {
+                Err(format!("there is no body named `{name}`"))
+            }
+        };
+
+        for orbit in self.bodies.iter().filter_map(|body| body.orbit.as_ref()) {
+            known(&orbit.parent)?;
+            if !(orbit.a.is_finite() && orbit.a > 0.) {
+                return Err(format!("the orbit's a of {} isn't positive", orbit.a));
+            }
+            if !(0. ..1.).contains(&orbit.e) {
+                return Err(format!("the orbit's e of {} isn't in 0..1", orbit.e));
+            }
+        }
+        let parent = |name: &str| {
+            self.bodies
+                .iter()
+                .find(|body| body.name.as_deref() == Some(name))
+                .and_then(|body| body.orbit.as_ref())
+                .map(|orbit| orbit.parent.as_str())
+        };
+        for body in &self.bodies {
+            // Without a circle the parents end at a body without an orbit
+            // before running out of bodies.
+            let mut ancestor = body.orbit.as_ref().map(|orbit| orbit.parent.as_str());
+            for _ in 0..self.bodies.len() {
+                ancestor = ancestor.and_then(parent);
+            }
+            if let Some(name) = ancestor {
+                return Err(format!("the orbits around `{name}` go around in a circle"));
+            }
+        }
+
+        for body in &self.bodies {
+            let Some(autopilot) = &body.autopilot else {
+                continue;
+            };
+            known(autopilot.reference())?;
+            if body.name.as_deref() == Some(autopilot.reference()) {
+                return Err(format!(
+                    "`{}` steers relative to itself",
+                    autopilot.reference()
+                ));
+            }
+            if let Some((field, value)) = autopilot
+                .quantities()
+                .into_iter()
+                .find(|(_, value)| !(value.is_finite() && *value > 0.))
+            {
+                return Err(format!("the autopilot's {field} of {value} isn't positive"));
+            }
+        }
+
+        let mut holding = Vec::new();
+        for tether in &self.tethers {
+            known(&tether.body)?;
+            known(&tether.other)?;
+            if tether.body == tether.other {
+                return Err(format!("`{}` is tethered to itself", tether.body));
+            }
+            if holding.contains(&tether.body.as_str()) {
+                return Err(format!("`{}` holds several tethers", tether.body));
+            }
+            holding.push(tether.body.as_str());
+            if let Some((field, value, _)) =
+                tether.quantities().into_iter().find(|&(_, value, zero)| {
+                    !(value.is_finite() && (value > 0. || zero && value == 0.))
+                })
+            {
+                return Err(format!("the tether's {field} of {value} isn't positive"));
+            }
+        }
+
+        for objective in &self.objectives {
+            objective.bodies().into_iter().try_for_each(known)?;
+            if let Some((field, value)) = objective
+                .quantities()
+                .into_iter()
+                .find(|(_, value)| !(value.is_finite() && *value > 0.))
+            {
+                return Err(format!("the objective's {field} of {value} isn't positive"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Time from the reference epoch the orbits are given at to the start of
+    /// the simulation.
+    pub fn start_elapsed(&self) -> Result<f32, String> {
+        let elapsed = match (self.reference_epoch, self.start) {
+            (Some(ScenarioTime::Duration(_)), _) => {
+                return Err("the reference epoch needs to be a date".into())
+            }
+            (_, None) => 0.,
+            (_, Some(ScenarioTime::Duration(duration))) => duration,
+            (Some(ScenarioTime::Date(reference)), Some(ScenarioTime::Date(start))) => {
+                start - reference
+            }
+            (None, Some(ScenarioTime::Date(_))) => {
+                return Err("a start date needs a reference epoch to count from".into())
+            }
+        } as f32;
+        if !elapsed.is_finite() {
+            return Err(format!("the start {elapsed} isn't a time"));
+        }
+        Ok(elapsed)
+    }
+
+    /// [`Self::start_elapsed`], or the reference epoch itself when the
+    /// times don't fit together.
+    fn elapsed(&self) -> f32 {
+        self.start_elapsed().unwrap_or_else(|err| {
+            warn!("Starting {} at the reference epoch, {err}", self.name);
+            0.
+        })
+    }
+
+    /// Position and velocity of every body `elapsed` time after the epoch of
+    /// the orbits, with the bodies on an orbit placed around their parents
+    /// under the gravitational constant `g`.
+    fn body_states(&self, g: f32, elapsed: f32) -> Vec<(Vec2, Vec2)> {
+        let index: HashMap<&str, usize> = self
+            .bodies
+            .iter()
+            .enumerate()
+            .filter_map(|(i, body)| Some((body.name.as_deref()?, i)))
+            .collect();
+        let mut states: Vec<Option<(Vec2, Vec2)>> = self
+            .bodies
+            .iter()
+            .map(|body| match body.orbit {
+                Some(_) => None,
+                None => Some((body.position, body.velocity)),
+            })
+            .collect();
+
+        // Parents before their children, until the orbits that are left
+        // can't be placed.
+        let mut placed = true;
+        while placed {
+            placed = false;
+            for (i, body) in self.bodies.iter().enumerate() {
+                let (None, Some(orbit)) = (states[i], &body.orbit) else {
+                    continue;
+                };
+                let Some(&parent) = index.get(orbit.parent.as_str()) else {
+                    continue;
+                };
+                let Some((position, velocity)) = states[parent] else {
+                    continue;
+                };
+                let total_mass = self.bodies[parent].mass + body.mass;
+                let (offset, relative_velocity) =
+                    orbit.orbit().relative_state(total_mass, g, elapsed);
+                states[i] = Some((position + offset, velocity + relative_velocity));
+                placed = true;
+            }
+        }
+        self.bodies
+            .iter()
+            .zip(states)
+            .map(|(body, state)| state.unwrap_or((body.position, body.velocity)))
+            .collect()
+    }
+
+    /// Spawns the bodies, with the groups drawn from `rng`, and sets up the
+    /// mission of the scenario. Bodies on an orbit are placed where they
+    /// are at the start, linked to their [`OrbitParent`] and left a
+    /// [`RelativeSpawn`] for the [`AnalyticOrbit`] the
+    /// [`EphemerisComparison`] compares them with.
+    ///
+    /// [`AnalyticOrbit`]: crate::orbits::AnalyticOrbit
+    fn spawn(
+        &self,
+        commands: &mut Commands,
+        g: f32,
+        circle: &Handle<Mesh>,
+        body_material: &Handle<ColorMaterial>,
+        materials: &mut Assets<ColorMaterial>,
+        rng: &mut impl Rng,
+    ) {
+        let mut colored: HashMap<[u32; 3], Handle<ColorMaterial>> = HashMap::default();
+        let mut material = |color: Option<[f32; 3]>| match color {
+            Some(color) => colored
+                .entry(color.map(f32::to_bits))
+                .or_insert_with(|| materials.add(Color::srgb(color[0], color[1], color[2])))
+                .clone(),
+            None => body_material.clone(),
+        };
+
+        let mut named: HashMap<&str, Entity> = HashMap::default();
+        let mut entities = Vec::new();
+        let elapsed = self.elapsed();
+        for (body, (position, velocity)) in self.bodies.iter().zip(self.body_states(g, elapsed)) {
+            let mut entity = commands.spawn((
+                Velocity(velocity),
+                Mass(body.mass),
+                Mesh2d(circle.clone()),
+                MeshMaterial2d(material(body.color)),
+                Transform::from_translation(position.extend(0.)),
+            ));
+            if let Some(radius) = body.radius {
+                entity.insert(BodyDensity::sized(body.mass, radius));
+            }
+            if let Some(dockable) = body.dockable {
+                entity.insert(dockable);
+            }
+            if let Some(name) = &body.name {
+                entity.insert(Name::new(name.clone()));
+                named.insert(name, entity.id());
+            }
+            entities.push(entity.id());
+        }
+        for (body, entity) in self.bodies.iter().zip(entities) {
+            if let Some(autopilot) = &body.autopilot {
+                commands
+                    .entity(entity)
+                    .insert(autopilot.autopilot(named[autopilot.reference()]));
+            }
+            if let Some(orbit) = &body.orbit {
+                let parent = named[orbit.parent.as_str()];
+                commands.entity(entity).insert((
+                    OrbitParent(parent),
+                    RelativeSpawn {
+                        parent,
+                        orbit: orbit.orbit(),
+                    },
+                ));
+            }
+        }
+        for tether in &self.tethers {
+            commands
+                .entity(named[tether.body.as_str()])
+                .insert(tether.tether(named[tether.other.as_str()]));
+        }
+        if self.bodies.iter().any(|body| body.orbit.is_some()) {
+            // Keeps the interval of one already running.
+            commands.init_resource::<EphemerisComparison>();
+        }
+        commands.insert_resource(SpawnEpoch { elapsed });
+        if !self.objectives.is_empty() {
+            commands.insert_resource(Mission::new(
+                self.objectives
+                    .iter()
+                    .map(|objective| objective.objective(&named)),
+            ));
+        }
+        for group in &self.groups {
+            let group_material = material(group.color);
+            for _ in 0..group.count {
+                let (position, velocity, mass) = group.sample(rng);
+                commands.spawn((
+                    Velocity(velocity),
+                    Mass(mass),
+                    Mesh2d(circle.clone()),
+                    MeshMaterial2d(group_material.clone()),
+                    Transform::from_translation(position.extend(0.)),
+                ));
+            }
+        }
+    }
+
+    /// Position, velocity and mass of every body the scenario spawns under
+    /// the gravitational constant `g`, with the groups drawn from `rng` the
+    /// same way as when spawning them.
+    pub fn initial_bodies(&self, g: f32, rng: &mut impl Rng) -> Vec<(Vec2, Vec2, f32)> {
+        let mut bodies: Vec<_> = self
+            .bodies
+            .iter()
+            .zip(self.body_states(g, self.elapsed()))
+            .map(|(body, (position, velocity))| (position, velocity, body.mass))
+            .collect();
+        for group in &self.groups {
+            bodies.extend((0..group.count).map(|_| group.sample(rng)));
+        }
+        bodies
+    }
+}
+
+/// Directories the menu lists the scenario files of: the ones bundled in
+/// `scenarios` and the user's own in the data directory, e.g.
+/// `~/.local/share/spacesim/scenarios` on Linux.
+pub fn scenario_dirs() -> Vec<PathBuf> {
+    let mut scenario_dirs = vec![PathBuf::from("scenarios")];
+    scenario_dirs.extend(dirs::data_dir().map(|dir| dir.join("spacesim").join("scenarios")));
+    scenario_dirs
+}
+
+/// The scenarios of the `.ron` and `.toml` files in `dir`, ordered by file
+/// name. Files that can't be loaded are left out with a warning, a missing
+/// directory has no scenarios.
+pub fn load_scenario_dir(dir: &Path) -> Vec<ScenarioFile> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "ron" || ext == "toml")
+        })
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| match ScenarioFile::load(&path) {
+            Ok(file) => Some(file),
+            Err(err) => {
+                warn!("Skipping scenario `{}`: {err}", path.display());
+                None
+            }
+        })
+        .collect()
+}
+
+/// Registering scenarios read from files.
+pub trait RegisterScenarioFile {
+    /// Adds the scenario of `file` and selects it, so it is the one loaded
+    /// when the app starts
+    /// [`Loading`](crate::state::SimState::Loading) rather than in the menu.
+    /// A file which isn't [valid](ScenarioFile::validate) is skipped with a
+    /// warning.
+    fn register_scenario_file(&mut self, file: ScenarioFile) -> &mut Self;
+
+    /// Adds the scenarios of the files in `dir`, see [`load_scenario_dir`],
+    /// keeping the selected one.
+    fn register_scenario_dir(&mut self, dir: &Path) -> &mut Self;
+}
+
+/// Adds the scenario of `file` to the others, unless it isn't valid.
+/// Returns whether it was added.
+fn add_scenario_file(app: &mut App, file: ScenarioFile) -> bool {
+    if let Err(err) = file.validate() {
+        warn!("Skipping scenario `{}`: {err}", file.name);
+        return false;
+    }
+    let name = file.name.clone();
+    let description = file.description.clone();
+    let physics = file.physics;
+    app.register_scenario(
+        &name,
+        &description,
+        move |mut commands: Commands,
+              mut meshes: ResMut<Assets<Mesh>>,
+              mut materials: ResMut<Assets<ColorMaterial>>,
+              theme: Res<Theme>,
+              settings: Res<PhysicsSettings>,
+              mut rng: ResMut<SimRng>| {
+            let circle = meshes.add(Circle::new(1.));
+            let body_material = materials.add(ColorMaterial::from(theme.body()));
+            commands.insert_resource(BodyMaterial(body_material.clone()));
+            file.spawn(
+                &mut commands,
+                settings.g,
+                &circle,
+                &body_material,
+                &mut materials,
+                &mut rng.rng,
+            );
+        },
+    )
+    .override_scenario_physics(&name, physics);
+    true
+}
+
+impl RegisterScenarioFile for App {
+    fn register_scenario_file(&mut self, file: ScenarioFile) -> &mut Self {
+        if !add_scenario_file(self, file) {
+            return self;
+        }
+        let mut scenarios = self.world_mut().resource_mut::<Scenarios>();
+        scenarios.selected = scenarios.entries.len() - 1;
+        self
+    }
+
+    fn register_scenario_dir(&mut self, dir: &Path) -> &mut Self {
+        for file in load_scenario_dir(dir) {
+            add_scenario_file(self, file);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::world::CommandQueue;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::f32::consts::PI;
+
+    const G: f32 = 1.;
+
+    /// A moon of mass `moon_mass` on `orbit` around a planet of 1000 that
+    /// is moving itself.
+    fn moon(moon_mass: f32, orbit: &str) -> ScenarioFile {
+        toml::from_str(&format!(
+            r#"
+            name = "Moon"
+
+            [[bodies]]
+            name = "planet"
+            position = [30.0, -20.0]
+            velocity = [1.0, 2.0]
+            mass = 1000.0
+
+            [[bodies]]
+            name = "moon"
+            mass = {moon_mass:?}
+            orbit = {orbit}
+            "#
+        ))
+        .unwrap()
+    }
+
+    /// Position and velocity of the moon relative to the planet.
+    fn relative_state(scenario: &ScenarioFile) -> (Vec2, Vec2) {
+        let states = scenario.body_states(G, 0.);
+        (states[1].0 - states[0].0, states[1].1 - states[0].1)
+    }
+
+    #[test]
+    fn circular_orbit_has_the_circular_speed() {
+        let scenario = moon(
+            0.001,
+            r#"{ parent = "planet", a = 50.0, mean_anomaly = 1.0 }"#,
+        );
+        scenario.check_links().unwrap();
+        let (offset, velocity) = relative_state(&scenario);
+        let speed = (G * 1000. / 50f32).sqrt();
+        assert!((offset.length() - 50.).abs() < 1e-3, "{offset}");
+        assert!((offset.to_angle() - 1.).abs() < 1e-5, "{offset}");
+        assert!(
+            (velocity.length() - speed).abs() < speed * 1e-4,
+            "{velocity}"
+        );
+        assert!(offset.dot(velocity).abs() < 1e-3, "{offset} {velocity}");
+        // Counterclockwise
+        assert!(offset.perp_dot(velocity) > 0.);
+    }
+
+    #[test]
+    fn inclined_past_a_right_angle_is_retrograde() {
+        let orbit = |i: f32| {
+            relative_state(&moon(
+                1.,
+                &format!(
+                    r#"{{ parent = "planet", a = 50.0, e = 0.3, i = {i:?}, arg_periapsis = 0.5, ascending_node = 1.0 }}"#
+                ),
+            ))
+        };
+        let (prograde, prograde_velocity) = orbit(0.);
+        let (retrograde, retrograde_velocity) = orbit(PI);
+        assert!(prograde.perp_dot(prograde_velocity) > 0.);
+        assert!(retrograde.perp_dot(retrograde_velocity) < 0.);
+        // Both at periapsis, the retrograde one measured clockwise from the
+        // ascending node.
+        assert!((prograde.length() - 35.).abs() < 1e-3, "{prograde}");
+        assert!((prograde.to_angle() - 1.5).abs() < 1e-5, "{prograde}");
+        assert!((retrograde.length() - 35.).abs() < 1e-3, "{retrograde}");
+        assert!((retrograde.to_angle() - 0.5).abs() < 1e-5, "{retrograde}");
+    }
+
+    #[test]
+    fn orbit_needs_a_known_parent_and_an_ellipse() {
+        let check = |orbit: &str| moon(1., orbit).check_links();
+        assert!(check(r#"{ parent = "planet", a = 50.0, e = 0.9 }"#).is_ok());
+        assert!(check(r#"{ parent = "star", a = 50.0 }"#).is_err());
+        assert!(check(r#"{ parent = "planet", a = -50.0 }"#).is_err());
+        assert!(check(r#"{ parent = "planet", a = 50.0, e = 1.0 }"#).is_err());
+    }
+
+    #[test]
+    fn orbits_must_not_go_around_in_a_circle() {
+        let mut scenario = moon(1., r#"{ parent = "planet", a = 50.0 }"#);
+        scenario.check_links().unwrap();
+        scenario.bodies[0].orbit = Some(OrbitSpec {
+            parent: "moon".into(),
+            a: 50.,
+            e: 0.,
+            i: 0.,
+            arg_periapsis: 0.,
+            ascending_node: 0.,
+            mean_anomaly: 0.,
+        });
+        assert!(scenario.check_links().is_err());
+        scenario.bodies[0].orbit.as_mut().unwrap().parent = "planet".into();
+        assert!(scenario.check_links().is_err());
+    }
+
+    #[test]
+    fn spawned_moon_is_linked_to_its_parent() {
+        let scenario = moon(1., r#"{ parent = "planet", a = 50.0 }"#);
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        scenario.spawn(
+            &mut Commands::new(&mut queue, &world),
+            G,
+            &Handle::default(),
+            &Handle::default(),
+            &mut Assets::default(),
+            &mut StdRng::seed_from_u64(0),
+        );
+        queue.apply(&mut world);
+
+        let mut bodies = world.query::<(Entity, &Mass, Option<&OrbitParent>)>();
+        let planet = bodies
+            .iter(&world)
+            .find(|(_, mass, _)| mass.0 == 1000.)
+            .unwrap()
+            .0;
+        let parents: Vec<_> = bodies
+            .iter(&world)
+            .map(|(_, _, parent)| parent.map(|parent| parent.0))
+            .collect();
+        assert_eq!(parents.len(), 2);
+        assert!(parents.contains(&None));
+        assert!(parents.contains(&Some(planet)));
+    }
+
+    #[test]
+    fn orbits_come_back_after_a_period() {
+        let scenario = moon(
+            1.,
+            r#"{ parent = "planet", a = 50.0, e = 0.5, arg_periapsis = 0.3, mean_anomaly = 2.0 }"#,
+        );
+        let start = scenario.body_states(G, 0.);
+        let period = std::f32::consts::TAU * (50f32.powi(3) / (G * 1001.)).sqrt();
+        for (elapsed, back) in [(period, true), (period / 2., false), (3. * period, true)] {
+            let moved = scenario.body_states(G, elapsed);
+            assert_eq!(moved[0], start[0], "The planet isn't on an orbit");
+            let relative =
+                |states: &[(Vec2, Vec2)]| (states[1].0 - states[0].0, states[1].1 - states[0].1);
+            let ((offset, velocity), (start_offset, start_velocity)) =
+                (relative(&moved), relative(&start));
+            assert_eq!(
+                offset.distance(start_offset) < 1e-2 && velocity.distance(start_velocity) < 1e-3,
+                back,
+                "{elapsed}: {offset} {velocity}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_files_are_not_registered() {
+        let mut app = App::new();
+        app.register_scenario_file(moon(1., r#"{ parent = "planet", a = 50.0 }"#));
+        let mut unknown_parent = moon(1., r#"{ parent = "planet", a = 50.0 }"#);
+        unknown_parent.name = "Lost moon".into();
+        unknown_parent.bodies[0].name = Some("star".into());
+        app.register_scenario_file(unknown_parent);
+        let mut massless = moon(1., r#"{ parent = "planet", a = 50.0 }"#);
+        massless.bodies[1].mass = 0.;
+        app.register_scenario_file(massless);
+
+        let scenarios = app.world().resource::<Scenarios>();
+        let names: Vec<_> = scenarios
+            .entries
+            .iter()
+            .map(|scenario| scenario.name.as_str())
+            .collect();
+        assert_eq!(names, ["Moon"]);
+        assert_eq!(scenarios.selected, 0);
+    }
+
+    #[test]
+    fn start_is_counted_from_the_reference_epoch() {
+        let elapsed = |times: &str| {
+            let scenario: ScenarioFile = toml::from_str(&format!("name = \"Times\"\n{times}"))
+                .map_err(|err| err.to_string())?;
+            scenario.start_elapsed()
+        };
+        let month = 30. * 86_400.;
+        assert_eq!(elapsed(""), Ok(0.));
+        assert_eq!(elapsed(r#"start = "30d""#), Ok(month));
+        assert_eq!(
+            elapsed(
+                r#"reference_epoch = "2024-02-01"
+            start = "2024-03-02""#
+            ),
+            Ok(month),
+            "2024 is a leap year"
+        );
+        assert_eq!(
+            elapsed(
+                r#"reference_epoch = "2024-12-31T18:00"
+            start = "2025-01-01T06:30:00Z""#
+            ),
+            Ok(12.5 * 3600.)
+        );
+        assert!(elapsed(r#"start = "2025-01-01""#).is_err());
+        assert!(elapsed(r#"reference_epoch = "1y""#).is_err());
+        assert!(elapsed(r#"start = "soon""#).is_err());
+        assert!(elapsed(r#"reference_epoch = "2025-02-30""#).is_err());
+
+        let date = ScenarioTime::try_from("2025-03-01T12:30:15".to_string()).unwrap();
+        assert_eq!(String::from(date), "2025-03-01T12:30:15");
+    }
+
+    #[test]
+    fn starting_a_period_later_starts_in_the_same_state() {
+        // A moon going around once a day
+        let a = (G * 1001. * (86_400. / std::f32::consts::TAU).powi(2)).cbrt();
+        let mut scenario = moon(
+            1.,
+            &format!(r#"{{ parent = "planet", a = {a:?}, e = 0.2, mean_anomaly = 1.0 }}"#),
+        );
+        let relative = |scenario: &ScenarioFile| {
+            let bodies = scenario.initial_bodies(G, &mut StdRng::seed_from_u64(0));
+            (bodies[1].0 - bodies[0].0, bodies[1].1 - bodies[0].1)
+        };
+        let (start_offset, start_velocity) = relative(&scenario);
+        let speed = start_velocity.length();
+
+        scenario.reference_epoch = Some("2025-03-01T00:00".to_string().try_into().unwrap());
+        for (start, back) in [("2025-03-02", true), ("1d", true), ("12h", false)] {
+            scenario.start = Some(start.to_string().try_into().unwrap());
+            let (offset, velocity) = relative(&scenario);
+            assert_eq!(
+                offset.distance(start_offset) < 1e-4 * a
+                    && velocity.distance(start_velocity) < 1e-4 * speed,
+                back,
+                "{start}: {offset} {velocity}"
+            );
+        }
+    }
+
+    #[test]
+    fn tethers_need_known_bodies_and_positive_forces() {
+        let check = |tethers: &[&str]| {
+            let mut scenario = moon(1., r#"{ parent = "planet", a = 50.0 }"#);
+            scenario.tethers = tethers
+                .iter()
+                .map(|tether| toml::from_str(tether).unwrap())
+                .collect();
+            scenario.check_links()
+        };
+        let tether = r#"
+            body = "moon"
+            other = "planet"
+            rest_length = 50.0
+            stiffness = 2.0
+        "#;
+        assert!(check(&[tether]).is_ok());
+        assert!(check(&[&format!("{tether}\nbreak_force = 10.0")]).is_ok());
+        assert!(check(&[&format!("{tether}\nbreak_force = 0.0")]).is_err());
+        assert!(check(&[&tether.replace("planet", "star")]).is_err());
+        assert!(check(&[&tether.replace("planet", "moon")]).is_err());
+        assert!(check(&[&tether.replace("2.0", "-2.0")]).is_err());
+        assert!(check(&[tether, tether]).is_err());
+        // The other end can hold a tether of its own.
+        let back = r#"
+            body = "planet"
+            other = "moon"
+            rest_length = 50.0
+            stiffness = 2.0
+        "#;
+        assert!(check(&[tether, back]).is_ok());
+    }
+
+    #[test]
+    fn autopilots_need_a_known_reference_and_positive_quantities() {
+        let check = |autopilot: &str| {
+            let mut scenario = moon(1., r#"{ parent = "planet", a = 50.0 }"#);
+            scenario.bodies[1].autopilot = Some(toml::from_str(autopilot).unwrap());
+            scenario.check_links()
+        };
+        let hold = r#"
+            max_acceleration = 5.0
+            mode = { HoldOrbit = { center = "planet", radius = 50.0 } }
+        "#;
+        assert!(check(hold).is_ok());
+        assert!(check(&hold.replace("planet", "star")).is_err());
+        assert!(check(&hold.replace("planet", "moon")).is_err());
+        assert!(check(&hold.replace("50.0", "0.0")).is_err());
+        assert!(check(&hold.replace("5.0", "-5.0")).is_err());
+        assert!(check(
+            r#"
+            max_acceleration = 5.0
+            mode = { Intercept = { target = "planet" } }
+            "#
+        )
+        .is_ok());
+    }
+}
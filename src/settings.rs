@@ -0,0 +1,149 @@
+use crate::input::{Action, Binding, InputMap};
+use crate::physics_config::{PhysicsConfig, PhysicsOverrides};
+use crate::quality::Quality;
+use crate::theme::{Palette, Theme};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Multiplier of the speed the camera pans and zooms with.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct CameraSensitivity(pub f32);
+
+impl Default for CameraSensitivity {
+    fn default() -> Self {
+        CameraSensitivity(1.)
+    }
+}
+
+/// Preferences of the user, kept in the platform config directory and
+/// shared by every scenario. Holds what was last saved, the live values
+/// are in their own resources.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    pub bindings: Vec<(Action, Binding)>,
+    pub camera_sensitivity: f32,
+    pub palette: Palette,
+    pub quality: Quality,
+    /// Physics parameters every scenario runs with unless it sets its own
+    pub physics: PhysicsOverrides,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        UserSettings {
+            bindings: InputMap::default().bindings,
+            camera_sensitivity: CameraSensitivity::default().0,
+            palette: Palette::default(),
+            quality: Quality::default(),
+            physics: PhysicsOverrides::default(),
+        }
+    }
+}
+
+/// Where the settings are kept, `None` on platforms without a config
+/// directory.
+pub fn settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("spacesim").join("settings.toml"))
+}
+
+impl UserSettings {
+    /// Reads the settings from `path`, falling back to the defaults when
+    /// there are none yet or they can't be read. Actions missing from the
+    /// saved bindings, e.g. ones added since, keep their default bindings.
+    pub fn load(path: &Path) -> Self {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return UserSettings::default()
+            }
+            Err(err) => {
+                warn!("Couldn't read the settings `{}`: {err}", path.display());
+                return UserSettings::default();
+            }
+        };
+        let mut settings: UserSettings = toml::from_str(&source).unwrap_or_else(|err| {
+            warn!("Invalid settings `{}`: {err}", path.display());
+            UserSettings::default()
+        });
+        for (action, binding) in InputMap::default().bindings {
+            if !settings.bindings.iter().any(|(bound, _)| *bound == action) {
+                settings.bindings.push((action, binding));
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let source = toml::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, source)
+    }
+}
+
+/// Saves the settings whenever one of them differs from what was saved
+/// last.
+fn save_settings(
+    input_map: Res<InputMap>,
+    sensitivity: Res<CameraSensitivity>,
+    theme: Res<Theme>,
+    quality: Res<Quality>,
+    mut settings: ResMut<UserSettings>,
+) {
+    let current = UserSettings {
+        bindings: input_map.bindings.clone(),
+        camera_sensitivity: sensitivity.0,
+        palette: theme.palette,
+        quality: *quality,
+        physics: settings.physics,
+    };
+    if current == *settings {
+        return;
+    }
+    *settings = current;
+    let Some(path) = settings_path() else {
+        return;
+    };
+    if let Err(err) = settings.save(&path) {
+        error!("Couldn't save the settings to `{}`: {err}", path.display());
+    }
+}
+
+/// Loads the [`UserSettings`] on start and saves them when they change.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = settings_path()
+            .map(|path| UserSettings::load(&path))
+            .unwrap_or_default();
+        let theme = Theme {
+            palette: settings.palette,
+            ..app
+                .world()
+                .get_resource::<Theme>()
+                .copied()
+                .unwrap_or_default()
+        };
+        app.insert_resource(InputMap {
+            bindings: settings.bindings.clone(),
+        })
+        .insert_resource(CameraSensitivity(settings.camera_sensitivity))
+        .insert_resource(theme)
+        .insert_resource(settings.quality)
+        .insert_resource(PhysicsConfig::new(settings.physics))
+        .insert_resource(settings)
+        .add_systems(
+            Update,
+            save_settings.run_if(
+                resource_changed::<InputMap>
+                    .or(resource_changed::<CameraSensitivity>)
+                    .or(resource_changed::<Theme>)
+                    .or(resource_changed::<Quality>),
+            ),
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::fixed_step::TickRate;
+use crate::timings::PhysicsTimings;
+use bevy::prelude::*;
+
+/// Weight of the latest step in the smoothed step cost.
+const SMOOTHING: f64 = 0.1;
+
+/// Simulated seconds in each of the units [`parse_duration`] accepts.
+const UNITS: &[(&str, f64)] = &[
+    ("s", 1.),
+    ("min", 60.),
+    ("h", 3_600.),
+    ("d", 86_400.),
+    ("y", 31_557_600.),
+];
+
+/// Runs the simulation at `target` simulated seconds per real second instead
+/// of in step with the clock.
+///
+/// The steps keep the length the [`TickRate`] gives them, so the accuracy
+/// doesn't change with the rate, only how many of them run each frame. That
+/// is limited by `budget`, when the steps would take longer the simulation
+/// runs as fast as the budget allows and reports that it can't keep up.
+#[derive(Resource, Debug)]
+pub struct SimRate {
+    /// Simulated seconds per real second to aim for
+    pub target: f64,
+    /// Time the physics steps may take each frame in seconds
+    pub budget: f64,
+    /// Simulated seconds per real second the budget allowed on the last
+    /// frame
+    pub achieved: f64,
+    smoothed_step_cost: Option<f64>,
+    behind: bool,
+}
+
+impl SimRate {
+    /// Rate aiming for `target` simulated seconds per real second, with the
+    /// steps taking at most 12 ms a frame.
+    pub fn new(target: f64) -> Self {
+        SimRate {
+            target,
+            budget: 0.012,
+            achieved: target,
+            smoothed_step_cost: None,
+            behind: false,
+        }
+    }
+
+    pub fn with_budget(mut self, budget: f64) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Whether the budget is too small for the target on the last frame.
+    pub fn is_behind(&self) -> bool {
+        self.behind
+    }
+
+    /// Simulated seconds per real second for a rate such as `1y`, `30d`,
+    /// `2.5h`, `10min` or `90s`, see [`parse_duration`].
+    pub fn parse(rate: &str) -> Option<f64> {
+        parse_duration(rate).filter(|seconds| *seconds > 0.)
+    }
+}
+
+/// Seconds in a duration such as `1y`, `30d`, `2.5h`, `10min` or `90s`, a
+/// bare number counts seconds. Years are Julian, 365.25 days.
+pub fn parse_duration(duration: &str) -> Option<f64> {
+    let duration = duration.trim();
+    let (number, seconds) = UNITS
+        .iter()
+        .find_map(|(unit, seconds)| Some((duration.strip_suffix(unit)?, *seconds)))
+        .unwrap_or((duration, 1.));
+    let value: f64 = number.trim().parse().ok()?;
+    value.is_finite().then_some(value * seconds)
+}
+
+/// Sets the speed of the virtual clock, which the fixed steps follow, to as
+/// close to the target rate as the budget allows with the current cost of a
+/// step.
+pub fn adjust_sim_rate(
+    time: Res<Time<Real>>,
+    tick_rate: Res<TickRate>,
+    timings: Res<PhysicsTimings>,
+    mut sim_rate: ResMut<SimRate>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+) {
+    let frame_time = time.delta_secs_f64();
+    if frame_time <= 0. {
+        return;
+    }
+    let step_cost = timings.total().as_secs_f64();
+    let smoothed = sim_rate.smoothed_step_cost.map_or(step_cost, |smoothed| {
+        smoothed + (step_cost - smoothed) * SMOOTHING
+    });
+    sim_rate.smoothed_step_cost = Some(smoothed);
+
+    // Until a step has been timed any rate looks affordable.
+    let affordable = if smoothed > 0. {
+        sim_rate.budget / smoothed / tick_rate.hz / frame_time
+    } else {
+        f64::INFINITY
+    };
+    sim_rate.achieved = sim_rate.target.min(affordable);
+    virtual_time.set_relative_speed_f64(sim_rate.achieved);
+
+    let behind = affordable < sim_rate.target;
+    if behind && !sim_rate.behind {
+        warn!(
+            "Can't keep up with {} simulated seconds per second, running at {:.3}",
+            sim_rate.target, sim_rate.achieved
+        );
+    } else if !behind && sim_rate.behind {
+        info!(
+            "Caught up with {} simulated seconds per second",
+            sim_rate.target
+        );
+    }
+    sim_rate.behind = behind;
+}
@@ -0,0 +1,481 @@
+use crate::forces::Drag;
+use crate::integrator::{rk4_step, IntegratorKind, LastAcceleration};
+use crate::physics_plugin::{
+    gravity_accelerations, rk4_gravity_step, GravitySource, GravityTrees, Mass, PhysicsSettings,
+    Velocity, G, THETA_THRESHOLD,
+};
+use crate::quadtree::{QuadTree, TreeError};
+use crate::timings::PhysicsTimings;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+
+/// A body of a [`Simulation`].
+#[derive(Debug, Clone, Copy)]
+pub struct SimBody {
+    /// The live body this one was copied from, [`Entity::PLACEHOLDER`] for
+    /// bodies which weren't
+    pub entity: Entity,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// Zero for bodies which are attracted but don't attract others
+    pub mass: f32,
+    /// Deceleration per unit of speed, zero for bodies without [`Drag`]
+    pub drag: f32,
+    pub last_acceleration: LastAcceleration,
+}
+
+impl SimBody {
+    /// A body which isn't a copy of a live one.
+    pub fn new(position: Vec2, velocity: Vec2, mass: f32) -> Self {
+        SimBody {
+            entity: Entity::PLACEHOLDER,
+            position,
+            velocity,
+            mass,
+            drag: 0.,
+            last_acceleration: LastAcceleration::default(),
+        }
+    }
+}
+
+/// How a [`Simulation`] computes the gravity and moves its bodies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulationConfig {
+    /// Gravitational constant
+    pub g: f32,
+    pub theta_threshold: f32,
+    pub integrator: IntegratorKind,
+    /// Plummer softening length of the gravity, zero like in the live
+    /// simulation unless set
+    pub softening: f32,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            g: G,
+            theta_threshold: THETA_THRESHOLD,
+            integrator: IntegratorKind::default(),
+            softening: 0.,
+        }
+    }
+}
+
+/// Bodies attracting each other which can be stepped with [`step`] on
+/// their own, without a Bevy app or touching any entities. Gravity and
+/// drag act on them, but they don't collide and feel none of the other
+/// forces, e.g. of tethers or autopilots.
+///
+/// The trajectory preview, the encounter prediction, the accuracy
+/// comparison and the stability probe step copies of the live bodies in
+/// one, and tests or other programs can drive one directly.
+///
+/// [`step`]: Simulation::step
+#[derive(Debug, Clone, Default)]
+pub struct Simulation {
+    pub bodies: Vec<SimBody>,
+    pub config: SimulationConfig,
+    /// Trees of the last force calculation, kept like the live ones are
+    trees: GravityTrees,
+}
+
+impl Simulation {
+    pub fn new(bodies: Vec<SimBody>, config: SimulationConfig) -> Self {
+        Simulation {
+            bodies,
+            config,
+            trees: GravityTrees::default(),
+        }
+    }
+
+    /// Copies the current state of the live `bodies`.
+    #[allow(clippy::type_complexity)]
+    pub fn capture<'a>(
+        bodies: impl IntoIterator<
+            Item = (
+                Entity,
+                &'a Transform,
+                &'a Velocity,
+                Option<&'a Mass>,
+                Option<&'a Drag>,
+            ),
+        >,
+        config: SimulationConfig,
+    ) -> Self {
+        let bodies = bodies
+            .into_iter()
+            .map(|(entity, transform, velocity, mass, drag)| SimBody {
+                entity,
+                drag: drag.map_or(0., |drag| drag.coefficient),
+                ..SimBody::new(
+                    transform.translation.xy(),
+                    velocity.0,
+                    mass.map_or(0., |mass| mass.0),
+                )
+            })
+            .collect();
+        Simulation::new(bodies, config)
+    }
+
+    /// The tree the gravity of the last step was computed from, `None`
+    /// before the first step or without any attracting bodies.
+    pub fn tree(&self) -> Option<&QuadTree> {
+        self.trees.tree(SimWorld::default())
+    }
+
+    /// Advances every body by `dt` like a live step with a single substep:
+    /// with the same integrator, the gravity of [`gravity_accelerations`]
+    /// and the drag. When the tree can't be built the velocities are left
+    /// as they are, like the live step is skipped then.
+    pub fn step(&mut self, dt: f32) {
+        // The drag follows the velocities before the step, like the live
+        // force systems set it before the substeps.
+        let drag: Vec<Vec2> = self
+            .bodies
+            .iter()
+            .map(|body| -body.velocity * body.drag)
+            .collect();
+        if self.config.integrator == IntegratorKind::Rk4 {
+            self.step_rk4(dt, &drag);
+            return;
+        }
+        let integrator = self.config.integrator;
+        for body in &mut self.bodies {
+            body.position += integrator.displacement(body.velocity, &body.last_acceleration, dt);
+        }
+        let positions: Vec<Vec2> = self.bodies.iter().map(|body| body.position).collect();
+        let masses: Vec<f32> = self.bodies.iter().map(|body| body.mass).collect();
+        let Ok(gravity) = gravity(&mut self.trees, &self.config, &masses, &positions) else {
+            return;
+        };
+        for ((body, gravity), drag) in self.bodies.iter_mut().zip(gravity).zip(drag) {
+            integrator.kick(
+                &mut body.velocity,
+                gravity + drag,
+                &mut body.last_acceleration,
+                dt,
+            );
+        }
+    }
+
+    fn step_rk4(&mut self, dt: f32, drag: &[Vec2]) {
+        let masses: Vec<f32> = self.bodies.iter().map(|body| body.mass).collect();
+        let mut positions: Vec<Vec2> = self.bodies.iter().map(|body| body.position).collect();
+        let mut velocities: Vec<Vec2> = self.bodies.iter().map(|body| body.velocity).collect();
+        let others: Vec<Option<Vec2>> = drag.iter().copied().map(Some).collect();
+        let (trees, config) = (&mut self.trees, &self.config);
+        let stepped = rk4_gravity_step(&mut positions, &mut velocities, dt, &others, |positions| {
+            gravity(trees, config, &masses, positions)
+        });
+        let Ok((_, first)) = stepped else {
+            return;
+        };
+        for (body, ((position, velocity), acceleration)) in self
+            .bodies
+            .iter_mut()
+            .zip(positions.into_iter().zip(velocities).zip(first))
+        {
+            body.position = position;
+            body.velocity = velocity;
+            body.last_acceleration.0 = Some(acceleration);
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&SimBody> {
+        self.bodies.iter().find(|body| body.entity == entity)
+    }
+
+    /// Positions of `entity` after each of `steps` steps of `dt`, empty if
+    /// the body isn't in the simulation.
+    pub fn trajectory(&mut self, entity: Entity, steps: usize, dt: f32) -> Vec<Vec2> {
+        let Some(index) = self.bodies.iter().position(|body| body.entity == entity) else {
+            return Vec::new();
+        };
+        (0..steps)
+            .map(|_| {
+                self.step(dt);
+                self.bodies[index].position
+            })
+            .collect()
+    }
+
+    /// Closest approaches of `entity` to the other bodies over `steps`
+    /// steps of `dt`, as the other body, the time from now and the miss
+    /// distance. A body can be approached more than once, approaches still
+    /// under way at the end aren't counted.
+    pub fn closest_approaches(
+        &mut self,
+        entity: Entity,
+        steps: usize,
+        dt: f32,
+    ) -> Vec<(Entity, f32, f32)> {
+        let Some(index) = self.bodies.iter().position(|body| body.entity == entity) else {
+            return Vec::new();
+        };
+        let distances = |simulation: &Simulation| -> Vec<f32> {
+            let position = simulation.bodies[index].position;
+            simulation
+                .bodies
+                .iter()
+                .map(|body| body.position.distance(position))
+                .collect()
+        };
+
+        let mut approaches = Vec::new();
+        let mut previous = distances(self);
+        let mut closing = vec![false; previous.len()];
+        for step in 1..=steps {
+            self.step(dt);
+            let current = distances(self);
+            for (other, (&distance, &last)) in current.iter().zip(&previous).enumerate() {
+                if other == index {
+                    continue;
+                }
+                // The distance stopped shrinking, the last step was the
+                // closest.
+                if closing[other] && distance > last {
+                    approaches.push((self.bodies[other].entity, (step - 1) as f32 * dt, last));
+                }
+                closing[other] = distance < last;
+            }
+            previous = current;
+        }
+        approaches
+    }
+}
+
+/// Positions of `body` after each of `steps` steps of `dt` through the
+/// gravity of `tree`, which stays frozen: the bodies in it don't move.
+///
+/// The body's own mass is taken out again where it starts, so when it is
+/// in the tree it isn't pulled back to where it was.
+pub fn path_through_tree(
+    tree: &QuadTree,
+    mut body: SimBody,
+    config: &SimulationConfig,
+    steps: usize,
+    dt: f32,
+) -> Vec<Vec2> {
+    let start = body.position;
+    let softening_squared = config.softening * config.softening;
+    let acceleration = |position: Vec2| {
+        let gravity = tree.accumulate_acceleration(
+            position,
+            config.theta_threshold,
+            config.g,
+            config.softening,
+        );
+        let offset = start - position;
+        let softened = offset.length_squared() + softening_squared;
+        if softened == 0. {
+            return gravity;
+        }
+        gravity - config.g * body.mass * offset / (softened * softened.sqrt())
+    };
+
+    (0..steps)
+        .map(|_| {
+            if config.integrator == IntegratorKind::Rk4 {
+                let mut position = [body.position];
+                let mut velocity = [body.velocity];
+                rk4_step(&mut position, &mut velocity, dt, |positions| {
+                    positions
+                        .iter()
+                        .map(|&position| acceleration(position))
+                        .collect()
+                });
+                body.position = position[0];
+                body.velocity = velocity[0];
+            } else {
+                let integrator = config.integrator;
+                body.position +=
+                    integrator.displacement(body.velocity, &body.last_acceleration, dt);
+                let gravity = acceleration(body.position);
+                integrator.kick(&mut body.velocity, gravity, &mut body.last_acceleration, dt);
+            }
+            body.position
+        })
+        .collect()
+}
+
+/// Gravity at each of the `positions` of the bodies with the `masses`,
+/// through the force calculation of the live simulation.
+fn gravity(
+    trees: &mut GravityTrees,
+    config: &SimulationConfig,
+    masses: &[f32],
+    positions: &[Vec2],
+) -> Result<Vec<Vec2>, TreeError> {
+    let sources: Vec<GravitySource> = positions
+        .iter()
+        .zip(masses)
+        .filter(|(_, &mass)| mass > 0.)
+        .map(|(&position, &mass)| GravitySource {
+            world: SimWorld::default(),
+            position,
+            mass,
+            background: false,
+        })
+        .collect();
+    let targets: Vec<(SimWorld, Vec2)> = positions
+        .iter()
+        .map(|&position| (SimWorld::default(), position))
+        .collect();
+    let settings = PhysicsSettings {
+        g: config.g,
+        softening: config.softening,
+        ..default()
+    };
+    gravity_accelerations(
+        &sources,
+        &targets,
+        config.theta_threshold,
+        None,
+        &settings,
+        0,
+        None,
+        trees,
+        &mut PhysicsTimings::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::background::BackgroundAggregation;
+    use crate::forces::{apply_drag, clear_accelerations, integrate_acceleration, Acceleration};
+    use crate::physics_config::PhysicsConfig;
+    use crate::physics_plugin::{apply_gravity, integrate_rk4, update_position};
+    use crate::quality::Quality;
+    use crate::tree_failure::TreeFailure;
+    use bevy::utils::Duration;
+
+    const STEPS: usize = 300;
+
+    /// A heavy body with a light one on a circular orbit around it, slowed
+    /// down by drag.
+    fn two_bodies() -> Vec<SimBody> {
+        let (mass, radius) = (1e11, 200.);
+        let speed = (G * mass / radius).sqrt();
+        vec![
+            SimBody::new(Vec2::ZERO, Vec2::ZERO, mass),
+            SimBody {
+                drag: 0.01,
+                ..SimBody::new(Vec2::new(radius, 0.), Vec2::new(0., speed), 1e6)
+            },
+        ]
+    }
+
+    /// A world with the `bodies` and the resources the live physics systems
+    /// read, with a step of a 60th of a second.
+    fn live_world(bodies: &[SimBody], integrator: IntegratorKind) -> (World, Vec<Entity>) {
+        let mut world = World::new();
+        world.insert_resource(integrator);
+        world.init_resource::<Quality>();
+        world.init_resource::<PhysicsConfig>();
+        world.init_resource::<PhysicsSettings>();
+        world.init_resource::<PhysicsTimings>();
+        world.init_resource::<GravityTrees>();
+        world.init_resource::<TreeFailure>();
+        world.init_resource::<BackgroundAggregation>();
+        let mut time = Time::<()>::default();
+        time.advance_by(Duration::from_secs_f32(1. / 60.));
+        world.insert_resource(time);
+        let entities = bodies
+            .iter()
+            .map(|body| {
+                world
+                    .spawn((
+                        Transform::from_translation(body.position.extend(0.)),
+                        Velocity(body.velocity),
+                        Mass(body.mass),
+                        Drag {
+                            coefficient: body.drag,
+                        },
+                        Acceleration::default(),
+                        LastAcceleration::default(),
+                    ))
+                    .id()
+            })
+            .collect();
+        (world, entities)
+    }
+
+    /// Position and velocity of every body.
+    type States = Vec<(Vec2, Vec2)>;
+
+    /// Positions and velocities of the `bodies` after [`STEPS`] steps of
+    /// the live systems and of a [`Simulation`].
+    fn live_and_simulated(bodies: &[SimBody], integrator: IntegratorKind) -> (States, States) {
+        let (mut world, entities) = live_world(bodies, integrator);
+        let mut schedule = Schedule::default();
+        if integrator == IntegratorKind::Rk4 {
+            schedule.add_systems((clear_accelerations, apply_drag, integrate_rk4).chain());
+        } else {
+            schedule.add_systems(
+                (
+                    clear_accelerations,
+                    apply_drag,
+                    update_position,
+                    apply_gravity,
+                    integrate_acceleration,
+                )
+                    .chain(),
+            );
+        }
+        for _ in 0..STEPS {
+            schedule.run(&mut world);
+        }
+        let live = entities
+            .iter()
+            .map(|&entity| {
+                let body = world.entity(entity);
+                (
+                    body.get::<Transform>().unwrap().translation.xy(),
+                    body.get::<Velocity>().unwrap().0,
+                )
+            })
+            .collect();
+
+        let dt = world.resource::<Time>().delta_secs();
+        let quality = PhysicsConfig::default().quality_settings(Quality::default());
+        let mut simulation = Simulation::new(
+            bodies.to_vec(),
+            SimulationConfig {
+                theta_threshold: quality.theta_threshold,
+                integrator,
+                ..default()
+            },
+        );
+        for _ in 0..STEPS {
+            simulation.step(dt);
+        }
+        let simulated = simulation
+            .bodies
+            .iter()
+            .map(|body| (body.position, body.velocity))
+            .collect();
+        (live, simulated)
+    }
+
+    #[test]
+    fn two_bodies_move_like_the_live_ones() {
+        for integrator in [
+            IntegratorKind::VelocityVerlet,
+            IntegratorKind::Euler,
+            IntegratorKind::Rk4,
+        ] {
+            let (live, simulated) = live_and_simulated(&two_bodies(), integrator);
+            assert_eq!(live, simulated, "{integrator:?}");
+        }
+    }
+
+    #[test]
+    fn drag_shrinks_the_orbit() {
+        let bodies = two_bodies();
+        let (_, simulated) = live_and_simulated(&bodies, IntegratorKind::VelocityVerlet);
+        // The drag takes energy out of the orbit, which shrinks.
+        let radius = simulated[1].0.distance(simulated[0].0);
+        assert!(radius < bodies[1].position.length() - 1. && radius > 150.);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::physics_config::PhysicsQuality;
+use crate::physics_plugin::{tree_potential, tree_tidal_tensor, GravityTrees, PhysicsSettings};
+use crate::worlds::SimWorld;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+/// The gravitational field at any point, for what isn't a body: overlays,
+/// guidance, audio.
+///
+/// Evaluated on the trees of the last physics step with the same
+/// Barnes-Hut threshold and [`PhysicsSettings`], so everything querying it
+/// sees the field the bodies felt. With domain decomposition the trees
+/// only live inside the tiles and the field reads as empty.
+#[derive(SystemParam)]
+pub struct SpatialIndex<'w> {
+    trees: Res<'w, GravityTrees>,
+    quality: PhysicsQuality<'w>,
+    settings: Res<'w, PhysicsSettings>,
+}
+
+impl SpatialIndex<'_> {
+    /// Acceleration at `point` in world 0.
+    pub fn acceleration_at(&self, point: Vec2) -> Vec2 {
+        self.acceleration_in(SimWorld::default(), point)
+    }
+
+    pub fn acceleration_in(&self, world: SimWorld, point: Vec2) -> Vec2 {
+        let theta_threshold = self.quality.settings().theta_threshold;
+        self.trees.tree(world).map_or(Vec2::ZERO, |q_tree| {
+            q_tree.accumulate_acceleration(
+                point,
+                theta_threshold,
+                self.settings.g,
+                self.settings.softening,
+            )
+        })
+    }
+
+    /// Tidal tensor at `point` in world 0, see [`tree_tidal_tensor`].
+    pub fn tidal_tensor_at(&self, point: Vec2) -> Mat2 {
+        self.tidal_tensor_in(SimWorld::default(), point)
+    }
+
+    pub fn tidal_tensor_in(&self, world: SimWorld, point: Vec2) -> Mat2 {
+        self.tidal_tensor_excluding(world, point, None)
+    }
+
+    /// Tidal tensor at a body of `mass` at `position` in `world`, which
+    /// should be its physics position, without the body's own gravity.
+    pub fn body_tidal_tensor_in(&self, world: SimWorld, position: Vec2, mass: f32) -> Mat2 {
+        self.tidal_tensor_excluding(world, position, Some(mass))
+    }
+
+    fn tidal_tensor_excluding(&self, world: SimWorld, point: Vec2, exclude: Option<f32>) -> Mat2 {
+        let theta_threshold = self.quality.settings().theta_threshold;
+        self.trees.tree(world).map_or(Mat2::ZERO, |q_tree| {
+            tree_tidal_tensor(
+                q_tree,
+                point,
+                theta_threshold,
+                self.settings.g,
+                self.settings.softening,
+                exclude,
+            )
+        })
+    }
+
+    /// Potential at `point` in world 0.
+    pub fn potential_at(&self, point: Vec2) -> f32 {
+        self.potential_in(SimWorld::default(), point)
+    }
+
+    pub fn potential_in(&self, world: SimWorld, point: Vec2) -> f32 {
+        let theta_threshold = self.quality.settings().theta_threshold;
+        self.trees.tree(world).map_or(0., |q_tree| {
+            tree_potential(
+                q_tree,
+                point,
+                theta_threshold,
+                self.settings.g,
+                self.settings.softening,
+                false,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics_config::PhysicsConfig;
+    use crate::physics_plugin::{gravity_accelerations, GravitySource};
+    use crate::quality::Quality;
+    use crate::timings::PhysicsTimings;
+    use bevy::ecs::system::SystemState;
+
+    #[test]
+    fn softened_potential_gradient_is_the_acceleration() {
+        let settings = PhysicsSettings {
+            g: 1.,
+            softening: 5.,
+            ..default()
+        };
+        let sources: Vec<GravitySource> = [
+            (Vec2::new(0., 0.), 1000.),
+            (Vec2::new(30., 10.), 400.),
+            (Vec2::new(-20., 25.), 250.),
+        ]
+        .into_iter()
+        .map(|(position, mass)| GravitySource {
+            world: SimWorld::default(),
+            position,
+            mass,
+            background: false,
+        })
+        .collect();
+        let mut trees = GravityTrees::default();
+        gravity_accelerations(
+            &sources,
+            &[],
+            0.,
+            None,
+            &settings,
+            0,
+            None,
+            &mut trees,
+            &mut PhysicsTimings::default(),
+        )
+        .unwrap();
+
+        let mut config = PhysicsConfig::default();
+        // Sum every body directly so only the softening is compared
+        config.set_theta_threshold(0.);
+        let mut world = World::new();
+        world.insert_resource(Quality::default());
+        world.insert_resource(config);
+        world.insert_resource(settings);
+        world.insert_resource(trees);
+        let mut state = SystemState::<SpatialIndex>::new(&mut world);
+        let index = state.get(&world);
+
+        let h = 1e-2;
+        // Inside the softening length of the bodies, where it matters most
+        for point in [Vec2::new(1., 2.), Vec2::new(28., 7.), Vec2::new(-10., 15.)] {
+            let gradient = Vec2::new(
+                index.potential_at(point + Vec2::X * h) - index.potential_at(point - Vec2::X * h),
+                index.potential_at(point + Vec2::Y * h) - index.potential_at(point - Vec2::Y * h),
+            ) / (2. * h);
+            let acceleration = index.acceleration_at(point);
+            assert!(
+                (-gradient - acceleration).length() < 1e-2 * acceleration.length(),
+                "{point}: -∇φ {} vs {acceleration}",
+                -gradient
+            );
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use crate::fixed_step::TickRate;
+use crate::forces::Drag;
+use crate::integrator::IntegratorKind;
+use crate::physics_config::PhysicsConfig;
+use crate::physics_plugin::{Mass, PhysicsSettings, Velocity};
+use crate::quality::Quality;
+use crate::radius::{radius_of, BodyDensity, Density};
+use crate::scenario::SimRng;
+use crate::simulation::{Simulation, SimulationConfig};
+use bevy::ecs::system::SystemId;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+/// How much heavier than the median body a body has to be to count as a
+/// planet rather than one of many similar bodies, which pass each other
+/// all the time.
+pub const PLANET_MASS_RATIO: f32 = 10.;
+
+/// When present, every scenario is probed right after it is spawned by
+/// integrating a sample of its bodies ahead in a [`Simulation`]. A
+/// scenario which ejects too many of them, or whose heaviest bodies cross
+/// each other's orbits, is spawned again from the next seed, up to
+/// `attempts` times, and flagged in the [`StabilityReport`] if none of the
+/// seeds gives a stable system.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct StabilityProbe {
+    pub attempts: u32,
+    /// Simulated seconds to integrate ahead, in the steps the simulation
+    /// takes
+    pub duration: f32,
+    /// Number of bodies probed. A quarter of them are the heaviest bodies,
+    /// the rest are picked evenly from the lighter ones and made heavier to
+    /// stand for the ones left out, which keeps the probe fast.
+    pub bodies: usize,
+    /// Largest number of planets whose orbits mustn't cross: the heaviest
+    /// bodies besides the one they orbit, if they are at least
+    /// [`PLANET_MASS_RATIO`] times heavier than the median body
+    pub planets: usize,
+    /// Largest fraction of the probed bodies allowed to be ejected
+    pub max_ejected: f32,
+    /// Largest number of orbit crossings allowed
+    pub max_crossings: usize,
+}
+
+impl StabilityProbe {
+    pub fn new(attempts: u32) -> Self {
+        StabilityProbe {
+            attempts: attempts.max(1),
+            duration: 10.,
+            bodies: 400,
+            planets: 16,
+            max_ejected: 0.02,
+            max_crossings: 0,
+        }
+    }
+
+    pub fn is_stable(&self, report: &StabilityReport) -> bool {
+        report.ejected as f32 <= self.max_ejected * report.probed as f32
+            && report.crossings <= self.max_crossings
+    }
+}
+
+/// Outcome of probing a scenario.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq)]
+pub struct StabilityReport {
+    pub probed: usize,
+    /// Bodies which ended up unbound and far out
+    pub ejected: usize,
+    /// Pairs of planets which came closer than their mutual Hill radius
+    /// around the heaviest body, where their orbits stop being orderly
+    pub crossings: usize,
+    /// Seed the scenario was finally spawned from
+    pub seed: u64,
+    /// Whether the probe found the scenario stable
+    pub stable: bool,
+}
+
+/// Mutual Hill radius of two bodies of masses `a` and `b` orbiting a body
+/// of `central_mass` at distances `distance_a` and `distance_b`.
+pub fn mutual_hill_radius(
+    a: f32,
+    b: f32,
+    distance_a: f32,
+    distance_b: f32,
+    central_mass: f32,
+) -> f32 {
+    ((a + b) / (3. * central_mass)).cbrt() * (distance_a + distance_b) / 2.
+}
+
+/// Integrates the `shadow` world `steps` steps of `dt` ahead and counts the
+/// bodies it ejects and the orbit crossings of up to `planets` planets
+/// around the heaviest body.
+///
+/// Bodies falling into the heaviest body or a planet, going by the `radii`
+/// of the bodies, are merged into it like in the simulation, a planet
+/// falling into the heaviest body counts as a crossing.
+pub fn probe(
+    mut shadow: Simulation,
+    radii: &HashMap<Entity, f32>,
+    steps: usize,
+    dt: f32,
+    planets: usize,
+) -> StabilityReport {
+    let probed = shadow.bodies.len();
+    let mut by_mass: Vec<usize> = (0..probed).collect();
+    by_mass.sort_by(|&a, &b| shadow.bodies[b].mass.total_cmp(&shadow.bodies[a].mass));
+    let Some((&central, others)) = by_mass.split_first() else {
+        return StabilityReport::default();
+    };
+    let mut masses: Vec<f32> = shadow.bodies.iter().map(|body| body.mass).collect();
+    let middle = masses.len() / 2;
+    let median_mass = *masses.select_nth_unstable_by(middle, f32::total_cmp).1;
+    let planets: Vec<usize> = others
+        .iter()
+        .copied()
+        .take(planets)
+        .take_while(|&index| shadow.bodies[index].mass >= PLANET_MASS_RATIO * median_mass)
+        .collect();
+    let radius = |index: usize| {
+        radii
+            .get(&shadow.bodies[index].entity)
+            .copied()
+            .unwrap_or(0.)
+    };
+    let heavy: Vec<(usize, f32)> = std::iter::once(central)
+        .chain(planets.iter().copied())
+        .map(|index| (index, radius(index)))
+        .collect();
+    let radii: Vec<f32> = (0..probed).map(radius).collect();
+    let total_mass: f32 = shadow.bodies.iter().map(|body| body.mass).sum();
+    let center_of_mass = |shadow: &Simulation| {
+        shadow
+            .bodies
+            .iter()
+            .map(|body| body.position * body.mass)
+            .sum::<Vec2>()
+            / total_mass.max(f32::MIN_POSITIVE)
+    };
+    let initial_center = center_of_mass(&shadow);
+    let initial_extent = shadow
+        .bodies
+        .iter()
+        .map(|body| body.position.distance(initial_center))
+        .fold(0., f32::max);
+
+    let mut crossed: HashSet<(usize, usize)> = HashSet::default();
+    let mut merged = vec![false; probed];
+    for _ in 0..steps {
+        shadow.step(dt);
+        for index in 0..probed {
+            if merged[index] || index == central {
+                continue;
+            }
+            let body = shadow.bodies[index];
+            let Some(&(into, _)) = heavy.iter().find(|&&(other, other_radius)| {
+                other != index
+                    && !merged[other]
+                    && body.position.distance(shadow.bodies[other].position)
+                        < radii[index] + other_radius
+            }) else {
+                continue;
+            };
+            if into == central && planets.contains(&index) {
+                crossed.insert((central, index));
+            }
+            let into_body = &mut shadow.bodies[into];
+            let mass = into_body.mass + body.mass;
+            if mass > 0. {
+                into_body.velocity =
+                    (into_body.velocity * into_body.mass + body.velocity * body.mass) / mass;
+            }
+            into_body.mass = mass;
+            shadow.bodies[index].mass = 0.;
+            merged[index] = true;
+        }
+
+        let central_body = shadow.bodies[central];
+        for (i, &a) in planets.iter().enumerate() {
+            for &b in &planets[i + 1..] {
+                if merged[a] || merged[b] || crossed.contains(&(a, b)) {
+                    continue;
+                }
+                let (body_a, body_b) = (&shadow.bodies[a], &shadow.bodies[b]);
+                let hill_radius = mutual_hill_radius(
+                    body_a.mass,
+                    body_b.mass,
+                    body_a.position.distance(central_body.position),
+                    body_b.position.distance(central_body.position),
+                    central_body.mass,
+                );
+                if body_a.position.distance(body_b.position) < hill_radius {
+                    crossed.insert((a, b));
+                }
+            }
+        }
+    }
+
+    let center = center_of_mass(&shadow);
+    let center_velocity = shadow
+        .bodies
+        .iter()
+        .map(|body| body.velocity * body.mass)
+        .sum::<Vec2>()
+        / total_mass.max(f32::MIN_POSITIVE);
+    let ejected = shadow
+        .bodies
+        .iter()
+        .zip(&merged)
+        .filter(|(_, &merged)| !merged)
+        .filter(|(body, _)| {
+            let distance = body.position.distance(center);
+            let speed = body.velocity.distance(center_velocity);
+            let energy = speed * speed / 2. - shadow.config.g * (total_mass - body.mass) / distance;
+            distance > 2. * initial_extent && energy > 0.
+        })
+        .count();
+    StabilityReport {
+        probed,
+        ejected,
+        crossings: crossed.len(),
+        seed: 0,
+        stable: true,
+    }
+}
+
+/// Keeps `count` of the bodies of `shadow`, which are sorted heaviest first:
+/// the heaviest quarter and an even pick of the rest, whose masses are
+/// scaled up to add up to the mass of all the rest.
+fn sample_bodies(mut shadow: Simulation, count: usize) -> Simulation {
+    let heaviest = (count / 4).max(1);
+    if shadow.bodies.len() <= count || count <= heaviest {
+        return shadow;
+    }
+    let rest = shadow.bodies.split_off(heaviest);
+    let stride = rest.len().div_ceil(count - heaviest);
+    let rest_mass: f32 = rest.iter().map(|body| body.mass).sum();
+    let picked: Vec<_> = rest.into_iter().step_by(stride).collect();
+    let picked_mass: f32 = picked.iter().map(|body| body.mass).sum();
+    let scale = if picked_mass > 0. {
+        rest_mass / picked_mass
+    } else {
+        1.
+    };
+    shadow.bodies.extend(picked.into_iter().map(|mut body| {
+        body.mass *= scale;
+        body
+    }));
+    shadow
+}
+
+/// Radii of the bodies not among the `existing` entities, which aren't
+/// updated until the first step.
+fn spawned_radii(world: &mut World, existing: &HashSet<Entity>) -> HashMap<Entity, f32> {
+    let density = world.resource::<Density>().0;
+    world
+        .query::<(Entity, &Mass, Option<&BodyDensity>)>()
+        .iter(world)
+        .filter(|(entity, ..)| !existing.contains(entity))
+        .map(|(entity, mass, body_density)| {
+            let radius = radius_of(mass.0, body_density.map_or(density, |d| d.0));
+            (entity, radius)
+        })
+        .collect()
+}
+
+/// Spawns the scenario with `spawn` from `seed` on, until the
+/// [`StabilityProbe`] finds it stable or runs out of attempts, and returns
+/// the seed to spawn it from. The bodies spawned to probe are despawned
+/// again.
+pub fn reroll_unstable(world: &mut World, spawn: SystemId, seed: u64) -> u64 {
+    let Some(probe_settings) = world.get_resource::<StabilityProbe>().copied() else {
+        return seed;
+    };
+    let settings = world
+        .resource::<PhysicsConfig>()
+        .quality_settings(*world.resource::<Quality>());
+    let integrator = *world.resource::<IntegratorKind>();
+    let physics = *world.resource::<PhysicsSettings>();
+    let dt = 1. / (world.resource::<TickRate>().hz as f32 * settings.substeps.max(1) as f32);
+    let steps = (probe_settings.duration / dt).ceil() as usize;
+
+    let mut report = StabilityReport::default();
+    for attempt in 0..probe_settings.attempts {
+        let attempt_seed = seed.wrapping_add(attempt as u64);
+        *world.resource_mut::<SimRng>() = SimRng::new(attempt_seed);
+        let existing: HashSet<Entity> = world.iter_entities().map(|entity| entity.id()).collect();
+        if let Err(err) = world.run_system(spawn) {
+            error!("Couldn't spawn the scenario to probe: {err}");
+            return seed;
+        }
+
+        let mut bodies: Vec<_> = world
+            .query_filtered::<(Entity, &Transform, &Velocity, Option<&Mass>, Option<&Drag>), Without<Parent>>()
+            .iter(world)
+            .filter(|(entity, ..)| !existing.contains(entity))
+            .collect();
+        bodies.sort_by(|a, b| {
+            let mass = |mass: Option<&Mass>| mass.map_or(0., |mass| mass.0);
+            mass(b.3).total_cmp(&mass(a.3))
+        });
+        let mut shadow = sample_bodies(
+            Simulation::capture(
+                bodies,
+                SimulationConfig {
+                    g: physics.g,
+                    theta_threshold: settings.theta_threshold,
+                    integrator,
+                    softening: physics.softening,
+                },
+            ),
+            probe_settings.bodies,
+        );
+        let radii = spawned_radii(world, &existing);
+        // Bodies passing closer than their radii merge in the simulation,
+        // the probe softens their gravity so the ones it misses aren't
+        // flung apart.
+        let mut sorted_radii: Vec<f32> = radii.values().copied().collect();
+        let middle = sorted_radii.len() / 2;
+        if middle > 0 {
+            shadow.config.softening = *sorted_radii
+                .select_nth_unstable_by(middle, f32::total_cmp)
+                .1;
+        }
+
+        let spawned: Vec<Entity> = world
+            .iter_entities()
+            .map(|entity| entity.id())
+            .filter(|entity| !existing.contains(entity))
+            .collect();
+        for entity in spawned {
+            if let Ok(entity) = world.get_entity_mut(entity) {
+                entity.despawn_recursive();
+            }
+        }
+
+        report = probe(shadow, &radii, steps, dt, probe_settings.planets);
+        report.seed = attempt_seed;
+        report.stable = probe_settings.is_stable(&report);
+        if report.stable {
+            break;
+        }
+        info!(
+            "Seed {attempt_seed} gives an unstable system, {} of {} bodies ejected and {} orbit crossings",
+            report.ejected, report.probed, report.crossings
+        );
+    }
+    if !report.stable {
+        warn!(
+            "No stable system in {} seeds, keeping seed {}",
+            probe_settings.attempts, report.seed
+        );
+    }
+    world.insert_resource(report);
+    report.seed
+}
@@ -0,0 +1,260 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::MainCamera;
+use crate::theme::Theme;
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Side of a tile of stars in pixels, tiles are between one and two times
+/// this size on the screen.
+const TILE_SIZE: f32 = 512.;
+/// Coarsest level of detail, every finer tile inherits the stars of the
+/// tile covering it at the next coarser level.
+const MAX_LEVEL: i32 = 12;
+const MIN_LEVEL: i32 = -6;
+
+/// A layer of the starfield at some depth.
+struct StarLayer {
+    /// How much the layer follows the camera: 0 stays fixed on the screen,
+    /// 1 moves and zooms along with the bodies
+    parallax: f32,
+    stars_per_tile: usize,
+    /// Size of the largest stars in pixels
+    size: f32,
+    brightness: f32,
+}
+
+/// Layers from the farthest to the nearest.
+const LAYERS: [StarLayer; 3] = [
+    StarLayer {
+        parallax: 0.05,
+        stars_per_tile: 60,
+        size: 1.5,
+        brightness: 0.35,
+    },
+    StarLayer {
+        parallax: 0.2,
+        stars_per_tile: 24,
+        size: 2.,
+        brightness: 0.6,
+    },
+    StarLayer {
+        parallax: 0.5,
+        stars_per_tile: 8,
+        size: 3.,
+        brightness: 0.9,
+    },
+];
+
+/// Procedural background of stars in layers moving with the camera at
+/// different rates, purely for looks. The stars are generated from `seed`
+/// as the view reaches them, so the same seed always shows the same sky.
+///
+/// Only drawn by the main camera, the views of the other worlds have a
+/// plain background.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct Starfield {
+    pub enabled: bool,
+    pub seed: u64,
+}
+
+/// Layer, level of detail and position of a tile of stars.
+type TileKey = (usize, i32, IVec2);
+
+/// The spawned stars of every tile in view.
+#[derive(Resource, Debug, Default)]
+struct StarTiles(HashMap<TileKey, Vec<Entity>>);
+
+/// A star of the [`Starfield`].
+#[derive(Component, Debug, Clone, Copy)]
+struct Star {
+    layer: usize,
+    /// Position in the layer, in pixels at no zoom
+    position: Vec2,
+    /// Size in pixels
+    size: f32,
+}
+
+fn toggle_starfield(actions: Actions, mut starfield: ResMut<Starfield>) {
+    if actions.just_pressed(Action::ToggleStarfield) {
+        starfield.enabled = !starfield.enabled;
+    }
+}
+
+/// Seed of the stars of a tile, mixed from its key so neighbouring tiles
+/// aren't correlated.
+fn tile_seed(seed: u64, (layer, level, tile): TileKey) -> u64 {
+    [layer as u64, level as u64, tile.x as u64, tile.y as u64]
+        .into_iter()
+        .fold(seed, |hash, part| {
+            (hash ^ part)
+                .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+                .rotate_left(31)
+        })
+}
+
+fn tile_size(level: i32) -> f32 {
+    TILE_SIZE * 2f32.powi(level)
+}
+
+/// Positions and brightnesses of the stars of a tile: the stars of the
+/// coarser tile covering it which fall inside it, filled up with new ones.
+/// Zooming in only adds stars, the ones already shown stay.
+fn tile_stars(seed: u64, key: TileKey) -> Vec<(Vec2, f32)> {
+    let (layer, level, tile) = key;
+    let count = LAYERS[layer].stars_per_tile;
+    let size = tile_size(level);
+    let bounds = Rect::from_corners(tile.as_vec2() * size, (tile + 1).as_vec2() * size);
+
+    let mut stars: Vec<(Vec2, f32)> = if level < MAX_LEVEL {
+        tile_stars(seed, (layer, level + 1, tile.div_euclid(IVec2::splat(2))))
+            .into_iter()
+            .filter(|star| bounds.contains(star.0))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let mut rng = StdRng::seed_from_u64(tile_seed(seed, key));
+    while stars.len() < count {
+        let position = Vec2::new(
+            rng.random_range(bounds.min.x..bounds.max.x),
+            rng.random_range(bounds.min.y..bounds.max.y),
+        );
+        // Most stars are faint.
+        stars.push((position, rng.random::<f32>().powi(3)));
+    }
+    stars
+}
+
+/// Zoom of a layer for the camera's `scale`, farther layers zoom less.
+fn layer_zoom(layer: &StarLayer, scale: f32) -> f32 {
+    scale.powf(layer.parallax)
+}
+
+/// Tiles covering the view of the layer, at the level of detail that keeps
+/// the stars equally dense on the screen at any zoom.
+fn visible_tiles(
+    index: usize,
+    layer: &StarLayer,
+    camera: Vec2,
+    scale: f32,
+    screen: Vec2,
+) -> impl Iterator<Item = TileKey> {
+    let zoom = layer_zoom(layer, scale);
+    let level = (zoom.log2().floor() as i32).clamp(MIN_LEVEL, MAX_LEVEL);
+    let size = tile_size(level);
+    let center = camera * layer.parallax;
+    let min = ((center - screen * zoom / 2.) / size).floor().as_ivec2();
+    let max = ((center + screen * zoom / 2.) / size).floor().as_ivec2();
+    (min.y..=max.y)
+        .flat_map(move |y| (min.x..=max.x).map(move |x| (index, level, IVec2::new(x, y))))
+}
+
+/// Spawns the stars of the tiles coming into view and despawns the ones
+/// which left it.
+fn spawn_stars(
+    mut commands: Commands,
+    starfield: Res<Starfield>,
+    theme: Res<Theme>,
+    mut tiles: ResMut<StarTiles>,
+    cameras: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+) {
+    // Everything is generated again for another seed or palette.
+    if starfield.is_changed() || theme.is_changed() {
+        for (_, stars) in tiles.0.drain() {
+            for star in stars {
+                commands.entity(star).despawn();
+            }
+        }
+    }
+    let Ok((camera, projection)) = cameras.get_single() else {
+        return;
+    };
+    if !starfield.enabled {
+        return;
+    }
+
+    let screen = projection.area.size() / projection.scale;
+    let camera = camera.translation.xy();
+    let visible: HashSet<TileKey> = LAYERS
+        .iter()
+        .enumerate()
+        .flat_map(|(index, layer)| visible_tiles(index, layer, camera, projection.scale, screen))
+        .collect();
+
+    tiles.0.retain(|key, stars| {
+        let keep = visible.contains(key);
+        if !keep {
+            for &star in stars.iter() {
+                commands.entity(star).despawn();
+            }
+        }
+        keep
+    });
+    for key in visible {
+        if tiles.0.contains_key(&key) {
+            continue;
+        }
+        let layer = &LAYERS[key.0];
+        let stars = tile_stars(starfield.seed, key)
+            .into_iter()
+            .map(|(position, brightness)| {
+                let alpha = layer.brightness * (0.2 + 0.8 * brightness);
+                commands
+                    .spawn((
+                        Star {
+                            layer: key.0,
+                            position,
+                            size: layer.size * (0.5 + 0.5 * brightness),
+                        },
+                        Sprite::from_color(theme.star().with_alpha(alpha), Vec2::ONE),
+                        // Behind the bodies, nearer layers in front.
+                        Transform::from_xyz(0., 0., -100. + key.0 as f32),
+                    ))
+                    .id()
+            })
+            .collect();
+        tiles.0.insert(key, stars);
+    }
+}
+
+/// Places the stars for the camera after it moved this frame.
+fn place_stars(
+    cameras: Query<(&Transform, &OrthographicProjection), With<MainCamera>>,
+    mut stars: Query<(&Star, &mut Transform), Without<MainCamera>>,
+) {
+    let Ok((camera, projection)) = cameras.get_single() else {
+        return;
+    };
+    let camera = camera.translation.xy();
+    let scale = projection.scale;
+    for (star, mut transform) in &mut stars {
+        let layer = &LAYERS[star.layer];
+        let zoom = layer_zoom(layer, scale);
+        let on_screen = (star.position - camera * layer.parallax) / zoom;
+        let position = camera + on_screen * scale;
+        transform.translation = position.extend(transform.translation.z);
+        // Stars keep their size in pixels at any zoom.
+        transform.scale = Vec3::splat(star.size * scale);
+    }
+}
+
+/// Draws the [`Starfield`], toggled with B by default.
+pub struct StarfieldPlugin;
+
+impl Plugin for StarfieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Starfield>()
+            .init_resource::<StarTiles>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .add_systems(Update, toggle_starfield)
+            .add_systems(
+                PostUpdate,
+                (spawn_stars, place_stars)
+                    .chain()
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
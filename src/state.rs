@@ -0,0 +1,71 @@
+use crate::input::{Action, Actions};
+use bevy::prelude::*;
+
+/// What the app is doing, the simulation only advances while running.
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SimState {
+    /// Picking a scenario
+    Menu,
+    /// Spawning the selected scenario, lasts a single frame
+    #[default]
+    Loading,
+    Running,
+    Paused,
+    /// Changing the scenario, the simulation holds still like when paused
+    Editing,
+    /// Framing a photo, paused with the UI hidden
+    Photo,
+}
+
+/// Switches between running and paused.
+pub fn toggle_pause(
+    actions: Actions,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    if !actions.just_pressed(Action::TogglePause) {
+        return;
+    }
+    match state.get() {
+        SimState::Running => next_state.set(SimState::Paused),
+        SimState::Paused => next_state.set(SimState::Running),
+        _ => {}
+    }
+}
+
+/// Switches into editing from running or paused, and back to running.
+pub fn toggle_editing(
+    actions: Actions,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
+) {
+    if !actions.just_pressed(Action::ToggleEditing) {
+        return;
+    }
+    match state.get() {
+        SimState::Running | SimState::Paused => next_state.set(SimState::Editing),
+        SimState::Editing => next_state.set(SimState::Running),
+        _ => {}
+    }
+}
+
+/// Stops the virtual clock so everything driven by it, not only the
+/// physics, holds still while paused.
+pub fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+pub fn resume_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+/// Starts the virtual clock over from zero, keeping its relative speed and
+/// whether it is paused.
+pub fn restart_time(time: &mut Time<Virtual>) {
+    let mut restarted = Time::<Virtual>::default();
+    restarted.set_relative_speed(time.relative_speed());
+    if time.is_paused() {
+        restarted.pause();
+    }
+    *time = restarted;
+}
@@ -0,0 +1,116 @@
+use crate::bodies::Bodies;
+use crate::cadence::{cadence_elapsed, Cadences, Subsystem};
+use crate::docking::DockedPart;
+use crate::state::SimState;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::time::Duration;
+
+/// Side of the square cells the bodies are counted in to find the density
+/// peak.
+const DENSITY_CELL: f32 = 50.;
+
+/// Summary of the simulation, updated every few steps, see [`Cadences`], for
+/// anything reacting to how the run goes, such as external music or
+/// visuals. Starts over when the scenario is restarted or the bodies are
+/// cleared.
+#[derive(Resource, Debug)]
+pub struct SimStatistics {
+    pub kinetic_energy: f32,
+    /// Bodies docked per simulated second over the last update
+    pub merge_rate: f32,
+    /// Center of the cell holding the most mass
+    pub density_peak: Vec2,
+    /// Mass per unit of area in that cell
+    pub peak_density: f32,
+    /// Bodies docked since the last update
+    merges: u32,
+    /// Simulated time of the last update
+    updated_at: Duration,
+}
+
+impl Default for SimStatistics {
+    fn default() -> Self {
+        SimStatistics {
+            kinetic_energy: 0.,
+            merge_rate: 0.,
+            density_peak: Vec2::ZERO,
+            peak_density: 0.,
+            merges: 0,
+            updated_at: Duration::ZERO,
+        }
+    }
+}
+
+/// Sent for every body which docked, as it happens.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MergeEvent {
+    pub part: Entity,
+    pub position: Vec2,
+    pub mass: f32,
+}
+
+fn count_merges(
+    mut statistics: ResMut<SimStatistics>,
+    mut merges: EventWriter<MergeEvent>,
+    parts: Query<(Entity, &GlobalTransform, &DockedPart), Added<DockedPart>>,
+) {
+    for (part, transform, docked) in &parts {
+        // Only the periodic update counts as a change of the statistics.
+        statistics.bypass_change_detection().merges += 1;
+        merges.send(MergeEvent {
+            part,
+            position: transform.translation().xy(),
+            mass: docked.mass,
+        });
+    }
+}
+
+fn update_statistics(time: Res<Time>, mut statistics: ResMut<SimStatistics>, bodies: Bodies) {
+    let mut kinetic_energy = 0.;
+    let mut cells: HashMap<IVec2, f32> = HashMap::default();
+    for body in bodies.iter() {
+        kinetic_energy += 0.5 * body.mass * body.velocity.length_squared();
+        let cell = (body.position / DENSITY_CELL).floor().as_ivec2();
+        *cells.entry(cell).or_default() += body.mass;
+    }
+    let (peak, peak_mass) = cells
+        .into_iter()
+        .max_by(|a, b| {
+            a.1.total_cmp(&b.1)
+                .then(b.0.to_array().cmp(&a.0.to_array()))
+        })
+        .unwrap_or_default();
+
+    statistics.kinetic_energy = kinetic_energy;
+    let elapsed = time.elapsed().saturating_sub(statistics.updated_at);
+    statistics.merge_rate = if elapsed.is_zero() {
+        0.
+    } else {
+        statistics.merges as f32 / elapsed.as_secs_f32()
+    };
+    statistics.updated_at = time.elapsed();
+    statistics.merges = 0;
+    statistics.density_peak = (peak.as_vec2() + 0.5) * DENSITY_CELL;
+    statistics.peak_density = peak_mass / (DENSITY_CELL * DENSITY_CELL);
+}
+
+/// Keeps the [`SimStatistics`] up to date while the simulation runs.
+pub struct StatisticsPlugin;
+
+impl Plugin for StatisticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimStatistics>()
+            .init_resource::<Cadences>()
+            .add_event::<MergeEvent>()
+            .add_systems(
+                Update,
+                (
+                    count_merges,
+                    update_statistics.run_if(cadence_elapsed(Subsystem::Statistics)),
+                )
+                    .chain()
+                    .run_if(in_state(SimState::Running)),
+            );
+    }
+}
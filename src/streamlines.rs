@@ -0,0 +1,131 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_config::PhysicsConfig;
+use crate::physics_plugin::{GravityTrees, MainCamera, PhysicsSettings, Velocity};
+use crate::quality::Quality;
+use crate::spatial_index::SpatialIndex;
+use crate::theme::Theme;
+use bevy::prelude::*;
+
+/// Number of streamline seeds along each side of the view.
+const SEEDS_PER_SIDE: usize = 24;
+/// Number of segments of every streamline.
+const SEGMENTS: usize = 12;
+
+/// Field the streamlines follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowField {
+    /// Gravitational acceleration the physics last computed, see
+    /// [`SpatialIndex`]
+    Acceleration,
+    /// Average velocity of the bodies in each cell of the seed grid
+    Velocity,
+}
+
+/// Streamlines traced from a grid of points over the view, showing global
+/// flow patterns like rotation, infall or tidal bridges. Off when `field`
+/// is `None`.
+#[derive(Resource, Debug, Default)]
+pub struct StreamlineOverlay {
+    pub field: Option<FlowField>,
+}
+
+/// Cycles the overlay from off through the acceleration and velocity fields.
+fn toggle_streamlines(actions: Actions, mut overlay: ResMut<StreamlineOverlay>) {
+    if actions.just_pressed(Action::CycleStreamlines) {
+        overlay.field = match overlay.field {
+            None => Some(FlowField::Acceleration),
+            Some(FlowField::Acceleration) => Some(FlowField::Velocity),
+            Some(FlowField::Velocity) => None,
+        };
+    }
+}
+
+fn draw_streamlines(
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+    overlay: Res<StreamlineOverlay>,
+    spatial_index: SpatialIndex,
+    cameras: Query<(&GlobalTransform, &OrthographicProjection), With<MainCamera>>,
+    bodies: Query<(&Transform, &Velocity)>,
+) {
+    let (Some(field), Ok((camera_transform, projection))) = (overlay.field, cameras.get_single())
+    else {
+        return;
+    };
+
+    let view = Rect::from_center_size(
+        camera_transform.translation().xy() + projection.area.center(),
+        projection.area.size(),
+    );
+    let cell_size = view.size() / SEEDS_PER_SIDE as f32;
+    let cell_of = |position: Vec2| {
+        let cell = ((position - view.min) / cell_size).floor();
+        let in_view =
+            cell.cmpge(Vec2::ZERO).all() && cell.cmplt(Vec2::splat(SEEDS_PER_SIDE as f32)).all();
+        in_view.then(|| cell.y as usize * SEEDS_PER_SIDE + cell.x as usize)
+    };
+
+    let mut velocities = vec![Vec2::ZERO; SEEDS_PER_SIDE * SEEDS_PER_SIDE];
+    if field == FlowField::Velocity {
+        let mut counts = vec![0; velocities.len()];
+        for (transform, velocity) in &bodies {
+            if let Some(cell) = cell_of(transform.translation.xy()) {
+                velocities[cell] += velocity.0;
+                counts[cell] += 1;
+            }
+        }
+        for (velocity, count) in velocities.iter_mut().zip(counts) {
+            if count > 0 {
+                *velocity /= count as f32;
+            }
+        }
+    }
+
+    // Each segment goes a quarter of a cell, so neighbouring lines rarely
+    // run into each other.
+    let segment_length = cell_size.min_element() / 4.;
+    for y in 0..SEEDS_PER_SIDE {
+        for x in 0..SEEDS_PER_SIDE {
+            let mut position = view.min + (Vec2::new(x as f32, y as f32) + 0.5) * cell_size;
+            let mut points = vec![position];
+            for _ in 0..SEGMENTS {
+                let direction = match field {
+                    FlowField::Acceleration => spatial_index.acceleration_at(position),
+                    FlowField::Velocity => {
+                        cell_of(position).map_or(Vec2::ZERO, |cell| velocities[cell])
+                    }
+                }
+                .normalize_or_zero();
+                if direction == Vec2::ZERO {
+                    break;
+                }
+                position += direction * segment_length;
+                points.push(position);
+            }
+            // Fades towards the end, so the direction of the flow shows.
+            let count = points.len() as f32;
+            gizmos.linestrip_gradient_2d(points.into_iter().enumerate().map(|(index, point)| {
+                (
+                    point,
+                    theme.highlight().with_alpha(1. - index as f32 / count),
+                )
+            }));
+        }
+    }
+}
+
+/// Overlay of streamlines of the acceleration or velocity field.
+pub struct StreamlinePlugin;
+
+impl Plugin for StreamlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StreamlineOverlay>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .init_resource::<GravityTrees>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<Quality>()
+            .init_resource::<PhysicsSettings>()
+            .add_systems(Update, (toggle_streamlines, draw_streamlines).chain());
+    }
+}
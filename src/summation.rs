@@ -0,0 +1,151 @@
+//! `spacesim summation`: how much adding up the accelerations and the
+//! centers of mass of the tree in double precision gains over single
+//! precision, and what it costs.
+
+use crate::physics_plugin::{build_tree, G, THETA_THRESHOLD};
+use crate::quadtree::{QuadTree, Summation};
+use bevy::math::{DVec2, Vec2};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+const BODIES: usize = 20_000;
+/// Bodies whose acceleration is compared with the exact one
+const TARGETS: usize = 500;
+/// Times every traversal is timed, the fastest run counts
+const REPEATS: usize = 5;
+
+/// A disc of bodies with masses over six orders of magnitude, so the small
+/// contributions of the light distant bodies are added to large ones. It
+/// fits into the bounds the tree starts with.
+fn bodies() -> Vec<(Vec2, f32)> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..BODIES)
+        .map(|_| {
+            let distance = rng.random_range(10f32..950.);
+            let angle = rng.random_range(0.0..std::f32::consts::TAU);
+            let mass = 10f32.powf(rng.random_range(0f32..6.));
+            (Vec2::from_angle(angle) * distance, mass)
+        })
+        .collect()
+}
+
+/// Acceleration at `position` summed directly over all the `bodies` in
+/// double precision.
+fn exact_acceleration(bodies: &[(Vec2, f32)], position: Vec2) -> DVec2 {
+    bodies
+        .iter()
+        .filter(|(body, _)| *body != position)
+        .map(|(body, mass)| {
+            let offset = body.as_dvec2() - position.as_dvec2();
+            G as f64 * *mass as f64 * offset / offset.length().powi(3)
+        })
+        .sum()
+}
+
+/// Center of mass as the tree kept it before, updated in single precision
+/// with every body added.
+fn single_center_of_mass(bodies: &[(Vec2, f32)]) -> Vec2 {
+    let mut total = 0f32;
+    let mut center_of_mass = Vec2::ZERO;
+    for &(position, mass) in bodies {
+        center_of_mass = (center_of_mass * total + position * mass) / (total + mass);
+        total += mass;
+    }
+    center_of_mass
+}
+
+fn root_center_of_mass(tree: &QuadTree) -> Vec2 {
+    let mut center_of_mass = Vec2::ZERO;
+    tree.for_each_node(|node, depth| {
+        if depth == 0 {
+            center_of_mass = node.center_of_mass;
+        }
+    });
+    center_of_mass
+}
+
+/// Mean and largest relative error of the accelerations of the targets,
+/// with the tree opened all the way down so only the summation differs
+/// from the exact accelerations.
+fn acceleration_errors(
+    tree: &QuadTree,
+    bodies: &[(Vec2, f32)],
+    summation: Summation,
+) -> (f64, f64) {
+    let errors: Vec<f64> = bodies[..TARGETS]
+        .iter()
+        .map(|&(position, _)| {
+            let exact = exact_acceleration(bodies, position);
+            let summed = tree.accumulate_acceleration_with(position, 0., G, 0., summation);
+            (summed.as_dvec2() - exact).length() / exact.length()
+        })
+        .collect();
+    let mean = errors.iter().sum::<f64>() / errors.len() as f64;
+    (mean, errors.iter().copied().fold(0., f64::max))
+}
+
+/// Fastest of the runs of the traversals for all the bodies with the
+/// simulation's theta threshold.
+fn traversal_time(tree: &QuadTree, bodies: &[(Vec2, f32)], summation: Summation) -> Duration {
+    (0..REPEATS)
+        .map(|_| {
+            let start = Instant::now();
+            for &(position, _) in bodies {
+                std::hint::black_box(tree.accumulate_acceleration_with(
+                    position,
+                    THETA_THRESHOLD,
+                    G,
+                    0.,
+                    summation,
+                ));
+            }
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// Prints the errors of the accelerations and the center of mass summed
+/// both ways, and how long the traversals take with either.
+pub fn run() {
+    let bodies = bodies();
+    let tree = build_tree(bodies.iter().copied());
+    println!("{BODIES} bodies with masses from 1 to 1e6");
+
+    println!();
+    println!("Acceleration of {TARGETS} bodies, relative error against the exact sum");
+    println!("{:>8} {:>12} {:>12}", "sum", "mean", "max");
+    for (name, summation) in [("single", Summation::Single), ("double", Summation::Double)] {
+        let (mean, max) = acceleration_errors(&tree, &bodies, summation);
+        println!("{name:>8} {mean:>12.4e} {max:>12.4e}");
+    }
+
+    println!();
+    println!("Center of mass of all the bodies, error against the exact one");
+    let total: f64 = bodies.iter().map(|(_, mass)| *mass as f64).sum();
+    let exact = bodies
+        .iter()
+        .map(|(position, mass)| position.as_dvec2() * *mass as f64)
+        .sum::<DVec2>()
+        / total;
+    for (name, center_of_mass) in [
+        ("single", single_center_of_mass(&bodies)),
+        ("double", root_center_of_mass(&tree)),
+    ] {
+        let error = (center_of_mass.as_dvec2() - exact).length();
+        println!("{name:>8} {error:>12.4e}");
+    }
+
+    println!();
+    println!("Traversals of all the bodies with theta {THETA_THRESHOLD}");
+    let single = traversal_time(&tree, &bodies, Summation::Single);
+    let double = traversal_time(&tree, &bodies, Summation::Double);
+    for (name, time) in [("single", single), ("double", double)] {
+        println!("{name:>8} {:>9.3} ms", time.as_secs_f64() * 1000.);
+    }
+    println!(
+        "double precision costs {:+.1}%",
+        (double.as_secs_f64() / single.as_secs_f64() - 1.) * 100.
+    );
+}
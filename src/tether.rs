@@ -0,0 +1,136 @@
+use crate::forces::Acceleration;
+use crate::physics_plugin::{Mass, Velocity};
+use crate::theme::Theme;
+use bevy::prelude::*;
+
+/// Spring connecting the body it is attached to with the `other` body.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Tether {
+    /// The body on the other end of the tether
+    pub other: Entity,
+    /// Length at which the tether exerts no force
+    pub rest_length: f32,
+    /// Force per unit of length the tether is stretched or compressed by
+    pub stiffness: f32,
+    /// Force per unit of relative speed along the tether, resisting
+    /// oscillation
+    pub damping: f32,
+    /// Force above which the tether snaps and is removed
+    pub break_force: f32,
+}
+
+/// Applies the spring force of every tether to the bodies on both its ends,
+/// removing the tethers that got pulled apart by more than they can hold.
+pub fn apply_tethers(
+    mut commands: Commands,
+    tethers: Query<(Entity, &Tether)>,
+    mut bodies: Query<(&Transform, &Mass, &Velocity, &mut Acceleration)>,
+) {
+    for (entity, tether) in &tethers {
+        let Ok(
+            [(transform, mass, velocity, mut acceleration), (other_transform, other_mass, other_velocity, mut other_acceleration)],
+        ) = bodies.get_many_mut([entity, tether.other])
+        else {
+            continue;
+        };
+
+        let offset = other_transform.translation.xy() - transform.translation.xy();
+        let length = offset.length();
+        if length == 0. {
+            continue;
+        }
+        let dir = offset / length;
+
+        // Positive force pulls the ends together, negative pushes them apart.
+        let relative_speed = (other_velocity.0 - velocity.0).dot(dir);
+        let force =
+            tether.stiffness * (length - tether.rest_length) + tether.damping * relative_speed;
+        if force.abs() > tether.break_force {
+            commands.entity(entity).remove::<Tether>();
+            continue;
+        }
+
+        acceleration.tether += dir * (force / mass.0);
+        other_acceleration.tether -= dir * (force / other_mass.0);
+    }
+}
+
+/// Draws every tether as a line between the bodies it connects.
+pub fn draw_tethers(
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+    tethers: Query<(&Transform, &Tether)>,
+    bodies: Query<&Transform>,
+) {
+    for (transform, tether) in &tethers {
+        if let Ok(other_transform) = bodies.get(tether.other) {
+            gizmos.line_2d(
+                transform.translation.xy(),
+                other_transform.translation.xy(),
+                theme.connection(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TETHER: Tether = Tether {
+        other: Entity::PLACEHOLDER,
+        rest_length: 10.,
+        stiffness: 2.,
+        damping: 0.,
+        break_force: 15.,
+    };
+
+    /// Accelerations of two bodies of masses 1 and 4 `length` apart along
+    /// the x axis after a step of `tether`, `None` when it snapped.
+    fn pull(tether: Tether, length: f32) -> Option<(Vec2, Vec2)> {
+        let mut world = World::new();
+        let mut body = |x: f32, mass: f32| {
+            world
+                .spawn((
+                    Transform::from_xyz(x, 0., 0.),
+                    Mass(mass),
+                    Velocity(Vec2::ZERO),
+                    Acceleration::default(),
+                ))
+                .id()
+        };
+        let a = body(0., 1.);
+        let b = body(length, 4.);
+        world.entity_mut(a).insert(Tether { other: b, ..tether });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(apply_tethers);
+        schedule.run(&mut world);
+        world.get::<Tether>(a)?;
+        let acceleration = |entity| world.get::<Acceleration>(entity).unwrap().tether;
+        Some((acceleration(a), acceleration(b)))
+    }
+
+    #[test]
+    fn spring_pulls_with_the_stretch_beyond_the_rest_length() {
+        assert_eq!(pull(TETHER, 10.), Some((Vec2::ZERO, Vec2::ZERO)));
+        // Stretched by 3, a force of 6
+        assert_eq!(
+            pull(TETHER, 13.),
+            Some((Vec2::new(6., 0.), Vec2::new(-1.5, 0.)))
+        );
+        // Compressed by 2, a force of 4 pushing the ends apart
+        assert_eq!(
+            pull(TETHER, 8.),
+            Some((Vec2::new(-4., 0.), Vec2::new(1., 0.)))
+        );
+    }
+
+    #[test]
+    fn tether_snaps_above_its_break_force() {
+        // Forces of 14 and 16
+        assert!(pull(TETHER, 17.).is_some());
+        assert_eq!(pull(TETHER, 18.), None);
+        assert_eq!(pull(TETHER, 2.), None);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::BodyMaterial;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Set of colors everything in the simulation is drawn with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    Default,
+    /// Okabe-Ito colors and a viridis ramp, distinguishable with the common
+    /// kinds of color blindness
+    ColorblindSafe,
+    /// White bodies and saturated overlays on black
+    HighContrast,
+}
+
+/// Central place for every color decision, so palettes apply everywhere.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct Theme {
+    pub palette: Palette,
+    /// Replaces the palette's background color when set
+    pub background: Option<Color>,
+}
+
+/// Stops of the viridis colormap.
+const VIRIDIS: [(u8, u8, u8); 5] = [
+    (68, 1, 84),
+    (59, 82, 139),
+    (33, 145, 140),
+    (94, 201, 98),
+    (253, 231, 37),
+];
+
+impl Palette {
+    fn next(self) -> Self {
+        match self {
+            Palette::Default => Palette::ColorblindSafe,
+            Palette::ColorblindSafe => Palette::HighContrast,
+            Palette::HighContrast => Palette::Default,
+        }
+    }
+}
+
+impl Theme {
+    pub fn background(&self) -> Color {
+        self.background.unwrap_or(match self.palette {
+            Palette::Default | Palette::ColorblindSafe => Color::srgb_u8(43, 44, 47),
+            Palette::HighContrast => Color::BLACK,
+        })
+    }
+
+    pub fn body(&self) -> Color {
+        match self.palette {
+            Palette::Default => Color::srgb_u8(255, 0, 0),
+            // Vermillion
+            Palette::ColorblindSafe => Color::srgb_u8(213, 94, 0),
+            Palette::HighContrast => Color::WHITE,
+        }
+    }
+
+    /// Lines connecting bodies, such as tethers.
+    pub fn connection(&self) -> Color {
+        match self.palette {
+            Palette::Default | Palette::ColorblindSafe => Color::srgb_u8(200, 200, 200),
+            Palette::HighContrast => Color::WHITE,
+        }
+    }
+
+    /// Highlighted parts of overlays, e.g. cells accepted by the probe.
+    pub fn highlight(&self) -> Color {
+        match self.palette {
+            Palette::Default => Color::srgb(0.2, 0.6, 1.),
+            // Sky blue
+            Palette::ColorblindSafe => Color::srgb_u8(86, 180, 233),
+            Palette::HighContrast => Color::srgb(0., 1., 1.),
+        }
+    }
+
+    /// Secondary parts of overlays, e.g. outlines of cells.
+    pub fn outline(&self) -> Color {
+        match self.palette {
+            Palette::Default | Palette::ColorblindSafe => Color::srgba(1., 1., 1., 0.4),
+            Palette::HighContrast => Color::WHITE,
+        }
+    }
+
+    /// Vectors and markers that need to stand out.
+    pub fn accent(&self) -> Color {
+        match self.palette {
+            Palette::Default => Color::srgb(1., 1., 0.),
+            // Yellow
+            Palette::ColorblindSafe => Color::srgb_u8(240, 228, 66),
+            Palette::HighContrast => Color::srgb(1., 1., 0.),
+        }
+    }
+
+    /// Bodies of a secondary simulation drawn over the live one.
+    pub fn ghost(&self) -> Color {
+        match self.palette {
+            Palette::Default => Color::srgba(0.4, 1., 0.4, 0.6),
+            // Bluish green
+            Palette::ColorblindSafe => Color::srgba_u8(0, 158, 115, 160),
+            Palette::HighContrast => Color::srgb(0., 1., 0.),
+        }
+    }
+
+    /// Stars of the background.
+    pub fn star(&self) -> Color {
+        match self.palette {
+            Palette::Default | Palette::ColorblindSafe => Color::srgb(0.9, 0.92, 1.),
+            Palette::HighContrast => Color::WHITE,
+        }
+    }
+
+    /// Color for `t` from 0 to 1 on the palette's continuous ramp, for
+    /// coloring by a quantity.
+    pub fn ramp(&self, t: f32) -> Color {
+        let t = t.clamp(0., 1.);
+        match self.palette {
+            Palette::Default => Color::srgb(0.1, 0.2, 1.).mix(&Color::srgb(1., 0.1, 0.1), t),
+            Palette::ColorblindSafe => {
+                let scaled = t * (VIRIDIS.len() - 1) as f32;
+                let idx = (scaled as usize).min(VIRIDIS.len() - 2);
+                let (r0, g0, b0) = VIRIDIS[idx];
+                let (r1, g1, b1) = VIRIDIS[idx + 1];
+                Color::srgb_u8(r0, g0, b0).mix(&Color::srgb_u8(r1, g1, b1), scaled - idx as f32)
+            }
+            Palette::HighContrast => Color::srgb(0.2, 0.2, 0.2).mix(&Color::WHITE, t),
+        }
+    }
+}
+
+fn cycle_palette(actions: Actions, mut theme: ResMut<Theme>) {
+    if actions.just_pressed(Action::CyclePalette) {
+        theme.palette = theme.palette.next();
+    }
+}
+
+/// Pushes the theme to the colors that live outside of it, the background
+/// and the shared body material.
+fn apply_theme(
+    theme: Res<Theme>,
+    body_material: Option<Res<BodyMaterial>>,
+    mut clear_color: ResMut<ClearColor>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !theme.is_changed() && !body_material.as_ref().is_some_and(|m| m.is_changed()) {
+        return;
+    }
+    clear_color.0 = theme.background();
+    if let Some(material) = body_material.and_then(|m| materials.get_mut(&m.0)) {
+        material.color = theme.body();
+    }
+}
+
+/// Keeps the [`Theme`] resource and applies it.
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .add_systems(Update, (cycle_palette, apply_theme).chain());
+    }
+}
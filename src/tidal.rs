@@ -0,0 +1,146 @@
+use crate::fixed_step::InterpolatedTranslation;
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_config::PhysicsConfig;
+use crate::physics_plugin::{GravityTrees, Mass, PhysicsSettings, Velocity};
+use crate::quality::Quality;
+use crate::spatial_index::SpatialIndex;
+use crate::theme::Theme;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+
+/// Largest eigenvalue of the symmetric tidal `tensor`, how strongly the
+/// field pulls apart along the direction it stretches the most. Negative
+/// where it only squeezes.
+pub fn tidal_stretch(tensor: Mat2) -> f32 {
+    let (xx, xy, yy) = (tensor.x_axis.x, tensor.x_axis.y, tensor.y_axis.y);
+    let half_difference = (xx - yy) / 2.;
+    (xx + yy) / 2. + (half_difference * half_difference + xy * xy).sqrt()
+}
+
+/// Distance from a body of `mass` within which its own gravity, with the
+/// gravitational constant `g`, holds against the tidal `tensor` around it,
+/// `None` where nothing stretches it. The tensor must leave out the body's
+/// own gravity, see [`SpatialIndex::body_tidal_tensor_in`].
+pub fn hill_radius(mass: f32, tensor: Mat2, g: f32) -> Option<f32> {
+    let stretch = tidal_stretch(tensor);
+    (stretch > 0.).then(|| (g * mass / stretch).cbrt())
+}
+
+/// Whether a body of `mass` reaching out to `radius` gets torn apart by
+/// the tidal `tensor`, its surface lying beyond its Hill radius.
+pub fn is_tidally_disrupted(mass: f32, radius: f32, tensor: Mat2, g: f32) -> bool {
+    hill_radius(mass, tensor, g).is_some_and(|hill_radius| radius > hill_radius)
+}
+
+/// Rings around the bodies colored by the tidal stretch at them, on a
+/// logarithmic scale from the least to the most stretched body.
+#[derive(Resource, Debug, Default)]
+pub struct TidalStressOverlay {
+    pub active: bool,
+}
+
+fn toggle_tidal_stress(actions: Actions, mut overlay: ResMut<TidalStressOverlay>) {
+    if actions.just_pressed(Action::ToggleTidalStress) {
+        overlay.active = !overlay.active;
+    }
+}
+
+/// Colors the bodies by the tidal field at their physics position, where
+/// the trees of the last step have them, rather than where they are drawn.
+#[allow(clippy::type_complexity)]
+fn draw_tidal_stress(
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+    overlay: Res<TidalStressOverlay>,
+    spatial_index: SpatialIndex,
+    bodies: Query<
+        (
+            &Transform,
+            Option<&InterpolatedTranslation>,
+            Option<&Mass>,
+            Option<&SimWorld>,
+        ),
+        With<Velocity>,
+    >,
+) {
+    if !overlay.active {
+        return;
+    }
+    let stretches: Vec<(Vec2, f32)> = bodies
+        .iter()
+        .filter_map(|(transform, interpolated, mass, world)| {
+            let position = interpolated
+                .map_or(transform.translation, InterpolatedTranslation::current)
+                .xy();
+            let tensor = spatial_index.body_tidal_tensor_in(
+                world.copied().unwrap_or_default(),
+                position,
+                mass.map_or(0., |mass| mass.0),
+            );
+            let stretch = tidal_stretch(tensor);
+            (stretch > 0.).then(|| (transform.translation.xy(), stretch.log10()))
+        })
+        .collect();
+    let (min, max) = stretches
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), &(_, stretch)| {
+            (min.min(stretch), max.max(stretch))
+        });
+    let range = (max - min).max(f32::EPSILON);
+    for (position, stretch) in stretches {
+        gizmos.circle_2d(position, 2., theme.ramp((stretch - min) / range));
+    }
+}
+
+/// Tidal stress coloring, toggled with S by default.
+pub struct TidalPlugin;
+
+impl Plugin for TidalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TidalStressOverlay>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .init_resource::<GravityTrees>()
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<Quality>()
+            .init_resource::<PhysicsSettings>()
+            .add_systems(Update, (toggle_tidal_stress, draw_tidal_stress).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics_plugin::{build_fitted_tree, tree_tidal_tensor};
+
+    const G: f32 = 1.;
+
+    #[test]
+    fn body_feels_only_the_external_tidal_field() {
+        // A body of 10 at the origin, 100 away from a body of 10⁶ on the x
+        // axis, whose field stretches along x by 2GM/d³ and squeezes along
+        // y by GM/d³.
+        let (mass, heavy, distance) = (10., 1e6, 100.);
+        let tree = build_fitted_tree(&[(Vec2::ZERO, mass), (Vec2::new(distance, 0.), heavy)]);
+        let external = G * heavy / (distance * distance * distance);
+
+        // Slightly off where the tree has the body, like between steps
+        for position in [Vec2::ZERO, Vec2::new(1e-3, -2e-3)] {
+            let tensor = tree_tidal_tensor(&tree, position, 0.5, G, 0., Some(mass));
+            let expected = Mat2::from_diagonal(Vec2::new(2. * external, -external));
+            assert!(tensor.abs_diff_eq(expected, external * 1e-3), "{tensor}");
+
+            let hill = hill_radius(mass, tensor, G).unwrap();
+            let expected_hill = distance * (mass / (2. * heavy)).cbrt();
+            assert!(
+                (hill - expected_hill).abs() < expected_hill * 1e-3,
+                "{hill}"
+            );
+            assert!(!is_tidally_disrupted(mass, 0.9 * expected_hill, tensor, G));
+            assert!(is_tidally_disrupted(mass, 1.1 * expected_hill, tensor, G));
+        }
+        // Without leaving the body out its own gravity swamps the field.
+        let tensor = tree_tidal_tensor(&tree, Vec2::new(1e-3, -2e-3), 0.5, G, 0., None);
+        assert!(tidal_stretch(tensor) > 1e6 * external);
+    }
+}
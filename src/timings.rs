@@ -0,0 +1,119 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::localization::Localization;
+use crate::sim_rate::SimRate;
+use crate::watchdog::Watchdog;
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// How long the parts of the last physics step took, so systems built on
+/// top can scale their own work with the physics load.
+///
+/// With domain decomposition the trees are built inside the tiles, so all
+/// of the force calculation counts as traversal.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct PhysicsTimings {
+    /// Building the quadtree
+    pub tree_build: Duration,
+    /// Walking the tree for the accelerations of all the bodies
+    pub traversal: Duration,
+    /// Moving the bodies
+    pub integration: Duration,
+    /// Finding and docking touching bodies
+    pub collision: Duration,
+}
+
+impl PhysicsTimings {
+    pub fn total(&self) -> Duration {
+        self.tree_build + self.traversal + self.integration + self.collision
+    }
+}
+
+/// Marks the text listing the timings.
+#[derive(Component)]
+struct TimingsText;
+
+fn spawn_timings_text(mut commands: Commands) {
+    commands.spawn((
+        TimingsText,
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.),
+            right: Val::Px(10.),
+            ..Default::default()
+        },
+        Visibility::Hidden,
+    ));
+}
+
+fn toggle_timings(actions: Actions, mut texts: Query<&mut Visibility, With<TimingsText>>) {
+    if !actions.just_pressed(Action::ToggleTimings) {
+        return;
+    }
+    for mut visibility in &mut texts {
+        visibility.toggle_visible_hidden();
+    }
+}
+
+fn update_timings_text(
+    timings: Res<PhysicsTimings>,
+    localization: Res<Localization>,
+    sim_rate: Option<Res<SimRate>>,
+    watchdog: Option<Res<Watchdog>>,
+    mut texts: Query<(&mut Text, &Visibility), With<TimingsText>>,
+) {
+    let lines = [
+        ("timings-tree-build", timings.tree_build),
+        ("timings-traversal", timings.traversal),
+        ("timings-integration", timings.integration),
+        ("timings-collision", timings.collision),
+        ("timings-total", timings.total()),
+    ];
+    for (mut text, visibility) in &mut texts {
+        if visibility == Visibility::Hidden {
+            continue;
+        }
+        let mut shown: Vec<String> = lines
+            .iter()
+            .map(|(id, duration)| {
+                // Hundredths of a millisecond are as fine as it's worth
+                // showing.
+                let milliseconds = (duration.as_secs_f64() * 100_000.).round() / 100.;
+                localization.text(id, &[("ms", milliseconds)])
+            })
+            .collect();
+        if let Some(sim_rate) = &sim_rate {
+            let id = if sim_rate.is_behind() {
+                "timings-sim-rate-behind"
+            } else {
+                "timings-sim-rate"
+            };
+            let rounded = |rate: f64| (rate * 1000.).round() / 1000.;
+            shown.push(localization.text(
+                id,
+                &[
+                    ("rate", rounded(sim_rate.achieved)),
+                    ("target", rounded(sim_rate.target)),
+                ],
+            ));
+        }
+        if let Some(watchdog) = watchdog.as_ref().filter(|watchdog| watchdog.skipped > 0) {
+            shown
+                .push(localization.text("timings-watchdog", &[("count", watchdog.skipped as f64)]));
+        }
+        text.0 = shown.join("\n");
+    }
+}
+
+/// Shows the [`PhysicsTimings`] in the corner of the screen.
+pub struct TimingsPlugin;
+
+impl Plugin for TimingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsTimings>()
+            .init_resource::<Localization>()
+            .init_resource::<InputMap>()
+            .add_systems(Startup, spawn_timings_text)
+            .add_systems(Update, (toggle_timings, update_timings_text).chain());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::{PhysicsSettings, Velocity};
+use crate::theme::Theme;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+/// Recent positions of a body, drawn behind it as a line fading out
+/// towards the oldest. Records one position every
+/// [`PhysicsSettings::trail_interval`] simulated seconds and keeps the
+/// last [`PhysicsSettings::trail_length`] of them.
+#[derive(Component, Debug, Default, Clone)]
+pub struct Trail {
+    /// Oldest first
+    positions: VecDeque<Vec2>,
+    /// Simulated seconds since the last recorded position
+    since_recorded: f32,
+}
+
+impl Trail {
+    pub fn positions(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.positions.iter().copied()
+    }
+}
+
+/// Whether every body gets a [`Trail`], toggled with W (with the default
+/// input map). Trails can also be put on single bodies while this is off.
+#[derive(Resource, Debug, Default)]
+pub struct Trails {
+    pub all: bool,
+}
+
+/// Puts a trail on every body, or takes all of them away.
+fn toggle_trails(
+    actions: Actions,
+    mut commands: Commands,
+    mut trails: ResMut<Trails>,
+    bodies: Query<Entity, With<Trail>>,
+) {
+    if !actions.just_pressed(Action::ToggleTrails) {
+        return;
+    }
+    trails.all = !trails.all;
+    if !trails.all {
+        for entity in &bodies {
+            commands.entity(entity).remove::<Trail>();
+        }
+    }
+}
+
+/// Gives the bodies without a trail one while every body should have one,
+/// including the ones spawned since.
+#[allow(clippy::type_complexity)]
+fn add_trails(
+    mut commands: Commands,
+    trails: Res<Trails>,
+    bodies: Query<Entity, (With<Velocity>, Without<Trail>, Without<Parent>)>,
+) {
+    if !trails.all {
+        return;
+    }
+    for entity in &bodies {
+        commands.entity(entity).insert(Trail::default());
+    }
+}
+
+/// Records the positions in simulated time, so the trails hold still while
+/// the simulation is paused.
+fn record_trails(
+    time: Res<Time>,
+    settings: Res<PhysicsSettings>,
+    mut bodies: Query<(&Transform, &mut Trail)>,
+) {
+    for (transform, mut trail) in &mut bodies {
+        trail.since_recorded += time.delta_secs();
+        if trail.since_recorded < settings.trail_interval && !trail.positions.is_empty() {
+            continue;
+        }
+        trail.since_recorded = 0.;
+        trail.positions.push_back(transform.translation.xy());
+        while trail.positions.len() > settings.trail_length {
+            trail.positions.pop_front();
+        }
+    }
+}
+
+/// Draws the trails of the bodies that are drawn themselves.
+fn draw_trails(
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+    bodies: Query<(&Transform, &Trail, Option<&Visibility>)>,
+) {
+    let color = theme.body();
+    for (transform, trail, visibility) in &bodies {
+        if visibility == Some(&Visibility::Hidden) {
+            continue;
+        }
+        let count = trail.positions.len() as f32;
+        // Up to where the body is now, between the recorded positions.
+        let points = trail
+            .positions()
+            .enumerate()
+            .map(|(index, position)| (position, color.with_alpha(index as f32 / count)))
+            .chain([(transform.translation.xy(), color)]);
+        gizmos.linestrip_gradient_2d(points);
+    }
+}
+
+/// Records and draws the [`Trail`]s.
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Trails>()
+            .init_resource::<PhysicsSettings>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .add_systems(
+                Update,
+                (toggle_trails, add_trails, record_trails, draw_trails).chain(),
+            );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::export::timestamped_path;
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::GravityTrees;
+use crate::quadtree::QuadTree;
+use crate::theme::Theme;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+use serde::Serialize;
+use std::path::Path;
+
+/// Radius of the marks on the centers of mass, relative to the size of
+/// the whole tree.
+const CENTER_OF_MASS_RADIUS: f32 = 0.002;
+
+/// A node of the tree as it is written to JSON.
+#[derive(Debug, Serialize)]
+struct ExportedNode {
+    /// Depth below the root, which is at 0
+    depth: usize,
+    center: [f32; 2],
+    half_size: f32,
+    mass: f32,
+    center_of_mass: [f32; 2],
+    leaf: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportedTree {
+    /// Corners of the square the root covers
+    bounds: [[f32; 2]; 2],
+    max_depth: usize,
+    /// Parents before their children
+    nodes: Vec<ExportedNode>,
+}
+
+fn exported_tree(tree: &QuadTree) -> ExportedTree {
+    let mut nodes = Vec::new();
+    tree.for_each_node(|node, depth| {
+        nodes.push(ExportedNode {
+            depth,
+            center: node.center.into(),
+            half_size: node.half_size,
+            mass: node.mass,
+            center_of_mass: node.center_of_mass.into(),
+            leaf: node.is_leaf(),
+        });
+    });
+    // The root is visited first.
+    let root = &nodes[0];
+    let bounds = [
+        [
+            root.center[0] - root.half_size,
+            root.center[1] - root.half_size,
+        ],
+        [
+            root.center[0] + root.half_size,
+            root.center[1] + root.half_size,
+        ],
+    ];
+    ExportedTree {
+        bounds,
+        max_depth: nodes.iter().map(|node| node.depth).max().unwrap_or(0),
+        nodes,
+    }
+}
+
+/// The bounds, depth, mass and center of mass of every node of the `tree`
+/// as JSON.
+pub fn tree_json(tree: &QuadTree) -> String {
+    // Only numbers and field names, which always serialize.
+    serde_json::to_string(&exported_tree(tree)).unwrap()
+}
+
+/// The `tree` drawn as an SVG image, the bounds of every node colored from
+/// the root to the deepest nodes and the centers of mass marked, the way
+/// the tree overlay draws it.
+pub fn tree_svg(tree: &QuadTree, theme: &Theme) -> String {
+    let exported = exported_tree(tree);
+    let [min, max] = exported.bounds;
+    let size = max[0] - min[0];
+    let hex = |color: Color| color.to_srgba().to_hex();
+    // The world's y axis points up and SVG's down, so the nodes are drawn
+    // in a flipped group.
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {size} {size}\">\n\
+         <rect x=\"{}\" y=\"{}\" width=\"{size}\" height=\"{size}\" fill=\"{}\"/>\n\
+         <g transform=\"scale(1 -1)\" fill=\"none\" stroke-width=\"1\">\n",
+        min[0],
+        -max[1],
+        min[0],
+        -max[1],
+        hex(theme.background()),
+    );
+    for node in &exported.nodes {
+        let color = hex(theme.ramp(node.depth as f32 / exported.max_depth.max(1) as f32));
+        let side = node.half_size * 2.;
+        svg += &format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{side}\" height=\"{side}\" stroke=\"{color}\" vector-effect=\"non-scaling-stroke\"/>\n",
+            node.center[0] - node.half_size,
+            node.center[1] - node.half_size,
+        );
+        if node.mass > 0. {
+            svg += &format!(
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{color}\"/>\n",
+                node.center_of_mass[0],
+                node.center_of_mass[1],
+                size * CENTER_OF_MASS_RADIUS,
+            );
+        }
+    }
+    svg += "</g>\n</svg>\n";
+    svg
+}
+
+/// Writes the gravity tree of world 0 next to each other as JSON and SVG,
+/// named after the current time.
+fn export_tree(actions: Actions, theme: Res<Theme>, trees: Res<GravityTrees>) {
+    if !actions.just_pressed(Action::ExportTree) {
+        return;
+    }
+    let Some(tree) = trees.tree(SimWorld::default()) else {
+        warn!("There is no gravity tree to export");
+        return;
+    };
+    let path = timestamped_path(Path::new("."));
+    let json = path.with_extension("json");
+    let svg = path.with_extension("svg");
+    let written = std::fs::write(&json, tree_json(tree))
+        .and_then(|()| std::fs::write(&svg, tree_svg(tree, &theme)));
+    match written {
+        Ok(()) => info!(
+            "Gravity tree exported to {} and {}",
+            json.display(),
+            svg.display()
+        ),
+        Err(err) => error!("Couldn't export the gravity tree: {err}"),
+    }
+}
+
+/// Writes the tree of the last force calculation to files with F2 (with
+/// the default input map), for looking at its structure outside the app.
+/// Like the [`TreeOverlay`](crate::tree_overlay::TreeOverlay) there is no
+/// tree while the domain decomposition computes the forces.
+pub struct TreeExportPlugin;
+
+impl Plugin for TreeExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GravityTrees>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .add_systems(Update, export_tree);
+    }
+}
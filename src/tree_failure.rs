@@ -0,0 +1,115 @@
+use crate::localization::Localization;
+use crate::quadtree::TreeError;
+use crate::state::SimState;
+use bevy::prelude::*;
+
+/// Whether the gravity trees could be built on the last physics step.
+///
+/// When they can't, the step skips the forces and the integration instead
+/// of running on a broken tree, and the error is shown until a build
+/// succeeds again.
+#[derive(Resource, Debug, Default)]
+pub struct TreeFailure {
+    /// Why the last build failed, `None` when it didn't
+    pub error: Option<TreeError>,
+    /// Pause the simulation when a build fails, so the moment of the
+    /// blowup can be looked at
+    pub auto_pause: bool,
+}
+
+impl TreeFailure {
+    pub fn record(&mut self, error: TreeError) {
+        self.error = Some(error);
+    }
+
+    /// Forgets the last failure, without marking the resource as changed
+    /// when there was none.
+    pub fn clear(&mut self) {
+        if self.error.is_some() {
+            self.error = None;
+        }
+    }
+}
+
+/// Run condition for the systems which need the gravity trees of the
+/// substep to have been built.
+pub fn tree_built(failure: Res<TreeFailure>) -> bool {
+    failure.error.is_none()
+}
+
+/// Marks the text showing the failure.
+#[derive(Component)]
+struct TreeFailureText;
+
+fn spawn_tree_failure_text(mut commands: Commands) {
+    commands.spawn((
+        TreeFailureText,
+        Text::new(""),
+        TextColor(Color::srgb(1., 0.3, 0.3)),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            width: Val::Percent(100.),
+            ..Default::default()
+        },
+        Visibility::Hidden,
+    ));
+}
+
+/// Logs when the trees start and stop failing, pausing on a new failure if
+/// asked to.
+fn report_tree_failure(
+    failure: Res<TreeFailure>,
+    state: Res<State<SimState>>,
+    mut next_state: ResMut<NextState<SimState>>,
+    mut reported: Local<bool>,
+) {
+    match (failure.error, *reported) {
+        (Some(error), false) => {
+            error!("Skipping the physics, the gravity tree can't be built: {error}");
+            if failure.auto_pause && *state.get() == SimState::Running {
+                next_state.set(SimState::Paused);
+            }
+        }
+        (None, true) => info!("Gravity tree built again, physics resumed"),
+        _ => {}
+    }
+    *reported = failure.error.is_some();
+}
+
+fn update_tree_failure_text(
+    failure: Res<TreeFailure>,
+    localization: Res<Localization>,
+    mut texts: Query<(&mut Text, &mut Visibility), With<TreeFailureText>>,
+) {
+    for (mut text, mut visibility) in &mut texts {
+        let Some(error) = failure.error else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        text.0 = match error {
+            TreeError::InvalidBody { .. } => localization.text("tree-failure-invalid-body", &[]),
+            TreeError::NotFound { .. } => localization.text("tree-failure-not-found", &[]),
+        };
+        *visibility = Visibility::Visible;
+    }
+}
+
+/// Reports failed gravity tree builds, see [`TreeFailure`].
+pub struct TreeFailurePlugin;
+
+impl Plugin for TreeFailurePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TreeFailure>()
+            .init_resource::<Localization>()
+            .add_systems(Startup, spawn_tree_failure_text)
+            .add_systems(
+                Update,
+                (
+                    report_tree_failure,
+                    update_tree_failure_text.run_if(resource_changed::<TreeFailure>),
+                ),
+            );
+    }
+}
@@ -0,0 +1,67 @@
+use crate::input::{Action, Actions, InputMap};
+use crate::physics_plugin::{GravityTrees, MainCamera};
+use crate::theme::Theme;
+use crate::worlds::SimWorld;
+use bevy::prelude::*;
+
+/// Radius in pixels of the marks on the centers of mass.
+const CENTER_OF_MASS_RADIUS: f32 = 2.;
+
+/// Draws the bounds and the center of mass of every node of the gravity
+/// tree of world 0, colored from the root to the deepest nodes, toggled
+/// with V (with the default input map).
+///
+/// Shows the tree of the last force calculation, so nothing is drawn while
+/// the domain decomposition, which builds its own trees, computes the
+/// forces.
+#[derive(Resource, Debug, Default)]
+pub struct TreeOverlay {
+    pub active: bool,
+}
+
+fn toggle_tree_overlay(actions: Actions, mut overlay: ResMut<TreeOverlay>) {
+    if actions.just_pressed(Action::ToggleTreeOverlay) {
+        overlay.active = !overlay.active;
+    }
+}
+
+fn draw_tree_overlay(
+    mut gizmos: Gizmos,
+    theme: Res<Theme>,
+    overlay: Res<TreeOverlay>,
+    trees: Res<GravityTrees>,
+    cameras: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    if !overlay.active {
+        return;
+    }
+    let Some(tree) = trees.tree(SimWorld::default()) else {
+        return;
+    };
+    let scale = cameras
+        .get_single()
+        .map_or(1., |projection| projection.scale);
+
+    let mut max_depth = 0;
+    tree.for_each_node(|_, depth| max_depth = max_depth.max(depth));
+    tree.for_each_node(|node, depth| {
+        let color = theme.ramp(depth as f32 / max_depth.max(1) as f32);
+        gizmos.rect_2d(node.center, Vec2::splat(node.half_size * 2.), color);
+        if node.mass > 0. {
+            gizmos.circle_2d(node.center_of_mass, CENTER_OF_MASS_RADIUS * scale, color);
+        }
+    });
+}
+
+/// Overlay of the Barnes-Hut tree, see [`TreeOverlay`].
+pub struct TreeOverlayPlugin;
+
+impl Plugin for TreeOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TreeOverlay>()
+            .init_resource::<GravityTrees>()
+            .init_resource::<Theme>()
+            .init_resource::<InputMap>()
+            .add_systems(Update, (toggle_tree_overlay, draw_tree_overlay).chain());
+    }
+}
@@ -0,0 +1,74 @@
+use crate::physics_plugin::MainCamera;
+use bevy::prelude::*;
+
+/// When present, the bodies outside the view get coarser gravity: their
+/// tree traversal accepts nodes up to `factor` times the theta threshold,
+/// leaving more of each step's time for the bodies being watched.
+///
+/// The view is the one of the [`MainCamera`] at the end of the last frame,
+/// widened by `margin` so bodies don't switch accuracy right at its edge.
+/// The domain decomposition always uses the same threshold for every body,
+/// and so does a run with [`Determinism`](crate::determinism::Determinism).
+#[derive(Resource, Debug, Clone)]
+pub struct ViewRelaxation {
+    pub factor: f32,
+    /// How far around the view the bodies still count as in it, as a
+    /// fraction of its size
+    pub margin: f32,
+    view: Option<Rect>,
+}
+
+impl ViewRelaxation {
+    pub fn new(factor: f32) -> Self {
+        ViewRelaxation {
+            factor,
+            margin: 0.25,
+            view: None,
+        }
+    }
+
+    /// Theta threshold for a body at `position`, `theta_threshold` within
+    /// the view and relaxed outside it. Before the view is known every body
+    /// counts as in it.
+    pub fn theta_threshold(&self, position: Vec2, theta_threshold: f32) -> f32 {
+        match self.view {
+            Some(view) if !view.contains(position) => theta_threshold * self.factor,
+            _ => theta_threshold,
+        }
+    }
+}
+
+/// Theta threshold for a body at `position`, relaxed outside the view when
+/// there is a [`ViewRelaxation`].
+pub fn relaxed_theta(
+    relaxation: Option<&ViewRelaxation>,
+    position: Vec2,
+    theta_threshold: f32,
+) -> f32 {
+    relaxation.map_or(theta_threshold, |relaxation| {
+        relaxation.theta_threshold(position, theta_threshold)
+    })
+}
+
+/// Records the part of the world the main camera shows, widened by the
+/// margin.
+pub fn track_view(
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    mut relaxation: ResMut<ViewRelaxation>,
+) {
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    let Some(viewport) = camera.logical_viewport_rect() else {
+        return;
+    };
+    let (Ok(min), Ok(max)) = (
+        camera.viewport_to_world_2d(camera_transform, viewport.min),
+        camera.viewport_to_world_2d(camera_transform, viewport.max),
+    ) else {
+        return;
+    };
+    // The viewport's y axis points down, so the corners swap in the world.
+    let view = Rect::from_corners(min, max);
+    relaxation.view = Some(view.inflate(view.size().max_element() * relaxation.margin));
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use bevy::utils::{Duration, Instant};
+
+/// When present, a soft real-time guarantee for installations where a
+/// stutter is worse than a slightly wrong step: once the physics of a
+/// frame is about to take longer than `budget`, the force calculation
+/// stops and the bodies it didn't get to keep the gravity of their last
+/// step.
+///
+/// The next force calculation starts with the bodies skipped last, so
+/// every body is brought up to date in turn. Only the gravity walking the
+/// tree is cut short, the domain decomposition and RK4 always finish. With
+/// [`Determinism`](crate::determinism::Determinism) the watchdog is
+/// ignored.
+#[derive(Resource, Debug, Clone)]
+pub struct Watchdog {
+    /// Time the physics may take each frame
+    pub budget: Duration,
+    /// Bodies which kept their last gravity this frame
+    pub skipped: usize,
+    /// How long the last tree build took, the next one is only started
+    /// when as much time is left
+    pub tree_build: Duration,
+    frame_start: Instant,
+    /// Body the next force calculation starts with
+    next_start: usize,
+}
+
+impl Watchdog {
+    pub fn new(budget: Duration) -> Self {
+        Watchdog {
+            budget,
+            skipped: 0,
+            tree_build: Duration::ZERO,
+            frame_start: Instant::now(),
+            next_start: 0,
+        }
+    }
+
+    /// Whether work taking `cost` still fits into the frame.
+    pub fn allows(&self, cost: Duration) -> bool {
+        self.frame_start.elapsed() + cost <= self.budget
+    }
+
+    /// Order in which to compute the gravity of `count` bodies, starting
+    /// with the ones skipped last.
+    pub fn order(&self, count: usize) -> impl Iterator<Item = usize> {
+        let start = if count > 0 {
+            self.next_start % count
+        } else {
+            0
+        };
+        (start..count).chain(0..start)
+    }
+
+    /// Records that the force calculation stopped after the first
+    /// `computed` bodies of the [`order`](Watchdog::order) of `count`.
+    pub fn record(&mut self, computed: usize, count: usize) {
+        self.skipped += count - computed;
+        if count > 0 {
+            self.next_start = (self.next_start % count + computed) % count;
+        }
+    }
+}
+
+/// Starts measuring the time the frame's physics takes.
+pub fn start_watchdog_frame(mut watchdog: ResMut<Watchdog>) {
+    watchdog.frame_start = Instant::now();
+    watchdog.skipped = 0;
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::render::view::RenderLayers;
+
+/// Which of the independent simulations the body belongs to. Bodies only
+/// attract bodies of the same world, bodies without the component are in
+/// world 0.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SimWorld(pub u32);
+
+/// How many independent copies of the scenario are simulated side by
+/// side. Every copy starts from the same state.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct SimWorlds {
+    pub count: u32,
+}
+
+impl Default for SimWorlds {
+    fn default() -> Self {
+        SimWorlds { count: 1 }
+    }
+}
+
+/// Camera showing the world other than world 0, which is shown by the
+/// main camera.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct WorldView(pub u32);
+
+fn spawn_world_views(mut commands: Commands, worlds: Res<SimWorlds>) {
+    for world in 1..worlds.count {
+        commands.spawn((
+            WorldView(world),
+            Camera2d,
+            Camera {
+                // Below the main camera, which keeps drawing the UI.
+                order: -(world as isize),
+                ..Default::default()
+            },
+            RenderLayers::layer(world as usize),
+        ));
+    }
+}
+
+/// Puts the bodies on the render layer of their world, so only its camera
+/// draws them.
+fn assign_render_layers(
+    mut commands: Commands,
+    bodies: Query<(Entity, &SimWorld), Without<RenderLayers>>,
+) {
+    for (entity, world) in &bodies {
+        commands
+            .entity(entity)
+            .insert(RenderLayers::layer(world.0 as usize));
+    }
+}
+
+/// Splits the window into equally wide columns, one per world.
+fn arrange_world_views(
+    worlds: Res<SimWorlds>,
+    windows: Query<&Window>,
+    mut cameras: Query<(&mut Camera, Option<&WorldView>), With<Camera2d>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let size = window.physical_size();
+    let width = size.x / worlds.count;
+    if width == 0 || size.y == 0 {
+        return;
+    }
+    for (mut camera, view) in &mut cameras {
+        let world = view.map_or(0, |view| view.0);
+        let position = UVec2::new(world * width, 0);
+        let view_size = UVec2::new(width, size.y);
+        let current = camera
+            .viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size));
+        // Only touch the camera when the layout changed.
+        if current != Some((position, view_size)) {
+            camera.viewport = Some(Viewport {
+                physical_position: position,
+                physical_size: view_size,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// Shows every world of [`SimWorlds`] in its own viewport.
+pub struct WorldsPlugin;
+
+impl Plugin for WorldsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimWorlds>()
+            .add_systems(Startup, spawn_world_views)
+            .add_systems(
+                Update,
+                (assign_render_layers, arrange_world_views)
+                    .run_if(|worlds: Res<SimWorlds>| worlds.count > 1),
+            );
+    }
+}